@@ -602,6 +602,41 @@ export function AdminApproved() {
     }
   };
 
+  const handleCertificateAction = async (item, action) => {
+    const body = {};
+    if (action === "reissue") {
+      const callsign = prompt(`Reissue certificate #${item.certificate_number} to callsign:`);
+      if (!callsign) return;
+      body.callsign = callsign;
+    }
+    const reason = prompt(
+      action === "revoke" ? "Reason for revoking:" : "Reason (optional):",
+    );
+    if (reason === null || (action === "revoke" && !reason.trim())) return;
+    body.reason = reason;
+
+    try {
+      const response = await adminFetch(
+        `${API_BASE}/api/admin/certificates/${item.id}/${action}`,
+        {
+          method: "POST",
+          body: JSON.stringify(body),
+        },
+      );
+      if (!response.ok) throw new Error(await response.text());
+      setToast({
+        message:
+          action === "revoke"
+            ? `Certificate #${item.certificate_number} revoked`
+            : `Certificate #${item.certificate_number} reissued`,
+        type: "success",
+      });
+      fetchApproved(data.page);
+    } catch (err) {
+      setToast({ message: err.message, type: "error" });
+    }
+  };
+
   const copyGeneratedEmail = () => {
     if (generatedEmail) {
       navigator.clipboard.writeText(generatedEmail.email);
@@ -771,14 +806,28 @@ export function AdminApproved() {
                       </span>
                     </td>
                     <td className="px-4 py-3">
-                      {item.email && (
+                      <div className="flex gap-2">
+                        {item.email && (
+                          <button
+                            onClick={() => generateEmail(item)}
+                            className="text-xs bg-amber-600 text-white px-2 py-1 hover:bg-amber-700"
+                          >
+                            Email
+                          </button>
+                        )}
+                        <button
+                          onClick={() => handleCertificateAction(item, "reissue")}
+                          className="text-xs border border-amber-600 text-amber-700 px-2 py-1 hover:bg-amber-50"
+                        >
+                          Reissue
+                        </button>
                         <button
-                          onClick={() => generateEmail(item)}
-                          className="text-xs bg-amber-600 text-white px-2 py-1 hover:bg-amber-700"
+                          onClick={() => handleCertificateAction(item, "revoke")}
+                          className="text-xs border border-red-600 text-red-700 px-2 py-1 hover:bg-red-50"
                         >
-                          Email
+                          Revoke
                         </button>
-                      )}
+                      </div>
                     </td>
                   </tr>
                 ))
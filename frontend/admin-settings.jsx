@@ -336,6 +336,293 @@ function EmailTemplateEditor() {
   );
 }
 
+// API Token Manager Component
+const TOKEN_SCOPES = [
+  { id: "roster:read", label: "Roster (read)" },
+  { id: "queue:read", label: "Queue (read)" },
+  { id: "queue:write", label: "Queue (write)" },
+];
+
+function ApiTokenManager() {
+  const { adminFetch } = useAdminAuth();
+  const [tokens, setTokens] = useState([]);
+  const [name, setName] = useState("");
+  const [scopes, setScopes] = useState(["roster:read"]);
+  const [newToken, setNewToken] = useState(null);
+  const [creating, setCreating] = useState(false);
+  const [toast, setToast] = useState(null);
+
+  const fetchTokens = async () => {
+    try {
+      const response = await adminFetch(`${API_BASE}/api/admin/tokens`);
+      if (!response.ok) throw new Error("Failed to fetch tokens");
+      setTokens(await response.json());
+    } catch (err) {
+      setToast({ message: err.message, type: "error" });
+    }
+  };
+
+  useEffect(() => {
+    fetchTokens();
+  }, []);
+
+  const toggleScope = (scope) => {
+    setScopes((prev) =>
+      prev.includes(scope) ? prev.filter((s) => s !== scope) : [...prev, scope],
+    );
+  };
+
+  const handleCreate = async (e) => {
+    e.preventDefault();
+    setCreating(true);
+    try {
+      const response = await adminFetch(`${API_BASE}/api/admin/tokens`, {
+        method: "POST",
+        body: JSON.stringify({ name, scopes }),
+      });
+      if (!response.ok) throw new Error(await response.text());
+      const data = await response.json();
+      setNewToken(data.token);
+      setName("");
+      fetchTokens();
+    } catch (err) {
+      setToast({ message: err.message, type: "error" });
+    } finally {
+      setCreating(false);
+    }
+  };
+
+  const handleRevoke = async (tokenId) => {
+    try {
+      const response = await adminFetch(`${API_BASE}/api/admin/tokens/${tokenId}`, {
+        method: "DELETE",
+      });
+      if (!response.ok) throw new Error("Failed to revoke token");
+      setToast({ message: "Token revoked", type: "success" });
+      fetchTokens();
+    } catch (err) {
+      setToast({ message: err.message, type: "error" });
+    }
+  };
+
+  return (
+    <div className="bg-white border-2 border-amber-300 shadow-sm">
+      {toast && (
+        <Toast
+          message={toast.message}
+          type={toast.type}
+          onClose={() => setToast(null)}
+        />
+      )}
+      <div className="bg-amber-900 text-amber-50 px-6 py-3">
+        <h3 className="font-mono text-sm tracking-widest">API TOKENS</h3>
+      </div>
+      <div className="p-6 space-y-4">
+        {newToken && (
+          <div className="bg-green-50 border-2 border-green-600 p-4">
+            <p className="font-mono text-xs text-green-800 mb-2">
+              Copy this token now - it will not be shown again:
+            </p>
+            <code className="block font-mono text-sm break-all text-green-900">
+              {newToken}
+            </code>
+            <button
+              onClick={() => setNewToken(null)}
+              className="mt-2 font-mono text-xs text-green-800 underline"
+            >
+              Dismiss
+            </button>
+          </div>
+        )}
+        <form onSubmit={handleCreate} className="space-y-3">
+          <input
+            type="text"
+            value={name}
+            onChange={(e) => setName(e.target.value)}
+            className="w-full border-2 border-amber-300 px-4 py-2 font-mono text-sm
+                     focus:border-amber-500 focus:outline-none"
+            placeholder="Token name (e.g., roster sync)"
+          />
+          <div className="flex gap-4 flex-wrap">
+            {TOKEN_SCOPES.map((scope) => (
+              <label
+                key={scope.id}
+                className="flex items-center gap-2 font-mono text-xs text-amber-800"
+              >
+                <input
+                  type="checkbox"
+                  checked={scopes.includes(scope.id)}
+                  onChange={() => toggleScope(scope.id)}
+                />
+                {scope.label}
+              </label>
+            ))}
+          </div>
+          <button
+            type="submit"
+            disabled={creating || !name.trim() || scopes.length === 0}
+            className="bg-amber-900 text-amber-50 px-6 py-2 font-mono text-sm
+                     hover:bg-amber-800 disabled:opacity-50"
+          >
+            {creating ? "Creating..." : "Create Token"}
+          </button>
+        </form>
+        {tokens.length > 0 && (
+          <table className="w-full font-mono text-sm">
+            <thead>
+              <tr className="text-amber-600 text-left border-b border-amber-200">
+                <th className="pr-4 pb-2">Name</th>
+                <th className="pr-4 pb-2">Scopes</th>
+                <th className="pr-4 pb-2">Last Used</th>
+                <th className="pb-2">Status</th>
+              </tr>
+            </thead>
+            <tbody>
+              {tokens.map((t) => (
+                <tr key={t.id} className="text-amber-800">
+                  <td className="pr-4 py-2">{t.name}</td>
+                  <td className="pr-4 text-xs">{t.scopes.join(", ")}</td>
+                  <td className="pr-4 text-xs">
+                    {t.last_used_at
+                      ? new Date(t.last_used_at).toLocaleString()
+                      : "Never"}
+                  </td>
+                  <td>
+                    {t.revoked_at ? (
+                      <span className="text-xs px-2 py-0.5 bg-red-100 text-red-800">
+                        revoked
+                      </span>
+                    ) : (
+                      <button
+                        onClick={() => handleRevoke(t.id)}
+                        className="text-xs bg-red-600 text-white px-2 py-1 hover:bg-red-700"
+                      >
+                        Revoke
+                      </button>
+                    )}
+                  </td>
+                </tr>
+              ))}
+            </tbody>
+          </table>
+        )}
+      </div>
+    </div>
+  );
+}
+
+function BackupManager() {
+  const { adminFetch } = useAdminAuth();
+  const [backups, setBackups] = useState([]);
+  const [running, setRunning] = useState(false);
+  const [toast, setToast] = useState(null);
+
+  const fetchBackups = async () => {
+    try {
+      const response = await adminFetch(`${API_BASE}/api/admin/backups`);
+      if (!response.ok) throw new Error("Failed to fetch backups");
+      setBackups(await response.json());
+    } catch (err) {
+      setToast({ message: err.message, type: "error" });
+    }
+  };
+
+  useEffect(() => {
+    fetchBackups();
+  }, []);
+
+  const handleBackup = async () => {
+    setRunning(true);
+    try {
+      const response = await adminFetch(`${API_BASE}/api/admin/backups`, {
+        method: "POST",
+      });
+      if (!response.ok) throw new Error(await response.text());
+      setToast({ message: "Backup created", type: "success" });
+      fetchBackups();
+    } catch (err) {
+      setToast({ message: err.message, type: "error" });
+    } finally {
+      setRunning(false);
+    }
+  };
+
+  const handleDownload = async (file) => {
+    try {
+      const response = await adminFetch(
+        `${API_BASE}/api/admin/backups/${encodeURIComponent(file)}`,
+      );
+      if (!response.ok) throw new Error("Failed to download backup");
+      const url = URL.createObjectURL(await response.blob());
+      const link = document.createElement("a");
+      link.href = url;
+      link.download = file;
+      link.click();
+      URL.revokeObjectURL(url);
+    } catch (err) {
+      setToast({ message: err.message, type: "error" });
+    }
+  };
+
+  return (
+    <div className="bg-white border-2 border-amber-300 shadow-sm">
+      {toast && (
+        <Toast
+          message={toast.message}
+          type={toast.type}
+          onClose={() => setToast(null)}
+        />
+      )}
+      <div className="bg-amber-900 text-amber-50 px-6 py-3">
+        <h3 className="font-mono text-sm tracking-widest">DATABASE BACKUPS</h3>
+      </div>
+      <div className="p-6 space-y-4">
+        <button
+          onClick={handleBackup}
+          disabled={running}
+          className="bg-amber-900 text-amber-50 px-6 py-2 font-mono text-sm
+                   hover:bg-amber-800 disabled:opacity-50"
+        >
+          {running ? "Backing up..." : "Back Up Now"}
+        </button>
+        {backups.length > 0 && (
+          <table className="w-full font-mono text-sm">
+            <thead>
+              <tr className="text-amber-600 text-left border-b border-amber-200">
+                <th className="pr-4 pb-2">Created</th>
+                <th className="pr-4 pb-2">Size</th>
+                <th className="pr-4 pb-2">Schema</th>
+                <th className="pb-2"></th>
+              </tr>
+            </thead>
+            <tbody>
+              {backups.map((b) => (
+                <tr key={b.file} className="text-amber-800">
+                  <td className="pr-4 py-2 text-xs">
+                    {new Date(b.created_at).toLocaleString()}
+                  </td>
+                  <td className="pr-4 text-xs">
+                    {(b.size_bytes / 1024).toFixed(0)} KB
+                  </td>
+                  <td className="pr-4 text-xs">v{b.schema_version}</td>
+                  <td>
+                    <button
+                      onClick={() => handleDownload(b.file)}
+                      className="text-xs bg-amber-600 text-white px-2 py-1 hover:bg-amber-700"
+                    >
+                      Download
+                    </button>
+                  </td>
+                </tr>
+              ))}
+            </tbody>
+          </table>
+        )}
+      </div>
+    </div>
+  );
+}
+
 // Admin Settings Page
 export function AdminSettings() {
   const { adminFetch } = useAdminAuth();
@@ -372,11 +659,40 @@ export function AdminSettings() {
         { label: "Listen Address", value: settings.listen_addr },
         { label: "Static Directory", value: settings.static_dir },
         { label: "Log Level", value: settings.log_level },
+        { label: "Admin User", value: settings.admin_username },
         {
           label: "QRZ Integration",
           value: settings.qrz_enabled ? "Enabled" : "Disabled",
           status: settings.qrz_enabled,
         },
+        {
+          label: "ntfy Notifications",
+          value: settings.ntfy_enabled ? "Enabled" : "Disabled",
+          status: settings.ntfy_enabled,
+        },
+        {
+          label: "Rate Limiting",
+          value: settings.rate_limit_enabled ? "Enabled" : "Disabled",
+          status: settings.rate_limit_enabled,
+        },
+        {
+          label: "Trusted Proxies",
+          value: settings.trusted_proxies.length
+            ? settings.trusted_proxies.join(", ")
+            : "None",
+        },
+        {
+          label: "Dev Mode",
+          value: settings.dev_mode ? "On (insecure defaults allowed)" : "Off",
+          status: !settings.dev_mode,
+        },
+        {
+          label: "Scheduled Backups",
+          value: settings.backup_enabled
+            ? `Every ${settings.backup_interval_hours}h to ${settings.backup_dir}`
+            : "Disabled",
+          status: settings.backup_enabled,
+        },
       ]
     : [];
 
@@ -420,6 +736,8 @@ export function AdminSettings() {
         </p>
       </div>
       <EmailTemplateEditor />
+      <ApiTokenManager />
+      <BackupManager />
     </div>
   );
 }
@@ -0,0 +1,300 @@
+import React, { useState, useEffect } from "react";
+import { API_BASE } from "./shared.jsx";
+import { useAdminAuth } from "./admin-auth.jsx";
+
+const PLACEHOLDERS = [
+  "{{CALLSIGN}}",
+  "{{NAME}}",
+  "{{SPEED}}",
+  "{{CLASS}}",
+  "{{CLASSES}}",
+  "{{YEAR}}",
+  "{{DATE}}",
+  "{{CERT_NO}}",
+  "{{QR_CODE}}",
+  "{{METADATA}}",
+];
+
+// Editor for one certificate template, with a live preview rendered by the server
+function TemplateEditor({ template, tests, onClose, onSaved, setToast }) {
+  const { adminFetch } = useAdminAuth();
+  const [form, setForm] = useState(template);
+  const [history, setHistory] = useState([]);
+  const [previewTest, setPreviewTest] = useState("");
+  const [previewUrl, setPreviewUrl] = useState(null);
+  const [previewError, setPreviewError] = useState(null);
+  const [saving, setSaving] = useState(false);
+
+  useEffect(() => {
+    if (template.isNew) return;
+    adminFetch(`${API_BASE}/api/admin/certificate-templates/${template.id}`)
+      .then((res) => (res.ok ? res.json() : null))
+      .then((data) => data && setHistory(data.history))
+      .catch(() => {});
+  }, [template.id, template.isNew, adminFetch]);
+
+  // Release the previous preview image when it is replaced
+  useEffect(() => {
+    return () => previewUrl && URL.revokeObjectURL(previewUrl);
+  }, [previewUrl]);
+
+  const handlePreview = async () => {
+    try {
+      const response = await adminFetch(`${API_BASE}/api/admin/certificate-templates/preview`, {
+        method: "POST",
+        body: JSON.stringify({ svg: form.svg, test_id: previewTest || null }),
+      });
+      if (!response.ok) throw new Error(await response.text());
+      const svg = await response.text();
+      setPreviewUrl(URL.createObjectURL(new Blob([svg], { type: "image/svg+xml" })));
+      setPreviewError(null);
+    } catch (err) {
+      setPreviewError(err.message);
+    }
+  };
+
+  const handleLoadVersion = async (version) => {
+    try {
+      const response = await adminFetch(
+        `${API_BASE}/api/admin/certificate-templates/${template.id}/versions/${version}`
+      );
+      if (!response.ok) throw new Error("Failed to load version");
+      const data = await response.json();
+      setForm({ ...form, svg: data.svg, note: `Restore version ${version}` });
+    } catch (err) {
+      setToast({ message: err.message, type: "error" });
+    }
+  };
+
+  const handleSave = async () => {
+    setSaving(true);
+    try {
+      const url = form.isNew
+        ? `${API_BASE}/api/admin/certificate-templates`
+        : `${API_BASE}/api/admin/certificate-templates/${form.id}`;
+      const body = { name: form.name, svg: form.svg, note: form.note || null };
+      const response = await adminFetch(url, {
+        method: form.isNew ? "POST" : "PUT",
+        body: JSON.stringify(form.isNew ? { ...body, id: form.id } : body),
+      });
+      if (!response.ok) throw new Error(await response.text());
+      const data = await response.json();
+      setToast({ message: `Template saved as version ${data.version}`, type: "success" });
+      onSaved();
+    } catch (err) {
+      setToast({ message: err.message, type: "error" });
+    } finally {
+      setSaving(false);
+    }
+  };
+
+  return (
+    <div className="fixed inset-0 z-50 flex items-center justify-center p-4">
+      <div className="absolute inset-0 bg-black/50" onClick={onClose} />
+
+      <div className="relative bg-amber-50 border-4 border-amber-800 shadow-2xl max-w-6xl w-full max-h-[90vh] overflow-hidden flex flex-col">
+        <div className="bg-amber-900 text-amber-50 px-6 py-4 flex items-center justify-between">
+          <h2 className="font-mono text-sm tracking-widest">
+            {form.isNew ? "NEW CERTIFICATE TEMPLATE" : `CERTIFICATE TEMPLATE: ${form.id}`}
+          </h2>
+          <button
+            onClick={onClose}
+            className="text-amber-50 hover:text-amber-200 font-mono text-xl leading-none"
+          >
+            x
+          </button>
+        </div>
+
+        <div className="flex-1 overflow-y-auto p-6 grid md:grid-cols-2 gap-6">
+          <div className="space-y-4">
+            <div className="flex gap-2">
+              {form.isNew && (
+                <input
+                  type="text"
+                  value={form.id}
+                  onChange={(e) => setForm({ ...form, id: e.target.value.toLowerCase() })}
+                  placeholder="13wpm-general"
+                  className="border-2 border-amber-300 px-3 py-2 font-mono text-sm w-40"
+                />
+              )}
+              <input
+                type="text"
+                value={form.name}
+                onChange={(e) => setForm({ ...form, name: e.target.value })}
+                placeholder="Template name"
+                className="border-2 border-amber-300 px-3 py-2 font-mono text-sm flex-1"
+              />
+            </div>
+            <textarea
+              value={form.svg}
+              onChange={(e) => setForm({ ...form, svg: e.target.value })}
+              spellCheck={false}
+              className="w-full border-2 border-amber-300 bg-white p-3 font-mono text-xs h-96 focus:border-amber-500 focus:outline-none"
+            />
+            <p className="font-mono text-xs text-amber-700">
+              Placeholders: {PLACEHOLDERS.join(" ")}
+            </p>
+            <input
+              type="text"
+              value={form.note || ""}
+              onChange={(e) => setForm({ ...form, note: e.target.value })}
+              placeholder="What changed in this version"
+              className="w-full border-2 border-amber-300 px-3 py-2 font-mono text-sm"
+            />
+            <div className="flex gap-4">
+              <button
+                type="button"
+                onClick={onClose}
+                className="flex-1 px-4 py-3 font-mono text-sm tracking-widest border-2 border-amber-300 text-amber-800 hover:border-amber-500 hover:bg-amber-100 transition-all"
+              >
+                Cancel
+              </button>
+              <button
+                type="button"
+                onClick={handleSave}
+                disabled={saving}
+                className="flex-1 px-4 py-3 font-mono text-sm tracking-widest bg-amber-700 text-amber-50 hover:bg-amber-800 transition-all disabled:opacity-50"
+              >
+                {saving ? "Saving..." : "Save Version"}
+              </button>
+            </div>
+          </div>
+
+          <div className="space-y-4">
+            <div className="flex gap-2">
+              <select
+                value={previewTest}
+                onChange={(e) => setPreviewTest(e.target.value)}
+                className="border-2 border-amber-300 px-3 py-2 font-mono text-sm flex-1"
+              >
+                <option value="">Sample data</option>
+                {tests.map((test) => (
+                  <option key={test.id} value={test.id}>
+                    {test.title} ({test.speed_wpm} WPM)
+                  </option>
+                ))}
+              </select>
+              <button
+                onClick={handlePreview}
+                className="bg-amber-900 text-amber-50 px-4 py-2 font-mono text-sm hover:bg-amber-800"
+              >
+                Preview
+              </button>
+            </div>
+            {previewError && (
+              <p className="font-mono text-xs text-red-700 bg-red-50 border-2 border-red-300 p-3">
+                {previewError}
+              </p>
+            )}
+            {previewUrl && (
+              <img src={previewUrl} alt="Certificate preview" className="w-full border-2 border-amber-300" />
+            )}
+            {history.length > 0 && (
+              <div>
+                <h4 className="font-mono text-xs tracking-widest text-amber-700 mb-2">VERSIONS</h4>
+                <div className="space-y-1">
+                  {history.map((revision) => (
+                    <div key={revision.version} className="flex items-center justify-between bg-white px-3 py-2 font-mono text-xs">
+                      <span className="text-amber-900">
+                        v{revision.version} · {new Date(revision.created_at).toLocaleString()}
+                        {revision.note && ` · ${revision.note}`}
+                      </span>
+                      <button
+                        onClick={() => handleLoadVersion(revision.version)}
+                        className="text-amber-600 hover:text-amber-800"
+                      >
+                        Load
+                      </button>
+                    </div>
+                  ))}
+                </div>
+              </div>
+            )}
+          </div>
+        </div>
+      </div>
+    </div>
+  );
+}
+
+// Certificate templates, bound to tests from the test list
+export function CertificateTemplateManager({ templates, tests, onChange, setToast }) {
+  const { adminFetch } = useAdminAuth();
+  const [editing, setEditing] = useState(null);
+
+  const handleNew = async () => {
+    try {
+      const response = await adminFetch(`${API_BASE}/api/admin/certificate-templates/builtin`);
+      if (!response.ok) throw new Error("Failed to load the built-in template");
+      const builtin = await response.json();
+      setEditing({ isNew: true, id: "", name: "", svg: builtin.svg, note: "" });
+    } catch (err) {
+      setToast({ message: err.message, type: "error" });
+    }
+  };
+
+  const handleEdit = async (id) => {
+    try {
+      const response = await adminFetch(`${API_BASE}/api/admin/certificate-templates/${id}`);
+      if (!response.ok) throw new Error("Failed to load template");
+      const data = await response.json();
+      setEditing({ isNew: false, id, name: data.name, svg: data.svg, note: "" });
+    } catch (err) {
+      setToast({ message: err.message, type: "error" });
+    }
+  };
+
+  return (
+    <div className="bg-white border-2 border-amber-300 shadow-sm mt-6">
+      <div className="bg-amber-900 text-amber-50 px-6 py-3 flex items-center justify-between">
+        <h3 className="font-mono text-sm tracking-widest">CERTIFICATE TEMPLATES</h3>
+        <button
+          onClick={handleNew}
+          className="font-mono text-xs border border-amber-300 px-3 py-1 hover:bg-amber-800"
+        >
+          New Template
+        </button>
+      </div>
+      <div className="p-4">
+        {templates.length === 0 ? (
+          <p className="font-serif text-amber-800 italic text-sm">
+            No templates yet. Tests use the built-in Know-Code Extra design.
+          </p>
+        ) : (
+          <div className="space-y-2">
+            {templates.map((template) => (
+              <div key={template.id} className="flex items-center justify-between bg-amber-50 px-3 py-2">
+                <div className="font-mono text-sm text-amber-800">
+                  {template.name}
+                  <span className="text-xs text-amber-600 ml-3">
+                    {template.id} · v{template.current_version} · {template.test_count} test
+                    {template.test_count !== 1 ? "s" : ""}
+                  </span>
+                </div>
+                <button
+                  onClick={() => handleEdit(template.id)}
+                  className="text-amber-600 hover:text-amber-800 font-mono text-sm"
+                >
+                  Edit
+                </button>
+              </div>
+            ))}
+          </div>
+        )}
+      </div>
+
+      {editing && (
+        <TemplateEditor
+          template={editing}
+          tests={tests}
+          onClose={() => setEditing(null)}
+          onSaved={() => {
+            setEditing(null);
+            onChange();
+          }}
+          setToast={setToast}
+        />
+      )}
+    </div>
+  );
+}
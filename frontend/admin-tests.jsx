@@ -1,6 +1,7 @@
 import React, { useState, useEffect, useCallback } from "react";
 import { API_BASE, getSegmentColor } from "./shared.jsx";
 import { useAdminAuth } from "./admin-auth.jsx";
+import { CertificateTemplateManager } from "./admin-templates.jsx";
 
 // Helper to parse time input (mm:ss or seconds) to seconds
 const parseTimeToSeconds = (value) => {
@@ -24,15 +25,18 @@ const formatSecondsToTime = (seconds) => {
   return `${mins}:${secs.toString().padStart(2, "0")}`;
 };
 
+const MAX_OPTIONS = 8;
+const optionLetter = (i) => String.fromCharCode(65 + i);
+
 export function QuestionForm({ question, onSave, onCancel }) {
   const [formData, setFormData] = useState({
     question_number: question?.question_number || "",
     question_text: question?.question_text || "",
-    option_a: question?.option_a || "",
-    option_b: question?.option_b || "",
-    option_c: question?.option_c || "",
-    option_d: question?.option_d || "",
+    question_type: question?.question_type || "choice",
+    options: question?.options?.length ? question.options : ["", "", "", ""],
     correct_option: question?.correct_option || "A",
+    accepted_answers: (question?.accepted_answers || []).join("\n"),
+    topic: question?.topic || "",
   });
   const [saving, setSaving] = useState(false);
 
@@ -40,13 +44,55 @@ export function QuestionForm({ question, onSave, onCancel }) {
     setFormData((prev) => ({ ...prev, [field]: value }));
   };
 
+  const handleTypeChange = (questionType) => {
+    const correct = { choice: "A", true_false: "TRUE", text: "" };
+    setFormData((prev) => ({
+      ...prev,
+      question_type: questionType,
+      correct_option: correct[questionType],
+    }));
+  };
+
+  const handleOptionChange = (index, value) => {
+    setFormData((prev) => ({
+      ...prev,
+      options: prev.options.map((option, i) => (i === index ? value : option)),
+    }));
+  };
+
+  const handleAddOption = () => {
+    setFormData((prev) => ({ ...prev, options: [...prev.options, ""] }));
+  };
+
+  // Removing an option moves the correct letter with the options after it
+  const handleRemoveOption = (index) => {
+    setFormData((prev) => {
+      const correct = prev.correct_option.charCodeAt(0) - 65;
+      const moved = correct > index ? correct - 1 : correct === index ? 0 : correct;
+      return {
+        ...prev,
+        options: prev.options.filter((_, i) => i !== index),
+        correct_option: optionLetter(moved),
+      };
+    });
+  };
+
   const handleSubmit = async (e) => {
     e.preventDefault();
     setSaving(true);
     try {
+      const { question_type } = formData;
       await onSave({
         ...formData,
         question_number: parseInt(formData.question_number, 10),
+        options: question_type === "choice" ? formData.options : [],
+        accepted_answers:
+          question_type === "text"
+            ? formData.accepted_answers
+                .split("\n")
+                .map((answer) => answer.trim())
+                .filter(Boolean)
+            : [],
       });
     } finally {
       setSaving(false);
@@ -82,71 +128,136 @@ export function QuestionForm({ question, onSave, onCancel }) {
         />
       </div>
 
-      <div className="grid grid-cols-1 md:grid-cols-2 gap-4">
-        <div>
-          <label className="font-mono text-xs text-amber-700 block mb-1">
-            OPTION A
-          </label>
-          <input
-            type="text"
-            value={formData.option_a}
-            onChange={(e) => handleChange("option_a", e.target.value)}
-            className="w-full border-2 border-amber-300 px-3 py-2 font-mono text-amber-900 focus:border-amber-500 focus:outline-none"
-            required
-          />
-        </div>
-        <div>
-          <label className="font-mono text-xs text-amber-700 block mb-1">
-            OPTION B
-          </label>
-          <input
-            type="text"
-            value={formData.option_b}
-            onChange={(e) => handleChange("option_b", e.target.value)}
-            className="w-full border-2 border-amber-300 px-3 py-2 font-mono text-amber-900 focus:border-amber-500 focus:outline-none"
-            required
-          />
-        </div>
-        <div>
-          <label className="font-mono text-xs text-amber-700 block mb-1">
-            OPTION C
-          </label>
-          <input
-            type="text"
-            value={formData.option_c}
-            onChange={(e) => handleChange("option_c", e.target.value)}
-            className="w-full border-2 border-amber-300 px-3 py-2 font-mono text-amber-900 focus:border-amber-500 focus:outline-none"
-            required
-          />
-        </div>
+      <div>
+        <label className="font-mono text-xs text-amber-700 block mb-1">
+          QUESTION TYPE
+        </label>
+        <select
+          value={formData.question_type}
+          onChange={(e) => handleTypeChange(e.target.value)}
+          className="w-full border-2 border-amber-300 px-3 py-2 font-mono text-amber-900 focus:border-amber-500 focus:outline-none bg-white"
+        >
+          <option value="choice">Multiple choice</option>
+          <option value="true_false">True / false</option>
+          <option value="text">Free text</option>
+        </select>
+      </div>
+
+      {formData.question_type === "choice" && (
+        <>
+          <div className="grid grid-cols-1 md:grid-cols-2 gap-4">
+            {formData.options.map((option, i) => (
+              <div key={i}>
+                <label className="font-mono text-xs text-amber-700 block mb-1">
+                  OPTION {optionLetter(i)}
+                </label>
+                <div className="flex gap-2">
+                  <input
+                    type="text"
+                    value={option}
+                    onChange={(e) => handleOptionChange(i, e.target.value)}
+                    className="w-full border-2 border-amber-300 px-3 py-2 font-mono text-amber-900 focus:border-amber-500 focus:outline-none"
+                    required
+                  />
+                  {formData.options.length > 2 && (
+                    <button
+                      type="button"
+                      onClick={() => handleRemoveOption(i)}
+                      className="px-3 font-mono text-xs border-2 border-amber-300 text-amber-800 hover:border-red-400 hover:text-red-700 transition-all"
+                      title="Remove option"
+                    >
+                      ×
+                    </button>
+                  )}
+                </div>
+              </div>
+            ))}
+          </div>
+          {formData.options.length < MAX_OPTIONS && (
+            <button
+              type="button"
+              onClick={handleAddOption}
+              className="px-3 py-1 font-mono text-xs border-2 border-amber-300 text-amber-800 hover:border-amber-500 hover:bg-amber-100 transition-all"
+            >
+              + Add Option
+            </button>
+          )}
+
+          <div>
+            <label className="font-mono text-xs text-amber-700 block mb-1">
+              CORRECT OPTION
+            </label>
+            <select
+              value={formData.correct_option}
+              onChange={(e) => handleChange("correct_option", e.target.value)}
+              className="w-full border-2 border-amber-300 px-3 py-2 font-mono text-amber-900 focus:border-amber-500 focus:outline-none bg-white"
+            >
+              {formData.options.map((_, i) => (
+                <option key={i} value={optionLetter(i)}>
+                  {optionLetter(i)}
+                </option>
+              ))}
+            </select>
+          </div>
+        </>
+      )}
+
+      {formData.question_type === "true_false" && (
         <div>
           <label className="font-mono text-xs text-amber-700 block mb-1">
-            OPTION D
+            CORRECT ANSWER
           </label>
-          <input
-            type="text"
-            value={formData.option_d}
-            onChange={(e) => handleChange("option_d", e.target.value)}
-            className="w-full border-2 border-amber-300 px-3 py-2 font-mono text-amber-900 focus:border-amber-500 focus:outline-none"
-            required
-          />
+          <select
+            value={formData.correct_option}
+            onChange={(e) => handleChange("correct_option", e.target.value)}
+            className="w-full border-2 border-amber-300 px-3 py-2 font-mono text-amber-900 focus:border-amber-500 focus:outline-none bg-white"
+          >
+            <option value="TRUE">True</option>
+            <option value="FALSE">False</option>
+          </select>
         </div>
-      </div>
+      )}
+
+      {formData.question_type === "text" && (
+        <>
+          <div>
+            <label className="font-mono text-xs text-amber-700 block mb-1">
+              CORRECT ANSWER
+            </label>
+            <input
+              type="text"
+              value={formData.correct_option}
+              onChange={(e) => handleChange("correct_option", e.target.value)}
+              className="w-full border-2 border-amber-300 px-3 py-2 font-mono text-amber-900 focus:border-amber-500 focus:outline-none"
+              required
+            />
+          </div>
+          <div>
+            <label className="font-mono text-xs text-amber-700 block mb-1">
+              ALSO ACCEPT (ONE PER LINE)
+            </label>
+            <textarea
+              value={formData.accepted_answers}
+              onChange={(e) => handleChange("accepted_answers", e.target.value)}
+              placeholder="Case and extra spaces are ignored"
+              className="w-full border-2 border-amber-300 px-3 py-2 font-mono text-amber-900 focus:border-amber-500 focus:outline-none resize-y"
+              rows={3}
+            />
+          </div>
+        </>
+      )}
 
       <div>
         <label className="font-mono text-xs text-amber-700 block mb-1">
-          CORRECT OPTION
+          TOPIC (OPTIONAL)
         </label>
-        <select
-          value={formData.correct_option}
-          onChange={(e) => handleChange("correct_option", e.target.value)}
-          className="w-full border-2 border-amber-300 px-3 py-2 font-mono text-amber-900 focus:border-amber-500 focus:outline-none bg-white"
-        >
-          <option value="A">A</option>
-          <option value="B">B</option>
-          <option value="C">C</option>
-          <option value="D">D</option>
-        </select>
+        <input
+          type="text"
+          value={formData.topic}
+          onChange={(e) => handleChange("topic", e.target.value)}
+          placeholder="e.g. operator name, rig, weather"
+          className="w-full border-2 border-amber-300 px-3 py-2 font-mono text-amber-900 focus:border-amber-500 focus:outline-none"
+        />
       </div>
 
       <div className="flex gap-4 pt-4">
@@ -304,6 +415,32 @@ export function SegmentEditor({ testId, segments: initialSegments, onClose, onSa
   const [isAdding, setIsAdding] = useState(false);
   const [saving, setSaving] = useState(false);
   const [error, setError] = useState(null);
+  const [importing, setImporting] = useState(false);
+  const [markers, setMarkers] = useState("");
+
+  // Convert marker text to segments on the server; nothing is saved until "Save All Changes"
+  const handlePreviewMarkers = async () => {
+    setError(null);
+    try {
+      const response = await adminFetch(`${API_BASE}/api/admin/segments/preview`, {
+        method: "POST",
+        body: JSON.stringify({ markers }),
+      });
+      if (!response.ok) throw new Error(await response.text());
+      const preview = await response.json();
+      setSegments(preview.segments);
+      setImporting(false);
+      setMarkers("");
+    } catch (err) {
+      setError(err.message);
+    }
+  };
+
+  const handleMarkerFile = async (e) => {
+    const file = e.target.files[0];
+    e.target.value = "";
+    if (file) setMarkers(await file.text());
+  };
 
   // Calculate total duration for timeline preview
   const maxEndTime = segments.reduce((max, seg) => {
@@ -451,18 +588,62 @@ export function SegmentEditor({ testId, segments: initialSegments, onClose, onSa
                 onCancel={() => setEditingIndex(null)}
               />
             </div>
+          ) : importing ? (
+            <div>
+              <h3 className="font-serif text-xl font-bold text-amber-900 mb-2">
+                Import Markers
+              </h3>
+              <p className="font-mono text-xs text-amber-700 mb-3">
+                Paste lines like "1:02-2:06 - practice" ("start" and "end" allowed) or an
+                Audacity label export. The preview replaces the segments below.
+              </p>
+              <textarea
+                value={markers}
+                onChange={(e) => setMarkers(e.target.value)}
+                placeholder={"start-1:02 - intro\n1:02-2:06 - practice\n3:41-8:51 - test\n8:51-end - outro"}
+                spellCheck={false}
+                className="w-full border-2 border-amber-300 bg-white p-3 font-mono text-xs h-48 focus:border-amber-500 focus:outline-none"
+              />
+              <div className="flex gap-4 mt-3">
+                <label className="px-4 py-2 font-mono text-sm border-2 border-amber-300 text-amber-800 hover:border-amber-500 hover:bg-amber-100 transition-all cursor-pointer">
+                  Open File
+                  <input type="file" accept=".txt" onChange={handleMarkerFile} className="hidden" />
+                </label>
+                <button
+                  onClick={() => setImporting(false)}
+                  className="flex-1 px-4 py-2 font-mono text-sm border-2 border-amber-300 text-amber-800 hover:border-amber-500 hover:bg-amber-100 transition-all"
+                >
+                  Cancel
+                </button>
+                <button
+                  onClick={handlePreviewMarkers}
+                  disabled={!markers.trim()}
+                  className="flex-1 px-4 py-2 font-mono text-sm tracking-widest bg-amber-700 text-amber-50 hover:bg-amber-800 transition-all disabled:opacity-50"
+                >
+                  Preview
+                </button>
+              </div>
+            </div>
           ) : (
             <div>
               <div className="flex items-center justify-between mb-4">
                 <h3 className="font-serif text-xl font-bold text-amber-900">
                   Segments ({segments.length})
                 </h3>
-                <button
-                  onClick={() => setIsAdding(true)}
-                  className="px-4 py-2 font-mono text-sm tracking-widest bg-amber-700 text-amber-50 hover:bg-amber-800 transition-all"
-                >
-                  Add Segment
-                </button>
+                <div className="flex gap-2">
+                  <button
+                    onClick={() => setImporting(true)}
+                    className="px-4 py-2 font-mono text-sm tracking-widest border-2 border-amber-300 text-amber-800 hover:border-amber-500 hover:bg-amber-100 transition-all"
+                  >
+                    Import Markers
+                  </button>
+                  <button
+                    onClick={() => setIsAdding(true)}
+                    className="px-4 py-2 font-mono text-sm tracking-widest bg-amber-700 text-amber-50 hover:bg-amber-800 transition-all"
+                  >
+                    Add Segment
+                  </button>
+                </div>
               </div>
 
               {segments.length === 0 ? (
@@ -544,8 +725,10 @@ export function TestManager() {
   const [editingQuestions, setEditingQuestions] = useState(null);
   const [editingSegments, setEditingSegments] = useState(null);
   const [editingTest, setEditingTest] = useState(null);
+  const [editingSchedule, setEditingSchedule] = useState(null);
   const [toggling, setToggling] = useState(null);
   const [toast, setToast] = useState(null);
+  const [templates, setTemplates] = useState([]);
 
   // Prosign state
   const [prosigns, setProsigns] = useState([]);
@@ -567,6 +750,61 @@ export function TestManager() {
     }
   }, [adminFetch]);
 
+  const fetchTemplates = useCallback(async () => {
+    try {
+      const response = await adminFetch(`${API_BASE}/api/admin/certificate-templates`);
+      if (!response.ok) throw new Error("Failed to fetch certificate templates");
+      setTemplates(await response.json());
+    } catch (err) {
+      setToast({ message: err.message, type: "error" });
+    }
+  }, [adminFetch]);
+
+  const handleBindTemplate = async (test, templateId) => {
+    try {
+      const response = await adminFetch(`${API_BASE}/api/admin/tests/${test.id}`, {
+        method: "PUT",
+        body: JSON.stringify({ certificate_template_id: templateId }),
+      });
+      if (!response.ok) throw new Error(await response.text());
+      await Promise.all([fetchTests(), fetchTemplates()]);
+      setToast({ message: "Certificate template updated", type: "success" });
+    } catch (err) {
+      setToast({ message: err.message, type: "error" });
+    }
+  };
+
+  const handleDrawCount = async (test, value) => {
+    try {
+      const response = await adminFetch(`${API_BASE}/api/admin/tests/${test.id}`, {
+        method: "PUT",
+        body: JSON.stringify({ draw_count: parseInt(value, 10) || 0 }),
+      });
+      if (!response.ok) throw new Error(await response.text());
+      await fetchTests();
+      setToast({ message: "Draw saved to the draft; publish to apply it", type: "success" });
+    } catch (err) {
+      setToast({ message: err.message, type: "error" });
+    }
+  };
+
+  const handleToggleShuffle = async (test) => {
+    try {
+      const response = await adminFetch(`${API_BASE}/api/admin/tests/${test.id}`, {
+        method: "PUT",
+        body: JSON.stringify({ shuffle: !test.shuffle }),
+      });
+      if (!response.ok) throw new Error(await response.text());
+      await fetchTests();
+      setToast({
+        message: test.shuffle ? "Questions keep their order" : "Questions and options are shuffled per session",
+        type: "success",
+      });
+    } catch (err) {
+      setToast({ message: err.message, type: "error" });
+    }
+  };
+
   const fetchProsigns = async () => {
     setLoadingProsigns(true);
     try {
@@ -649,8 +887,9 @@ export function TestManager() {
 
   useEffect(() => {
     fetchTests();
+    fetchTemplates();
     fetchProsigns();
-  }, [fetchTests]);
+  }, [fetchTests, fetchTemplates]);
 
   const handleToggleActive = async (test) => {
     setToggling(test.id);
@@ -671,6 +910,98 @@ export function TestManager() {
     }
   };
 
+  const handleClone = async (test) => {
+    const id = prompt(`Id for the copy of ${test.id}:`, `${test.id}-b`);
+    if (!id) return;
+    const title = prompt("Title for the copy:", test.title);
+    if (!title) return;
+    const duplicate_audio = confirm(
+      "Give the copy its own copy of the audio file? Cancel to share the original's."
+    );
+    try {
+      const response = await adminFetch(`${API_BASE}/api/admin/tests/${test.id}/clone`, {
+        method: "POST",
+        body: JSON.stringify({ id, title, duplicate_audio }),
+      });
+      if (!response.ok) throw new Error(await response.text());
+      const cloned = await response.json();
+      setToast({
+        message: `Cloned ${test.id} as ${cloned.id} with ${cloned.questions} questions; it is inactive until published and activated`,
+        type: "success",
+      });
+      await fetchTests();
+    } catch (err) {
+      setToast({ message: err.message, type: "error" });
+    }
+  };
+
+  const handlePurge = async (test) => {
+    if (!confirm(`Permanently delete ${test.id} with its questions, revisions and audio? This cannot be undone.`)) return;
+    try {
+      const response = await adminFetch(`${API_BASE}/api/admin/tests/${test.id}?purge=true`, {
+        method: "DELETE",
+      });
+      if (!response.ok) throw new Error(await response.text());
+      setToast({ message: `Purged ${test.id}`, type: "success" });
+      await fetchTests();
+    } catch (err) {
+      setToast({ message: err.message, type: "error" });
+    }
+  };
+
+  const handlePublish = async (test) => {
+    const note = prompt(`Publish ${test.id} so candidates see the current draft. What changed?`);
+    if (note === null) return;
+    try {
+      const response = await adminFetch(`${API_BASE}/api/admin/tests/${test.id}/publish`, {
+        method: "POST",
+        body: JSON.stringify({ note }),
+      });
+      if (!response.ok) throw new Error(await response.text());
+      const published = await response.json();
+      setToast({ message: `Published ${test.id} revision ${published.revision}`, type: "success" });
+      await fetchTests();
+    } catch (err) {
+      setToast({ message: err.message, type: "error" });
+    }
+  };
+
+  const handleExportBundle = async (test) => {
+    try {
+      const response = await adminFetch(`${API_BASE}/api/admin/tests/${test.id}/bundle`);
+      if (!response.ok) throw new Error(await response.text());
+      const url = URL.createObjectURL(await response.blob());
+      const link = document.createElement("a");
+      link.href = url;
+      link.download = `${test.id}.tar.gz`;
+      link.click();
+      URL.revokeObjectURL(url);
+    } catch (err) {
+      setToast({ message: err.message, type: "error" });
+    }
+  };
+
+  const handleImportBundle = async (e) => {
+    const file = e.target.files[0];
+    e.target.value = "";
+    if (!file) return;
+    try {
+      const response = await adminFetch(`${API_BASE}/api/admin/test-bundles`, {
+        method: "POST",
+        body: file,
+      });
+      if (!response.ok) throw new Error(await response.text());
+      const summary = await response.json();
+      setToast({
+        message: `Imported ${summary.id} with ${summary.questions} questions as a draft; publish it to go live`,
+        type: "success",
+      });
+      await fetchTests();
+    } catch (err) {
+      setToast({ message: err.message, type: "error" });
+    }
+  };
+
   if (loading) {
     return (
       <div className="flex items-center justify-center h-64">
@@ -709,9 +1040,15 @@ export function TestManager() {
       )}
 
       <div className="bg-white border-2 border-amber-300 p-6">
-        <h3 className="font-serif text-xl font-bold text-amber-900 mb-4">
-          Tests ({tests.length})
-        </h3>
+        <div className="flex items-center justify-between mb-4">
+          <h3 className="font-serif text-xl font-bold text-amber-900">
+            Tests ({tests.length})
+          </h3>
+          <label className="px-3 py-1 font-mono text-xs border-2 border-amber-300 text-amber-800 hover:border-amber-500 hover:bg-amber-100 transition-all cursor-pointer">
+            Import Bundle
+            <input type="file" accept=".tar.gz,.tgz" onChange={handleImportBundle} className="hidden" />
+          </label>
+        </div>
 
         {tests.length === 0 ? (
           <div className="text-center py-8">
@@ -750,6 +1087,14 @@ export function TestManager() {
                     }`}>
                       {test.active ? "Active" : "Inactive"}
                     </span>
+                    <span className={`font-mono text-xs px-2 py-0.5 ${
+                      test.published_revision
+                        ? "bg-amber-200 text-amber-800"
+                        : "bg-red-100 text-red-700"
+                    }`}>
+                      {test.published_revision ? `Revision ${test.published_revision}` : "Draft only"}
+                    </span>
+                    <AvailabilityBadge test={test} />
                   </div>
                   <p className="font-serif text-amber-900">
                     {test.title || "Untitled Test"}
@@ -766,6 +1111,44 @@ export function TestManager() {
                       No copy text set
                     </p>
                   )}
+                  <label className="font-mono text-xs text-amber-600 mt-1 flex items-center gap-2">
+                    Certificate:
+                    <select
+                      value={test.certificate_template_id || ""}
+                      onChange={(e) => handleBindTemplate(test, e.target.value)}
+                      className="border border-amber-300 px-1 py-0.5 font-mono text-xs"
+                    >
+                      <option value="">Built-in design</option>
+                      {templates.map((template) => (
+                        <option key={template.id} value={template.id}>
+                          {template.name}
+                        </option>
+                      ))}
+                    </select>
+                  </label>
+                  <label className="font-mono text-xs text-amber-600 mt-1 flex items-center gap-2">
+                    <input
+                      type="checkbox"
+                      checked={!!test.shuffle}
+                      onChange={() => handleToggleShuffle(test)}
+                    />
+                    Shuffle questions and options per session
+                  </label>
+                  <label className="font-mono text-xs text-amber-600 mt-1 flex items-center gap-2">
+                    Questions per session:
+                    <input
+                      type="number"
+                      min="0"
+                      defaultValue={test.draw_count || ""}
+                      placeholder="all"
+                      onBlur={(e) => {
+                        if ((parseInt(e.target.value, 10) || 0) !== (test.draw_count || 0)) {
+                          handleDrawCount(test, e.target.value);
+                        }
+                      }}
+                      className="w-16 border border-amber-300 px-1 py-0.5 font-mono text-xs"
+                    />
+                  </label>
                 </div>
                 <div className="flex gap-2 shrink-0">
                   <button
@@ -786,6 +1169,30 @@ export function TestManager() {
                   >
                     Segments
                   </button>
+                  <button
+                    onClick={() => setEditingSchedule(test)}
+                    className="px-3 py-1 font-mono text-xs border-2 border-amber-300 text-amber-800 hover:border-amber-500 hover:bg-amber-100 transition-all"
+                  >
+                    Schedule
+                  </button>
+                  <button
+                    onClick={() => handlePublish(test)}
+                    className="px-3 py-1 font-mono text-xs border-2 border-green-300 text-green-800 hover:border-green-500 hover:bg-green-100 transition-all"
+                  >
+                    Publish
+                  </button>
+                  <button
+                    onClick={() => handleExportBundle(test)}
+                    className="px-3 py-1 font-mono text-xs border-2 border-amber-300 text-amber-800 hover:border-amber-500 hover:bg-amber-100 transition-all"
+                  >
+                    Export
+                  </button>
+                  <button
+                    onClick={() => handleClone(test)}
+                    className="px-3 py-1 font-mono text-xs border-2 border-amber-300 text-amber-800 hover:border-amber-500 hover:bg-amber-100 transition-all"
+                  >
+                    Clone
+                  </button>
                   <button
                     onClick={() => handleToggleActive(test)}
                     disabled={toggling === test.id}
@@ -799,6 +1206,14 @@ export function TestManager() {
                   >
                     {toggling === test.id ? "..." : test.active ? "Deactivate" : "Activate"}
                   </button>
+                  {!test.active && (
+                    <button
+                      onClick={() => handlePurge(test)}
+                      className="px-3 py-1 font-mono text-xs border-2 border-red-300 text-red-800 hover:border-red-500 hover:bg-red-100 transition-all"
+                    >
+                      Purge
+                    </button>
+                  )}
                 </div>
               </div>
             ))}
@@ -806,6 +1221,13 @@ export function TestManager() {
         )}
       </div>
 
+      <CertificateTemplateManager
+        templates={templates}
+        tests={tests}
+        onChange={fetchTemplates}
+        setToast={setToast}
+      />
+
       {/* Prosign Mappings */}
       <div className="bg-white border-2 border-amber-300 shadow-sm mt-6">
         <div className="bg-amber-900 text-amber-50 px-6 py-3 flex items-center justify-between">
@@ -917,6 +1339,14 @@ export function TestManager() {
         />
       )}
 
+      {editingSchedule && (
+        <ScheduleEditor
+          test={editingSchedule}
+          onClose={() => setEditingSchedule(null)}
+          onSave={() => fetchTests()}
+        />
+      )}
+
       {/* Test Edit Modal */}
       {editingTest && (
         <div className="fixed inset-0 z-50 flex items-center justify-center p-4">
@@ -982,6 +1412,159 @@ export function TestManager() {
   );
 }
 
+// Schedule times are edited in UTC, as the server stores them
+const toUtcInput = (iso) => (iso ? iso.slice(0, 16) : "");
+const fromUtcInput = (value) => (value ? `${value}:00Z` : null);
+
+const AVAILABILITY_STYLES = {
+  open: "bg-green-200 text-green-800",
+  upcoming: "bg-amber-200 text-amber-800",
+  expired: "bg-gray-200 text-gray-600",
+};
+
+// Badge for a test's scheduled availability, shown when it has a schedule
+export const AvailabilityBadge = ({ test }) => {
+  const { schedule, availability } = test;
+  if (!schedule?.available_from && !schedule?.available_until && !schedule?.windows?.length) {
+    return null;
+  }
+  const label = {
+    open: availability.closes_at ? `Open until ${availability.closes_at.slice(0, 16).replace("T", " ")} UTC` : "Open",
+    upcoming: `Opens ${availability.opens_at?.slice(0, 16).replace("T", " ")} UTC`,
+    expired: "Expired",
+  }[availability.status];
+  return (
+    <span className={`font-mono text-xs px-2 py-0.5 ${AVAILABILITY_STYLES[availability.status]}`}>
+      {label}
+    </span>
+  );
+};
+
+// Schedule Editor Component for a test's availability dates and recurring windows
+export function ScheduleEditor({ test, onClose, onSave }) {
+  const { adminFetch } = useAdminAuth();
+  const [from, setFrom] = useState(toUtcInput(test.schedule?.available_from));
+  const [until, setUntil] = useState(toUtcInput(test.schedule?.available_until));
+  const [windows, setWindows] = useState(
+    (test.schedule?.windows || []).map((w) => ({ ...w, start: toUtcInput(w.start), end: toUtcInput(w.end) }))
+  );
+  const [error, setError] = useState(null);
+  const [saving, setSaving] = useState(false);
+
+  const updateWindow = (index, field, value) => {
+    setWindows((prev) => prev.map((w, i) => (i === index ? { ...w, [field]: value } : w)));
+  };
+
+  const handleSave = async () => {
+    setSaving(true);
+    try {
+      const schedule = {
+        available_from: fromUtcInput(from),
+        available_until: fromUtcInput(until),
+        windows: windows.map((w) => ({ ...w, start: fromUtcInput(w.start), end: fromUtcInput(w.end) })),
+      };
+      const response = await adminFetch(`${API_BASE}/api/admin/tests/${test.id}`, {
+        method: "PUT",
+        body: JSON.stringify({ schedule }),
+      });
+      if (!response.ok) throw new Error(await response.text());
+      onSave();
+      onClose();
+    } catch (err) {
+      setError(err.message);
+    } finally {
+      setSaving(false);
+    }
+  };
+
+  const inputClass =
+    "w-full border-2 border-amber-300 px-3 py-2 font-mono text-sm text-amber-900 focus:border-amber-500 focus:outline-none";
+
+  return (
+    <div className="fixed inset-0 z-50 flex items-center justify-center p-4">
+      <div className="absolute inset-0 bg-black/50" onClick={onClose} />
+      <div className="relative bg-amber-50 border-4 border-amber-800 shadow-2xl max-w-2xl w-full max-h-[90vh] overflow-hidden flex flex-col">
+        <div className="bg-amber-900 text-amber-50 px-6 py-4 flex items-center justify-between">
+          <h2 className="font-mono text-sm tracking-widest">
+            SCHEDULE: {test.title || `Test ${test.id}`}
+          </h2>
+          <button onClick={onClose} className="text-amber-50 hover:text-amber-200 font-mono text-xl leading-none">
+            x
+          </button>
+        </div>
+
+        <div className="flex-1 overflow-y-auto p-6 space-y-4">
+          {error && (
+            <div className="bg-red-100 border-2 border-red-400 text-red-800 px-3 py-2 font-mono text-xs">{error}</div>
+          )}
+          <p className="font-mono text-xs text-amber-700">
+            All times are UTC. Leave everything blank to offer the test whenever it is active.
+          </p>
+          <div className="grid grid-cols-2 gap-4">
+            <div>
+              <label className="font-mono text-xs text-amber-700 block mb-1">AVAILABLE FROM</label>
+              <input type="datetime-local" value={from} onChange={(e) => setFrom(e.target.value)} className={inputClass} />
+            </div>
+            <div>
+              <label className="font-mono text-xs text-amber-700 block mb-1">AVAILABLE UNTIL</label>
+              <input type="datetime-local" value={until} onChange={(e) => setUntil(e.target.value)} className={inputClass} />
+            </div>
+          </div>
+
+          <div>
+            <label className="font-mono text-xs text-amber-700 block mb-1">
+              RECURRING WINDOWS (first occurrence, then repeated)
+            </label>
+            {windows.map((w, i) => (
+              <div key={i} className="grid grid-cols-[1fr_1fr_auto_auto] gap-2 mb-2">
+                <input type="datetime-local" value={w.start} onChange={(e) => updateWindow(i, "start", e.target.value)} className={inputClass} />
+                <input type="datetime-local" value={w.end} onChange={(e) => updateWindow(i, "end", e.target.value)} className={inputClass} />
+                <select value={w.repeat} onChange={(e) => updateWindow(i, "repeat", e.target.value)} className="border-2 border-amber-300 px-2 font-mono text-sm">
+                  {["daily", "weekly", "monthly", "yearly"].map((r) => (
+                    <option key={r} value={r}>{r}</option>
+                  ))}
+                </select>
+                <button
+                  type="button"
+                  onClick={() => setWindows((prev) => prev.filter((_, j) => j !== i))}
+                  className="px-2 font-mono text-xs border-2 border-red-300 text-red-800 hover:border-red-500"
+                >
+                  x
+                </button>
+              </div>
+            ))}
+            <button
+              type="button"
+              onClick={() => setWindows((prev) => [...prev, { start: "", end: "", repeat: "yearly" }])}
+              className="px-3 py-1 font-mono text-xs border-2 border-amber-300 text-amber-800 hover:border-amber-500 hover:bg-amber-100 transition-all"
+            >
+              + Add Window
+            </button>
+          </div>
+
+          <div className="flex gap-4 pt-4">
+            <button
+              type="button"
+              onClick={onClose}
+              className="flex-1 px-4 py-3 font-mono text-sm tracking-widest border-2 border-amber-300 text-amber-800 hover:border-amber-500 hover:bg-amber-100 transition-all"
+            >
+              Cancel
+            </button>
+            <button
+              type="button"
+              onClick={handleSave}
+              disabled={saving}
+              className="flex-1 px-4 py-3 font-mono text-sm tracking-widest bg-amber-700 text-amber-50 hover:bg-amber-800 transition-all disabled:opacity-50"
+            >
+              {saving ? "Saving..." : "Save"}
+            </button>
+          </div>
+        </div>
+      </div>
+    </div>
+  );
+}
+
 // Question Editor Component for managing test questions
 export function QuestionEditor({ testId, onClose }) {
   const { adminFetch } = useAdminAuth();
@@ -1157,8 +1740,18 @@ export function QuestionEditor({ testId, onClose }) {
                                 Q{q.question_number}
                               </span>
                               <span className="font-mono text-xs text-amber-600">
-                                Answer: {q.correct_option}
+                                {q.question_type === "true_false"
+                                  ? "True/false"
+                                  : q.question_type === "text"
+                                    ? "Text"
+                                    : `${q.options.length} options`}
+                                , answer: {q.correct_option}
                               </span>
+                              {q.topic && (
+                                <span className="font-mono text-xs text-amber-600">
+                                  Topic: {q.topic}
+                                </span>
+                              )}
                             </div>
                             <p className="font-serif text-amber-900 text-sm truncate">
                               {q.question_text}
@@ -7,12 +7,26 @@ import React, {
 } from "react";
 import {
   API_BASE,
+  LICENSE_CLASSES,
   morsePatterns,
   getSegmentColor,
   VintagePattern,
   TelegraphKey,
 } from "./shared.jsx";
 
+// Answer buttons for a question: its options lettered A, B, C..., or True
+// and False. Text questions are typed in instead.
+const answerChoices = (q) =>
+  q.question_type === "true_false"
+    ? [
+        { letter: "TRUE", label: null, text: "True" },
+        { letter: "FALSE", label: null, text: "False" },
+      ]
+    : (q.options || []).map((text, i) => {
+        const letter = String.fromCharCode(65 + i);
+        return { letter, label: letter, text };
+      });
+
 // Confirmation Modal component (local version with different styling than shared)
 const ConfirmModal = ({
   isOpen,
@@ -101,7 +115,9 @@ export function App() {
   const [tests, setTests] = useState([]);
   const [currentTest, setCurrentTest] = useState(null);
   const [questions, setQuestions] = useState([]);
+  const [sessionId, setSessionId] = useState(null);
   const [correctAnswers, setCorrectAnswers] = useState(null);
+  const [questionResults, setQuestionResults] = useState({});
   const [loadingTest, setLoadingTest] = useState(false);
 
   // Fetch available tests on mount
@@ -178,12 +194,24 @@ export function App() {
       const test = tests.find((t) => t.id === testId);
       setCurrentTest(test);
 
-      const res = await fetch(`${API_BASE}/api/tests/${testId}/questions`);
+      // Shuffled tests show each session its own order of questions and options
+      const sessionRes = await fetch(`${API_BASE}/api/tests/${testId}/sessions`, {
+        method: "POST",
+      });
+      if (!sessionRes.ok) throw new Error(await sessionRes.text());
+      const session = await sessionRes.json();
+
+      const res = await fetch(
+        `${API_BASE}/api/tests/${testId}/questions?session=${session.session_id}`
+      );
+      if (!res.ok) throw new Error(await res.text());
       const qs = await res.json();
+      setSessionId(session.session_id);
       setQuestions(qs);
       setSelectedTest(testId);
       setAnswers({});
       setCorrectAnswers(null);
+      setQuestionResults({});
       setCopyText("");
       setTestComplete(false);
       setScore(null);
@@ -201,7 +229,7 @@ export function App() {
   };
 
   const handleAnswer = (questionId, option) => {
-    setAnswers((prev) => ({ ...prev, [questionId]: option })); // option is "A", "B", "C", or "D"
+    setAnswers((prev) => ({ ...prev, [questionId]: option })); // a letter, TRUE/FALSE, or typed text
   };
 
   // Show abandon confirmation modal
@@ -263,6 +291,7 @@ export function App() {
           answers: answers, // { questionId: "A", ... }
           copy_text: copyText || null,
           audio_progress: audioProgress,
+          session_id: sessionId,
         }),
       });
 
@@ -291,6 +320,13 @@ export function App() {
       if (result.passed && result.correct_answers) {
         setCorrectAnswers(result.correct_answers);
       }
+      if (result.question_results) {
+        setQuestionResults(
+          Object.fromEntries(
+            result.question_results.map((r) => [r.question_id, r.correct]),
+          ),
+        );
+      }
       if (result.certificate_id) {
         setCertificateNumber(result.certificate_id);
       }
@@ -596,10 +632,20 @@ export function App() {
                     </span>
                     <h3 className="font-serif text-3xl font-bold text-amber-900 mb-1">
                       {test.speed_wpm} WPM
+                      {LICENSE_CLASSES[test.speed_wpm] && (
+                        <span className="font-mono text-sm tracking-widest text-amber-600 ml-3 uppercase">
+                          {LICENSE_CLASSES[test.speed_wpm]}
+                        </span>
+                      )}
                     </h3>
                     <p className="font-serif text-amber-700 mb-4">
                       {test.title}
                     </p>
+                    {test.closes_at && (
+                      <p className="font-mono text-xs text-red-700 -mt-3 mb-4">
+                        Available until {new Date(test.closes_at).toLocaleString()}
+                      </p>
+                    )}
 
                     <div
                       className="flex items-center gap-2 text-amber-600 font-mono text-sm font-medium
@@ -627,8 +673,8 @@ export function App() {
               <p className="font-serif text-amber-800 text-sm leading-relaxed">
                 <strong>Historical Note:</strong> From 1936 until 2007, the FCC
                 required amateur radio operators to demonstrate Morse code
-                proficiency. The 20 WPM test was required for the Amateur Extra
-                class license through 2000, and the speed was relaxed to 5 WPM
+                proficiency. Novice licenses required 5 WPM, General 13 WPM and the
+                Amateur Extra class 20 WPM through 2000, and the speed was relaxed to 5 WPM
                 until 2007, when the requirement was removed altogether.
               </p>
             </div>
@@ -861,12 +907,7 @@ export function App() {
 
                 <div className="space-y-6">
                   {questions.map((q) => {
-                    const options = [
-                      { letter: "A", text: q.option_a },
-                      { letter: "B", text: q.option_b },
-                      { letter: "C", text: q.option_c },
-                      { letter: "D", text: q.option_d },
-                    ];
+                    const options = answerChoices(q);
                     const isSelected = (letter) => answers[q.id] === letter;
                     const isCorrect = (letter) =>
                       correctAnswers && correctAnswers[q.id] === letter;
@@ -884,6 +925,24 @@ export function App() {
                           </span>
                           {q.question_text}
                         </p>
+                        {q.question_type === "text" ? (
+                          <div>
+                            <input
+                              type="text"
+                              value={answers[q.id] || ""}
+                              onChange={(e) => handleAnswer(q.id, e.target.value)}
+                              disabled={testComplete}
+                              className="w-full border-2 border-amber-300 bg-amber-50 px-3 py-2 font-serif text-sm text-amber-900
+                                       focus:border-amber-500 focus:outline-none"
+                              placeholder="Type your answer..."
+                            />
+                            {showCorrect && (
+                              <p className="mt-1 font-mono text-xs text-green-700">
+                                Answer: {correctAnswers[q.id]}
+                              </p>
+                            )}
+                          </div>
+                        ) : (
                         <div className="grid grid-cols-2 gap-2">
                           {options.map((opt) => (
                             <button
@@ -900,13 +959,16 @@ export function App() {
                                        }
                                        ${showCorrect && isCorrect(opt.letter) ? "ring-2 ring-green-500" : ""}`}
                             >
-                              <span className="font-mono text-xs text-amber-600 mr-2 font-medium">
-                                {opt.letter}.
-                              </span>
+                              {opt.label && (
+                                <span className="font-mono text-xs text-amber-600 mr-2 font-medium">
+                                  {opt.label}.
+                                </span>
+                              )}
                               {opt.text}
                             </button>
                           ))}
                         </div>
+                        )}
                       </div>
                     );
                   })}
@@ -1045,12 +1107,7 @@ export function App() {
                   </h3>
                   <div className="space-y-4">
                     {questions.map((q) => {
-                      const options = [
-                        { letter: "A", text: q.option_a },
-                        { letter: "B", text: q.option_b },
-                        { letter: "C", text: q.option_c },
-                        { letter: "D", text: q.option_d },
-                      ];
+                      const options = answerChoices(q);
                       return (
                         <div
                           key={q.id}
@@ -1062,6 +1119,22 @@ export function App() {
                             </span>
                             {q.question_text}
                           </p>
+                          {q.question_type === "text" ? (
+                            <div className="space-y-1 text-xs font-serif">
+                              <div
+                                className={`p-2 border border-amber-600 bg-amber-100
+                                  ${questionResults[q.id] ? "ring-2 ring-green-500" : ""}`}
+                              >
+                                <span className="font-mono text-amber-600 mr-1">
+                                  Your answer:
+                                </span>
+                                {answers[q.id] || "—"}
+                              </div>
+                              <p className="font-mono text-green-700">
+                                Answer: {correctAnswers[q.id]}
+                              </p>
+                            </div>
+                          ) : (
                           <div className="grid grid-cols-2 gap-1">
                             {options.map((opt) => {
                               const isSelected = answers[q.id] === opt.letter;
@@ -1074,14 +1147,17 @@ export function App() {
                                     ${isSelected ? "border-amber-600 bg-amber-100" : "border-amber-200 bg-amber-50"}
                                     ${isCorrect ? "ring-2 ring-green-500" : ""}`}
                                 >
-                                  <span className="font-mono text-amber-600 mr-1">
-                                    {opt.letter}.
-                                  </span>
+                                  {opt.label && (
+                                    <span className="font-mono text-amber-600 mr-1">
+                                      {opt.label}.
+                                    </span>
+                                  )}
                                   {opt.text}
                                 </div>
                               );
                             })}
                           </div>
+                          )}
                         </div>
                       );
                     })}
@@ -1308,8 +1384,21 @@ export function App() {
                                 ? "\uD83E\uDD49"
                                 : index + 1}
                         </div>
-                        <div className="col-span-4 font-mono text-lg font-bold text-amber-900">
-                          {entry.callsign}
+                        <div className="col-span-4">
+                          <div className="font-mono text-lg font-bold text-amber-900">
+                            {entry.callsign}
+                          </div>
+                          <div className="flex flex-wrap gap-1 mt-1">
+                            {entry.classes.map((endorsement) => (
+                              <span
+                                key={endorsement.class}
+                                title={`${endorsement.speed_wpm} WPM, certificate #${endorsement.certificate_number}`}
+                                className="bg-amber-900 text-amber-50 px-2 py-0.5 font-mono text-xs tracking-widest uppercase"
+                              >
+                                {endorsement.class}
+                              </span>
+                            ))}
+                          </div>
                         </div>
                         <div className="col-span-3 text-center">
                           <span className="inline-block bg-amber-100 border border-amber-300 px-3 py-1 font-mono font-bold text-amber-800">
@@ -1405,8 +1494,21 @@ export function App() {
                         <div className="col-span-2 font-mono text-amber-600">
                           {index + 1}
                         </div>
-                        <div className="col-span-4 font-mono text-lg font-bold text-amber-900">
-                          {entry.callsign}
+                        <div className="col-span-4">
+                          <div className="font-mono text-lg font-bold text-amber-900">
+                            {entry.callsign}
+                          </div>
+                          <div className="flex flex-wrap gap-1 mt-1">
+                            {entry.classes.map((endorsement) => (
+                              <span
+                                key={endorsement.class}
+                                title={`${endorsement.speed_wpm} WPM, certificate #${endorsement.certificate_number}`}
+                                className="bg-amber-900 text-amber-50 px-2 py-0.5 font-mono text-xs tracking-widest uppercase"
+                              >
+                                {endorsement.class}
+                              </span>
+                            ))}
+                          </div>
                         </div>
                         <div className="col-span-3 text-center">
                           <span className="inline-block bg-amber-100 border border-amber-300 px-3 py-1 font-mono text-amber-800">
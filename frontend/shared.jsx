@@ -92,3 +92,6 @@ export const ConfirmModal = ({
 
 // API Configuration - empty string means same-origin requests
 export const API_BASE = "";
+
+// License class examined at each element 1 speed
+export const LICENSE_CLASSES = { 5: "Novice", 13: "General", 20: "Extra" };
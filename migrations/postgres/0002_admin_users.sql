@@ -0,0 +1,9 @@
+-- Additional admin accounts created with `knowcodeextra create-admin`.
+-- The account from config.toml keeps working alongside these.
+
+CREATE TABLE admin_users (
+    id TEXT PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
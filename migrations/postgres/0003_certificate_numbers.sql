@@ -0,0 +1,18 @@
+-- Ledger of issued certificate numbers. The primary key makes each number
+-- unique and `attempt_id` gives every attempt at most one number; the next
+-- number is always MAX(number) + 1, allocated in the approval transaction.
+
+CREATE TABLE certificate_numbers (
+    number INTEGER PRIMARY KEY,
+    attempt_id TEXT NOT NULL UNIQUE REFERENCES attempts(id),
+    allocated_at TIMESTAMPTZ NOT NULL
+);
+
+-- Backfill from existing approvals. Where a number was handed out twice the
+-- earliest approval keeps it; `repair-certificates` reports the others.
+INSERT INTO certificate_numbers (number, attempt_id, allocated_at)
+SELECT certificate_number, id, COALESCE(validated_at, created_at)
+FROM attempts
+WHERE certificate_number IS NOT NULL
+ORDER BY validated_at NULLS FIRST, created_at
+ON CONFLICT DO NOTHING;
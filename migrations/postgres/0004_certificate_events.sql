@@ -0,0 +1,15 @@
+-- History of changes to issued certificates. Revoking an attempt keeps its
+-- row in `certificate_numbers`, so the number is retired rather than reused.
+
+CREATE TABLE certificate_events (
+    id TEXT PRIMARY KEY,
+    attempt_id TEXT NOT NULL REFERENCES attempts(id),
+    certificate_number INTEGER,
+    action TEXT NOT NULL,
+    reason TEXT,
+    old_callsign TEXT,
+    new_callsign TEXT,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_certificate_events_attempt ON certificate_events(attempt_id);
@@ -0,0 +1,23 @@
+-- Certificate designs, bound to tests. Saving a template adds a version
+-- rather than overwriting, so earlier designs stay on record. Tests without
+-- a template use the built-in design in templates/certificate.svg.
+
+CREATE TABLE certificate_templates (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE certificate_template_versions (
+    template_id TEXT NOT NULL REFERENCES certificate_templates(id),
+    version INTEGER NOT NULL,
+    svg TEXT NOT NULL,
+    note TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (template_id, version)
+);
+
+ALTER TABLE tests ADD COLUMN certificate_template_id TEXT REFERENCES certificate_templates(id);
+
+-- Operator name from QRZ at approval, for the {{NAME}} placeholder
+ALTER TABLE attempts ADD COLUMN name TEXT;
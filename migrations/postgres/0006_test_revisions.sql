@@ -0,0 +1,51 @@
+-- Published test revisions. Admin edits change the draft in `tests` and
+-- `questions`; publishing snapshots it here, and candidates only ever see
+-- the revision `tests.published_revision_id` points at. Revisions are never
+-- changed once written. Revision ids are `<test id>@<revision number>`.
+
+CREATE TABLE test_revisions (
+    id TEXT PRIMARY KEY,
+    test_id TEXT NOT NULL REFERENCES tests(id),
+    revision INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    speed_wpm INTEGER NOT NULL,
+    year TEXT NOT NULL,
+    audio_url TEXT NOT NULL,
+    passing_score INTEGER NOT NULL,
+    segments TEXT,
+    expected_copy_text TEXT,
+    note TEXT,
+    published_at TIMESTAMPTZ NOT NULL,
+    UNIQUE (test_id, revision)
+);
+
+-- Questions keep their draft ids, so answers match across revisions
+CREATE TABLE test_revision_questions (
+    revision_id TEXT NOT NULL REFERENCES test_revisions(id),
+    question_id TEXT NOT NULL,
+    question_number INTEGER NOT NULL,
+    question_text TEXT NOT NULL,
+    option_a TEXT NOT NULL,
+    option_b TEXT NOT NULL,
+    option_c TEXT NOT NULL,
+    option_d TEXT NOT NULL,
+    correct_option TEXT NOT NULL,
+    PRIMARY KEY (revision_id, question_id)
+);
+
+ALTER TABLE tests ADD COLUMN published_revision_id TEXT REFERENCES test_revisions(id);
+
+-- The revision each attempt was graded against
+ALTER TABLE attempts ADD COLUMN revision_id TEXT REFERENCES test_revisions(id);
+
+-- Existing tests are published as they stand, so candidates see no change
+INSERT INTO test_revisions (id, test_id, revision, title, speed_wpm, year, audio_url, passing_score, segments, expected_copy_text, note, published_at)
+SELECT id || '@1', id, 1, title, speed_wpm, year, audio_url, passing_score, segments, expected_copy_text, 'Published when revisions were introduced', created_at
+FROM tests;
+
+INSERT INTO test_revision_questions (revision_id, question_id, question_number, question_text, option_a, option_b, option_c, option_d, correct_option)
+SELECT test_id || '@1', id, question_number, question_text, option_a, option_b, option_c, option_d, correct_option
+FROM questions
+WHERE test_id IN (SELECT id FROM tests);
+
+UPDATE tests SET published_revision_id = id || '@1';
@@ -0,0 +1,9 @@
+-- Scheduled availability for tests, on top of the manual `active` switch.
+-- A test is open from `available_from` until `available_until` (either may
+-- be NULL for no limit), and, when `availability_windows` holds a JSON list
+-- of recurring windows, only while one of them is open. The schedule is not
+-- part of published revisions: changing it takes effect immediately.
+
+ALTER TABLE tests ADD COLUMN available_from TIMESTAMPTZ;
+ALTER TABLE tests ADD COLUMN available_until TIMESTAMPTZ;
+ALTER TABLE tests ADD COLUMN availability_windows TEXT;
@@ -0,0 +1,16 @@
+-- Exam sessions. A candidate starts one before seeing a test's questions and
+-- submits against it. Tests with `shuffle` set show each session its own
+-- order of questions and options, derived from the session id, so sessions
+-- store no order. A session is graded against the revision it started on
+-- and can only be submitted once.
+
+ALTER TABLE tests ADD COLUMN shuffle BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE exam_sessions (
+    id TEXT PRIMARY KEY,
+    test_id TEXT NOT NULL REFERENCES tests(id),
+    revision_id TEXT NOT NULL REFERENCES test_revisions(id),
+    shuffled BOOLEAN NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    submitted_at TIMESTAMPTZ
+);
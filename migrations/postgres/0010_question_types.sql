@@ -0,0 +1,26 @@
+-- Question types. Besides multiple choice, a question can be true/false or
+-- take a short free-text answer. Multiple-choice options move from the fixed
+-- option_a..option_d columns to a JSON list, lettered A, B, C... in order, so
+-- questions can have as many options as the original exam did.
+--
+-- `correct_option` holds the option letter for multiple choice, TRUE or FALSE
+-- for true/false, and the expected answer for free text, which also accepts
+-- any of `accepted_answers` (a JSON list) after normalizing case and spacing.
+
+ALTER TABLE questions ADD COLUMN question_type TEXT NOT NULL DEFAULT 'choice';
+ALTER TABLE questions ADD COLUMN options TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE questions ADD COLUMN accepted_answers TEXT NOT NULL DEFAULT '[]';
+UPDATE questions SET options = json_build_array(option_a, option_b, option_c, option_d)::TEXT;
+ALTER TABLE questions DROP COLUMN option_a;
+ALTER TABLE questions DROP COLUMN option_b;
+ALTER TABLE questions DROP COLUMN option_c;
+ALTER TABLE questions DROP COLUMN option_d;
+
+ALTER TABLE test_revision_questions ADD COLUMN question_type TEXT NOT NULL DEFAULT 'choice';
+ALTER TABLE test_revision_questions ADD COLUMN options TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE test_revision_questions ADD COLUMN accepted_answers TEXT NOT NULL DEFAULT '[]';
+UPDATE test_revision_questions SET options = json_build_array(option_a, option_b, option_c, option_d)::TEXT;
+ALTER TABLE test_revision_questions DROP COLUMN option_a;
+ALTER TABLE test_revision_questions DROP COLUMN option_b;
+ALTER TABLE test_revision_questions DROP COLUMN option_c;
+ALTER TABLE test_revision_questions DROP COLUMN option_d;
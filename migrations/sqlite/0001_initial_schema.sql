@@ -0,0 +1,79 @@
+-- Schema as of the introduction of versioned migrations.
+-- Databases created before then are adopted at this version (see migrations.rs).
+
+CREATE TABLE IF NOT EXISTS attempts (
+    id TEXT PRIMARY KEY,
+    callsign TEXT NOT NULL,
+    test_speed INTEGER NOT NULL,
+    questions_correct INTEGER NOT NULL,
+    copy_chars INTEGER NOT NULL,
+    passed BOOLEAN NOT NULL,
+    created_at TEXT NOT NULL,
+    test_id TEXT,
+    validation_status TEXT,
+    certificate_number INTEGER,
+    validated_at TEXT,
+    admin_note TEXT,
+    email TEXT,
+    reached_out INTEGER DEFAULT 0,
+    audio_progress REAL,
+    copy_text TEXT,
+    consecutive_correct INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS tests (
+    id TEXT PRIMARY KEY,
+    title TEXT NOT NULL,
+    speed_wpm INTEGER NOT NULL,
+    year TEXT NOT NULL,
+    audio_url TEXT NOT NULL,
+    passing_score INTEGER NOT NULL DEFAULT 7,
+    active INTEGER NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL,
+    segments TEXT,
+    expected_copy_text TEXT
+);
+
+CREATE TABLE IF NOT EXISTS questions (
+    id TEXT PRIMARY KEY,
+    test_id TEXT NOT NULL,
+    question_number INTEGER NOT NULL,
+    question_text TEXT NOT NULL,
+    option_a TEXT NOT NULL,
+    option_b TEXT NOT NULL,
+    option_c TEXT NOT NULL,
+    option_d TEXT NOT NULL,
+    correct_option TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (test_id) REFERENCES tests(id),
+    UNIQUE(test_id, question_number)
+);
+
+CREATE TABLE IF NOT EXISTS prosign_mappings (
+    id TEXT PRIMARY KEY,
+    prosign TEXT NOT NULL UNIQUE,
+    alternate TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    scopes TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    last_used_at TEXT,
+    revoked_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_questions_test_id ON questions(test_id);
+CREATE INDEX IF NOT EXISTS idx_callsign ON attempts(callsign);
+CREATE INDEX IF NOT EXISTS idx_passed ON attempts(passed);
+CREATE INDEX IF NOT EXISTS idx_test_speed ON attempts(test_speed);
+CREATE INDEX IF NOT EXISTS idx_created_at ON attempts(created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_validation_status ON attempts(validation_status);
@@ -0,0 +1,12 @@
+-- Question banks. A question may name the topic it asks about, such as the
+-- operator's name, the rig or the weather in the transmission. A test with
+-- `draw_count` set asks each exam session that many of its questions, drawn
+-- across topics, and the session records which were drawn so its grading can
+-- be reproduced. Attempts record the session they were submitted in.
+
+ALTER TABLE questions ADD COLUMN topic TEXT;
+ALTER TABLE test_revision_questions ADD COLUMN topic TEXT;
+ALTER TABLE tests ADD COLUMN draw_count INTEGER;
+ALTER TABLE test_revisions ADD COLUMN draw_count INTEGER;
+ALTER TABLE exam_sessions ADD COLUMN question_ids TEXT;
+ALTER TABLE attempts ADD COLUMN session_id TEXT REFERENCES exam_sessions(id);
@@ -7,6 +7,8 @@ use axum::{
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use crate::db::{is_unique_violation, Db, JsonList, Value};
+use crate::grading::QuestionType;
 use std::sync::Arc;
 use tokio::fs;
 
@@ -39,6 +41,14 @@ pub struct UpdateTestRequest {
     pub active: Option<bool>,
     pub segments: Option<Vec<crate::Segment>>,
     pub expected_copy_text: Option<String>,
+    /// Certificate template to bind; an empty string unbinds it
+    pub certificate_template_id: Option<String>,
+    /// Replaces the whole schedule; `{}` removes it
+    pub schedule: Option<crate::schedule::Schedule>,
+    /// Shuffle questions and options per exam session
+    pub shuffle: Option<bool>,
+    /// Questions drawn per exam session; 0 asks them all
+    pub draw_count: Option<i32>,
 }
 
 #[derive(Debug, Serialize)]
@@ -49,26 +59,43 @@ pub struct AdminTest {
     pub year: String,
     pub audio_url: String,
     pub passing_score: i32,
+    /// Questions drawn per exam session, or None to ask them all
+    pub draw_count: Option<i32>,
     pub active: bool,
     pub created_at: DateTime<Utc>,
     pub question_count: i64,
     pub segments: Option<Vec<crate::Segment>>,
     pub expected_copy_text: Option<String>,
+    pub certificate_template_id: Option<String>,
+    pub shuffle: bool,
+    /// The revision candidates see, or None while the test is a draft
+    pub published_revision: Option<i32>,
+    pub schedule: crate::schedule::Schedule,
+    /// Open, upcoming or expired by the schedule, regardless of `active`
+    pub availability: crate::schedule::Availability,
 }
 
 #[derive(Debug, FromRow)]
-struct AdminTestRow {
+pub struct AdminTestRow {
     pub id: String,
     pub title: String,
     pub speed_wpm: i32,
     pub year: String,
     pub audio_url: String,
     pub passing_score: i32,
+    pub draw_count: Option<i32>,
     pub active: bool,
     pub created_at: DateTime<Utc>,
     pub question_count: i64,
     pub segments: Option<String>,
     pub expected_copy_text: Option<String>,
+    pub certificate_template_id: Option<String>,
+    pub shuffle: bool,
+    /// The revision candidates see, or None while the test is a draft
+    pub published_revision: Option<i32>,
+    pub available_from: Option<DateTime<Utc>>,
+    pub available_until: Option<DateTime<Utc>>,
+    pub availability_windows: Option<String>,
 }
 
 /// Form data for rejection
@@ -164,6 +191,14 @@ pub struct SettingsResponse {
     pub static_dir: String,
     pub log_level: String,
     pub qrz_enabled: bool,
+    pub ntfy_enabled: bool,
+    pub admin_username: String,
+    pub rate_limit_enabled: bool,
+    pub trusted_proxies: Vec<String>,
+    pub dev_mode: bool,
+    pub backup_enabled: bool,
+    pub backup_dir: String,
+    pub backup_interval_hours: u64,
 }
 
 /// Queue item for JSON API
@@ -235,6 +270,10 @@ pub struct AttemptListItem {
     pub validation_status: Option<String>,
     pub created_at: DateTime<Utc>,
     pub copy_text: Option<String>,
+    /// The test revision the attempt was graded against
+    pub revision_id: Option<String>,
+    /// The exam session it was submitted in, for reproducing its grading
+    pub session_id: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -282,83 +321,53 @@ pub struct GenerateEmailResponse {
 pub async fn get_admin_stats(
     State(state): State<Arc<crate::AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let pending: (i64,) =
-        sqlx::query_as("SELECT COUNT(*) FROM attempts WHERE validation_status = 'pending'")
-            .fetch_one(&state.db)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(admin_stats(&state).await?))
+}
 
-    let today = chrono::Utc::now().date_naive();
-    let approved_today: (i64,) = sqlx::query_as(
-        "SELECT COUNT(*) FROM attempts WHERE validation_status = 'approved' AND date(validated_at) = ?"
-    )
-    .bind(today.to_string())
-    .fetch_one(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    let total_certs: (i64,) =
-        sqlx::query_as("SELECT COUNT(*) FROM attempts WHERE validation_status = 'approved'")
-            .fetch_one(&state.db)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+/// Dashboard statistics, shared by the stats endpoint and the `stats` CLI command
+pub async fn admin_stats(
+    state: &crate::AppState,
+) -> Result<AdminStatsResponse, (StatusCode, String)> {
+    let attempts = state.db.attempts();
+    let db_err = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
 
-    let rejected: (i64,) =
-        sqlx::query_as("SELECT COUNT(*) FROM attempts WHERE validation_status = 'rejected'")
-            .fetch_one(&state.db)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let pending = attempts.count_by_status("pending").await.map_err(db_err)?;
+
+    let today = crate::repo::day_start(chrono::Utc::now().date_naive());
+    let approved_today = attempts
+        .count_approved_between(today, today + chrono::Duration::days(1))
+        .await
+        .map_err(db_err)?;
+
+    let total_certs = attempts.count_by_status("approved").await.map_err(db_err)?;
+    let rejected = attempts.count_by_status("rejected").await.map_err(db_err)?;
 
     // Recent activity (last 10 approvals/rejections)
-    let recent: Vec<RecentActivity> = sqlx::query_as(
-        r#"
-        SELECT id, callsign, validation_status as action, validated_at as created_at
-        FROM attempts
-        WHERE validation_status IN ('approved', 'rejected')
-        ORDER BY validated_at DESC
-        LIMIT 10
-        "#,
-    )
-    .fetch_all(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let recent = attempts.recent_activity(10).await.map_err(db_err)?;
 
     // Recent attempts (last 10, both pass and fail)
-    let recent_attempts: Vec<RecentAttempt> = sqlx::query_as(
-        r#"
-        SELECT id, callsign, questions_correct, consecutive_correct, passed, created_at
-        FROM attempts
-        ORDER BY created_at DESC
-        LIMIT 10
-        "#,
-    )
-    .fetch_all(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    Ok(Json(AdminStatsResponse {
-        pending_count: pending.0,
-        approved_today: approved_today.0,
-        total_certificates: total_certs.0,
-        rejected_count: rejected.0,
+    let recent_attempts = attempts.recent(10).await.map_err(db_err)?;
+
+    Ok(AdminStatsResponse {
+        pending_count: pending,
+        approved_today,
+        total_certificates: total_certs,
+        rejected_count: rejected,
         recent_activity: recent,
         recent_attempts,
-    }))
+    })
 }
 
 /// GET /api/admin/queue - Get pending queue as JSON
 pub async fn get_queue(
     State(state): State<Arc<crate::AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let pending: Vec<QueueItem> = sqlx::query_as(
-        "SELECT id, callsign, questions_correct, copy_chars, created_at
-         FROM attempts
-         WHERE validation_status = 'pending'
-         ORDER BY created_at ASC",
-    )
-    .fetch_all(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let pending = state
+        .db
+        .attempts()
+        .pending_queue()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     Ok(Json(pending))
 }
@@ -368,16 +377,12 @@ pub async fn get_callsign_history(
     State(state): State<Arc<crate::AppState>>,
     Path(callsign): Path<String>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let history: Vec<AttemptHistory> = sqlx::query_as(
-        "SELECT id, questions_correct, copy_chars, passed, validation_status, created_at
-         FROM attempts
-         WHERE callsign = ?
-         ORDER BY created_at DESC",
-    )
-    .bind(&callsign)
-    .fetch_all(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let history = state
+        .db
+        .attempts()
+        .history(&callsign)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     Ok(Json(history))
 }
@@ -387,61 +392,7 @@ pub async fn approve_attempt_json(
     State(state): State<Arc<crate::AppState>>,
     Path(attempt_id): Path<String>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let mut tx = state
-        .db
-        .begin()
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    let max_cert: (Option<i32>,) = sqlx::query_as("SELECT MAX(certificate_number) FROM attempts")
-        .fetch_one(&mut *tx)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    let next_cert = max_cert.0.unwrap_or(0) + 1;
-    let now = chrono::Utc::now();
-
-    // Fetch email from QRZ if configured
-    let email: Option<String> = if let Some(ref qrz) = state.qrz_client {
-        let callsign: (String,) = sqlx::query_as("SELECT callsign FROM attempts WHERE id = ?")
-            .bind(&attempt_id)
-            .fetch_one(&mut *tx)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-        qrz.lookup_email(&callsign.0).await.ok().flatten()
-    } else {
-        None
-    };
-
-    let result = sqlx::query(
-        "UPDATE attempts SET validation_status = 'approved', certificate_number = ?, validated_at = ?, email = ?
-         WHERE id = ? AND validation_status = 'pending'"
-    )
-    .bind(next_cert)
-    .bind(now.to_rfc3339())
-    .bind(&email)
-    .bind(&attempt_id)
-    .execute(&mut *tx)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    if result.rows_affected() == 0 {
-        return Err((
-            StatusCode::NOT_FOUND,
-            "Attempt not found or not pending".to_string(),
-        ));
-    }
-
-    tx.commit()
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    // Regenerate the Ham2K PoLo notes file
-    if let Err(e) = regenerate_polo_notes(&state).await {
-        tracing::error!("Failed to regenerate PoLo notes: {}", e);
-        // Don't fail the approval, just log the error
-    }
+    let next_cert = state.attempt_service().approve(&attempt_id).await?;
 
     Ok(Json(serde_json::json!({
         "success": true,
@@ -455,111 +406,100 @@ pub async fn reject_attempt_json(
     Path(attempt_id): Path<String>,
     Json(form): Json<RejectForm>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let now = chrono::Utc::now();
-
-    let result = sqlx::query(
-        "UPDATE attempts SET validation_status = 'rejected', validated_at = ?, admin_note = ?
-         WHERE id = ? AND validation_status = 'pending'",
-    )
-    .bind(now.to_rfc3339())
-    .bind(form.note)
-    .bind(&attempt_id)
-    .execute(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    if result.rows_affected() == 0 {
-        return Err((
-            StatusCode::NOT_FOUND,
-            "Attempt not found or not pending".to_string(),
-        ));
-    }
+    state
+        .attempt_service()
+        .reject(&attempt_id, form.note.as_deref())
+        .await?;
 
     Ok(Json(serde_json::json!({ "success": true })))
 }
 
-/// GET /api/admin/approved - Paginated approved list
-pub async fn get_approved_list(
+/// Body for revoke, reinstate and reissue
+#[derive(Deserialize)]
+pub struct CertificateChangeForm {
+    reason: Option<String>,
+    /// Required by reissue
+    callsign: Option<String>,
+}
+
+/// POST /api/admin/certificates/:id/revoke - Revoke an approved certificate
+pub async fn revoke_certificate(
     State(state): State<Arc<crate::AppState>>,
-    Query(query): Query<ApprovedListQuery>,
+    Path(attempt_id): Path<String>,
+    Json(form): Json<CertificateChangeForm>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let offset = (query.page - 1) * query.per_page;
+    let event = state
+        .attempt_service()
+        .revoke(&attempt_id, form.reason.as_deref().unwrap_or(""))
+        .await?;
 
-    let (items, total): (Vec<ApprovedAttempt>, (i64,)) = match query.reached_out {
-        Some(true) => {
-            let items: Vec<ApprovedAttempt> = sqlx::query_as(
-                "SELECT id, callsign, certificate_number, validated_at, email, reached_out
-                 FROM attempts
-                 WHERE validation_status = 'approved' AND reached_out = 1
-                 ORDER BY certificate_number DESC
-                 LIMIT ? OFFSET ?",
-            )
-            .bind(query.per_page)
-            .bind(offset)
-            .fetch_all(&state.db)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(event))
+}
 
-            let total: (i64,) = sqlx::query_as(
-                "SELECT COUNT(*) FROM attempts WHERE validation_status = 'approved' AND reached_out = 1"
-            )
-            .fetch_one(&state.db)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+/// POST /api/admin/certificates/:id/reinstate - Reinstate a revoked certificate
+pub async fn reinstate_certificate(
+    State(state): State<Arc<crate::AppState>>,
+    Path(attempt_id): Path<String>,
+    Json(form): Json<CertificateChangeForm>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let event = state
+        .attempt_service()
+        .reinstate(&attempt_id, form.reason.as_deref())
+        .await?;
 
-            (items, total)
-        }
-        Some(false) => {
-            let items: Vec<ApprovedAttempt> = sqlx::query_as(
-                "SELECT id, callsign, certificate_number, validated_at, email, reached_out
-                 FROM attempts
-                 WHERE validation_status = 'approved' AND reached_out = 0
-                 ORDER BY certificate_number DESC
-                 LIMIT ? OFFSET ?",
-            )
-            .bind(query.per_page)
-            .bind(offset)
-            .fetch_all(&state.db)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(event))
+}
 
-            let total: (i64,) = sqlx::query_as(
-                "SELECT COUNT(*) FROM attempts WHERE validation_status = 'approved' AND reached_out = 0"
-            )
-            .fetch_one(&state.db)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+/// POST /api/admin/certificates/:id/reissue - Reissue a certificate to a new callsign
+pub async fn reissue_certificate(
+    State(state): State<Arc<crate::AppState>>,
+    Path(attempt_id): Path<String>,
+    Json(form): Json<CertificateChangeForm>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let event = state
+        .attempt_service()
+        .reissue(
+            &attempt_id,
+            form.callsign.as_deref().unwrap_or(""),
+            form.reason.as_deref(),
+        )
+        .await?;
 
-            (items, total)
-        }
-        None => {
-            let items: Vec<ApprovedAttempt> = sqlx::query_as(
-                "SELECT id, callsign, certificate_number, validated_at, email, reached_out
-                 FROM attempts
-                 WHERE validation_status = 'approved'
-                 ORDER BY certificate_number DESC
-                 LIMIT ? OFFSET ?",
-            )
-            .bind(query.per_page)
-            .bind(offset)
-            .fetch_all(&state.db)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(event))
+}
 
-            let total: (i64,) = sqlx::query_as(
-                "SELECT COUNT(*) FROM attempts WHERE validation_status = 'approved'",
-            )
-            .fetch_one(&state.db)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+/// GET /api/admin/certificates/:id/history - Revocations, reinstatements and reissues
+pub async fn get_certificate_history(
+    State(state): State<Arc<crate::AppState>>,
+    Path(attempt_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let history = state
+        .db
+        .certificates()
+        .history(&attempt_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-            (items, total)
-        }
-    };
+    Ok(Json(history))
+}
+
+/// GET /api/admin/approved - Paginated approved list
+pub async fn get_approved_list(
+    State(state): State<Arc<crate::AppState>>,
+    Query(query): Query<ApprovedListQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let offset = (query.page - 1) * query.per_page;
+
+    let (items, total) = state
+        .db
+        .attempts()
+        .approved(query.reached_out, query.per_page, offset)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     Ok(Json(ApprovedListResponse {
         items,
-        total: total.0,
+        total,
         page: query.page,
         per_page: query.per_page,
     }))
@@ -570,29 +510,16 @@ pub async fn mark_reached_out_json(
     State(state): State<Arc<crate::AppState>>,
     Json(form): Json<BulkIdsForm>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    if form.ids.is_empty() {
-        return Ok(Json(serde_json::json!({ "success": true, "count": 0 })));
-    }
-
-    let placeholders: Vec<&str> = form.ids.iter().map(|_| "?").collect();
-    let query = format!(
-        "UPDATE attempts SET reached_out = 1 WHERE id IN ({})",
-        placeholders.join(", ")
-    );
-
-    let mut q = sqlx::query(&query);
-    for id in &form.ids {
-        q = q.bind(id);
-    }
-
-    let result = q
-        .execute(&state.db)
+    let count = state
+        .db
+        .attempts()
+        .mark_reached_out(&form.ids)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     Ok(Json(serde_json::json!({
         "success": true,
-        "count": result.rows_affected()
+        "count": count
     })))
 }
 
@@ -601,19 +528,12 @@ pub async fn search_attempts(
     State(state): State<Arc<crate::AppState>>,
     Query(query): Query<SearchQuery>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let search_term = format!("%{}%", query.q.to_uppercase());
-
-    let results: Vec<SearchResult> = sqlx::query_as(
-        "SELECT id, callsign, questions_correct, copy_chars, passed, validation_status, certificate_number, admin_note, created_at
-         FROM attempts
-         WHERE callsign LIKE ?
-         ORDER BY callsign, created_at DESC
-         LIMIT 100"
-    )
-    .bind(&search_term)
-    .fetch_all(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let results = state
+        .db
+        .attempts()
+        .search(&query.q)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     Ok(Json(results))
 }
@@ -623,380 +543,742 @@ pub async fn list_all_attempts(
     State(state): State<Arc<crate::AppState>>,
     Query(query): Query<AllAttemptsQuery>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    // Validate pagination
-    if query.page < 1 {
-        return Err((StatusCode::BAD_REQUEST, "Page must be >= 1".to_string()));
-    }
-    if query.per_page < 1 || query.per_page > 100 {
-        return Err((StatusCode::BAD_REQUEST, "per_page must be between 1 and 100".to_string()));
-    }
+    let response = state.attempt_service().list(&query).await?;
 
-    let offset = match (query.page - 1).checked_mul(query.per_page) {
-        Some(o) => o,
-        None => return Err((StatusCode::BAD_REQUEST, "Pagination overflow".to_string())),
-    };
+    Ok(Json(response))
+}
 
-    // Validate date format (YYYY-MM-DD) using chrono parsing
-    if let Some(ref date_from) = query.date_from {
-        if chrono::NaiveDate::parse_from_str(date_from, "%Y-%m-%d").is_err() {
-            return Err((StatusCode::BAD_REQUEST, "date_from must be YYYY-MM-DD format".to_string()));
+/// GET /api/admin/settings - Get safe config values
+pub async fn get_settings(State(state): State<Arc<crate::AppState>>) -> impl IntoResponse {
+    // AppState holds a redacted copy of the config, so secrets never leave the server
+    let config = &state.config;
+    Json(SettingsResponse {
+        database_url: config.database_url.clone(),
+        listen_addr: config.listen_addr.clone(),
+        static_dir: config.static_dir.clone(),
+        log_level: config.log_level.clone(),
+        qrz_enabled: state.qrz_client.is_some(),
+        ntfy_enabled: config.ntfy_url.is_some(),
+        admin_username: config.admin_username.clone(),
+        rate_limit_enabled: config.rate_limit_enabled,
+        trusted_proxies: config.trusted_proxies.clone(),
+        dev_mode: config.dev_mode,
+        backup_enabled: config.backup_enabled,
+        backup_dir: config.backup_dir.clone(),
+        backup_interval_hours: config.backup_interval_hours,
+    })
+}
+
+// ============================================================================
+// ADMIN TEST CRUD ENDPOINTS
+// ============================================================================
+
+/// GET /api/admin/tests - List all tests (including inactive) with their availability
+pub async fn list_tests_admin(
+    State(state): State<Arc<crate::AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let rows = state
+        .db
+        .tests()
+        .list_admin()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let now = Utc::now();
+    let tests: Vec<AdminTest> = rows.into_iter().map(|row| {
+        let test_id = row.id.clone();
+        let schedule = crate::schedule::Schedule::from_stored(
+            &test_id,
+            row.available_from,
+            row.available_until,
+            row.availability_windows.as_deref(),
+        );
+        AdminTest {
+            id: row.id,
+            title: row.title,
+            speed_wpm: row.speed_wpm,
+            year: row.year,
+            audio_url: row.audio_url,
+            passing_score: row.passing_score,
+            draw_count: row.draw_count,
+            active: row.active,
+            created_at: row.created_at,
+            question_count: row.question_count,
+            segments: row.segments.and_then(|s| {
+                serde_json::from_str(&s).map_err(|e| {
+                    tracing::warn!("Failed to parse segments JSON for test {}: {}", test_id, e);
+                    e
+                }).ok()
+            }),
+            expected_copy_text: row.expected_copy_text,
+            certificate_template_id: row.certificate_template_id,
+            shuffle: row.shuffle,
+            published_revision: row.published_revision,
+            availability: schedule.availability(now),
+            schedule,
         }
+    }).collect();
+
+    Ok(Json(tests))
+}
+
+/// POST /api/admin/tests - Create new test as an unpublished draft
+pub async fn create_test(
+    State(state): State<Arc<crate::AppState>>,
+    Json(req): Json<CreateTestRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    crate::classes::check_speed(req.speed_wpm).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    state
+        .db
+        .tests()
+        .insert(&crate::repo::NewTest {
+            id: &req.id,
+            title: &req.title,
+            speed_wpm: req.speed_wpm,
+            year: &req.year,
+            audio_url: &req.audio_url,
+            passing_score: req.passing_score,
+            draw_count: None,
+            active: true,
+            segments: None,
+            expected_copy_text: None,
+        })
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(serde_json::json!({ "success": true, "id": req.id })))
+}
+
+/// PUT /api/admin/tests/:id - Update the test's draft
+pub async fn update_test(
+    State(state): State<Arc<crate::AppState>>,
+    Path(test_id): Path<String>,
+    Json(req): Json<UpdateTestRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    tracing::info!("update_test called for id {}: {:?}", test_id, req);
+    let mut changes: Vec<(&'static str, Value)> = Vec::new();
+
+    if let Some(ref title) = req.title {
+        changes.push(("title", title.into()));
+    }
+    if let Some(speed) = req.speed_wpm {
+        crate::classes::check_speed(speed).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+        changes.push(("speed_wpm", speed.into()));
+    }
+    if let Some(ref year) = req.year {
+        changes.push(("year", year.into()));
     }
-    if let Some(ref date_to) = query.date_to {
-        if chrono::NaiveDate::parse_from_str(date_to, "%Y-%m-%d").is_err() {
-            return Err((StatusCode::BAD_REQUEST, "date_to must be YYYY-MM-DD format".to_string()));
+    if let Some(ref url) = req.audio_url {
+        changes.push(("audio_url", url.into()));
+    }
+    if let Some(score) = req.passing_score {
+        changes.push(("passing_score", score.into()));
+    }
+    if let Some(active) = req.active {
+        changes.push(("active", active.into()));
+    }
+    if let Some(ref segments) = req.segments {
+        let problems = crate::segments::validate(segments);
+        if !problems.is_empty() {
+            return Err((StatusCode::BAD_REQUEST, format!("Invalid segments: {}", problems.join("; "))));
         }
+        let segments_json = serde_json::to_string(segments)
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid segments: {}", e)))?;
+        changes.push(("segments", segments_json.into()));
     }
-
-    // Build query based on which filters are present
-    // We use a match on the combination of filters to build type-safe queries
-    let (total, items) = match (query.passed, &query.callsign, &query.date_from, &query.date_to) {
-        // No filters
-        (None, None, None, None) => {
-            let total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM attempts")
-                .fetch_one(&state.db)
+    if let Some(ref text) = req.expected_copy_text {
+        changes.push(("expected_copy_text", text.into()));
+    }
+    if let Some(ref template_id) = req.certificate_template_id {
+        if template_id.is_empty() {
+            changes.push(("certificate_template_id", Value::Text(None)));
+        } else {
+            let exists = state
+                .db
+                .certificate_templates()
+                .exists(template_id)
                 .await
                 .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            if !exists {
+                return Err((StatusCode::BAD_REQUEST, "Certificate template not found".to_string()));
+            }
+            changes.push(("certificate_template_id", template_id.into()));
+        }
+    }
+    if let Some(shuffle) = req.shuffle {
+        changes.push(("shuffle", shuffle.into()));
+    }
+    if let Some(count) = req.draw_count {
+        if count < 0 {
+            return Err((StatusCode::BAD_REQUEST, "draw_count must not be negative".to_string()));
+        }
+        changes.push(("draw_count", Value::Int32((count > 0).then_some(count))));
+    }
+    if let Some(ref schedule) = req.schedule {
+        let problems = schedule.validate();
+        if !problems.is_empty() {
+            return Err((StatusCode::BAD_REQUEST, format!("Invalid schedule: {}", problems.join("; "))));
+        }
+        changes.push(("available_from", schedule.available_from.into()));
+        changes.push(("available_until", schedule.available_until.into()));
+        changes.push(("availability_windows", schedule.windows_json().into()));
+    }
 
-            let items: Vec<AttemptListItem> = sqlx::query_as(
-                "SELECT id, callsign, test_speed, questions_correct, copy_chars, consecutive_correct, passed, validation_status, created_at, copy_text
-                 FROM attempts ORDER BY created_at DESC LIMIT ? OFFSET ?"
-            )
-            .bind(query.per_page)
-            .bind(offset)
-            .fetch_all(&state.db)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if changes.is_empty() {
+        return Ok(Json(
+            serde_json::json!({ "success": true, "updated": false }),
+        ));
+    }
 
-            (total.0, items)
-        }
-        // Only passed filter
-        (Some(passed), None, None, None) => {
-            let total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM attempts WHERE passed = ?")
-                .bind(passed)
-                .fetch_one(&state.db)
-                .await
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let updated = state
+        .db
+        .tests()
+        .update(&test_id, changes)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-            let items: Vec<AttemptListItem> = sqlx::query_as(
-                "SELECT id, callsign, test_speed, questions_correct, copy_chars, consecutive_correct, passed, validation_status, created_at, copy_text
-                 FROM attempts WHERE passed = ? ORDER BY created_at DESC LIMIT ? OFFSET ?"
-            )
-            .bind(passed)
-            .bind(query.per_page)
-            .bind(offset)
-            .fetch_all(&state.db)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if updated == 0 {
+        return Err((StatusCode::NOT_FOUND, "Test not found".to_string()));
+    }
 
-            (total.0, items)
-        }
-        // Only callsign filter
-        (None, Some(callsign), None, None) => {
-            let pattern = format!("%{}%", callsign.to_uppercase());
-            let total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM attempts WHERE callsign LIKE ?")
-                .bind(&pattern)
-                .fetch_one(&state.db)
-                .await
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(serde_json::json!({ "success": true })))
+}
 
-            let items: Vec<AttemptListItem> = sqlx::query_as(
-                "SELECT id, callsign, test_speed, questions_correct, copy_chars, consecutive_correct, passed, validation_status, created_at, copy_text
-                 FROM attempts WHERE callsign LIKE ? ORDER BY created_at DESC LIMIT ? OFFSET ?"
-            )
-            .bind(&pattern)
-            .bind(query.per_page)
-            .bind(offset)
-            .fetch_all(&state.db)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+#[derive(Debug, Deserialize)]
+pub struct DeleteTestQuery {
+    /// Remove the test and its questions, revisions and audio for good
+    #[serde(default)]
+    pub purge: bool,
+}
 
-            (total.0, items)
-        }
-        // Default: use dynamic query building for complex filter combinations
-        _ => {
-            // Build conditions with placeholders
-            let mut conditions = Vec::new();
-            let mut bind_values: Vec<String> = Vec::new();
-
-            if let Some(passed) = query.passed {
-                conditions.push(format!("passed = {}", if passed { 1 } else { 0 }));
-            }
+/// DELETE /api/admin/tests/:id - Deactivate test, or purge it with
+/// `?purge=true` if no attempts were graded against it
+pub async fn delete_test(
+    State(state): State<Arc<crate::AppState>>,
+    Path(test_id): Path<String>,
+    Query(query): Query<DeleteTestQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    if query.purge {
+        return purge_test(&state, &test_id).await;
+    }
 
-            if let Some(ref callsign) = query.callsign {
-                conditions.push("callsign LIKE ?".to_string());
-                bind_values.push(format!("%{}%", callsign.to_uppercase()));
-            }
+    let updated = state
+        .db
+        .tests()
+        .deactivate(&test_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-            if let Some(ref date_from) = query.date_from {
-                conditions.push("date(created_at) >= ?".to_string());
-                bind_values.push(date_from.clone());
-            }
+    if updated == 0 {
+        return Err((StatusCode::NOT_FOUND, "Test not found".to_string()));
+    }
 
-            if let Some(ref date_to) = query.date_to {
-                conditions.push("date(created_at) <= ?".to_string());
-                bind_values.push(date_to.clone());
+    Ok(Json(serde_json::json!({ "success": true })))
+}
+
+async fn purge_test(
+    state: &crate::AppState,
+    test_id: &str,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let audio_url = state
+        .db
+        .tests()
+        .purge(test_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(|e| match e {
+            crate::repo::PurgeError::NotFound => (StatusCode::NOT_FOUND, "Test not found".to_string()),
+            crate::repo::PurgeError::HasAttempts(count) => (
+                StatusCode::CONFLICT,
+                format!(
+                    "Test '{}' cannot be purged: {} attempt(s) were taken on it. Deactivate it instead.",
+                    test_id, count
+                ),
+            ),
+        })?;
+
+    // The rows are gone either way; a leftover file only takes up space
+    let audio_removed = match audio_url.as_deref().and_then(crate::bundle::local_audio_file) {
+        Some(file) => {
+            let path = std::path::Path::new(&state.static_dir).join("audio").join(file);
+            match fs::remove_file(&path).await {
+                Ok(()) => true,
+                Err(e) => {
+                    tracing::warn!("Purged test {} but kept audio {}: {}", test_id, path.display(), e);
+                    false
+                }
             }
+        }
+        None => false,
+    };
+    tracing::info!("Purged test {}", test_id);
 
-            let where_clause = format!("WHERE {}", conditions.join(" AND "));
+    Ok(Json(serde_json::json!({ "success": true, "audio_removed": audio_removed })))
+}
 
-            // Count query
-            let count_sql = format!("SELECT COUNT(*) FROM attempts {}", where_clause);
-            let mut count_query = sqlx::query_as::<_, (i64,)>(&count_sql);
-            for val in &bind_values {
-                count_query = count_query.bind(val);
-            }
-            let total: (i64,) = count_query
-                .fetch_one(&state.db)
-                .await
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+#[derive(Debug, Deserialize)]
+pub struct CloneTestRequest {
+    pub id: String,
+    pub title: String,
+    /// Give the copy its own audio file instead of sharing the original's
+    #[serde(default)]
+    pub duplicate_audio: bool,
+}
 
-            // Items query
-            let items_sql = format!(
-                "SELECT id, callsign, test_speed, questions_correct, copy_chars, consecutive_correct, passed, validation_status, created_at, copy_text
-                 FROM attempts {} ORDER BY created_at DESC LIMIT ? OFFSET ?",
-                where_clause
-            );
-            let mut items_query = sqlx::query_as::<_, AttemptListItem>(&items_sql);
-            for val in &bind_values {
-                items_query = items_query.bind(val);
-            }
-            let items: Vec<AttemptListItem> = items_query
-                .bind(query.per_page)
-                .bind(offset)
-                .fetch_all(&state.db)
-                .await
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+/// POST /api/admin/tests/:id/clone - Copy a test's draft, questions, segments
+/// and copy text under a new id and title. The copy starts inactive and
+/// unpublished.
+pub async fn clone_test(
+    State(state): State<Arc<crate::AppState>>,
+    Path(test_id): Path<String>,
+    Json(req): Json<CloneTestRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let (id, title) = (req.id.trim(), req.title.trim());
+    check_test_id(id).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    if title.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Test title is empty".to_string()));
+    }
+    let source = state
+        .db
+        .tests()
+        .find_admin(&test_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Test not found".to_string()))?;
+    let taken = |id: &str| (StatusCode::CONFLICT, format!("Test '{}' already exists", id));
+    // Checked again inside the clone transaction; this spares copying the audio
+    let existing = state
+        .db
+        .tests()
+        .find_admin(id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if existing.is_some() {
+        return Err(taken(id));
+    }
 
-            (total.0, items)
-        }
+    let (audio_url, written) = if req.duplicate_audio {
+        let (url, path) = duplicate_audio(&state.static_dir, &source.audio_url, id).await?;
+        (url, Some(path))
+    } else {
+        (source.audio_url.clone(), None)
     };
 
-    Ok(Json(AllAttemptsResponse {
-        items,
-        total,
-        page: query.page,
-        per_page: query.per_page,
-    }))
+    let cloned = match state.db.tests().clone_test(&test_id, id, title, &audio_url).await {
+        Ok(Ok(questions)) => Ok(questions),
+        Ok(Err(crate::repo::CloneError::NotFound)) => Err((StatusCode::NOT_FOUND, "Test not found".to_string())),
+        Ok(Err(crate::repo::CloneError::IdTaken)) => Err(taken(id)),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    };
+    if let (Err(_), Some(path)) = (&cloned, &written) {
+        let _ = fs::remove_file(path).await;
+    }
+    let questions = cloned?;
+    tracing::info!("Cloned test {} as {} with {} questions", test_id, id, questions);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "success": true,
+            "id": id,
+            "questions": questions,
+            "audio_url": audio_url,
+        })),
+    ))
 }
 
-/// GET /api/admin/settings - Get safe config values
-pub async fn get_settings(State(state): State<Arc<crate::AppState>>) -> impl IntoResponse {
-    // Note: We need to store config in AppState or pass it differently
-    // For now, return what we can from AppState
-    Json(SettingsResponse {
-        database_url: "[configured]".to_string(),
-        listen_addr: "[configured]".to_string(),
-        static_dir: "[configured]".to_string(),
-        log_level: "[configured]".to_string(),
-        qrz_enabled: state.qrz_client.is_some(),
-    })
+/// Copy a test's audio file next to the original, named after the test that
+/// will use it, whose id must already be checked. Returns the copy's URL and
+/// path.
+async fn duplicate_audio(
+    static_dir: &str,
+    audio_url: &str,
+    test_id: &str,
+) -> Result<(String, std::path::PathBuf), (StatusCode, String)> {
+    let file = crate::bundle::local_audio_file(audio_url).ok_or((
+        StatusCode::BAD_REQUEST,
+        format!("Only audio under /audio/ can be duplicated, not '{}'", audio_url),
+    ))?;
+    let file = std::path::Path::new(file);
+    let extension = file
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    let dir = file.parent().unwrap_or(std::path::Path::new(""));
+
+    let audio_dir = std::path::Path::new(static_dir).join("audio");
+    let mut copy = dir.join(format!("{}{}", test_id, extension));
+    if audio_dir.join(&copy).exists() {
+        copy = dir.join(format!("{}-{}{}", test_id, Utc::now().timestamp(), extension));
+    }
+    let path = audio_dir.join(&copy);
+    fs::copy(audio_dir.join(file), &path)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to copy audio {}: {}", audio_url, e)))?;
+
+    Ok((format!("/audio/{}", copy.to_string_lossy()), path))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SegmentMarkersRequest {
+    /// Marker lines (`1:02-2:06 - practice`) or an Audacity label track
+    pub markers: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SegmentMarkersPreview {
+    pub format: crate::segments::MarkerFormat,
+    pub segments: Vec<crate::Segment>,
+}
+
+/// POST /api/admin/segments/preview - Convert marker text into segments.
+/// Nothing is saved; the segments are sent back with `PUT /tests/:id`.
+pub async fn preview_segment_markers(
+    Json(req): Json<SegmentMarkersRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let (format, segments) =
+        crate::segments::parse_markers(&req.markers).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(Json(SegmentMarkersPreview { format, segments }))
+}
+
+// ============================================================================
+// TEST REVISION ENDPOINTS
+// ============================================================================
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PublishTestRequest {
+    /// What changed in this revision
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PublishedRevision {
+    pub id: String,
+    pub revision: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TestRevisionDetail {
+    #[serde(flatten)]
+    pub revision: crate::repo::TestRevisionRow,
+    pub segments: Option<Vec<crate::Segment>>,
+    pub questions: Vec<ExportedQuestion>,
+}
+
+/// POST /api/admin/tests/:id/publish - Snapshot the draft as the revision
+/// candidates see. The draft is checked as an import would be.
+pub async fn publish_test(
+    State(state): State<Arc<crate::AppState>>,
+    Path(test_id): Path<String>,
+    Json(req): Json<PublishTestRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let draft = export_test(&state.db, &test_id).await?;
+    validate_test_export(&draft)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Cannot publish: {}", e)))?;
+
+    let note = req.note.as_deref().map(str::trim).filter(|n| !n.is_empty());
+    let (revision, id) = state
+        .db
+        .test_revisions()
+        .publish(&test_id, note, Utc::now())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Test not found".to_string()))?;
+
+    tracing::info!("Published {} as revision {}", test_id, revision);
+    Ok((StatusCode::CREATED, Json(PublishedRevision { id, revision })))
+}
+
+/// GET /api/admin/tests/:id/revisions - Published revisions, newest first
+pub async fn list_test_revisions(
+    State(state): State<Arc<crate::AppState>>,
+    Path(test_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let exists = state
+        .db
+        .tests()
+        .find_admin(&test_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if exists.is_none() {
+        return Err((StatusCode::NOT_FOUND, "Test not found".to_string()));
+    }
+
+    let revisions = state
+        .db
+        .test_revisions()
+        .list(&test_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(revisions))
+}
+
+/// GET /api/admin/tests/:id/revisions/:revision - A revision as published
+pub async fn get_test_revision(
+    State(state): State<Arc<crate::AppState>>,
+    Path((test_id, revision)): Path<(String, i32)>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let revisions = state.db.test_revisions();
+    let revision = revisions
+        .find(&test_id, revision)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Revision not found".to_string()))?;
+    let questions = revisions
+        .exported_questions(&revision.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let segments = match &revision.segments {
+        Some(s) => Some(serde_json::from_str(s).map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Invalid segments JSON for revision {}: {}", revision.id, e),
+            )
+        })?),
+        None => None,
+    };
+
+    Ok(Json(TestRevisionDetail { revision, segments, questions }))
 }
 
-// ============================================================================
-// HAM2K POLO NOTES FILE GENERATION
-// ============================================================================
+// ============================================================================
+// EXAM SESSION ENDPOINTS
+// ============================================================================
+
+/// An exam session with the questions it was asked, for reviewing an attempt
+#[derive(Debug, Serialize)]
+pub struct ExamSessionDetail {
+    #[serde(flatten)]
+    pub session: crate::repo::ExamSession,
+    /// The questions drawn for the session, as it showed them
+    pub questions: Vec<crate::PublicQuestion>,
+    /// Question id -> correct answer, in the answer key's letters
+    pub correct_answers: std::collections::HashMap<String, String>,
+    /// Question id -> answer-key letter of each option as shown, for the
+    /// multiple-choice questions of a shuffled session. Candidates never see
+    /// this, or an answer key would tell them which option to pick.
+    #[serde(skip_serializing_if = "std::collections::HashMap::is_empty")]
+    pub option_letters: std::collections::HashMap<String, Vec<String>>,
+}
+
+/// GET /api/admin/exam-sessions/:id - An exam session as the candidate saw it
+pub async fn get_exam_session(
+    State(state): State<Arc<crate::AppState>>,
+    Path(session_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let session = state
+        .db
+        .exam_sessions()
+        .find(&session_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Exam session not found".to_string()))?;
+
+    let catalog = state.test_catalog();
+    let questions = catalog.session_questions(&session).await?;
+    let correct_answers = catalog.session_answer_key(&session).await?;
+    let option_letters = questions
+        .iter()
+        .filter(|q| session.shuffled && q.question_type == QuestionType::Choice)
+        .map(|q| (q.id.clone(), crate::shuffle::answer_key_letters(&session.id, &q.id, q.options.0.len())))
+        .collect();
+
+    Ok(Json(ExamSessionDetail { session, questions, correct_answers, option_letters }))
+}
+
+// ============================================================================
+// CERTIFICATE TEMPLATE ENDPOINTS
+// ============================================================================
+
+/// Ids that name fixed routes under `/certificate-templates/`
+const RESERVED_TEMPLATE_IDS: [&str; 2] = ["builtin", "preview"];
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTemplateRequest {
+    pub id: String,
+    pub name: String,
+    pub svg: String,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SaveTemplateRequest {
+    pub name: String,
+    pub svg: String,
+    /// What changed in this version
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PreviewTemplateRequest {
+    pub svg: String,
+    /// Fill in this test's speed and year instead of the sample ones
+    pub test_id: Option<String>,
+    /// `svg` (default), `pdf` or `png`
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TemplateDetail {
+    #[serde(flatten)]
+    pub template: crate::repo::CertificateTemplateVersion,
+    pub history: Vec<crate::repo::CertificateTemplateRevision>,
+}
+
+fn check_template(name: &str, svg: &str) -> Result<(), (StatusCode, String)> {
+    if name.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Template name is required".to_string()));
+    }
+    crate::certificate::validate_template(svg).map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+/// GET /api/admin/certificate-templates - All templates with their latest version
+pub async fn list_certificate_templates(
+    State(state): State<Arc<crate::AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let templates = state
+        .db
+        .certificate_templates()
+        .list()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(templates))
+}
 
-/// Regenerate the Ham2K PoLo callsign notes file from approved members
-/// File format: one callsign per line with notes
-/// Example: W1ABC 🎉 Know Code Extra #1
-pub async fn regenerate_polo_notes(state: &Arc<crate::AppState>) -> Result<(), String> {
-    #[derive(FromRow)]
-    struct ApprovedMember {
-        callsign: String,
-        certificate_number: Option<i32>,
-    }
+/// GET /api/admin/certificate-templates/builtin - The design tests use without a template
+pub async fn get_builtin_certificate_template() -> impl IntoResponse {
+    Json(serde_json::json!({
+        "version": crate::certificate::DEFAULT_TEMPLATE_VERSION,
+        "svg": crate::certificate::DEFAULT_TEMPLATE,
+    }))
+}
 
-    let members: Vec<ApprovedMember> = sqlx::query_as(
-        "SELECT callsign, certificate_number FROM attempts
-         WHERE validation_status = 'approved'
-         ORDER BY certificate_number ASC",
-    )
-    .fetch_all(&state.db)
-    .await
-    .map_err(|e| format!("Database error: {}", e))?;
-
-    let mut content = String::from("# Know Code Extra Club Members\n");
-    content.push_str("# Ham2K PoLo Callsign Notes - https://knowcodeextra.com\n\n");
-
-    for member in members {
-        let cert_num = member.certificate_number.unwrap_or(0);
-        content.push_str(&format!(
-            "{} 📜 Know Code Extra #{}\n",
-            member.callsign, cert_num
+/// POST /api/admin/certificate-templates - Create a template
+pub async fn create_certificate_template(
+    State(state): State<Arc<crate::AppState>>,
+    Json(req): Json<CreateTemplateRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let valid_id = !req.id.is_empty()
+        && req.id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !valid_id || RESERVED_TEMPLATE_IDS.contains(&req.id.as_str()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Template id must be lowercase letters, digits and hyphens".to_string(),
         ));
     }
+    check_template(&req.name, &req.svg)?;
 
-    let file_path = format!("{}/members.txt", state.static_dir);
-    fs::write(&file_path, content)
+    let created = state
+        .db
+        .certificate_templates()
+        .create(&req.id, req.name.trim(), &req.svg, req.note.as_deref(), Utc::now())
         .await
-        .map_err(|e| format!("Failed to write PoLo notes file: {}", e))?;
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    tracing::info!("Regenerated PoLo notes file at {}", file_path);
-    Ok(())
-}
+    if !created {
+        return Err((StatusCode::CONFLICT, format!("Template {} already exists", req.id)));
+    }
 
-// ============================================================================
-// ADMIN TEST CRUD ENDPOINTS
-// ============================================================================
+    Ok(Json(serde_json::json!({ "success": true, "id": req.id, "version": 1 })))
+}
 
-/// GET /api/admin/tests - List all tests (including inactive)
-pub async fn list_tests_admin(
+/// GET /api/admin/certificate-templates/:id - Latest version and history
+pub async fn get_certificate_template(
     State(state): State<Arc<crate::AppState>>,
+    Path(template_id): Path<String>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let rows: Vec<AdminTestRow> = sqlx::query_as(
-        r#"
-        SELECT t.id, t.title, t.speed_wpm, t.year, t.audio_url, t.passing_score, t.active, t.created_at, t.segments,
-               (SELECT COUNT(*) FROM questions WHERE test_id = t.id) as question_count,
-               t.expected_copy_text
-        FROM tests t
-        ORDER BY t.speed_wpm, t.title
-        "#,
-    )
-    .fetch_all(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    let tests: Vec<AdminTest> = rows.into_iter().map(|row| {
-        let test_id = row.id.clone();
-        AdminTest {
-            id: row.id,
-            title: row.title,
-            speed_wpm: row.speed_wpm,
-            year: row.year,
-            audio_url: row.audio_url,
-            passing_score: row.passing_score,
-            active: row.active,
-            created_at: row.created_at,
-            question_count: row.question_count,
-            segments: row.segments.and_then(|s| {
-                serde_json::from_str(&s).map_err(|e| {
-                    tracing::warn!("Failed to parse segments JSON for test {}: {}", test_id, e);
-                    e
-                }).ok()
-            }),
-            expected_copy_text: row.expected_copy_text,
-        }
-    }).collect();
+    let templates = state.db.certificate_templates();
+    let template = templates
+        .find(&template_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Template not found".to_string()))?;
+    let history = templates
+        .history(&template_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    Ok(Json(tests))
+    Ok(Json(TemplateDetail { template, history }))
 }
 
-/// POST /api/admin/tests - Create new test
-pub async fn create_test(
+/// GET /api/admin/certificate-templates/:id/versions/:version - An earlier version
+pub async fn get_certificate_template_version(
     State(state): State<Arc<crate::AppState>>,
-    Json(req): Json<CreateTestRequest>,
+    Path((template_id, version)): Path<(String, i32)>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let now = chrono::Utc::now();
-
-    sqlx::query(
-        "INSERT INTO tests (id, title, speed_wpm, year, audio_url, passing_score, active, created_at)
-         VALUES (?, ?, ?, ?, ?, ?, 1, ?)",
-    )
-    .bind(&req.id)
-    .bind(&req.title)
-    .bind(req.speed_wpm)
-    .bind(&req.year)
-    .bind(&req.audio_url)
-    .bind(req.passing_score)
-    .bind(now.to_rfc3339())
-    .execute(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let template = state
+        .db
+        .certificate_templates()
+        .find_version(&template_id, version)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Template version not found".to_string()))?;
 
-    Ok(Json(serde_json::json!({ "success": true, "id": req.id })))
+    Ok(Json(template))
 }
 
-/// PUT /api/admin/tests/:id - Update test
-pub async fn update_test(
+/// PUT /api/admin/certificate-templates/:id - Save a new version
+pub async fn save_certificate_template(
     State(state): State<Arc<crate::AppState>>,
-    Path(test_id): Path<String>,
-    Json(req): Json<UpdateTestRequest>,
+    Path(template_id): Path<String>,
+    Json(req): Json<SaveTemplateRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    tracing::info!("update_test called for id {}: {:?}", test_id, req);
-    let mut updates = Vec::new();
-    let mut bindings: Vec<String> = Vec::new();
-
-    if let Some(ref title) = req.title {
-        updates.push("title = ?");
-        bindings.push(title.clone());
-    }
-    if let Some(speed) = req.speed_wpm {
-        updates.push("speed_wpm = ?");
-        bindings.push(speed.to_string());
-    }
-    if let Some(ref year) = req.year {
-        updates.push("year = ?");
-        bindings.push(year.clone());
-    }
-    if let Some(ref url) = req.audio_url {
-        updates.push("audio_url = ?");
-        bindings.push(url.clone());
-    }
-    if let Some(score) = req.passing_score {
-        updates.push("passing_score = ?");
-        bindings.push(score.to_string());
-    }
-    if let Some(active) = req.active {
-        updates.push("active = ?");
-        bindings.push(if active {
-            "1".to_string()
-        } else {
-            "0".to_string()
-        });
-    }
-    if let Some(ref segments) = req.segments {
-        let segments_json = serde_json::to_string(segments)
-            .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid segments: {}", e)))?;
-        updates.push("segments = ?");
-        bindings.push(segments_json);
-    }
-    if let Some(ref text) = req.expected_copy_text {
-        updates.push("expected_copy_text = ?");
-        bindings.push(text.clone());
-    }
-
-    if updates.is_empty() {
-        return Ok(Json(
-            serde_json::json!({ "success": true, "updated": false }),
-        ));
-    }
+    check_template(&req.name, &req.svg)?;
 
-    let query = format!("UPDATE tests SET {} WHERE id = ?", updates.join(", "));
-    let mut q = sqlx::query(&query);
-    for b in &bindings {
-        q = q.bind(b);
-    }
-    q = q.bind(&test_id);
-
-    let result = q
-        .execute(&state.db)
+    let version = state
+        .db
+        .certificate_templates()
+        .save_version(&template_id, req.name.trim(), &req.svg, req.note.as_deref(), Utc::now())
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Template not found".to_string()))?;
 
-    if result.rows_affected() == 0 {
-        return Err((StatusCode::NOT_FOUND, "Test not found".to_string()));
-    }
-
-    Ok(Json(serde_json::json!({ "success": true })))
+    Ok(Json(serde_json::json!({ "success": true, "version": version })))
 }
 
-/// DELETE /api/admin/tests/:id - Deactivate test
-pub async fn delete_test(
+/// POST /api/admin/certificate-templates/preview - Render unsaved SVG with sample data
+pub async fn preview_certificate_template(
     State(state): State<Arc<crate::AppState>>,
-    Path(test_id): Path<String>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let result = sqlx::query("UPDATE tests SET active = 0 WHERE id = ?")
-        .bind(&test_id)
-        .execute(&state.db)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    if result.rows_affected() == 0 {
-        return Err((StatusCode::NOT_FOUND, "Test not found".to_string()));
+    Json(req): Json<PreviewTemplateRequest>,
+) -> Result<axum::response::Response, (StatusCode, String)> {
+    let format = match req.format.as_deref() {
+        None | Some("svg") => None,
+        Some(format) => Some(crate::render::RenderFormat::parse(format).ok_or((
+            StatusCode::BAD_REQUEST,
+            "format must be svg, pdf or png".to_string(),
+        ))?),
+    };
+    crate::certificate::validate_template(&req.svg).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let mut sample = crate::certificate::CertificateData::sample(&state.config.public_url);
+    if let Some(test_id) = &req.test_id {
+        let test = state
+            .db
+            .tests()
+            .find_admin(test_id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .ok_or((StatusCode::NOT_FOUND, "Test not found".to_string()))?;
+        sample.speed_wpm = test.speed_wpm;
+        sample.year = test.year;
     }
+    let svg = sample.render(&req.svg);
 
-    Ok(Json(serde_json::json!({ "success": true })))
+    let Some(format) = format else {
+        return Ok(crate::certificate::svg_response(svg));
+    };
+
+    let bytes = state
+        .certificate_renderer
+        .render_uncached(format, svg)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    Ok(([(axum::http::header::CONTENT_TYPE, format.content_type())], bytes).into_response())
 }
 
 // ============================================================================
@@ -1007,22 +1289,30 @@ pub async fn delete_test(
 pub struct CreateQuestionRequest {
     pub question_number: i32,
     pub question_text: String,
-    pub option_a: String,
-    pub option_b: String,
-    pub option_c: String,
-    pub option_d: String,
+    #[serde(default)]
+    pub question_type: QuestionType,
+    /// Multiple-choice options, lettered A, B, C... in order
+    #[serde(default)]
+    pub options: Vec<String>,
+    /// The option letter, TRUE or FALSE, or the expected text
     pub correct_option: String,
+    /// Further free-text answers accepted
+    #[serde(default)]
+    pub accepted_answers: Vec<String>,
+    /// What the question asks about, such as "operator name" or "weather"
+    pub topic: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateQuestionRequest {
     pub question_number: Option<i32>,
     pub question_text: Option<String>,
-    pub option_a: Option<String>,
-    pub option_b: Option<String>,
-    pub option_c: Option<String>,
-    pub option_d: Option<String>,
+    pub question_type: Option<QuestionType>,
+    pub options: Option<Vec<String>>,
     pub correct_option: Option<String>,
+    pub accepted_answers: Option<Vec<String>>,
+    /// An empty string removes the topic
+    pub topic: Option<String>,
 }
 
 #[derive(Debug, Serialize, FromRow)]
@@ -1031,27 +1321,41 @@ pub struct AdminQuestion {
     pub test_id: String,
     pub question_number: i32,
     pub question_text: String,
-    pub option_a: String,
-    pub option_b: String,
-    pub option_c: String,
-    pub option_d: String,
+    #[sqlx(try_from = "String")]
+    pub question_type: QuestionType,
+    #[sqlx(try_from = "String")]
+    pub options: JsonList,
     pub correct_option: String,
+    #[sqlx(try_from = "String")]
+    pub accepted_answers: JsonList,
+    pub topic: Option<String>,
     pub created_at: DateTime<Utc>,
 }
 
+/// A topic as stored: trimmed, with blank meaning none
+fn normalize_topic(topic: Option<&str>) -> Option<&str> {
+    topic.map(str::trim).filter(|topic| !topic.is_empty())
+}
+
+/// A correct answer as stored: letters and TRUE/FALSE upper-case, text trimmed
+fn normalize_correct_option(question_type: QuestionType, correct: &str) -> String {
+    match question_type {
+        QuestionType::Choice | QuestionType::TrueFalse => correct.trim().to_uppercase(),
+        QuestionType::Text => correct.trim().to_string(),
+    }
+}
+
 /// GET /api/admin/tests/:test_id/questions - List all questions for a test
 pub async fn list_questions_admin(
     State(state): State<Arc<crate::AppState>>,
     Path(test_id): Path<String>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let questions: Vec<AdminQuestion> = sqlx::query_as(
-        "SELECT id, test_id, question_number, question_text, option_a, option_b, option_c, option_d, correct_option, created_at
-         FROM questions WHERE test_id = ? ORDER BY question_number"
-    )
-    .bind(&test_id)
-    .fetch_all(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let questions = state
+        .db
+        .questions()
+        .list_admin(&test_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     Ok(Json(questions))
 }
@@ -1062,31 +1366,37 @@ pub async fn create_question(
     Path(test_id): Path<String>,
     Json(req): Json<CreateQuestionRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    // Validate correct_option
-    if !["A", "B", "C", "D"].contains(&req.correct_option.to_uppercase().as_str()) {
-        return Err((StatusCode::BAD_REQUEST, "correct_option must be A, B, C, or D".to_string()));
+    let question = ExportedQuestion {
+        question_number: req.question_number,
+        question_text: req.question_text,
+        question_type: req.question_type,
+        correct_option: normalize_correct_option(req.question_type, &req.correct_option),
+        options: JsonList(req.options),
+        accepted_answers: JsonList(req.accepted_answers),
+        topic: normalize_topic(req.topic.as_deref()).map(str::to_string),
+    };
+    let problems = validate_question(&question);
+    if !problems.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, problems.join("; ")));
     }
 
-    let id = uuid::Uuid::new_v4().to_string();
-    let now = chrono::Utc::now();
-
-    sqlx::query(
-        "INSERT INTO questions (id, test_id, question_number, question_text, option_a, option_b, option_c, option_d, correct_option, created_at)
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
-    )
-    .bind(&id)
-    .bind(&test_id)
-    .bind(req.question_number)
-    .bind(&req.question_text)
-    .bind(&req.option_a)
-    .bind(&req.option_b)
-    .bind(&req.option_c)
-    .bind(&req.option_d)
-    .bind(req.correct_option.to_uppercase())
-    .bind(now.to_rfc3339())
-    .execute(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let options = question.options.as_strs();
+    let accepted_answers = question.accepted_answers.as_strs();
+    let id = state
+        .db
+        .questions()
+        .insert(&crate::repo::NewQuestion {
+            test_id: &test_id,
+            question_number: question.question_number,
+            question_text: &question.question_text,
+            question_type: question.question_type,
+            options: &options,
+            correct_option: &question.correct_option,
+            accepted_answers: &accepted_answers,
+            topic: question.topic.as_deref(),
+        })
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     Ok(Json(serde_json::json!({ "success": true, "id": id })))
 }
@@ -1097,59 +1407,78 @@ pub async fn update_question(
     Path(question_id): Path<String>,
     Json(req): Json<UpdateQuestionRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    if let Some(ref opt) = req.correct_option {
-        if !["A", "B", "C", "D"].contains(&opt.to_uppercase().as_str()) {
-            return Err((StatusCode::BAD_REQUEST, "correct_option must be A, B, C, or D".to_string()));
-        }
+    let current = state
+        .db
+        .questions()
+        .find_admin(&question_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Question not found".to_string()))?;
+
+    // The question as it will be, checked as a whole since type, options and
+    // answer depend on each other
+    let question_type = req.question_type.unwrap_or(current.question_type);
+    // Options and accepted answers belong to one type, so a type change
+    // drops whichever of them the request leaves out
+    let type_changed = question_type != current.question_type;
+    let (current_options, current_accepted) = if type_changed {
+        (JsonList::default(), JsonList::default())
+    } else {
+        (current.options, current.accepted_answers)
+    };
+    let question = ExportedQuestion {
+        question_number: req.question_number.unwrap_or(current.question_number),
+        question_text: req.question_text.clone().unwrap_or(current.question_text),
+        question_type,
+        options: req.options.clone().map_or(current_options, JsonList),
+        correct_option: normalize_correct_option(
+            question_type,
+            req.correct_option.as_deref().unwrap_or(&current.correct_option),
+        ),
+        accepted_answers: req.accepted_answers.clone().map_or(current_accepted, JsonList),
+        topic: None,
+    };
+    let problems = validate_question(&question);
+    if !problems.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, problems.join("; ")));
     }
 
-    let mut updates = Vec::new();
-    let mut bindings: Vec<String> = Vec::new();
+    let mut changes: Vec<(&'static str, Value)> = Vec::new();
 
-    if let Some(num) = req.question_number {
-        updates.push("question_number = ?");
-        bindings.push(num.to_string());
+    if req.question_number.is_some() {
+        changes.push(("question_number", question.question_number.into()));
     }
-    if let Some(ref text) = req.question_text {
-        updates.push("question_text = ?");
-        bindings.push(text.clone());
+    if req.question_text.is_some() {
+        changes.push(("question_text", question.question_text.into()));
     }
-    if let Some(ref a) = req.option_a {
-        updates.push("option_a = ?");
-        bindings.push(a.clone());
+    if req.question_type.is_some() {
+        changes.push(("question_type", question_type.as_str().into()));
     }
-    if let Some(ref b) = req.option_b {
-        updates.push("option_b = ?");
-        bindings.push(b.clone());
+    if req.options.is_some() || type_changed {
+        changes.push(("options", question.options.0.as_slice().into()));
     }
-    if let Some(ref c) = req.option_c {
-        updates.push("option_c = ?");
-        bindings.push(c.clone());
+    if req.correct_option.is_some() || req.question_type.is_some() {
+        changes.push(("correct_option", question.correct_option.into()));
     }
-    if let Some(ref d) = req.option_d {
-        updates.push("option_d = ?");
-        bindings.push(d.clone());
+    if req.accepted_answers.is_some() || type_changed {
+        changes.push(("accepted_answers", question.accepted_answers.0.as_slice().into()));
     }
-    if let Some(ref opt) = req.correct_option {
-        updates.push("correct_option = ?");
-        bindings.push(opt.to_uppercase());
+    if let Some(ref topic) = req.topic {
+        changes.push(("topic", normalize_topic(Some(topic)).into()));
     }
 
-    if updates.is_empty() {
+    if changes.is_empty() {
         return Ok(Json(serde_json::json!({ "success": true, "updated": false })));
     }
 
-    let query = format!("UPDATE questions SET {} WHERE id = ?", updates.join(", "));
-    let mut q = sqlx::query(&query);
-    for b in &bindings {
-        q = q.bind(b);
-    }
-    q = q.bind(&question_id);
-
-    let result = q.execute(&state.db).await
+    let updated = state
+        .db
+        .questions()
+        .update(&question_id, changes)
+        .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    if result.rows_affected() == 0 {
+    if updated == 0 {
         return Err((StatusCode::NOT_FOUND, "Question not found".to_string()));
     }
 
@@ -1161,13 +1490,14 @@ pub async fn delete_question(
     State(state): State<Arc<crate::AppState>>,
     Path(question_id): Path<String>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let result = sqlx::query("DELETE FROM questions WHERE id = ?")
-        .bind(&question_id)
-        .execute(&state.db)
+    let deleted = state
+        .db
+        .questions()
+        .delete(&question_id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    if result.rows_affected() == 0 {
+    if deleted == 0 {
         return Err((StatusCode::NOT_FOUND, "Question not found".to_string()));
     }
 
@@ -1182,12 +1512,12 @@ pub async fn delete_question(
 pub async fn list_prosigns(
     State(state): State<Arc<crate::AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let mappings: Vec<ProsignMapping> = sqlx::query_as(
-        "SELECT id, prosign, alternate, created_at FROM prosign_mappings ORDER BY prosign"
-    )
-    .fetch_all(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let mappings = state
+        .db
+        .prosigns()
+        .list()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     Ok(Json(mappings))
 }
@@ -1210,31 +1540,19 @@ pub async fn create_prosign(
         return Err((StatusCode::BAD_REQUEST, "Prosign must be wrapped in <> (e.g., <BT>)".to_string()));
     }
 
-    let id = uuid::Uuid::new_v4().to_string();
-    let now = chrono::Utc::now();
-
-    let result = sqlx::query(
-        "INSERT INTO prosign_mappings (id, prosign, alternate, created_at) VALUES (?, ?, ?, ?)"
-    )
-    .bind(&id)
-    .bind(&prosign)
-    .bind(&alternate)
-    .bind(now.to_rfc3339())
-    .execute(&state.db)
-    .await;
+    let result = state.db.prosigns().insert(&prosign, &alternate).await;
 
     match result {
-        Ok(_) => {
+        Ok(id) => {
             tracing::info!("create_prosign success: {} = {}", prosign, alternate);
             Ok(Json(serde_json::json!({ "success": true, "id": id })))
         }
         Err(e) => {
-            let err_str = e.to_string();
-            tracing::error!("create_prosign failed: {}", err_str);
-            if err_str.contains("UNIQUE constraint failed") {
+            tracing::error!("create_prosign failed: {}", e);
+            if is_unique_violation(&e) {
                 Err((StatusCode::CONFLICT, format!("Prosign '{}' already exists", prosign)))
             } else {
-                Err((StatusCode::INTERNAL_SERVER_ERROR, err_str))
+                Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
             }
         }
     }
@@ -1265,16 +1583,13 @@ pub async fn update_prosign(
         ));
     }
 
-    let result = sqlx::query(
-        "UPDATE prosign_mappings SET prosign = ?, alternate = ? WHERE id = ?",
-    )
-    .bind(&prosign)
-    .bind(&alternate)
-    .bind(&prosign_id)
-    .execute(&state.db)
-    .await
-    .map_err(|e| {
-        if e.to_string().contains("UNIQUE constraint failed") {
+    let updated = state
+        .db
+        .prosigns()
+        .update(&prosign_id, &prosign, &alternate)
+        .await
+        .map_err(|e| {
+        if is_unique_violation(&e) {
             (
                 StatusCode::CONFLICT,
                 format!("Prosign '{}' already exists", prosign),
@@ -1284,7 +1599,7 @@ pub async fn update_prosign(
         }
     })?;
 
-    if result.rows_affected() == 0 {
+    if updated == 0 {
         return Err((
             StatusCode::NOT_FOUND,
             "Prosign mapping not found".to_string(),
@@ -1299,13 +1614,14 @@ pub async fn delete_prosign(
     State(state): State<Arc<crate::AppState>>,
     Path(prosign_id): Path<String>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let result = sqlx::query("DELETE FROM prosign_mappings WHERE id = ?")
-        .bind(&prosign_id)
-        .execute(&state.db)
+    let deleted = state
+        .db
+        .prosigns()
+        .delete(&prosign_id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    if result.rows_affected() == 0 {
+    if deleted == 0 {
         return Err((StatusCode::NOT_FOUND, "Prosign mapping not found".to_string()));
     }
 
@@ -1316,7 +1632,7 @@ pub async fn delete_prosign(
 // AUDIO UPLOAD ENDPOINT
 // ============================================================================
 
-const MAX_AUDIO_SIZE: usize = 50 * 1024 * 1024; // 50MB
+pub const MAX_AUDIO_SIZE: usize = 50 * 1024 * 1024; // 50MB
 
 #[derive(Debug, Serialize)]
 pub struct UploadResponse {
@@ -1429,25 +1745,22 @@ pub async fn upload_audio(
 pub async fn get_email_template(
     State(state): State<Arc<crate::AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let template_result: Option<(String,)> = sqlx::query_as(
-        "SELECT value FROM settings WHERE key = 'email_template'"
-    )
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    let subject_result: Option<(String,)> = sqlx::query_as(
-        "SELECT value FROM settings WHERE key = 'email_subject'"
-    )
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    let template = template_result.map(|r| r.0).unwrap_or_else(|| {
+    let settings = state.db.settings();
+    let template_result = settings
+        .get("email_template")
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let subject_result = settings
+        .get("email_subject")
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let template = template_result.unwrap_or_else(|| {
         "Hello {nickname},\n\nCongratulations on passing the Know Code Extra examination!\n\nYour certificate number is #{member_number}.\n\n73,\nKnow Code Extra".to_string()
     });
 
-    let subject = subject_result.map(|r| r.0).unwrap_or_else(|| {
+    let subject = subject_result.unwrap_or_else(|| {
         "Congratulations {callsign} - Know Code Extra Certificate #{member_number}".to_string()
     });
 
@@ -1459,24 +1772,17 @@ pub async fn save_email_template(
     State(state): State<Arc<crate::AppState>>,
     Json(req): Json<EmailTemplateRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    sqlx::query(
-        "INSERT INTO settings (key, value) VALUES ('email_template', ?)
-         ON CONFLICT(key) DO UPDATE SET value = excluded.value"
-    )
-    .bind(&req.template)
-    .execute(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    if let Some(ref subject) = req.subject {
-        sqlx::query(
-            "INSERT INTO settings (key, value) VALUES ('email_subject', ?)
-             ON CONFLICT(key) DO UPDATE SET value = excluded.value"
-        )
-        .bind(subject)
-        .execute(&state.db)
+    let settings = state.db.settings();
+    settings
+        .set("email_template", &req.template)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if let Some(ref subject) = req.subject {
+        settings
+            .set("email_subject", subject)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     }
 
     Ok(Json(serde_json::json!({ "success": true })))
@@ -1488,71 +1794,359 @@ pub async fn generate_email(
     Json(req): Json<GenerateEmailRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     // Get member info
-    #[derive(FromRow)]
-    struct MemberInfo {
-        callsign: String,
-        certificate_number: Option<i32>,
-        email: Option<String>,
-    }
-
-    let member: MemberInfo = sqlx::query_as(
-        "SELECT callsign, certificate_number, email FROM attempts WHERE id = ? AND validation_status = 'approved'"
-    )
-    .bind(&req.member_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-    .ok_or((StatusCode::NOT_FOUND, "Member not found".to_string()))?;
+    let (callsign, certificate_number, recipient_email) = state
+        .db
+        .attempts()
+        .approved_member(&req.member_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Member not found".to_string()))?;
 
     // Get template
-    let template_result: Option<(String,)> = sqlx::query_as(
-        "SELECT value FROM settings WHERE key = 'email_template'"
-    )
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    let template = template_result.map(|r| r.0).unwrap_or_else(|| {
+    let settings = state.db.settings();
+    let template_result = settings
+        .get("email_template")
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let template = template_result.unwrap_or_else(|| {
         "Hello {nickname},\n\nCongratulations on passing the Know Code Extra examination!\n\nYour certificate number is #{member_number}.\n\n73,\nKnow Code Extra".to_string()
     });
 
     // Get subject template
-    let subject_result: Option<(String,)> = sqlx::query_as(
-        "SELECT value FROM settings WHERE key = 'email_subject'"
-    )
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    let subject_template = subject_result.map(|r| r.0).unwrap_or_else(|| {
+    let subject_result = settings
+        .get("email_subject")
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let subject_template = subject_result.unwrap_or_else(|| {
         "Congratulations {callsign} - Know Code Extra Certificate #{member_number}".to_string()
     });
 
     // Get nickname from QRZ if available
     let nickname = if let Some(ref qrz) = state.qrz_client {
-        qrz.lookup_name(&member.callsign).await.ok().flatten()
-            .unwrap_or_else(|| member.callsign.clone())
+        qrz.lookup_name(&callsign).await.ok().flatten()
+            .unwrap_or_else(|| callsign.clone())
     } else {
-        member.callsign.clone()
+        callsign.clone()
     };
 
-    let member_number = member.certificate_number.unwrap_or(0).to_string();
+    let member_number = certificate_number.unwrap_or(0).to_string();
 
     // Replace placeholders in subject
     let subject = subject_template
-        .replace("{callsign}", &member.callsign)
+        .replace("{callsign}", &callsign)
         .replace("{member_number}", &member_number)
         .replace("{nickname}", &nickname);
 
     // Replace placeholders in body
     let email = template
-        .replace("{callsign}", &member.callsign)
+        .replace("{callsign}", &callsign)
         .replace("{member_number}", &member_number)
         .replace("{nickname}", &nickname);
 
     Ok(Json(GenerateEmailResponse {
         subject,
         email,
-        recipient_email: member.email,
+        recipient_email,
     }))
 }
+
+// ============================================================================
+// TEST IMPORT / EXPORT
+// ============================================================================
+
+/// Portable JSON representation of a test and its questions
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TestExport {
+    pub id: String,
+    pub title: String,
+    pub speed_wpm: i32,
+    pub year: String,
+    pub audio_url: String,
+    pub passing_score: i32,
+    pub active: bool,
+    #[serde(default)]
+    pub segments: Option<Vec<crate::Segment>>,
+    #[serde(default)]
+    pub expected_copy_text: Option<String>,
+    /// Questions drawn per exam session, or None to ask them all
+    #[serde(default)]
+    pub draw_count: Option<i32>,
+    pub questions: Vec<ExportedQuestion>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[serde(from = "ExportedQuestionFields")]
+pub struct ExportedQuestion {
+    pub question_number: i32,
+    pub question_text: String,
+    #[sqlx(try_from = "String")]
+    pub question_type: QuestionType,
+    /// Multiple-choice options, lettered A, B, C... in order
+    #[sqlx(try_from = "String")]
+    pub options: JsonList,
+    pub correct_option: String,
+    #[sqlx(try_from = "String")]
+    pub accepted_answers: JsonList,
+    pub topic: Option<String>,
+}
+
+/// An exported question as read. Exports from before question types list
+/// exactly four options as `option_a` to `option_d`.
+#[derive(Deserialize)]
+struct ExportedQuestionFields {
+    question_number: i32,
+    question_text: String,
+    #[serde(default)]
+    question_type: QuestionType,
+    #[serde(default)]
+    options: Vec<String>,
+    option_a: Option<String>,
+    option_b: Option<String>,
+    option_c: Option<String>,
+    option_d: Option<String>,
+    correct_option: String,
+    #[serde(default)]
+    accepted_answers: Vec<String>,
+    #[serde(default)]
+    topic: Option<String>,
+}
+
+impl From<ExportedQuestionFields> for ExportedQuestion {
+    fn from(fields: ExportedQuestionFields) -> Self {
+        let mut options = fields.options;
+        if options.is_empty() {
+            options = [fields.option_a, fields.option_b, fields.option_c, fields.option_d]
+                .into_iter()
+                .flatten()
+                .collect();
+        }
+        ExportedQuestion {
+            question_number: fields.question_number,
+            question_text: fields.question_text,
+            question_type: fields.question_type,
+            options: JsonList(options),
+            correct_option: fields.correct_option,
+            accepted_answers: JsonList(fields.accepted_answers),
+            topic: fields.topic,
+        }
+    }
+}
+
+/// Load a test with its questions for export
+pub async fn export_test(db: &Db, test_id: &str) -> Result<TestExport, (StatusCode, String)> {
+    let row = db
+        .tests()
+        .find_admin(test_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let row = row.ok_or((StatusCode::NOT_FOUND, "Test not found".to_string()))?;
+
+    let questions = db
+        .questions()
+        .list_exported(test_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let segments = match row.segments {
+        Some(s) => Some(serde_json::from_str(&s).map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Invalid segments JSON for test {}: {}", row.id, e),
+            )
+        })?),
+        None => None,
+    };
+
+    Ok(TestExport {
+        id: row.id,
+        title: row.title,
+        speed_wpm: row.speed_wpm,
+        year: row.year,
+        audio_url: row.audio_url,
+        passing_score: row.passing_score,
+        active: row.active,
+        segments,
+        expected_copy_text: row.expected_copy_text,
+        draw_count: row.draw_count,
+        questions,
+    })
+}
+
+/// Test ids appear in URLs and file names, so they are kept to a safe set
+fn check_test_id(id: &str) -> Result<(), String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Test id '{}' must be letters, digits, '-' or '_'", id));
+    }
+    Ok(())
+}
+
+/// Check an exported test before it is imported, reporting every problem found
+pub fn validate_test_export(test: &TestExport) -> Result<(), String> {
+    let mut problems = Vec::new();
+
+    if let Err(e) = check_test_id(&test.id) {
+        problems.push(e);
+    }
+    if test.title.trim().is_empty() {
+        problems.push("Test title is empty".to_string());
+    }
+    if let Err(e) = crate::classes::check_speed(test.speed_wpm) {
+        problems.push(e);
+    }
+    if test.passing_score < 0 {
+        problems.push("passing_score must not be negative".to_string());
+    }
+
+    // Questions are numbered 1..=n, in any order
+    let mut numbers: Vec<i32> = test.questions.iter().map(|q| q.question_number).collect();
+    numbers.sort_unstable();
+    if numbers.iter().zip(1..).any(|(n, expected)| *n != expected) {
+        problems.push(format!(
+            "Questions must be numbered 1 to {} without gaps or duplicates, found {:?}",
+            numbers.len(),
+            numbers
+        ));
+    }
+    for q in &test.questions {
+        problems.extend(validate_question(q));
+    }
+
+    if let Some(count) = test.draw_count {
+        if count < 1 || count as usize > test.questions.len() {
+            problems.push(format!(
+                "draw_count must be between 1 and the {} questions, found {}",
+                test.questions.len(),
+                count
+            ));
+        }
+        if test.passing_score > count {
+            problems.push(format!(
+                "passing_score {} is more than the {} questions drawn",
+                test.passing_score, count
+            ));
+        }
+    }
+
+    problems.extend(crate::segments::validate(test.segments.as_deref().unwrap_or_default()));
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems.join("; "))
+    }
+}
+
+/// Problems with a question's text, options and answer for its type
+pub fn validate_question(q: &ExportedQuestion) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut problem = |message: String| problems.push(format!("Question {}: {}", q.question_number, message));
+
+    if q.question_text.trim().is_empty() {
+        problem("question text is required".to_string());
+    }
+    if q.question_type != QuestionType::Choice && !q.options.0.is_empty() {
+        problem("only multiple-choice questions have options".to_string());
+    }
+    if q.question_type != QuestionType::Text && !q.accepted_answers.0.is_empty() {
+        problem("only text questions have accepted_answers".to_string());
+    }
+    match q.question_type {
+        QuestionType::Choice => {
+            let count = q.options.0.len();
+            if !(2..=crate::grading::MAX_OPTIONS).contains(&count) {
+                problem(format!(
+                    "multiple choice needs 2 to {} options, found {}",
+                    crate::grading::MAX_OPTIONS,
+                    count
+                ));
+            } else if q.options.0.iter().any(|option| option.trim().is_empty()) {
+                problem("options must not be empty".to_string());
+            }
+            if crate::grading::option_index(&q.correct_option, count).is_none() {
+                let last = crate::grading::option_letter(count.clamp(1, crate::grading::MAX_OPTIONS) - 1);
+                problem(format!(
+                    "correct_option must be a letter from A to {}, found '{}'",
+                    last, q.correct_option
+                ));
+            }
+        }
+        QuestionType::TrueFalse => {
+            if !["TRUE", "FALSE"].contains(&q.correct_option.as_str()) {
+                problem(format!("correct_option must be TRUE or FALSE, found '{}'", q.correct_option));
+            }
+        }
+        QuestionType::Text => {
+            if q.correct_option.trim().is_empty() {
+                problem("correct_option must give the expected answer".to_string());
+            }
+        }
+    }
+    problems
+}
+
+/// Insert an exported test and its questions in a single transaction.
+/// Fails if a test with the same id already exists.
+pub async fn import_test(db: &Db, test: &TestExport) -> Result<(), (StatusCode, String)> {
+    validate_test_export(test).map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid test: {}", e)))?;
+    let segments_json = test
+        .segments
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid segments: {}", e)))?;
+
+    let new_test = crate::repo::NewTest {
+        id: &test.id,
+        title: &test.title,
+        speed_wpm: test.speed_wpm,
+        year: &test.year,
+        audio_url: &test.audio_url,
+        passing_score: test.passing_score,
+        draw_count: test.draw_count,
+        active: test.active,
+        segments: segments_json,
+        expected_copy_text: test.expected_copy_text.as_deref(),
+    };
+    let lists: Vec<(Vec<&str>, Vec<&str>)> = test
+        .questions
+        .iter()
+        .map(|q| (q.options.as_strs(), q.accepted_answers.as_strs()))
+        .collect();
+    let questions: Vec<crate::repo::NewQuestion> = test
+        .questions
+        .iter()
+        .zip(&lists)
+        .map(|(q, (options, accepted_answers))| crate::repo::NewQuestion {
+            test_id: &test.id,
+            question_number: q.question_number,
+            question_text: &q.question_text,
+            question_type: q.question_type,
+            options,
+            correct_option: &q.correct_option,
+            accepted_answers,
+            topic: normalize_topic(q.topic.as_deref()),
+        })
+        .collect();
+
+    let inserted = db
+        .tests()
+        .insert_with_questions(&new_test, &questions)
+        .await
+        .map_err(|e| match e {
+            // Constraint violations (e.g. duplicate question numbers) are bad input
+            sqlx::Error::Database(_) => (StatusCode::BAD_REQUEST, format!("Invalid test: {}", e)),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })?;
+
+    if !inserted {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("Test '{}' already exists", test.id),
+        ));
+    }
+
+    Ok(())
+}
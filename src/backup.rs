@@ -0,0 +1,596 @@
+//! Online database backups
+//!
+//! Snapshots are taken with `VACUUM INTO` on the live pool, so they are
+//! consistent without stopping the server. Every backup is recorded in
+//! `manifest.json` in the backup directory along with its SHA-256 checksum and
+//! schema version, which `restore` checks before swapping files.
+
+use axum::{
+    extract::{Path as UrlPath, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::SqlitePool;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Error for backup/restore requests against a PostgreSQL database
+pub const POSTGRES_UNSUPPORTED: &str =
+    "Built-in backups only support SQLite; use pg_dump for PostgreSQL";
+
+/// Serializes backups so a scheduled run and a manual trigger never overlap
+static BACKUP_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Backup settings, built from `Config`
+#[derive(Debug, Clone)]
+pub struct BackupSettings {
+    /// Take backups on a schedule while the server runs
+    pub enabled: bool,
+    pub dir: PathBuf,
+    pub interval: Duration,
+    /// Backups older than this many days are pruned (the newest is always kept)
+    pub retention_days: u32,
+    pub compress: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupEntry {
+    pub file: String,
+    pub created_at: DateTime<Utc>,
+    pub size_bytes: u64,
+    pub sha256: String,
+    pub schema_version: i64,
+    pub compressed: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub backups: Vec<BackupEntry>,
+}
+
+impl Manifest {
+    pub fn find(&self, file: &str) -> Option<&BackupEntry> {
+        self.backups.iter().find(|b| b.file == file)
+    }
+}
+
+/// Load the manifest from a backup directory (empty if none exists yet)
+pub fn read_manifest(dir: &Path) -> Result<Manifest, String> {
+    let path = dir.join(MANIFEST_FILE);
+    if !path.exists() {
+        return Ok(Manifest::default());
+    }
+    let json = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&json).map_err(|e| format!("Invalid {}: {}", path.display(), e))
+}
+
+/// Replace the manifest atomically
+fn write_manifest(dir: &Path, manifest: &Manifest) -> Result<(), String> {
+    let path = dir.join(MANIFEST_FILE);
+    let tmp = dir.join(format!("{}.tmp", MANIFEST_FILE));
+    let json = serde_json::to_string_pretty(manifest).map_err(|e| e.to_string())?;
+    std::fs::write(&tmp, json).map_err(|e| format!("Failed to write manifest: {}", e))?;
+    std::fs::rename(&tmp, &path).map_err(|e| format!("Failed to write manifest: {}", e))
+}
+
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file =
+        File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Write a consistent copy of the live database to `path`, which must not exist
+pub async fn snapshot(pool: &SqlitePool, path: &Path) -> Result<(), String> {
+    if path.exists() {
+        return Err(format!("{} already exists", path.display()));
+    }
+    let target = path.to_str().ok_or("Backup path is not valid UTF-8")?;
+
+    sqlx::query("VACUUM INTO ?")
+        .bind(target)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Snapshot failed: {}", e))?;
+    Ok(())
+}
+
+fn gzip_file(src: &Path, dest: &Path) -> Result<(), String> {
+    let mut input = BufReader::new(File::open(src).map_err(|e| e.to_string())?);
+    let output = BufWriter::new(File::create(dest).map_err(|e| e.to_string())?);
+    let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+    std::io::copy(&mut input, &mut encoder).map_err(|e| format!("Compression failed: {}", e))?;
+    encoder
+        .finish()
+        .map_err(|e| format!("Compression failed: {}", e))?;
+    Ok(())
+}
+
+fn gunzip_file(src: &Path, dest: &Path) -> Result<(), String> {
+    let input = BufReader::new(File::open(src).map_err(|e| e.to_string())?);
+    let mut decoder = flate2::read::GzDecoder::new(input);
+    let mut output = BufWriter::new(File::create(dest).map_err(|e| e.to_string())?);
+    std::io::copy(&mut decoder, &mut output)
+        .map_err(|e| format!("Failed to decompress {}: {}", src.display(), e))?;
+    Ok(())
+}
+
+/// Remove backups older than the retention period from disk and the manifest.
+/// The most recent backup is always kept. Returns the removed file names.
+fn prune(dir: &Path, manifest: &mut Manifest, retention_days: u32, now: DateTime<Utc>) -> Vec<String> {
+    let cutoff = now - chrono::Duration::days(i64::from(retention_days));
+    let newest = manifest.backups.iter().map(|b| b.created_at).max();
+
+    let (expired, kept): (Vec<BackupEntry>, Vec<BackupEntry>) = manifest
+        .backups
+        .drain(..)
+        .partition(|b| b.created_at < cutoff && Some(b.created_at) != newest);
+    manifest.backups = kept;
+
+    expired
+        .into_iter()
+        .map(|b| {
+            if let Err(e) = std::fs::remove_file(dir.join(&b.file)) {
+                tracing::warn!("Failed to remove expired backup {}: {}", b.file, e);
+            }
+            b.file
+        })
+        .collect()
+}
+
+/// Take a backup into the backup directory, record it in the manifest and
+/// prune expired backups
+pub async fn create_backup(
+    pool: &SqlitePool,
+    settings: &BackupSettings,
+) -> Result<BackupEntry, String> {
+    let _guard = BACKUP_LOCK.lock().await;
+
+    std::fs::create_dir_all(&settings.dir)
+        .map_err(|e| format!("Failed to create {}: {}", settings.dir.display(), e))?;
+
+    let now = Utc::now();
+    let base = format!("knowcodeextra-{}.db", now.format("%Y%m%d-%H%M%S"));
+    if read_manifest(&settings.dir)?
+        .backups
+        .iter()
+        .any(|b| b.file.starts_with(&base))
+    {
+        return Err("A backup was already taken this second".to_string());
+    }
+    let staging = settings.dir.join(format!(".{}.tmp", base));
+    let _ = std::fs::remove_file(&staging);
+
+    snapshot(pool, &staging).await?;
+
+    let schema_version: (Option<i64>,) = sqlx::query_as("SELECT MAX(version) FROM schema_version")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Failed to read schema version: {}", e))?;
+
+    let dir = settings.dir.clone();
+    let compress = settings.compress;
+    let retention_days = settings.retention_days;
+
+    tokio::task::spawn_blocking(move || {
+        let file = if compress { format!("{}.gz", base) } else { base };
+        let path = dir.join(&file);
+
+        if compress {
+            let result = gzip_file(&staging, &path);
+            let _ = std::fs::remove_file(&staging);
+            result?;
+        } else {
+            std::fs::rename(&staging, &path).map_err(|e| e.to_string())?;
+        }
+
+        let entry = BackupEntry {
+            size_bytes: std::fs::metadata(&path).map_err(|e| e.to_string())?.len(),
+            sha256: sha256_file(&path)?,
+            file,
+            created_at: now,
+            schema_version: schema_version.0.unwrap_or(0),
+            compressed: compress,
+        };
+
+        let mut manifest = read_manifest(&dir)?;
+        manifest.backups.push(entry.clone());
+        for removed in prune(&dir, &mut manifest, retention_days, now) {
+            tracing::info!("Pruned expired backup {}", removed);
+        }
+        write_manifest(&dir, &manifest)?;
+
+        tracing::info!("Created backup {} ({} bytes)", entry.file, entry.size_bytes);
+        Ok(entry)
+    })
+    .await
+    .map_err(|e| format!("Backup task failed: {}", e))?
+}
+
+/// Run backups on the configured interval. The first backup is due one
+/// interval after the newest existing backup, so restarts don't add extra ones.
+pub fn spawn_scheduler(pool: SqlitePool, settings: BackupSettings) {
+    tokio::spawn(async move {
+        loop {
+            let last = read_manifest(&settings.dir)
+                .ok()
+                .and_then(|m| m.backups.iter().map(|b| b.created_at).max());
+            let wait = last
+                .map(|last| {
+                    let elapsed = (Utc::now() - last).to_std().unwrap_or_default();
+                    settings.interval.saturating_sub(elapsed)
+                })
+                .unwrap_or_default();
+
+            tokio::time::sleep(wait).await;
+
+            if let Err(e) = create_backup(&pool, &settings).await {
+                tracing::error!("Scheduled backup failed: {}", e);
+                // Avoid a tight loop when the backup directory is broken
+                tokio::time::sleep(settings.interval.min(Duration::from_secs(3600))).await;
+            }
+        }
+    });
+}
+
+/// Result of a successful restore
+#[derive(Debug)]
+pub struct RestoreOutcome {
+    pub schema_version: i64,
+    /// Where the replaced database was moved, if one existed
+    pub previous: Option<PathBuf>,
+}
+
+/// Replace the database file at `db_path` with a backup.
+///
+/// The backup's checksum is verified against the manifest next to it (when
+/// listed), it is decompressed to a staging file, integrity-checked, and its
+/// schema version must not be newer than this build supports. Only then is the
+/// current database moved aside and the staged copy renamed into place.
+/// The server must be stopped while restoring.
+pub async fn restore(db_path: &Path, backup: &Path) -> Result<RestoreOutcome, String> {
+    let file_name = backup
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or("Invalid backup file name")?;
+    let backup_dir = backup.parent().unwrap_or(Path::new("."));
+
+    match read_manifest(backup_dir)?.find(file_name) {
+        Some(entry) => {
+            let actual = sha256_file(backup)?;
+            if actual != entry.sha256 {
+                return Err(format!(
+                    "Checksum mismatch for {}: manifest has {}, file is {}",
+                    file_name, entry.sha256, actual
+                ));
+            }
+        }
+        None => tracing::warn!("{} is not listed in a manifest; checksum not verified", file_name),
+    }
+
+    let staging = db_path.with_extension("restore-tmp");
+    let _ = std::fs::remove_file(&staging);
+    if file_name.ends_with(".gz") {
+        gunzip_file(backup, &staging)?;
+    } else {
+        std::fs::copy(backup, &staging).map_err(|e| format!("Failed to copy backup: {}", e))?;
+    }
+
+    let schema_version = match check_staged(&staging).await {
+        Ok(version) => version,
+        Err(e) => {
+            let _ = std::fs::remove_file(&staging);
+            return Err(e);
+        }
+    };
+
+    let previous = if db_path.exists() {
+        let moved = PathBuf::from(format!(
+            "{}.pre-restore-{}",
+            db_path.display(),
+            Utc::now().format("%Y%m%d-%H%M%S")
+        ));
+        std::fs::rename(db_path, &moved)
+            .map_err(|e| format!("Failed to move current database aside: {}", e))?;
+        // WAL and shared-memory files belong to the old database
+        for suffix in ["-wal", "-shm"] {
+            let side = PathBuf::from(format!("{}{}", db_path.display(), suffix));
+            if side.exists() {
+                let _ = std::fs::rename(&side, format!("{}{}", moved.display(), suffix));
+            }
+        }
+        Some(moved)
+    } else {
+        None
+    };
+
+    std::fs::rename(&staging, db_path)
+        .map_err(|e| format!("Failed to move restored database into place: {}", e))?;
+
+    Ok(RestoreOutcome {
+        schema_version,
+        previous,
+    })
+}
+
+/// Integrity-check a staged backup and return its schema version
+async fn check_staged(path: &Path) -> Result<i64, String> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(SqliteConnectOptions::new().filename(path).read_only(true))
+        .await
+        .map_err(|e| format!("Backup is not a readable SQLite database: {}", e))?;
+
+    let result = async {
+        let integrity: (String,) = sqlx::query_as("PRAGMA integrity_check")
+            .fetch_one(&pool)
+            .await
+            .map_err(|e| format!("Integrity check failed: {}", e))?;
+        if integrity.0 != "ok" {
+            return Err(format!("Integrity check failed: {}", integrity.0));
+        }
+
+        let plan = crate::migrations::plan(&crate::db::Db::Sqlite(pool.clone()))
+            .await
+            .map_err(|e| format!("Failed to read schema version: {}", e))?;
+        if plan.current_version == 0 && !plan.adopt_legacy {
+            return Err("Backup does not contain a knowcodeextra database".to_string());
+        }
+        let latest = crate::migrations::latest_version();
+        if plan.current_version > latest {
+            return Err(format!(
+                "Backup schema version {} is newer than this build supports ({})",
+                plan.current_version, latest
+            ));
+        }
+        Ok(plan.current_version)
+    }
+    .await;
+
+    pool.close().await;
+    result
+}
+
+// ============================================================================
+// ADMIN ENDPOINTS
+// ============================================================================
+
+/// GET /api/admin/backups - List backups from the manifest, newest first
+pub async fn list_backups(
+    State(state): State<Arc<crate::AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let settings = state.config.backup_settings();
+    let mut manifest =
+        read_manifest(&settings.dir).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    manifest
+        .backups
+        .sort_by_key(|b| std::cmp::Reverse(b.created_at));
+
+    Ok(Json(manifest.backups))
+}
+
+/// POST /api/admin/backups - Take a backup now
+pub async fn trigger_backup(
+    State(state): State<Arc<crate::AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let pool = state
+        .db
+        .as_sqlite()
+        .ok_or((StatusCode::BAD_REQUEST, POSTGRES_UNSUPPORTED.to_string()))?;
+    let settings = state.config.backup_settings();
+    let entry = create_backup(pool, &settings)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok((StatusCode::CREATED, Json(entry)))
+}
+
+/// GET /api/admin/backups/:file - Download a backup listed in the manifest
+pub async fn download_backup(
+    State(state): State<Arc<crate::AppState>>,
+    UrlPath(file): UrlPath<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let settings = state.config.backup_settings();
+    let manifest =
+        read_manifest(&settings.dir).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    // Only names from the manifest are served, which rules out path traversal
+    let entry = manifest
+        .find(&file)
+        .ok_or((StatusCode::NOT_FOUND, "Backup not found".to_string()))?;
+
+    let bytes = tokio::fs::read(settings.dir.join(&entry.file))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let content_type = if entry.compressed {
+        "application/gzip"
+    } else {
+        "application/vnd.sqlite3"
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", entry.file),
+            ),
+        ],
+        bytes,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("kce-backup-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// `VACUUM INTO` writes through the source database's VFS, so tests need
+    /// a file-backed source rather than `sqlite::memory:`
+    async fn file_pool(path: &Path) -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .connect_with(
+                SqliteConnectOptions::new()
+                    .filename(path)
+                    .create_if_missing(true),
+            )
+            .await
+            .unwrap();
+        crate::setup_database(&crate::db::Db::Sqlite(pool.clone()))
+            .await
+            .unwrap();
+        pool
+    }
+
+    fn settings(dir: &Path, compress: bool) -> BackupSettings {
+        BackupSettings {
+            enabled: true,
+            dir: dir.join("backups"),
+            interval: Duration::from_secs(3600),
+            retention_days: 30,
+            compress,
+        }
+    }
+
+    fn entry(file: &str, created_at: DateTime<Utc>) -> BackupEntry {
+        BackupEntry {
+            file: file.to_string(),
+            created_at,
+            size_bytes: 0,
+            sha256: String::new(),
+            schema_version: 1,
+            compressed: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backup_and_restore_round_trip() {
+        let tmp = TempDir::new();
+        let db_path = tmp.0.join("live.db");
+        let pool = file_pool(&db_path).await;
+        let settings = settings(&tmp.0, true);
+
+        let entry = create_backup(&pool, &settings).await.unwrap();
+        assert!(entry.compressed);
+        assert_eq!(entry.schema_version, crate::migrations::latest_version());
+        let manifest = read_manifest(&settings.dir).unwrap();
+        assert_eq!(manifest.backups.len(), 1);
+        assert_eq!(
+            sha256_file(&settings.dir.join(&entry.file)).unwrap(),
+            entry.sha256
+        );
+
+        // Change the live database, then restore the earlier snapshot
+        sqlx::query("DELETE FROM prosign_mappings").execute(&pool).await.unwrap();
+        pool.close().await;
+
+        let outcome = restore(&db_path, &settings.dir.join(&entry.file))
+            .await
+            .unwrap();
+        assert_eq!(outcome.schema_version, entry.schema_version);
+        assert!(outcome.previous.unwrap().exists());
+
+        let restored = file_pool(&db_path).await;
+        let prosigns: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM prosign_mappings")
+            .fetch_one(&restored)
+            .await
+            .unwrap();
+        assert_eq!(prosigns.0, 2);
+        restored.close().await;
+    }
+
+    #[tokio::test]
+    async fn test_restore_rejects_checksum_mismatch() {
+        let tmp = TempDir::new();
+        let db_path = tmp.0.join("live.db");
+        let pool = file_pool(&db_path).await;
+        let settings = settings(&tmp.0, false);
+
+        let entry = create_backup(&pool, &settings).await.unwrap();
+        pool.close().await;
+
+        let mut manifest = read_manifest(&settings.dir).unwrap();
+        manifest.backups[0].sha256 = "0".repeat(64);
+        write_manifest(&settings.dir, &manifest).unwrap();
+
+        let err = restore(&db_path, &settings.dir.join(&entry.file))
+            .await
+            .unwrap_err();
+        assert!(err.contains("Checksum mismatch"));
+        assert!(db_path.exists(), "current database is left in place");
+    }
+
+    #[tokio::test]
+    async fn test_restore_rejects_newer_schema() {
+        let tmp = TempDir::new();
+        let db_path = tmp.0.join("live.db");
+        let pool = file_pool(&db_path).await;
+        let settings = settings(&tmp.0, false);
+
+        sqlx::query("INSERT INTO schema_version (version, name, applied_at) VALUES (9999, 'future', '2030-01-01')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let entry = create_backup(&pool, &settings).await.unwrap();
+        pool.close().await;
+
+        let err = restore(&db_path, &settings.dir.join(&entry.file))
+            .await
+            .unwrap_err();
+        assert!(err.contains("newer than this build supports"));
+    }
+
+    #[test]
+    fn test_prune_keeps_recent_and_newest() {
+        let tmp = TempDir::new();
+        let now = Utc::now();
+        let mut manifest = Manifest {
+            backups: vec![
+                entry("old.db", now - chrono::Duration::days(40)),
+                entry("recent.db", now - chrono::Duration::days(5)),
+            ],
+        };
+
+        let removed = prune(&tmp.0, &mut manifest, 30, now);
+        assert_eq!(removed, vec!["old.db".to_string()]);
+        assert_eq!(manifest.backups.len(), 1);
+
+        // Even an expired backup survives when it is the only one left
+        let mut manifest = Manifest {
+            backups: vec![entry("only.db", now - chrono::Duration::days(90))],
+        };
+        assert!(prune(&tmp.0, &mut manifest, 30, now).is_empty());
+    }
+}
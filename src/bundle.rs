@@ -0,0 +1,603 @@
+//! Test bundles, for moving a test between servers
+//!
+//! A bundle is a gzipped tarball holding `manifest.json`, `questions.json`,
+//! `segments.json` and the test's audio under `audio/`. The manifest records
+//! the bundle format version and the audio file's size and SHA-256. Import
+//! checks those, plus question numbering, option letters and segment bounds,
+//! before writing anything, and inserts the test and its questions in one
+//! transaction.
+
+use crate::admin::{self, ExportedQuestion, TestExport};
+use crate::db::Db;
+use axum::{
+    body::Bytes,
+    extract::{Path as UrlPath, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// Identifies a bundle in its manifest
+pub const FORMAT: &str = "knowcodeextra-test-bundle";
+/// Bumped when the layout changes; newer bundles are refused. Version 2 added
+/// question topics and `draw_count`; version 3 question types, with options
+/// as a list in place of `option_a` to `option_d`, which older bundles still
+/// read into.
+pub const FORMAT_VERSION: u32 = 3;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const QUESTIONS_FILE: &str = "questions.json";
+const SEGMENTS_FILE: &str = "segments.json";
+const AUDIO_DIR: &str = "audio/";
+
+/// Largest bundle accepted: the audio plus room for the JSON files
+pub const MAX_BUNDLE_SIZE: usize = admin::MAX_AUDIO_SIZE + 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format: String,
+    pub format_version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub test: BundledTest,
+    /// None when the test's audio is hosted elsewhere
+    pub audio: Option<BundledAudio>,
+}
+
+/// The test row; questions and segments sit in their own files
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundledTest {
+    pub id: String,
+    pub title: String,
+    pub speed_wpm: i32,
+    pub year: String,
+    pub audio_url: String,
+    pub passing_score: i32,
+    pub active: bool,
+    pub expected_copy_text: Option<String>,
+    #[serde(default)]
+    pub draw_count: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundledAudio {
+    /// Path under the static `audio/` directory, e.g. `20wpm/test.mp3`
+    pub file: String,
+    pub size_bytes: u64,
+    pub sha256: String,
+}
+
+/// A bundle read back into memory
+#[derive(Debug)]
+pub struct TestBundle {
+    pub manifest: BundleManifest,
+    pub test: TestExport,
+    pub audio: Option<Vec<u8>>,
+}
+
+/// What an import created, or would create on a dry run
+#[derive(Debug, Serialize)]
+pub struct ImportSummary {
+    pub id: String,
+    pub title: String,
+    pub questions: usize,
+    pub segments: usize,
+    pub audio_url: String,
+    pub dry_run: bool,
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The audio file behind a local `/audio/...` URL, as a path under `audio/`.
+/// None for URLs elsewhere or paths that try to leave the directory.
+pub fn local_audio_file(audio_url: &str) -> Option<&str> {
+    let file = audio_url.strip_prefix("/audio/")?;
+    let safe = Path::new(file)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)));
+    (safe && !file.is_empty()).then_some(file)
+}
+
+/// Pack an exported test and its audio into a bundle
+pub fn write_bundle(test: &TestExport, audio: Option<(&str, &[u8])>) -> Result<Vec<u8>, String> {
+    let manifest = BundleManifest {
+        format: FORMAT.to_string(),
+        format_version: FORMAT_VERSION,
+        exported_at: Utc::now(),
+        test: BundledTest {
+            id: test.id.clone(),
+            title: test.title.clone(),
+            speed_wpm: test.speed_wpm,
+            year: test.year.clone(),
+            audio_url: test.audio_url.clone(),
+            passing_score: test.passing_score,
+            active: test.active,
+            expected_copy_text: test.expected_copy_text.clone(),
+            draw_count: test.draw_count,
+        },
+        audio: audio.map(|(file, bytes)| BundledAudio {
+            file: file.to_string(),
+            size_bytes: bytes.len() as u64,
+            sha256: sha256_hex(bytes),
+        }),
+    };
+
+    let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let mut tar = tar::Builder::new(encoder);
+    let mut append = |name: &str, data: &[u8]| {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(manifest.exported_at.timestamp().max(0) as u64);
+        header.set_cksum();
+        tar.append_data(&mut header, name, data)
+            .map_err(|e| format!("Failed to write {}: {}", name, e))
+    };
+
+    append(MANIFEST_FILE, &pretty_json(&manifest)?)?;
+    append(QUESTIONS_FILE, &pretty_json(&test.questions)?)?;
+    append(SEGMENTS_FILE, &pretty_json(&test.segments.as_deref().unwrap_or_default())?)?;
+    if let Some((file, bytes)) = audio {
+        append(&format!("{}{}", AUDIO_DIR, file), bytes)?;
+    }
+
+    tar.into_inner()
+        .and_then(|encoder| encoder.finish())
+        .map_err(|e| format!("Failed to write bundle: {}", e))
+}
+
+fn pretty_json<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    serde_json::to_vec_pretty(value).map_err(|e| e.to_string())
+}
+
+/// Unpack a bundle, checking its format version and audio checksum
+pub fn read_bundle(bytes: &[u8]) -> Result<TestBundle, String> {
+    let mut manifest: Option<BundleManifest> = None;
+    let mut questions: Option<Vec<ExportedQuestion>> = None;
+    let mut segments: Option<Vec<crate::Segment>> = None;
+    let mut audio: Option<(String, Vec<u8>)> = None;
+
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(bytes));
+    let entries = archive.entries().map_err(|e| format!("Not a test bundle: {}", e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Corrupt bundle: {}", e))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry
+            .path()
+            .map_err(|e| format!("Corrupt bundle: {}", e))?
+            .to_string_lossy()
+            .into_owned();
+        if entry.size() > MAX_BUNDLE_SIZE as u64 {
+            return Err(format!("{} is too large", name));
+        }
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry
+            .read_to_end(&mut data)
+            .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+
+        let parse_error = |e: serde_json::Error| format!("Invalid {}: {}", name, e);
+        match name.as_str() {
+            MANIFEST_FILE => manifest = Some(serde_json::from_slice(&data).map_err(parse_error)?),
+            QUESTIONS_FILE => questions = Some(serde_json::from_slice(&data).map_err(parse_error)?),
+            SEGMENTS_FILE => segments = Some(serde_json::from_slice(&data).map_err(parse_error)?),
+            _ => match name.strip_prefix(AUDIO_DIR) {
+                Some(file) if audio.is_none() => audio = Some((file.to_string(), data)),
+                Some(_) => return Err("Bundle holds more than one audio file".to_string()),
+                None => return Err(format!("Unexpected file in bundle: {}", name)),
+            },
+        }
+    }
+
+    let manifest = manifest.ok_or(format!("Bundle has no {}", MANIFEST_FILE))?;
+    if manifest.format != FORMAT {
+        return Err(format!("Not a test bundle: format is '{}'", manifest.format));
+    }
+    if manifest.format_version > FORMAT_VERSION {
+        return Err(format!(
+            "Bundle format version {} is newer than this build supports ({})",
+            manifest.format_version, FORMAT_VERSION
+        ));
+    }
+    let questions = questions.ok_or(format!("Bundle has no {}", QUESTIONS_FILE))?;
+
+    let audio = match (&manifest.audio, audio) {
+        (None, None) => None,
+        (Some(expected), Some((file, data))) => {
+            if file != expected.file {
+                return Err(format!(
+                    "Manifest lists audio {} but the bundle holds {}",
+                    expected.file, file
+                ));
+            }
+            if local_audio_file(&format!("/audio/{}", file)).is_none() {
+                return Err(format!("Invalid audio file name: {}", file));
+            }
+            if data.len() as u64 != expected.size_bytes || sha256_hex(&data) != expected.sha256 {
+                return Err(format!("Checksum mismatch for audio {}", file));
+            }
+            Some(data)
+        }
+        (Some(expected), None) => return Err(format!("Bundle is missing audio {}", expected.file)),
+        (None, Some((file, _))) => return Err(format!("Audio {} is not listed in the manifest", file)),
+    };
+
+    let test = TestExport {
+        id: manifest.test.id.clone(),
+        title: manifest.test.title.clone(),
+        speed_wpm: manifest.test.speed_wpm,
+        year: manifest.test.year.clone(),
+        audio_url: manifest.test.audio_url.clone(),
+        passing_score: manifest.test.passing_score,
+        active: manifest.test.active,
+        segments: segments.filter(|s| !s.is_empty()),
+        expected_copy_text: manifest.test.expected_copy_text.clone(),
+        draw_count: manifest.test.draw_count,
+        questions,
+    };
+
+    Ok(TestBundle { manifest, test, audio })
+}
+
+/// Bundle a test, including its audio when it is served from `static_dir`
+pub async fn export(db: &Db, static_dir: &str, test_id: &str) -> Result<Vec<u8>, (StatusCode, String)> {
+    let test = admin::export_test(db, test_id).await?;
+
+    let audio = match local_audio_file(&test.audio_url) {
+        Some(file) => {
+            let path = Path::new(static_dir).join("audio").join(file);
+            match tokio::fs::read(&path).await {
+                Ok(bytes) => Some((file.to_string(), bytes)),
+                Err(e) => {
+                    tracing::warn!("Exporting test {} without audio {}: {}", test_id, path.display(), e);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    write_bundle(&test, audio.as_ref().map(|(file, bytes)| (file.as_str(), bytes.as_slice())))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+/// Import a bundle: validate everything, place the audio under `static_dir`,
+/// then insert the test and its questions in one transaction. The audio is
+/// removed again if the insert fails. A dry run stops after validation.
+pub async fn import(
+    db: &Db,
+    static_dir: &str,
+    bytes: &[u8],
+    dry_run: bool,
+) -> Result<ImportSummary, (StatusCode, String)> {
+    let TestBundle { manifest, mut test, audio } =
+        read_bundle(bytes).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    admin::validate_test_export(&test)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid test: {}", e)))?;
+    // Checked again inside the insert transaction; this spares writing the audio
+    let existing = db
+        .tests()
+        .find_admin(&test.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if existing.is_some() {
+        return Err((StatusCode::CONFLICT, format!("Test '{}' already exists", test.id)));
+    }
+
+    let mut written: Option<PathBuf> = None;
+    if let (Some(info), Some(data)) = (&manifest.audio, &audio) {
+        let audio_dir = Path::new(static_dir).join("audio");
+        let (file, new_file) = place_audio(&audio_dir, &info.file, &info.sha256)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        if new_file && !dry_run {
+            let path = audio_dir.join(&file);
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create {}: {}", parent.display(), e)))?;
+            }
+            tokio::fs::write(&path, data)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to write {}: {}", path.display(), e)))?;
+            written = Some(path);
+        }
+        test.audio_url = format!("/audio/{}", file);
+    }
+
+    let summary = ImportSummary {
+        id: test.id.clone(),
+        title: test.title.clone(),
+        questions: test.questions.len(),
+        segments: test.segments.as_ref().map_or(0, Vec::len),
+        audio_url: test.audio_url.clone(),
+        dry_run,
+    };
+    if dry_run {
+        return Ok(summary);
+    }
+
+    if let Err(e) = admin::import_test(db, &test).await {
+        if let Some(path) = written {
+            let _ = tokio::fs::remove_file(&path).await;
+        }
+        return Err(e);
+    }
+
+    tracing::info!(
+        "Imported test bundle {} with {} questions, audio {}",
+        summary.id,
+        summary.questions,
+        summary.audio_url
+    );
+    Ok(summary)
+}
+
+/// Where to put bundled audio under `audio_dir`: its own name, unless another
+/// file already sits there, in which case a timestamped name as
+/// `upload_audio` does. Returns the path under `audio_dir` and whether the
+/// file still needs writing.
+fn place_audio(audio_dir: &Path, file: &str, sha256: &str) -> Result<(String, bool), String> {
+    let path = audio_dir.join(file);
+    if !path.exists() {
+        return Ok((file.to_string(), true));
+    }
+    if crate::backup::sha256_file(&path)? == sha256 {
+        return Ok((file.to_string(), false));
+    }
+    let stem = file.trim_end_matches(".mp3").trim_end_matches(".MP3");
+    Ok((format!("{}-{}.mp3", stem, Utc::now().timestamp()), true))
+}
+
+// ============================================================================
+// HTTP HANDLERS
+// ============================================================================
+
+/// GET /api/admin/tests/:id/bundle - Download a test as a bundle
+pub async fn download_bundle(
+    State(state): State<Arc<crate::AppState>>,
+    UrlPath(test_id): UrlPath<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let bytes = export(&state.db, &state.static_dir, &test_id).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/gzip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}.tar.gz\"", test_id),
+            ),
+        ],
+        bytes,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportQuery {
+    /// Validate the bundle without importing it
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// POST /api/admin/test-bundles - Import a bundle sent as the request body
+pub async fn upload_bundle(
+    State(state): State<Arc<crate::AppState>>,
+    Query(query): Query<ImportQuery>,
+    body: Bytes,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let summary = import(&state.db, &state.static_dir, &body, query.dry_run).await?;
+    let status = if summary.dry_run { StatusCode::OK } else { StatusCode::CREATED };
+    Ok((status, Json(summary)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing;
+    use crate::grading::QuestionType;
+    use crate::repo::NewQuestion;
+
+    const TEST_ID: &str = "20wpm-extra-1991";
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("kce-bundle-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(dir.join("audio/20wpm")).unwrap();
+            TempDir(dir)
+        }
+
+        fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    async fn seeded_db() -> Db {
+        let db = testing::sqlite().await;
+        for n in 1..=3 {
+            db.questions()
+                .insert(&NewQuestion {
+                    test_id: TEST_ID,
+                    question_number: n,
+                    question_text: "What was the call?",
+                    question_type: QuestionType::Choice,
+                    options: &["W1AW", "K1ABC", "N0CALL", "AA1AA"],
+                    correct_option: "B",
+                    accepted_answers: &[],
+                    topic: None,
+                })
+                .await
+                .unwrap();
+        }
+        db
+    }
+
+    #[tokio::test]
+    async fn test_bundle_round_trip_with_audio() {
+        let staging = TempDir::new();
+        std::fs::write(staging.0.join("audio/20wpm/test.mp3"), b"ID3 staging audio").unwrap();
+        let bundle = export(&seeded_db().await, staging.path(), TEST_ID).await.unwrap();
+
+        let read = read_bundle(&bundle).unwrap();
+        assert_eq!(read.manifest.format_version, FORMAT_VERSION);
+        assert_eq!(read.manifest.audio.unwrap().file, "20wpm/test.mp3");
+        assert_eq!(read.test.questions.len(), 3);
+
+        // A fresh server already holds the seeded test, so import under another id
+        let production = TempDir::new();
+        let db = testing::sqlite().await;
+        let err = import(&db, production.path(), &bundle, false).await.unwrap_err();
+        assert_eq!(err.0, StatusCode::CONFLICT);
+        assert!(
+            !production.0.join("audio/20wpm/test.mp3").exists(),
+            "audio is removed when the insert fails"
+        );
+
+        testing::delete_test(&db, TEST_ID).await;
+
+        let dry_run = import(&db, production.path(), &bundle, true).await.unwrap();
+        assert!(dry_run.dry_run);
+        assert!(db.tests().find_admin(TEST_ID).await.unwrap().is_none());
+
+        let summary = import(&db, production.path(), &bundle, false).await.unwrap();
+        assert_eq!(summary.questions, 3);
+        assert_eq!(summary.audio_url, "/audio/20wpm/test.mp3");
+        assert_eq!(
+            std::fs::read(production.0.join("audio/20wpm/test.mp3")).unwrap(),
+            b"ID3 staging audio"
+        );
+        let imported = admin::export_test(&db, TEST_ID).await.unwrap();
+        assert_eq!(imported.questions[2].correct_option, "B");
+    }
+
+    #[tokio::test]
+    async fn test_import_renames_conflicting_audio() {
+        let dir = TempDir::new();
+        std::fs::write(dir.0.join("audio/20wpm/test.mp3"), b"ID3 other audio").unwrap();
+
+        let mut test = admin::export_test(&seeded_db().await, TEST_ID).await.unwrap();
+        test.id = "20wpm-extra-copy".to_string();
+        let bundle = write_bundle(&test, Some(("20wpm/test.mp3", b"ID3 bundled audio"))).unwrap();
+
+        let db = testing::sqlite().await;
+        let summary = import(&db, dir.path(), &bundle, false).await.unwrap();
+        assert_ne!(summary.audio_url, "/audio/20wpm/test.mp3");
+        assert!(summary.audio_url.starts_with("/audio/20wpm/test-"));
+        assert_eq!(
+            std::fs::read(dir.0.join("audio/20wpm/test.mp3")).unwrap(),
+            b"ID3 other audio",
+            "existing audio is left alone"
+        );
+    }
+
+    fn pack<const N: usize>(files: [(&str, Vec<u8>); N]) -> Vec<u8> {
+        let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        let mut tar = tar::Builder::new(encoder);
+        for (name, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            tar.append_data(&mut header, name, data.as_slice()).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_read_bundle_rejects_tampering() {
+        let mut test = admin::export_test(&seeded_db().await, TEST_ID).await.unwrap();
+        test.id = "20wpm-extra-copy".to_string();
+        let bundle = write_bundle(&test, Some(("test.mp3", b"ID3 audio"))).unwrap();
+        let mut read = read_bundle(&bundle).unwrap();
+
+        // Swap the audio behind the manifest's back
+        let mut tampered = read.manifest.audio.take().unwrap();
+        tampered.sha256 = "0".repeat(64);
+        read.manifest.audio = Some(tampered);
+        let rebuilt = pack([
+            (MANIFEST_FILE, serde_json::to_vec(&read.manifest).unwrap()),
+            (QUESTIONS_FILE, serde_json::to_vec(&read.test.questions).unwrap()),
+            ("audio/test.mp3", b"ID3 audio".to_vec()),
+        ]);
+        assert!(read_bundle(&rebuilt).unwrap_err().contains("Checksum mismatch"));
+
+        assert!(read_bundle(b"not a bundle").is_err());
+        assert_eq!(local_audio_file("/audio/../config.toml"), None);
+        assert_eq!(local_audio_file("https://example.com/test.mp3"), None);
+        assert_eq!(local_audio_file("/audio/20wpm/test.mp3"), Some("20wpm/test.mp3"));
+    }
+
+    #[tokio::test]
+    async fn test_import_validates_before_writing() {
+        let dir = TempDir::new();
+        let mut test = admin::export_test(&seeded_db().await, TEST_ID).await.unwrap();
+        test.id = "20wpm-extra-copy".to_string();
+        test.questions[1].question_number = 5;
+        test.questions[2].correct_option = "E".to_string();
+        test.segments = Some(vec![
+            crate::Segment {
+                name: "Practice".to_string(),
+                start_time: 0,
+                end_time: Some(60),
+                enables_copy: false,
+                enables_questions: false,
+            },
+            crate::Segment {
+                name: "Test".to_string(),
+                start_time: 45,
+                end_time: Some(30),
+                enables_copy: true,
+                enables_questions: true,
+            },
+        ]);
+        let bundle = write_bundle(&test, Some(("copy.mp3", b"ID3 audio"))).unwrap();
+
+        let db = testing::sqlite().await;
+        let (status, message) = import(&db, dir.path(), &bundle, false).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(message.contains("numbered 1 to 3"), "{}", message);
+        assert!(message.contains("correct_option"), "{}", message);
+        assert!(message.contains("before 'Practice' ends"), "{}", message);
+        assert!(message.contains("must end after it starts"), "{}", message);
+        assert!(!dir.0.join("audio/copy.mp3").exists());
+        assert!(db.tests().find_admin("20wpm-extra-copy").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reads_lettered_options_of_older_bundles() {
+        let mut test = admin::export_test(&seeded_db().await, TEST_ID).await.unwrap();
+        test.questions.truncate(1);
+        let mut manifest = read_bundle(&write_bundle(&test, None).unwrap()).unwrap().manifest;
+        manifest.format_version = 2;
+        let questions = serde_json::json!([{
+            "question_number": 1,
+            "question_text": "What was the call?",
+            "option_a": "W1AW",
+            "option_b": "K1ABC",
+            "option_c": "N0CALL",
+            "option_d": "AA1AA",
+            "correct_option": "C",
+        }]);
+        let bundle = pack([
+            (MANIFEST_FILE, serde_json::to_vec(&manifest).unwrap()),
+            (QUESTIONS_FILE, serde_json::to_vec(&questions).unwrap()),
+        ]);
+
+        let question = &read_bundle(&bundle).unwrap().test.questions[0];
+        assert_eq!(question.question_type, QuestionType::Choice);
+        assert_eq!(question.options.as_strs(), ["W1AW", "K1ABC", "N0CALL", "AA1AA"]);
+        assert!(admin::validate_question(question).is_empty());
+    }
+}
@@ -10,185 +10,418 @@
 //   .route("/api/certificate/:attempt_id", get(certificate::get_certificate_svg))
 
 use axum::{
-    extract::{Path, State},
-    http::{header, StatusCode},
-    response::IntoResponse,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Json, Response},
 };
+use crate::classes::LicenseClass;
+use crate::render::RenderFormat;
+use crate::repo::IssuedCertificate;
+use crate::signing::{CertificateSigner, SignedFields};
+use chrono::{DateTime, Utc};
+use qrcode::{Color, EcLevel, QrCode};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
-/// SVG template with placeholders - emphasizes "Know-Code" status
-const CERTIFICATE_TEMPLATE: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 800 600" width="800" height="600">
-  <defs>
-    <linearGradient id="parchment" x1="0%" y1="0%" x2="100%" y2="100%">
-      <stop offset="0%" style="stop-color:#FEF3C7"/>
-      <stop offset="50%" style="stop-color:#FFFBEB"/>
-      <stop offset="100%" style="stop-color:#FDE68A"/>
-    </linearGradient>
-    <linearGradient id="gold" x1="0%" y1="0%" x2="100%" y2="100%">
-      <stop offset="0%" style="stop-color:#B45309"/>
-      <stop offset="50%" style="stop-color:#D97706"/>
-      <stop offset="100%" style="stop-color:#92400E"/>
-    </linearGradient>
-    <radialGradient id="sealGradient" cx="50%" cy="50%" r="50%">
-      <stop offset="0%" style="stop-color:#DC2626"/>
-      <stop offset="70%" style="stop-color:#991B1B"/>
-      <stop offset="100%" style="stop-color:#7F1D1D"/>
-    </radialGradient>
-    <linearGradient id="badgeGradient" x1="0%" y1="0%" x2="0%" y2="100%">
-      <stop offset="0%" style="stop-color:#78350F"/>
-      <stop offset="50%" style="stop-color:#92400E"/>
-      <stop offset="100%" style="stop-color:#78350F"/>
-    </linearGradient>
-    <pattern id="noisePattern" x="0" y="0" width="100" height="100" patternUnits="userSpaceOnUse">
-      <circle cx="25" cy="25" r="0.5" fill="#92400E" opacity="0.03"/>
-      <circle cx="75" cy="75" r="0.5" fill="#92400E" opacity="0.03"/>
-      <circle cx="50" cy="10" r="0.5" fill="#92400E" opacity="0.02"/>
-      <circle cx="10" cy="60" r="0.5" fill="#92400E" opacity="0.02"/>
-      <circle cx="90" cy="40" r="0.5" fill="#92400E" opacity="0.02"/>
-    </pattern>
-  </defs>
-  
-  <rect width="800" height="600" fill="url(#parchment)"/>
-  <rect width="800" height="600" fill="url(#noisePattern)"/>
-  
-  <rect x="20" y="20" width="760" height="560" fill="none" stroke="#92400E" stroke-width="3"/>
-  <rect x="28" y="28" width="744" height="544" fill="none" stroke="#B45309" stroke-width="1"/>
-  <rect x="40" y="40" width="720" height="520" fill="none" stroke="#92400E" stroke-width="2"/>
-  <rect x="46" y="46" width="708" height="508" fill="none" stroke="#D97706" stroke-width="1" stroke-dasharray="4,2"/>
-  
-  <g fill="none" stroke="#92400E" stroke-width="2">
-    <path d="M60,80 L60,60 L80,60"/><path d="M65,85 L65,65 L85,65"/><circle cx="60" cy="60" r="3" fill="#92400E"/>
-    <path d="M740,80 L740,60 L720,60"/><path d="M735,85 L735,65 L715,65"/><circle cx="740" cy="60" r="3" fill="#92400E"/>
-    <path d="M60,520 L60,540 L80,540"/><path d="M65,515 L65,535 L85,535"/><circle cx="60" cy="540" r="3" fill="#92400E"/>
-    <path d="M740,520 L740,540 L720,540"/><path d="M735,515 L735,535 L715,535"/><circle cx="740" cy="540" r="3" fill="#92400E"/>
-  </g>
-  
-  <g transform="translate(400, 70)">
-    <ellipse cx="0" cy="20" rx="30" ry="5" fill="#92400E" opacity="0.3"/>
-    <rect x="-25" y="8" width="50" height="8" rx="2" fill="#78350F"/>
-    <rect x="-5" y="-5" width="10" height="15" rx="1" fill="#78350F"/>
-    <circle cx="0" cy="-10" r="6" fill="#92400E"/>
-    <rect x="-30" y="12" width="60" height="3" rx="1" fill="#78350F" opacity="0.6"/>
-  </g>
-  
-  <text x="400" y="115" text-anchor="middle" font-family="Georgia, serif" font-size="38" font-weight="bold" fill="#78350F" letter-spacing="3">CERTIFICATE</text>
-  <text x="400" y="138" text-anchor="middle" font-family="monospace" font-size="11" fill="#92400E" letter-spacing="5">OF MORSE CODE PROFICIENCY</text>
-  
-  <line x1="200" y1="152" x2="600" y2="152" stroke="url(#gold)" stroke-width="2"/>
-  <circle cx="200" cy="152" r="3" fill="#92400E"/>
-  <circle cx="400" cy="152" r="4" fill="#B45309"/>
-  <circle cx="600" cy="152" r="3" fill="#92400E"/>
-  
-  <text x="400" y="182" text-anchor="middle" font-family="Georgia, serif" font-size="15" fill="#78350F" font-style="italic">This is to certify that</text>
-  
-  <text x="400" y="222" text-anchor="middle" font-family="monospace" font-size="44" font-weight="bold" fill="#78350F" letter-spacing="5">{{CALLSIGN}}</text>
-  <line x1="250" y1="232" x2="550" y2="232" stroke="#D97706" stroke-width="2"/>
-  
-  <text x="400" y="262" text-anchor="middle" font-family="Georgia, serif" font-size="15" fill="#78350F">has demonstrated proficiency in International Morse Code</text>
-  <text x="400" y="282" text-anchor="middle" font-family="Georgia, serif" font-size="15" fill="#78350F">at a speed of <tspan font-weight="bold" font-size="18">20 WPM</tspan> and is hereby recognized as a</text>
-  
-  <g transform="translate(400, 340)">
-    <rect x="-180" y="-35" width="360" height="70" fill="url(#badgeGradient)" rx="4"/>
-    <rect x="-176" y="-31" width="352" height="62" fill="none" stroke="#D97706" stroke-width="1" rx="2"/>
-    <path d="M-170,-25 L-160,-25 L-160,-15" fill="none" stroke="#FDE68A" stroke-width="1.5"/>
-    <path d="M170,-25 L160,-25 L160,-15" fill="none" stroke="#FDE68A" stroke-width="1.5"/>
-    <path d="M-170,25 L-160,25 L-160,15" fill="none" stroke="#FDE68A" stroke-width="1.5"/>
-    <path d="M170,25 L160,25 L160,15" fill="none" stroke="#FDE68A" stroke-width="1.5"/>
-    <text x="-150" y="8" text-anchor="middle" font-size="18" fill="#FDE68A">★</text>
-    <text x="150" y="8" text-anchor="middle" font-size="18" fill="#FDE68A">★</text>
-    <text x="0" y="8" text-anchor="middle" font-family="Georgia, serif" font-size="36" font-weight="bold" fill="#FFFBEB" letter-spacing="3">KNOW-CODE</text>
-    <text x="0" y="28" text-anchor="middle" font-family="monospace" font-size="14" fill="#FDE68A" letter-spacing="8">EXTRA</text>
-  </g>
-  
-  <text x="400" y="395" text-anchor="middle" font-family="Georgia, serif" font-size="12" fill="#78350F" font-style="italic">"Proving the code lives on"</text>
-  
-  <g transform="translate(400, 418)">
-    <circle cx="-40" cy="0" r="2" fill="#92400E"/>
-    <rect x="-35" y="-1" width="10" height="2" fill="#92400E"/>
-    <circle cx="-20" cy="0" r="2" fill="#92400E"/>
-    <rect x="-15" y="-1" width="30" height="2" fill="#92400E"/>
-    <circle cx="20" cy="0" r="2" fill="#92400E"/>
-    <rect x="25" y="-1" width="10" height="2" fill="#92400E"/>
-    <circle cx="40" cy="0" r="2" fill="#92400E"/>
-  </g>
-  
-  <g transform="translate(0, 450)">
-    <text x="150" y="0" text-anchor="middle" font-family="monospace" font-size="10" fill="#92400E" letter-spacing="2">DATE ISSUED</text>
-    <text x="150" y="20" text-anchor="middle" font-family="Georgia, serif" font-size="14" fill="#78350F">{{DATE}}</text>
-    <line x1="80" y1="25" x2="220" y2="25" stroke="#D97706" stroke-width="1"/>
-    
-    <g transform="translate(400, 15)">
-      <circle cx="0" cy="0" r="35" fill="url(#sealGradient)" stroke="#7F1D1D" stroke-width="2"/>
-      <circle cx="0" cy="0" r="28" fill="none" stroke="#FCA5A5" stroke-width="1" opacity="0.5"/>
-      <circle cx="0" cy="0" r="22" fill="none" stroke="#FCA5A5" stroke-width="1" opacity="0.3"/>
-      <text x="0" y="-2" text-anchor="middle" font-family="monospace" font-size="10" font-weight="bold" fill="#FEE2E2">KNOW</text>
-      <text x="0" y="10" text-anchor="middle" font-family="monospace" font-size="10" font-weight="bold" fill="#FEE2E2">CODE</text>
-      <text x="0" y="-18" text-anchor="middle" font-size="8" fill="#FCA5A5">★</text>
-      <text x="16" y="-10" text-anchor="middle" font-size="6" fill="#FCA5A5">★</text>
-      <text x="-16" y="-10" text-anchor="middle" font-size="6" fill="#FCA5A5">★</text>
-    </g>
-    
-    <text x="650" y="0" text-anchor="middle" font-family="monospace" font-size="10" fill="#92400E" letter-spacing="2">CERTIFICATE NO.</text>
-    <text x="650" y="20" text-anchor="middle" font-family="monospace" font-size="12" fill="#78350F">{{CERT_NO}}</text>
-    <line x1="580" y1="25" x2="720" y2="25" stroke="#D97706" stroke-width="1"/>
-  </g>
-  
-  <text x="400" y="515" text-anchor="middle" font-family="Georgia, serif" font-size="10" fill="#92400E" font-style="italic">Historical examination courtesy of WB4WXD &amp; KB6NU</text>
-  <text x="400" y="530" text-anchor="middle" font-family="Georgia, serif" font-size="8" fill="#92400E" opacity="0.7">This certificate is based on the honor system and is not an official FCC document</text>
-  <a href="https://knowcodeextra.com" target="_blank"><text x="400" y="548" text-anchor="middle" font-family="monospace" font-size="10" fill="#B45309" text-decoration="underline">KNOWCODEEXTRA.COM</text></a>
-  <text x="400" y="565" text-anchor="middle" font-family="monospace" font-size="9" fill="#D97706" opacity="0.5">−·−· ·−−·   ·−·−·   ···−·−</text>
-</svg>"##;
-
-/// Certificate data for generation (20 WPM Extra only)
+/// Built-in design, used for tests without a certificate template of their own
+pub const DEFAULT_TEMPLATE: &str = include_str!("../templates/certificate.svg");
+
+/// Bumped whenever the built-in template changes, so cached PDF and PNG renders are redrawn
+pub const DEFAULT_TEMPLATE_VERSION: i32 = 2;
+
+/// Largest template accepted from the admin portal
+pub const MAX_TEMPLATE_BYTES: usize = 512 * 1024;
+
+/// Every template must carry this, so certificates can embed their signature
+const METADATA_PLACEHOLDER: &str = "{{METADATA}}";
+
+/// Where the verification QR code sits: under the certificate number
+const QR_X: f64 = 615.0;
+const QR_Y: f64 = 481.0;
+const QR_SIZE: f64 = 70.0;
+/// Light modules around the code so scanners can find its edges
+const QR_QUIET_ZONE: usize = 2;
+
+/// Namespace of the signature element in certificate metadata
+const SIGNATURE_NS: &str = "https://knowcodeextra.com/ns/certificate/1";
+
+/// A certificate's signed fields and their Ed25519 signature
+#[derive(Debug, Clone, PartialEq)]
+pub struct CertificateSignature {
+    pub fields: SignedFields,
+    /// base64url
+    pub value: String,
+}
+
+impl CertificateSignature {
+    /// The `<metadata>` element carrying the signature
+    fn to_metadata(&self) -> String {
+        format!(
+            r#"<metadata><kce:signature xmlns:kce="{ns}" algorithm="Ed25519" callsign="{callsign}" certificate-number="{number}" test-id="{test_id}" issued-at="{issued_at}" value="{value}"/></metadata>"#,
+            ns = SIGNATURE_NS,
+            callsign = escape_xml(&self.fields.callsign),
+            number = self.fields.certificate_number,
+            test_id = escape_xml(self.fields.test_id.as_deref().unwrap_or("")),
+            issued_at = self.fields.issued_at_string(),
+            value = self.value,
+        )
+    }
+
+    /// Read the signature back out of a certificate SVG
+    pub fn from_svg(svg: &str) -> Result<Self, String> {
+        let start = svg
+            .find("<kce:signature ")
+            .ok_or("No signature found; the certificate was issued unsigned or is not a certificate")?;
+        let end = svg[start..]
+            .find("/>")
+            .ok_or("Malformed signature element")?;
+        let element = &svg[start..start + end];
+
+        let attribute = |name: &str| -> Result<String, String> {
+            let prefix = format!(" {}=\"", name);
+            let value_start = element
+                .find(&prefix)
+                .map(|i| i + prefix.len())
+                .ok_or_else(|| format!("Signature is missing {}", name))?;
+            let value_end = element[value_start..]
+                .find('"')
+                .ok_or_else(|| format!("Malformed {}", name))?;
+            Ok(unescape_xml(&element[value_start..value_start + value_end]))
+        };
+
+        let algorithm = attribute("algorithm")?;
+        if algorithm != "Ed25519" {
+            return Err(format!("Unsupported signature algorithm {}", algorithm));
+        }
+        let certificate_number = attribute("certificate-number")?
+            .parse()
+            .map_err(|_| "Malformed certificate-number".to_string())?;
+        let issued_at = DateTime::parse_from_rfc3339(&attribute("issued-at")?)
+            .map_err(|_| "Malformed issued-at".to_string())?
+            .with_timezone(&Utc);
+        let test_id = attribute("test-id")?;
+
+        Ok(CertificateSignature {
+            fields: SignedFields {
+                callsign: attribute("callsign")?,
+                certificate_number,
+                test_id: Some(test_id).filter(|t| !t.is_empty()),
+                issued_at,
+            },
+            value: attribute("value")?,
+        })
+    }
+}
+
+/// Certificate data for generation
 pub struct CertificateData {
     pub callsign: String,
+    /// Operator name, empty when unknown
+    pub name: String,
+    pub speed_wpm: i32,
+    /// Every class the holder has earned, this one included
+    pub classes: Vec<LicenseClass>,
+    /// Year of the examination, empty for attempts without a stored test
+    pub year: String,
     pub date: String,
     pub cert_no: String,
+    /// Public verification page the QR code links to
+    pub verify_url: String,
+    /// Embedded in the metadata and the QR code when the server has a signing key
+    pub signature: Option<CertificateSignature>,
 }
 
 impl CertificateData {
     /// Create certificate data from an attempt
-    pub fn from_attempt(callsign: &str, cert_no: &str, date: &str) -> Self {
+    pub fn from_attempt(callsign: &str, speed_wpm: i32, cert_no: &str, date: &str, verify_url: &str) -> Self {
         Self {
             callsign: callsign.to_uppercase(),
+            name: String::new(),
+            speed_wpm,
+            classes: LicenseClass::from_speed(speed_wpm).into_iter().collect(),
+            year: String::new(),
             date: date.to_string(),
             cert_no: cert_no.to_string(),
+            verify_url: verify_url.to_string(),
+            signature: None,
         }
     }
 
-    /// Generate the SVG certificate
+    /// Made-up data for previewing templates in the admin portal
+    pub fn sample(public_url: &str) -> Self {
+        let mut sample = Self::from_attempt(
+            "W1AW",
+            20,
+            "#1234",
+            &Utc::now().format("%B %d, %Y").to_string(),
+            &verify_url(public_url, 1234),
+        );
+        sample.name = "Hiram Percy Maxim".to_string();
+        sample.year = "1991".to_string();
+        sample.classes = vec![LicenseClass::General, LicenseClass::Extra];
+        sample
+    }
+
+    /// Sign the certificate with the server's key
+    pub fn signed(mut self, signer: &CertificateSigner, fields: SignedFields) -> Self {
+        let value = signer.sign(&fields);
+        self.signature = Some(CertificateSignature { fields, value });
+        self
+    }
+
+    /// Generate the SVG certificate from the built-in template
     pub fn to_svg(&self) -> String {
-        CERTIFICATE_TEMPLATE
-            .replace("{{CALLSIGN}}", &self.callsign)
-            .replace("{{DATE}}", &self.date)
-            .replace("{{CERT_NO}}", &self.cert_no)
+        self.render(DEFAULT_TEMPLATE)
+    }
+
+    /// This certificate's class, upper case
+    fn class_label(&self) -> String {
+        LicenseClass::from_speed(self.speed_wpm)
+            .map(|class| class.name().to_uppercase())
+            .unwrap_or_default()
+    }
+
+    /// Every class earned, slowest first, falling back to this certificate's class
+    fn classes_label(&self) -> String {
+        if self.classes.is_empty() {
+            return self.class_label();
+        }
+        self.classes
+            .iter()
+            .map(|class| class.name().to_uppercase())
+            .collect::<Vec<_>>()
+            .join(" · ")
+    }
+
+    /// Fill in a template's placeholders. Values are XML-escaped; the
+    /// signature metadata and QR code are inserted as markup.
+    pub fn render(&self, template: &str) -> String {
+        let (metadata, qr_payload) = match &self.signature {
+            Some(signature) => (
+                signature.to_metadata(),
+                format!("{}?sig={}", self.verify_url, signature.value),
+            ),
+            None => (String::new(), self.verify_url.clone()),
+        };
+
+        // One pass, so placeholders inside values are left alone
+        let mut svg = String::with_capacity(template.len() + metadata.len());
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            let Some(len) = rest[start..].find("}}") else {
+                break;
+            };
+            let value = match &rest[start + 2..start + len] {
+                "METADATA" => metadata.clone(),
+                "CALLSIGN" => escape_xml(&self.callsign),
+                "NAME" => escape_xml(&self.name),
+                "SPEED" => self.speed_wpm.to_string(),
+                "CLASS" => self.class_label(),
+                "CLASSES" => self.classes_label(),
+                "YEAR" => escape_xml(&self.year),
+                "DATE" => escape_xml(&self.date),
+                "CERT_NO" => escape_xml(&self.cert_no),
+                "QR_CODE" => qr_code_svg(&qr_payload),
+                _ => {
+                    // Not a placeholder; keep the braces and move on
+                    svg.push_str(&rest[..start + 2]);
+                    rest = &rest[start + 2..];
+                    continue;
+                }
+            };
+            svg.push_str(&rest[..start]);
+            svg.push_str(&value);
+            rest = &rest[start + len + 2..];
+        }
+        svg.push_str(rest);
+        svg
+    }
+}
+
+/// Check a template from the admin portal before it is saved or previewed
+pub fn validate_template(svg: &str) -> Result<(), String> {
+    if svg.len() > MAX_TEMPLATE_BYTES {
+        return Err(format!("Template must be at most {} KiB", MAX_TEMPLATE_BYTES / 1024));
+    }
+    if !svg.contains(METADATA_PLACEHOLDER) {
+        return Err("Template must contain {{METADATA}} so certificates can carry their signature".to_string());
+    }
+    let lower = svg.to_ascii_lowercase();
+    if lower.contains("<script") || lower.contains("<foreignobject") {
+        return Err("Template must not contain scripts or foreignObject elements".to_string());
+    }
+
+    let sample = CertificateData::sample("https://example.com").render(svg);
+    crate::render::parse_svg(&sample)?;
+    check_scripting(&sample)
+}
+
+/// Reject markup that runs script: script and foreignObject elements, on*
+/// event handler attributes, and attributes linking to javascript: or
+/// data:text/html URLs. The document is parsed as the browser would, so
+/// character and entity references are expanded first.
+fn check_scripting(svg: &str) -> Result<(), String> {
+    use resvg::usvg::roxmltree::{Document, ParsingOptions};
+
+    let options = ParsingOptions { allow_dtd: true, ..ParsingOptions::default() };
+    let doc = Document::parse_with_options(svg, options).map_err(|e| format!("Invalid certificate SVG: {}", e))?;
+    for node in doc.descendants() {
+        let tag = node.tag_name().name();
+        if tag.eq_ignore_ascii_case("script") || tag.eq_ignore_ascii_case("foreignobject") {
+            return Err("Template must not contain scripts or foreignObject elements".to_string());
+        }
+        for attribute in node.attributes() {
+            if attribute.name().to_ascii_lowercase().starts_with("on") {
+                return Err(format!(
+                    "Template must not contain event handler attributes, found '{}'",
+                    attribute.name()
+                ));
+            }
+            // Browsers skip whitespace and control characters in a URL scheme
+            let value: String = attribute
+                .value()
+                .chars()
+                .filter(|c| !c.is_whitespace() && !c.is_control())
+                .collect::<String>()
+                .to_ascii_lowercase();
+            if value.contains("javascript:") || value.contains("data:text/html") {
+                return Err("Template must not link to javascript: or data:text/html URLs".to_string());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Public verification page for a certificate number
+pub fn verify_url(public_url: &str, certificate_number: i32) -> String {
+    format!("{}/verify/{}", public_url.trim_end_matches('/'), certificate_number)
+}
+
+/// A QR code for `data` as SVG paths, one rectangle per run of dark modules
+fn qr_code_svg(data: &str) -> String {
+    let code = match QrCode::with_error_correction_level(data.as_bytes(), EcLevel::M) {
+        Ok(code) => code,
+        Err(e) => {
+            tracing::warn!("Could not encode certificate QR code for {}: {}", data, e);
+            return String::new();
+        }
+    };
+
+    let width = code.width();
+    let colors = code.to_colors();
+    let mut path = String::new();
+    for y in 0..width {
+        let row = &colors[y * width..(y + 1) * width];
+        let mut x = 0;
+        while x < width {
+            if row[x] == Color::Light {
+                x += 1;
+                continue;
+            }
+            let start = x;
+            while x < width && row[x] == Color::Dark {
+                x += 1;
+            }
+            let run = x - start;
+            path.push_str(&format!(
+                "M{},{}h{}v1h-{}z",
+                start + QR_QUIET_ZONE,
+                y + QR_QUIET_ZONE,
+                run,
+                run
+            ));
+        }
+    }
+
+    let modules = width + 2 * QR_QUIET_ZONE;
+    format!(
+        r##"<a href="{url}" target="_blank"><g transform="translate({x}, {y}) scale({scale})"><rect width="{modules}" height="{modules}" fill="#FFFBEB"/><path d="{path}" fill="#78350F" shape-rendering="crispEdges"/></g></a>"##,
+        url = escape_xml(data),
+        x = QR_X,
+        y = QR_Y,
+        scale = QR_SIZE / modules as f64,
+        modules = modules,
+        path = path,
+    )
+}
+
+/// Reverse `escape_xml`
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Escape text for use in SVG or HTML content and attribute values
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
     }
+    escaped
 }
 
-/// Handler to get certificate SVG for a passing attempt
+/// Query string of the certificate route
+#[derive(Debug, Deserialize)]
+pub struct CertificateQuery {
+    /// `svg` (default), `pdf` or `png`
+    pub format: Option<String>,
+}
+
+/// Handler to get the certificate for a passing attempt as SVG, PDF or PNG
 pub async fn get_certificate_svg(
     State(state): State<Arc<crate::AppState>>,
     Path(attempt_id): Path<String>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+    Query(query): Query<CertificateQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let format = match query.format.as_deref() {
+        None | Some("svg") => None,
+        Some(format) => Some(RenderFormat::parse(format).ok_or((
+            StatusCode::BAD_REQUEST,
+            "format must be svg, pdf or png".to_string(),
+        ))?),
+    };
+
     // Fetch the attempt from database - requires approved status
-    let attempt: Option<crate::Attempt> = sqlx::query_as(
-        "SELECT id, callsign, test_speed, questions_correct, copy_chars, passed, created_at,
-                validation_status, certificate_number, validated_at, admin_note
-         FROM attempts WHERE id = ? AND passed = true AND validation_status = 'approved'",
-    )
-    .bind(&attempt_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let attempt = state
+        .db
+        .attempts()
+        .find_certified(&attempt_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    let attempt = attempt.ok_or((
-        StatusCode::NOT_FOUND,
-        "Certificate not available. Attempt may be pending approval or not passed.".to_string(),
-    ))?;
+    let attempt = match attempt {
+        Some(attempt) => attempt,
+        None => {
+            let revoked = state
+                .db
+                .certificates()
+                .is_revoked(&attempt_id)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            return Err(if revoked {
+                (StatusCode::GONE, "This certificate has been revoked.".to_string())
+            } else {
+                (
+                    StatusCode::NOT_FOUND,
+                    "Certificate not available. Attempt may be pending approval or not passed.".to_string(),
+                )
+            });
+        }
+    };
 
     // Use the assigned certificate number from database
-    let cert_no = match attempt.certificate_number {
-        Some(num) => format!("#{}", num),
+    let number = match attempt.certificate_number {
+        Some(num) => num,
         None => {
             return Err((
                 StatusCode::NOT_FOUND,
@@ -200,12 +433,296 @@ pub async fn get_certificate_svg(
     // Format date
     let date = attempt.created_at.format("%B %d, %Y").to_string();
 
+    // The test's own template and year, when the attempt has a stored test
+    let (template, year) = match &attempt.test_id {
+        Some(test_id) => {
+            let template = state
+                .db
+                .certificate_templates()
+                .for_test(test_id)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            let test = state
+                .db
+                .tests()
+                .find_admin(test_id)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            (template, test.map(|test| test.year))
+        }
+        None => (None, None),
+    };
+
     // Generate SVG
-    let cert_data = CertificateData::from_attempt(&attempt.callsign, &cert_no, &date);
+    let mut cert_data = CertificateData::from_attempt(
+        &attempt.callsign,
+        attempt.test_speed,
+        &format!("#{}", number),
+        &date,
+        &verify_url(&state.config.public_url, number),
+    );
+    cert_data.name = attempt.name.clone().unwrap_or_default();
+    cert_data.year = year.unwrap_or_default();
+    let speeds = state
+        .db
+        .attempts()
+        .approved_speeds(&attempt.callsign)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    cert_data.classes = speeds.into_iter().filter_map(LicenseClass::from_speed).collect();
+    if let Some(signer) = &state.certificate_signer {
+        let fields = SignedFields {
+            callsign: cert_data.callsign.clone(),
+            certificate_number: number,
+            test_id: attempt.test_id.clone(),
+            issued_at: attempt.validated_at.unwrap_or(attempt.created_at),
+        };
+        cert_data = cert_data.signed(signer, fields);
+    }
+
+    let (svg, template_id, template_version) = match &template {
+        Some(template) => (cert_data.render(&template.svg), Some(template.template_id.as_str()), template.version),
+        None => (cert_data.to_svg(), None, DEFAULT_TEMPLATE_VERSION),
+    };
+
+    let Some(format) = format else {
+        return Ok(svg_response(svg));
+    };
 
-    let svg = cert_data.to_svg();
+    let bytes = state
+        .certificate_renderer
+        .render(number, template_id, template_version, format, svg)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
-    Ok(([(header::CONTENT_TYPE, "image/svg+xml")], svg))
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "inline; filename=\"knowcodeextra-certificate-{}.{}\"",
+                    number,
+                    format.extension()
+                ),
+            ),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+/// An SVG response that browsers will not run scripts in, should a template carry any
+pub fn svg_response(svg: String) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "image/svg+xml"),
+            (header::CONTENT_SECURITY_POLICY, "default-src 'none'; style-src 'unsafe-inline'; img-src data:"),
+        ],
+        svg,
+    )
+        .into_response()
+}
+
+/// Public verification result for `GET /verify/:certificate_number`
+#[derive(Debug, Serialize)]
+pub struct CertificateVerification {
+    pub certificate_number: i32,
+    pub callsign: String,
+    /// YYYY-MM-DD, as printed on the certificate
+    pub date_issued: String,
+    pub test_id: Option<String>,
+    pub test_title: Option<String>,
+    pub test_speed: i32,
+    /// `valid` or `revoked`
+    pub status: String,
+    /// Whether the `sig` from the QR code matches, when one was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature_valid: Option<bool>,
+}
+
+/// Query string of `/verify/:certificate_number`
+#[derive(Debug, Deserialize)]
+pub struct VerifyQuery {
+    /// Signature carried in the QR code
+    pub sig: Option<String>,
+}
+
+impl From<IssuedCertificate> for CertificateVerification {
+    fn from(issued: IssuedCertificate) -> Self {
+        Self {
+            signature_valid: None,
+            certificate_number: issued.certificate_number,
+            callsign: issued.callsign,
+            date_issued: issued.created_at.format("%Y-%m-%d").to_string(),
+            test_id: issued.test_id,
+            test_title: issued.test_title,
+            test_speed: issued.test_speed,
+            status: if issued.validation_status == "revoked" {
+                "revoked"
+            } else {
+                "valid"
+            }
+            .to_string(),
+        }
+    }
+}
+
+/// Handler to check a certificate number: JSON for `Accept: application/json`,
+/// otherwise a page for people following the QR code
+pub async fn verify_certificate(
+    State(state): State<Arc<crate::AppState>>,
+    Path(certificate_number): Path<String>,
+    Query(query): Query<VerifyQuery>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    let wants_json = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/json") && !accept.contains("text/html"));
+
+    // Printed numbers carry a leading '#'
+    let issued = match certificate_number.trim_start_matches('#').parse::<i32>() {
+        Ok(number) => state
+            .db
+            .certificates()
+            .find_by_number(number)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+        Err(_) => None,
+    };
+
+    let Some(issued) = issued else {
+        if wants_json {
+            return Err((StatusCode::NOT_FOUND, "Certificate not found".to_string()));
+        }
+        return Ok((
+            StatusCode::NOT_FOUND,
+            Html(verification_page(&certificate_number, None)),
+        )
+            .into_response());
+    };
+
+    // Check the QR code's signature against the certificate as it stands
+    let signature_valid = match (&state.certificate_signer, &query.sig) {
+        (Some(signer), Some(sig)) => Some(
+            crate::signing::verify(&signer.public_key_base64(), &signed_fields(&issued), sig).is_ok(),
+        ),
+        _ => None,
+    };
+
+    let mut verification = CertificateVerification::from(issued);
+    verification.signature_valid = signature_valid;
+    if wants_json {
+        Ok(Json(verification).into_response())
+    } else {
+        Ok(Html(verification_page(&certificate_number, Some(&verification))).into_response())
+    }
+}
+
+/// What the certificate for `issued` was signed over
+fn signed_fields(issued: &IssuedCertificate) -> SignedFields {
+    SignedFields {
+        callsign: issued.callsign.to_uppercase(),
+        certificate_number: issued.certificate_number,
+        test_id: issued.test_id.clone(),
+        issued_at: issued.validated_at.unwrap_or(issued.created_at),
+    }
+}
+
+/// Handler publishing the certificate signing public key
+pub async fn get_certificate_key(
+    State(state): State<Arc<crate::AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let signer = state.certificate_signer.as_ref().ok_or((
+        StatusCode::NOT_FOUND,
+        "Certificate signing is not configured".to_string(),
+    ))?;
+
+    Ok(Json(serde_json::json!({
+        "algorithm": "Ed25519",
+        "encoding": "base64url",
+        "public_key": signer.public_key_base64(),
+    })))
+}
+
+/// Human-readable verification page
+fn verification_page(requested: &str, verification: Option<&CertificateVerification>) -> String {
+    let body = match verification {
+        None => format!(
+            r#"<p class="status bad">NOT FOUND</p>
+    <p>No Know Code Extra certificate has number {}.</p>"#,
+            escape_xml(requested)
+        ),
+        Some(v) => {
+            let (class, status, note) = if v.status == "revoked" {
+                ("bad", "REVOKED", "This certificate was issued but has since been revoked.")
+            } else {
+                ("good", "VALID", "This certificate was issued by Know Code Extra.")
+            };
+            let test = v
+                .test_title
+                .as_deref()
+                .map(|title| format!("{} ({} WPM)", title, v.test_speed))
+                .unwrap_or_else(|| format!("{} WPM", v.test_speed));
+            let signature = match v.signature_valid {
+                Some(true) => "<p>The signature in the QR code matches.</p>",
+                Some(false) => r#"<p class="bad">The signature in the QR code does not match this certificate.</p>"#,
+                None => "",
+            };
+            format!(
+                r#"<p class="status {class}">{status}</p>
+    <p>{note}</p>
+    {signature}
+    <dl>
+      <dt>Certificate No.</dt><dd>#{number}</dd>
+      <dt>Callsign</dt><dd>{callsign}</dd>
+      <dt>Date Issued</dt><dd>{date}</dd>
+      <dt>Test</dt><dd>{test}</dd>
+    </dl>"#,
+                class = class,
+                status = status,
+                note = note,
+                signature = signature,
+                number = v.certificate_number,
+                callsign = escape_xml(&v.callsign),
+                date = escape_xml(&v.date_issued),
+                test = escape_xml(&test),
+            )
+        }
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <meta name="viewport" content="width=device-width, initial-scale=1">
+  <title>Certificate Verification - Know Code Extra</title>
+  <style>
+    body {{ background: #FFFBEB; color: #78350F; font-family: Georgia, serif; margin: 0; padding: 2rem; }}
+    main {{ max-width: 32rem; margin: 0 auto; border: 2px solid #92400E; background: #FEF3C7; padding: 2rem; }}
+    h1 {{ font-size: 1.5rem; letter-spacing: 0.1em; margin-top: 0; }}
+    .status {{ font-family: monospace; font-size: 1.5rem; font-weight: bold; letter-spacing: 0.2em; }}
+    .good {{ color: #166534; }}
+    .bad {{ color: #991B1B; }}
+    dl {{ display: grid; grid-template-columns: auto 1fr; gap: 0.5rem 1rem; }}
+    dt {{ font-family: monospace; font-size: 0.8rem; letter-spacing: 0.1em; text-transform: uppercase; color: #92400E; }}
+    dd {{ margin: 0; }}
+    a {{ color: #B45309; }}
+  </style>
+</head>
+<body>
+  <main>
+    <h1>CERTIFICATE VERIFICATION</h1>
+    {body}
+    <p><a href="/">knowcodeextra.com</a></p>
+  </main>
+</body>
+</html>
+"#,
+        body = body
+    )
 }
 
 #[cfg(test)]
@@ -214,7 +731,13 @@ mod tests {
 
     #[test]
     fn test_certificate_generation() {
-        let cert = CertificateData::from_attempt("W6JSV", "20WPM-ABC123", "January 17, 2025");
+        let cert = CertificateData::from_attempt(
+            "W6JSV",
+            20,
+            "20WPM-ABC123",
+            "January 17, 2025",
+            "https://knowcodeextra.com/verify/1",
+        );
         let svg = cert.to_svg();
 
         assert!(svg.contains("W6JSV"));
@@ -224,5 +747,134 @@ mod tests {
         assert!(svg.contains("January 17, 2025"));
         assert!(svg.contains("20WPM-ABC123"));
         assert!(svg.contains("Proving the code lives on"));
+        assert!(svg.contains(r#"<a href="https://knowcodeextra.com/verify/1""#));
+        assert!(!svg.contains("{{QR_CODE}}"));
+    }
+
+    #[test]
+    fn test_template_placeholders_are_escaped() {
+        let mut cert = CertificateData::from_attempt("k1abc", 13, "#7", "March 1, 2026", "https://knowcodeextra.com/verify/7");
+        cert.name = "Tom & <Jerry> {{CALLSIGN}}".to_string();
+        cert.year = "1987".to_string();
+        let svg = cert.render(
+            "<svg>{{METADATA}}<text>{{CALLSIGN}} {{NAME}} {{SPEED}} WPM {{YEAR}} {{DATE}} {{CERT_NO}} {{UNKNOWN}}</text></svg>",
+        );
+
+        assert_eq!(
+            svg,
+            "<svg><text>K1ABC Tom &amp; &lt;Jerry&gt; {{CALLSIGN}} 13 WPM 1987 March 1, 2026 #7 {{UNKNOWN}}</text></svg>"
+        );
+    }
+
+    #[test]
+    fn test_default_template_uses_test_speed() {
+        let svg = CertificateData::from_attempt("W6JSV", 13, "#3", "January 17, 2026", "https://knowcodeextra.com/verify/3").to_svg();
+        assert!(svg.contains(">13 WPM</tspan>"));
+        assert!(!svg.contains("20 WPM"));
+        assert!(!svg.contains("{{"));
+        assert!(validate_template(DEFAULT_TEMPLATE).is_ok());
+    }
+
+    #[test]
+    fn test_class_placeholders() {
+        let mut cert = CertificateData::from_attempt("W6JSV", 13, "#3", "January 17, 2026", "https://knowcodeextra.com/verify/3");
+        assert_eq!(cert.render("{{CLASS}}|{{CLASSES}}"), "GENERAL|GENERAL");
+
+        cert.classes = vec![LicenseClass::Novice, LicenseClass::General, LicenseClass::Extra];
+        assert_eq!(cert.render("{{CLASS}}|{{CLASSES}}"), "GENERAL|NOVICE · GENERAL · EXTRA");
+        assert!(cert.to_svg().contains(">NOVICE · GENERAL · EXTRA</text>"));
+
+        cert.classes.clear();
+        cert.speed_wpm = 25;
+        assert_eq!(cert.render("{{CLASS}}|{{CLASSES}}"), "|");
+    }
+
+    #[test]
+    fn test_validate_template() {
+        assert!(validate_template(r#"<svg xmlns="http://www.w3.org/2000/svg" width="8" height="6">{{METADATA}}<text>{{NAME}}</text></svg>"#).is_ok());
+
+        let missing_metadata = validate_template(r#"<svg xmlns="http://www.w3.org/2000/svg"></svg>"#).unwrap_err();
+        assert!(missing_metadata.contains("{{METADATA}}"));
+
+        assert!(validate_template("<svg>{{METADATA}}<g></svg>").is_err());
+        assert!(validate_template(r#"<svg xmlns="http://www.w3.org/2000/svg">{{METADATA}}<script>alert(1)</script></svg>"#).is_err());
+
+        // Event handlers, in any case and spacing, but not text that merely reads like one
+        for svg in [
+            r#"<svg xmlns="http://www.w3.org/2000/svg" onload="alert(1)">{{METADATA}}</svg>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg">{{METADATA}}<rect OnClick = 'alert(1)'/></svg>"#,
+        ] {
+            assert!(validate_template(svg).unwrap_err().contains("event handler"), "{}", svg);
+        }
+        assert!(validate_template(r#"<svg xmlns="http://www.w3.org/2000/svg" data-note="x onload=y">{{METADATA}}<text>one = two</text></svg>"#).is_ok());
+
+        for svg in [
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">{{METADATA}}<a xlink:href="javascript:alert(1)"><text>x</text></a></svg>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg">{{METADATA}}<a href="  JavaScript :alert(1)"><text>x</text></a></svg>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg">{{METADATA}}<a href="data:text/html;base64,PHNjcmlwdD4="><text>x</text></a></svg>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg">{{METADATA}}<a href="&#106;avascript:alert(1)"><text>x</text></a></svg>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg">{{METADATA}}<a href="java&#x09;script:alert(1)"><text>x</text></a></svg>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg">{{METADATA}}<set attributeName="href" to="javascript:alert(1)"/></svg>"#,
+            r#"<!DOCTYPE svg [<!ENTITY js "javascript:alert(1)">]><svg xmlns="http://www.w3.org/2000/svg">{{METADATA}}<a href="&js;"><text>x</text></a></svg>"#,
+        ] {
+            assert!(validate_template(svg).unwrap_err().contains("javascript:"), "{}", svg);
+        }
+        // Nor can an entity smuggle in a script element
+        let entity_script = r#"<!DOCTYPE svg [<!ENTITY s "<script>alert(1)</script>">]><svg xmlns="http://www.w3.org/2000/svg">{{METADATA}}&s;</svg>"#;
+        assert!(validate_template(entity_script).unwrap_err().contains("scripts"));
+
+        let too_large = format!("<svg>{{{{METADATA}}}}{}</svg>", " ".repeat(MAX_TEMPLATE_BYTES));
+        assert!(validate_template(&too_large).unwrap_err().contains("KiB"));
+    }
+
+    #[test]
+    fn test_qr_code_matches_modules() {
+        let url = "https://knowcodeextra.com/verify/42";
+        let code = QrCode::with_error_correction_level(url.as_bytes(), EcLevel::M).unwrap();
+        let dark = code.to_colors().iter().filter(|c| **c == Color::Dark).count();
+
+        let svg = qr_code_svg(url);
+        let path = svg.split(r#"d=""#).nth(1).unwrap().split('"').next().unwrap();
+        // Every run is a rectangle one module high
+        let covered: usize = path
+            .split('M')
+            .skip(1)
+            .map(|run| run.split('h').nth(1).unwrap().split('v').next().unwrap().parse::<usize>().unwrap())
+            .sum();
+        assert_eq!(covered, dark);
+        assert!(svg.contains(&format!("translate({}, {})", QR_X, QR_Y)));
+    }
+
+    #[test]
+    fn test_signed_certificate_round_trip() {
+        let signer = CertificateSigner::generate();
+        let fields = SignedFields {
+            callsign: "W6JSV".to_string(),
+            certificate_number: 12,
+            test_id: Some("20wpm-extra-1991".to_string()),
+            issued_at: DateTime::parse_from_rfc3339("2026-01-17T15:04:05Z").unwrap().with_timezone(&Utc),
+        };
+        let svg = CertificateData::from_attempt("W6JSV", 20, "#12", "January 17, 2026", "https://knowcodeextra.com/verify/12")
+            .signed(&signer, fields.clone())
+            .to_svg();
+
+        let signature = CertificateSignature::from_svg(&svg).unwrap();
+        assert_eq!(signature.fields, fields);
+        assert!(crate::signing::verify(&signer.public_key_base64(), &signature.fields, &signature.value).is_ok());
+        assert!(svg.contains(&format!(r#"href="https://knowcodeextra.com/verify/12?sig={}""#, signature.value)));
+
+        // Editing the signed callsign breaks the signature
+        let forged = CertificateSignature::from_svg(&svg.replace(r#"callsign="W6JSV""#, r#"callsign="K1ABC""#)).unwrap();
+        assert!(crate::signing::verify(&signer.public_key_base64(), &forged.fields, &forged.value).is_err());
+
+        let unsigned = CertificateData::from_attempt("W6JSV", 20, "#12", "January 17, 2026", "https://knowcodeextra.com/verify/12").to_svg();
+        assert!(CertificateSignature::from_svg(&unsigned).is_err());
+        assert!(!unsigned.contains("{{METADATA}}"));
+    }
+
+    #[test]
+    fn test_verify_url_and_escaping() {
+        assert_eq!(verify_url("https://example.com/", 7), "https://example.com/verify/7");
+        assert_eq!(escape_xml(r#"<K1"A'&>"#), "&lt;K1&quot;A&apos;&amp;&gt;");
     }
 }
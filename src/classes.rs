@@ -0,0 +1,60 @@
+//! License classes and their element 1 code speeds
+//!
+//! Until 2000 each amateur license class required a Morse code exam at its
+//! own speed: 5 WPM for Novice, 13 WPM for General and 20 WPM for Extra.
+//! Tests, attempts and certificates carry the speed; the class follows from it.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LicenseClass {
+    Novice,
+    General,
+    Extra,
+}
+
+impl LicenseClass {
+    /// Every class, slowest first
+    pub const ALL: [LicenseClass; 3] = [LicenseClass::Novice, LicenseClass::General, LicenseClass::Extra];
+
+    pub fn from_speed(speed_wpm: i32) -> Option<Self> {
+        Self::ALL.into_iter().find(|class| class.speed_wpm() == speed_wpm)
+    }
+
+    pub fn speed_wpm(self) -> i32 {
+        match self {
+            LicenseClass::Novice => 5,
+            LicenseClass::General => 13,
+            LicenseClass::Extra => 20,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LicenseClass::Novice => "Novice",
+            LicenseClass::General => "General",
+            LicenseClass::Extra => "Extra",
+        }
+    }
+}
+
+/// Check that a test or attempt speed belongs to a class
+pub fn check_speed(speed_wpm: i32) -> Result<LicenseClass, String> {
+    LicenseClass::from_speed(speed_wpm).ok_or_else(|| "Invalid test speed. Must be 5, 13 or 20".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classes_by_speed() {
+        assert_eq!(LicenseClass::from_speed(5), Some(LicenseClass::Novice));
+        assert_eq!(LicenseClass::from_speed(13), Some(LicenseClass::General));
+        assert_eq!(LicenseClass::from_speed(20), Some(LicenseClass::Extra));
+        assert!(check_speed(25).is_err());
+        assert!(LicenseClass::Novice < LicenseClass::Extra);
+        assert_eq!(serde_json::to_string(&LicenseClass::General).unwrap(), r#""general""#);
+    }
+}
@@ -0,0 +1,409 @@
+//! Server operations exposed as CLI subcommands
+//!
+//! Each command opens the configured database and reuses the same logic as
+//! the admin API, so routine work can be done over SSH without the web UI.
+
+use crate::certificate::CertificateSignature;
+use crate::db::{Backend, Db};
+use crate::repo::CertificateFix;
+use crate::{admin, backup, bundle, jwt, migrations, Command, Config};
+use sqlx::sqlite::SqliteConnectOptions;
+use std::error::Error;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::Path;
+
+/// Minimum password length for accounts created with `create-admin`
+const MIN_PASSWORD_LEN: usize = 12;
+
+/// Run a non-server subcommand against the configured database
+pub async fn run(command: Command, config: &Config) -> Result<(), Box<dyn Error>> {
+    // `restore` replaces the database file, so it must not hold it open
+    if let Command::Restore { file } = command {
+        return restore(config, &file).await;
+    }
+
+    // Signature commands work offline, without a database
+    match command {
+        Command::Verify { file, public_key } => {
+            return verify_certificate(config, &file, public_key.as_deref());
+        }
+        Command::GenerateSigningKey => {
+            let signer = crate::signing::CertificateSigner::generate();
+            println!("certificate_signing_key = \"{}\"", signer.private_key_base64());
+            println!("# public key: {}", signer.public_key_base64());
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let db = crate::connect_database(config).await?;
+
+    // Only `migrate` may look at an un-migrated schema
+    if let Command::Migrate { dry_run } = command {
+        return migrate(&db, dry_run).await;
+    }
+    crate::setup_database(&db).await?;
+
+    let state = crate::build_state(config, db)?;
+
+    match command {
+        Command::Serve
+        | Command::Migrate { .. }
+        | Command::Restore { .. }
+        | Command::Verify { .. }
+        | Command::GenerateSigningKey => {
+            unreachable!("handled above")
+        }
+        Command::CreateAdmin { username } => {
+            let password = read_password()?;
+            if password.chars().count() < MIN_PASSWORD_LEN {
+                return Err(format!(
+                    "Password must be at least {} characters",
+                    MIN_PASSWORD_LEN
+                )
+                .into());
+            }
+            jwt::create_admin_user(&state.db, &username, &password).await?;
+            println!("Admin account '{}' saved", username);
+        }
+        Command::ImportTest { file } => {
+            let json = std::fs::read_to_string(&file)?;
+            let test: admin::TestExport = serde_json::from_str(&json)?;
+            admin::import_test(&state.db, &test).await.map_err(api_error)?;
+            println!(
+                "Imported test '{}' with {} questions as a draft; publish it from the admin portal",
+                test.id,
+                test.questions.len()
+            );
+        }
+        Command::ExportTest { test_id, output } => {
+            let test = admin::export_test(&state.db, &test_id)
+                .await
+                .map_err(api_error)?;
+            let json = serde_json::to_string_pretty(&test)?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, json)?;
+                    println!("Exported test '{}' to {}", test_id, path.display());
+                }
+                None => println!("{}", json),
+            }
+        }
+        Command::ExportBundle { test_id, output } => {
+            let bytes = bundle::export(&state.db, &state.static_dir, &test_id)
+                .await
+                .map_err(api_error)?;
+            std::fs::write(&output, bytes)?;
+            println!("Exported test '{}' to {}", test_id, output.display());
+        }
+        Command::ImportBundle { file, dry_run } => {
+            let bytes = std::fs::read(&file)?;
+            let summary = bundle::import(&state.db, &state.static_dir, &bytes, dry_run)
+                .await
+                .map_err(api_error)?;
+            println!(
+                "{} test '{}' with {} questions, {} segments and audio {}",
+                if dry_run { "Bundle is valid:" } else { "Imported" },
+                summary.id,
+                summary.questions,
+                summary.segments,
+                summary.audio_url
+            );
+        }
+        Command::RegenerateMembers => {
+            let roster = state.member_roster();
+            roster.regenerate().await?;
+            println!("Regenerated {}", roster.members_txt_path());
+        }
+        Command::Approve { attempt_id } => {
+            let cert = state
+                .attempt_service()
+                .approve(&attempt_id)
+                .await
+                .map_err(api_error)?;
+            println!("Approved {} as certificate #{}", attempt_id, cert);
+        }
+        Command::Reject { attempt_id, note } => {
+            state
+                .attempt_service()
+                .reject(&attempt_id, note.as_deref())
+                .await
+                .map_err(api_error)?;
+            println!("Rejected {}", attempt_id);
+        }
+        Command::Revoke { attempt_id, reason } => {
+            let event = state
+                .attempt_service()
+                .revoke(&attempt_id, &reason)
+                .await
+                .map_err(api_error)?;
+            println!("Revoked {}{}", attempt_id, certificate_suffix(event.certificate_number));
+        }
+        Command::Reinstate { attempt_id, reason } => {
+            let event = state
+                .attempt_service()
+                .reinstate(&attempt_id, reason.as_deref())
+                .await
+                .map_err(api_error)?;
+            println!("Reinstated {}{}", attempt_id, certificate_suffix(event.certificate_number));
+        }
+        Command::Reissue {
+            attempt_id,
+            callsign,
+            reason,
+        } => {
+            let event = state
+                .attempt_service()
+                .reissue(&attempt_id, &callsign, reason.as_deref())
+                .await
+                .map_err(api_error)?;
+            println!(
+                "Reissued{} from {} to {}",
+                certificate_suffix(event.certificate_number),
+                event.old_callsign.unwrap_or_default(),
+                event.new_callsign.unwrap_or_default()
+            );
+        }
+        Command::Backup { output: Some(output) } => {
+            backup::snapshot(sqlite_pool(&state.db)?, &output).await?;
+            println!("Backed up database to {}", output.display());
+        }
+        Command::Backup { output: None } => {
+            let settings = config.backup_settings();
+            let entry = backup::create_backup(sqlite_pool(&state.db)?, &settings).await?;
+            println!(
+                "Created {} ({} bytes, sha256 {})",
+                settings.dir.join(&entry.file).display(),
+                entry.size_bytes,
+                entry.sha256
+            );
+        }
+        Command::Stats => {
+            let stats = admin::admin_stats(&state).await.map_err(api_error)?;
+            println!("Pending:            {}", stats.pending_count);
+            println!("Approved today:     {}", stats.approved_today);
+            println!("Total certificates: {}", stats.total_certificates);
+            println!("Rejected:           {}", stats.rejected_count);
+        }
+        Command::RepairCertificates { fix } => {
+            repair_certificates(&state, fix).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// `verify`: check the signature embedded in a certificate SVG
+fn verify_certificate(
+    config: &Config,
+    file: &Path,
+    public_key: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let public_key = match public_key {
+        Some(key) => key.to_string(),
+        None => config
+            .certificate_signer()?
+            .ok_or("No signing key configured; pass --public-key (from GET /api/certificate-key)")?
+            .public_key_base64(),
+    };
+
+    let svg = std::fs::read_to_string(file)?;
+    let signature = CertificateSignature::from_svg(&svg)?;
+    crate::signing::verify(&public_key, &signature.fields, &signature.value)?;
+
+    let fields = &signature.fields;
+    println!("Signature valid");
+    println!("Callsign:    {}", fields.callsign);
+    println!("Certificate: #{}", fields.certificate_number);
+    println!("Test:        {}", fields.test_id.as_deref().unwrap_or("-"));
+    println!("Issued at:   {}", fields.issued_at_string());
+    println!("Compare these with the printed certificate; revocations are only shown online.");
+    Ok(())
+}
+
+/// `migrate`: apply (or with --dry-run, list) pending migrations
+async fn migrate(db: &Db, dry_run: bool) -> Result<(), Box<dyn Error>> {
+    if dry_run {
+        let plan = migrations::plan(db).await?;
+        println!(
+            "Current schema version: {} (latest: {})",
+            plan.current_version,
+            migrations::latest_version()
+        );
+        if plan.adopt_legacy {
+            println!("Existing pre-migration database will be adopted at version 1");
+        }
+        if plan.pending.is_empty() {
+            println!("No pending migrations");
+        }
+        for migration in &plan.pending {
+            println!("Pending: {:04}_{}", migration.version, migration.name);
+        }
+        return Ok(());
+    }
+
+    let version = migrations::run(db).await?;
+    println!("Database schema at version {}", version);
+    Ok(())
+}
+
+/// `restore`: validate a backup and swap it in for the configured database
+async fn restore(config: &Config, file: &Path) -> Result<(), Box<dyn Error>> {
+    if Backend::from_url(&config.database_url) == Backend::Postgres {
+        return Err(backup::POSTGRES_UNSUPPORTED.into());
+    }
+
+    let db_path = config
+        .database_url
+        .parse::<SqliteConnectOptions>()?
+        .get_filename()
+        .into_owned();
+
+    let outcome = backup::restore(&db_path, file).await?;
+    println!(
+        "Restored {} from {} (schema version {})",
+        db_path.display(),
+        file.display(),
+        outcome.schema_version
+    );
+    if let Some(previous) = outcome.previous {
+        println!("Previous database moved to {}", previous.display());
+    }
+    if outcome.schema_version < migrations::latest_version() {
+        println!("Pending migrations will be applied on the next start");
+    }
+    Ok(())
+}
+
+/// `repair-certificates`: report certificate numbering problems, and with
+/// --fix bring approved attempts back in line with the ledger
+async fn repair_certificates(state: &crate::AppState, fix: bool) -> Result<(), Box<dyn Error>> {
+    let certificates = state.db.certificates();
+    let report = certificates.audit().await?;
+
+    if report.is_clean() {
+        println!("Certificate numbers are consistent");
+        return Ok(());
+    }
+
+    for (number, callsigns) in &report.duplicates {
+        println!("Duplicate #{}: {}", number, callsigns.join(", "));
+    }
+    if !report.gaps.is_empty() {
+        let gaps: Vec<String> = report.gaps.iter().map(|n| format!("#{}", n)).collect();
+        println!("Unused numbers: {}", gaps.join(", "));
+    }
+    for problem in &report.problems {
+        let current = problem
+            .certificate_number
+            .map_or("no number".to_string(), |n| format!("#{}", n));
+        let action = match problem.fix {
+            CertificateFix::Record(n) => format!("record #{} in the ledger", n),
+            CertificateFix::Restore(n) => format!("restore ledger number #{}", n),
+            CertificateFix::Renumber => "allocate a new number".to_string(),
+        };
+        println!("{} ({}, {}): {}", problem.callsign, problem.attempt_id, current, action);
+    }
+
+    if !fix {
+        if !report.problems.is_empty() {
+            println!("Run with --fix to apply these changes");
+        }
+        return Ok(());
+    }
+
+    let renumbered = certificates.repair().await?;
+    for change in &renumbered {
+        let old = change.old.map_or("no number".to_string(), |n| format!("#{}", n));
+        println!(
+            "Renumbered {} ({}): {} -> #{}",
+            change.callsign, change.attempt_id, old, change.new
+        );
+    }
+    if !renumbered.is_empty() {
+        let roster = state.member_roster();
+        roster.regenerate().await?;
+        println!(
+            "Regenerated {}; send renumbered members their new certificates",
+            roster.members_txt_path()
+        );
+    }
+    if !report.gaps.is_empty() {
+        println!("Unused numbers are left alone; issued certificates keep their numbers");
+    }
+
+    Ok(())
+}
+
+/// Built-in backups work on the SQLite file; PostgreSQL has pg_dump
+fn sqlite_pool(db: &Db) -> Result<&sqlx::SqlitePool, Box<dyn Error>> {
+    db.as_sqlite()
+        .ok_or_else(|| backup::POSTGRES_UNSUPPORTED.into())
+}
+
+/// Read a password from stdin, prompting when attached to a terminal
+fn read_password() -> Result<String, Box<dyn Error>> {
+    let stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        eprint!("Password: ");
+        std::io::stderr().flush()?;
+    }
+    let mut line = String::new();
+    stdin.lock().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Admin API errors carry a status code that means nothing on the command line
+fn api_error((_, message): (axum::http::StatusCode, String)) -> Box<dyn Error> {
+    message.into()
+}
+
+/// " (certificate #N)" for output lines about a numbered certificate
+fn certificate_suffix(number: Option<i32>) -> String {
+    number.map(|n| format!(" (certificate #{})", n)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grading::QuestionType;
+
+    async fn test_db() -> Db {
+        let db = Db::connect("sqlite::memory:", 1).await.unwrap();
+        crate::setup_database(&db).await.unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn test_export_import_round_trip() {
+        let pool = test_db().await;
+        pool.questions()
+            .insert(&crate::repo::NewQuestion {
+                test_id: "20wpm-extra-1991",
+                question_number: 1,
+                question_text: "What was the call?",
+                question_type: QuestionType::Choice,
+                options: &["W1AW", "K1ABC", "N0CALL", "AA1AA"],
+                correct_option: "A",
+                accepted_answers: &[],
+                topic: None,
+            })
+            .await
+            .unwrap();
+
+        let mut exported = admin::export_test(&pool, "20wpm-extra-1991").await.unwrap();
+        assert_eq!(exported.questions.len(), 1);
+
+        // Importing over an existing id is refused
+        let err = admin::import_test(&pool, &exported).await.unwrap_err();
+        assert_eq!(err.0, axum::http::StatusCode::CONFLICT);
+
+        exported.id = "20wpm-extra-copy".to_string();
+        admin::import_test(&pool, &exported).await.unwrap();
+
+        let copy = admin::export_test(&pool, "20wpm-extra-copy").await.unwrap();
+        assert_eq!(copy.title, exported.title);
+        assert_eq!(copy.questions[0].question_text, "What was the call?");
+        assert_eq!(copy.questions[0].correct_option, "A");
+    }
+}
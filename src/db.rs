@@ -0,0 +1,602 @@
+//! Database handle shared by the SQLite and PostgreSQL backends
+//!
+//! `database_url` selects the backend: `postgres://` (or `postgresql://`)
+//! connects to PostgreSQL, anything else is treated as a SQLite URL.
+//! Queries are written once with `?` placeholders, which are rewritten to
+//! `$1, $2, ...` for PostgreSQL, and parameters are carried as `Value`s so the
+//! same query can be bound to either driver. Table-specific SQL lives in the
+//! repositories under `repo`.
+
+use chrono::{DateTime, Utc};
+use sqlx::{
+    postgres::{PgPool, PgPoolOptions, PgRow},
+    sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow},
+    FromRow, Postgres, Sqlite, Transaction,
+};
+
+/// Which database engine a `Db` talks to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Sqlite,
+    Postgres,
+}
+
+impl Backend {
+    /// Backend selected by a `database_url`
+    pub fn from_url(url: &str) -> Self {
+        if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            Backend::Postgres
+        } else {
+            Backend::Sqlite
+        }
+    }
+}
+
+/// Connection pool for the configured backend
+#[derive(Debug, Clone)]
+pub enum Db {
+    Sqlite(SqlitePool),
+    Postgres(PgPool),
+}
+
+/// Row types that can be decoded from either backend
+pub trait Record: for<'r> FromRow<'r, SqliteRow> + for<'r> FromRow<'r, PgRow> + Send + Unpin {}
+
+impl<T> Record for T where
+    T: for<'r> FromRow<'r, SqliteRow> + for<'r> FromRow<'r, PgRow> + Send + Unpin
+{
+}
+
+/// A bind parameter
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(Option<String>),
+    Int(Option<i64>),
+    Int32(Option<i32>),
+    Float(Option<f32>),
+    Bool(Option<bool>),
+    Timestamp(Option<DateTime<Utc>>),
+}
+
+macro_rules! value_from {
+    ($variant:ident, $ty:ty) => {
+        impl From<$ty> for Value {
+            fn from(v: $ty) -> Self {
+                Value::$variant(Some(v))
+            }
+        }
+
+        impl From<Option<$ty>> for Value {
+            fn from(v: Option<$ty>) -> Self {
+                Value::$variant(v)
+            }
+        }
+    };
+}
+
+value_from!(Text, String);
+value_from!(Int, i64);
+value_from!(Int32, i32);
+value_from!(Float, f32);
+value_from!(Bool, bool);
+value_from!(Timestamp, DateTime<Utc>);
+
+impl From<&str> for Value {
+    fn from(v: &str) -> Self {
+        Value::Text(Some(v.to_string()))
+    }
+}
+
+impl From<&String> for Value {
+    fn from(v: &String) -> Self {
+        Value::Text(Some(v.clone()))
+    }
+}
+
+impl From<Option<&str>> for Value {
+    fn from(v: Option<&str>) -> Self {
+        Value::Text(v.map(str::to_string))
+    }
+}
+
+/// A list of strings kept as a JSON array in a TEXT column, such as a
+/// question's options. Decode it with `#[sqlx(try_from = "String")]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct JsonList(pub Vec<String>);
+
+impl JsonList {
+    pub fn as_strs(&self) -> Vec<&str> {
+        self.0.iter().map(String::as_str).collect()
+    }
+}
+
+impl TryFrom<String> for JsonList {
+    type Error = serde_json::Error;
+
+    fn try_from(json: String) -> Result<Self, Self::Error> {
+        serde_json::from_str(&json).map(JsonList)
+    }
+}
+
+impl<S: AsRef<str>> From<&[S]> for Value {
+    fn from(list: &[S]) -> Self {
+        let list: Vec<&str> = list.iter().map(AsRef::as_ref).collect();
+        Value::Text(serde_json::to_string(&list).ok())
+    }
+}
+
+impl From<&Option<String>> for Value {
+    fn from(v: &Option<String>) -> Self {
+        Value::Text(v.clone())
+    }
+}
+
+/// SQL text with its bind parameters
+#[derive(Debug, Clone)]
+pub struct Query {
+    sql: String,
+    args: Vec<Value>,
+}
+
+impl Query {
+    pub fn new(sql: impl Into<String>) -> Self {
+        Query {
+            sql: sql.into(),
+            args: Vec::new(),
+        }
+    }
+
+    pub fn bind(mut self, value: impl Into<Value>) -> Self {
+        self.args.push(value.into());
+        self
+    }
+
+    /// SQL text for a backend, with `?` placeholders numbered for PostgreSQL
+    fn sql_for(&self, backend: Backend) -> String {
+        match backend {
+            Backend::Sqlite => self.sql.clone(),
+            Backend::Postgres => number_placeholders(&self.sql),
+        }
+    }
+}
+
+/// Shorthand for `Query::new`
+pub fn query(sql: impl Into<String>) -> Query {
+    Query::new(sql)
+}
+
+/// A WHERE clause assembled from optional conditions and their parameters
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    conditions: Vec<String>,
+    args: Vec<Value>,
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Filter::default()
+    }
+
+    /// Add a condition with a single `?` placeholder
+    pub fn and(mut self, condition: &str, value: impl Into<Value>) -> Self {
+        self.conditions.push(condition.to_string());
+        self.args.push(value.into());
+        self
+    }
+
+    /// Add a condition only when `value` is present
+    pub fn and_some<T: Into<Value>>(self, condition: &str, value: Option<T>) -> Self {
+        match value {
+            Some(value) => self.and(condition, value),
+            None => self,
+        }
+    }
+
+    /// Add a condition without placeholders only when `enabled`
+    pub fn and_if(mut self, enabled: bool, condition: &str) -> Self {
+        if enabled {
+            self.conditions.push(condition.to_string());
+        }
+        self
+    }
+
+    /// `WHERE a AND b ...`, or nothing when there are no conditions
+    pub fn where_clause(&self) -> String {
+        if self.conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", self.conditions.join(" AND "))
+        }
+    }
+
+    /// Build a query from `sql`, replacing `{where}` with the clause and
+    /// binding its parameters. Any `?` before `{where}` would be misnumbered,
+    /// so further parameters must come after it.
+    pub fn query(&self, sql: &str) -> Query {
+        let mut q = query(sql.replace("{where}", &self.where_clause()));
+        for arg in &self.args {
+            q = q.bind(arg.clone());
+        }
+        q
+    }
+}
+
+/// Rewrite `?` placeholders to `$1, $2, ...`, leaving quoted text alone
+fn number_placeholders(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len() + 8);
+    let mut n = 0;
+    let mut in_string = false;
+    for c in sql.chars() {
+        match c {
+            '\'' => {
+                in_string = !in_string;
+                out.push(c);
+            }
+            '?' if !in_string => {
+                n += 1;
+                out.push('$');
+                out.push_str(&n.to_string());
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Whether an error is a UNIQUE constraint violation on either backend
+pub fn is_unique_violation(e: &sqlx::Error) -> bool {
+    e.as_database_error()
+        .map(|e| e.is_unique_violation())
+        .unwrap_or(false)
+}
+
+macro_rules! bind_values {
+    ($query:expr, $args:expr) => {{
+        let mut q = $query;
+        for arg in $args {
+            q = match arg {
+                Value::Text(v) => q.bind(v),
+                Value::Int(v) => q.bind(v),
+                Value::Int32(v) => q.bind(v),
+                Value::Float(v) => q.bind(v),
+                Value::Bool(v) => q.bind(v),
+                Value::Timestamp(v) => q.bind(v),
+            };
+        }
+        q
+    }};
+}
+
+/// Executor-generic query functions for one backend
+macro_rules! backend_ops {
+    ($module:ident, $database:ty) => {
+        mod $module {
+            use super::{Record, Value};
+
+            pub async fn fetch_all<'e, E, T>(
+                exec: E,
+                sql: &str,
+                args: Vec<Value>,
+            ) -> Result<Vec<T>, sqlx::Error>
+            where
+                E: sqlx::Executor<'e, Database = $database>,
+                T: Record,
+            {
+                bind_values!(sqlx::query_as::<_, T>(sql), args)
+                    .fetch_all(exec)
+                    .await
+            }
+
+            pub async fn fetch_optional<'e, E, T>(
+                exec: E,
+                sql: &str,
+                args: Vec<Value>,
+            ) -> Result<Option<T>, sqlx::Error>
+            where
+                E: sqlx::Executor<'e, Database = $database>,
+                T: Record,
+            {
+                bind_values!(sqlx::query_as::<_, T>(sql), args)
+                    .fetch_optional(exec)
+                    .await
+            }
+
+            pub async fn execute<'e, E>(
+                exec: E,
+                sql: &str,
+                args: Vec<Value>,
+            ) -> Result<u64, sqlx::Error>
+            where
+                E: sqlx::Executor<'e, Database = $database>,
+            {
+                Ok(bind_values!(sqlx::query(sql), args)
+                    .execute(exec)
+                    .await?
+                    .rows_affected())
+            }
+        }
+    };
+}
+
+backend_ops!(sqlite_ops, sqlx::Sqlite);
+backend_ops!(pg_ops, sqlx::Postgres);
+
+impl Db {
+    /// Connect to the backend selected by `url`, creating a SQLite file if needed
+    pub async fn connect(url: &str, max_connections: u32) -> Result<Self, sqlx::Error> {
+        match Backend::from_url(url) {
+            Backend::Postgres => Ok(Db::Postgres(
+                PgPoolOptions::new()
+                    .max_connections(max_connections)
+                    .connect(url)
+                    .await?,
+            )),
+            Backend::Sqlite => {
+                // SQLite only enforces foreign keys when each connection asks
+                let options = url
+                    .parse::<SqliteConnectOptions>()?
+                    .create_if_missing(true)
+                    .foreign_keys(true);
+                Ok(Db::Sqlite(
+                    SqlitePoolOptions::new()
+                        .max_connections(max_connections)
+                        .connect_with(options)
+                        .await?,
+                ))
+            }
+        }
+    }
+
+    pub fn backend(&self) -> Backend {
+        match self {
+            Db::Sqlite(_) => Backend::Sqlite,
+            Db::Postgres(_) => Backend::Postgres,
+        }
+    }
+
+    /// The SQLite pool, for features that only exist on SQLite (backups)
+    pub fn as_sqlite(&self) -> Option<&SqlitePool> {
+        match self {
+            Db::Sqlite(pool) => Some(pool),
+            Db::Postgres(_) => None,
+        }
+    }
+
+    pub async fn fetch_all<T: Record>(&self, q: Query) -> Result<Vec<T>, sqlx::Error> {
+        let sql = q.sql_for(self.backend());
+        match self {
+            Db::Sqlite(pool) => sqlite_ops::fetch_all(pool, &sql, q.args).await,
+            Db::Postgres(pool) => pg_ops::fetch_all(pool, &sql, q.args).await,
+        }
+    }
+
+    pub async fn fetch_optional<T: Record>(&self, q: Query) -> Result<Option<T>, sqlx::Error> {
+        let sql = q.sql_for(self.backend());
+        match self {
+            Db::Sqlite(pool) => sqlite_ops::fetch_optional(pool, &sql, q.args).await,
+            Db::Postgres(pool) => pg_ops::fetch_optional(pool, &sql, q.args).await,
+        }
+    }
+
+    pub async fn fetch_one<T: Record>(&self, q: Query) -> Result<T, sqlx::Error> {
+        self.fetch_optional(q).await?.ok_or(sqlx::Error::RowNotFound)
+    }
+
+    /// Run a statement, returning the number of rows affected
+    pub async fn execute(&self, q: Query) -> Result<u64, sqlx::Error> {
+        let sql = q.sql_for(self.backend());
+        match self {
+            Db::Sqlite(pool) => sqlite_ops::execute(pool, &sql, q.args).await,
+            Db::Postgres(pool) => pg_ops::execute(pool, &sql, q.args).await,
+        }
+    }
+
+    pub async fn begin(&self) -> Result<Tx, sqlx::Error> {
+        match self {
+            Db::Sqlite(pool) => Ok(Tx::Sqlite(pool.begin().await?)),
+            Db::Postgres(pool) => Ok(Tx::Postgres(Box::new(pool.begin().await?))),
+        }
+    }
+
+    pub async fn close(&self) {
+        match self {
+            Db::Sqlite(pool) => pool.close().await,
+            Db::Postgres(pool) => pool.close().await,
+        }
+    }
+}
+
+/// An open transaction on either backend; rolled back if dropped uncommitted
+pub enum Tx {
+    Sqlite(Transaction<'static, Sqlite>),
+    Postgres(Box<Transaction<'static, Postgres>>),
+}
+
+impl Tx {
+    pub fn backend(&self) -> Backend {
+        match self {
+            Tx::Sqlite(_) => Backend::Sqlite,
+            Tx::Postgres(_) => Backend::Postgres,
+        }
+    }
+
+    pub async fn fetch_all<T: Record>(&mut self, q: Query) -> Result<Vec<T>, sqlx::Error> {
+        let sql = q.sql_for(self.backend());
+        match self {
+            Tx::Sqlite(tx) => sqlite_ops::fetch_all(&mut **tx, &sql, q.args).await,
+            Tx::Postgres(tx) => pg_ops::fetch_all(&mut ***tx, &sql, q.args).await,
+        }
+    }
+
+    pub async fn fetch_optional<T: Record>(&mut self, q: Query) -> Result<Option<T>, sqlx::Error> {
+        let sql = q.sql_for(self.backend());
+        match self {
+            Tx::Sqlite(tx) => sqlite_ops::fetch_optional(&mut **tx, &sql, q.args).await,
+            Tx::Postgres(tx) => pg_ops::fetch_optional(&mut ***tx, &sql, q.args).await,
+        }
+    }
+
+    pub async fn fetch_one<T: Record>(&mut self, q: Query) -> Result<T, sqlx::Error> {
+        self.fetch_optional(q).await?.ok_or(sqlx::Error::RowNotFound)
+    }
+
+    pub async fn execute(&mut self, q: Query) -> Result<u64, sqlx::Error> {
+        let sql = q.sql_for(self.backend());
+        match self {
+            Tx::Sqlite(tx) => sqlite_ops::execute(&mut **tx, &sql, q.args).await,
+            Tx::Postgres(tx) => pg_ops::execute(&mut ***tx, &sql, q.args).await,
+        }
+    }
+
+    /// Run a multi-statement SQL script without parameters (migrations)
+    pub async fn execute_script(&mut self, sql: &str) -> Result<(), sqlx::Error> {
+        match self {
+            Tx::Sqlite(tx) => {
+                sqlx::raw_sql(sql).execute(&mut **tx).await?;
+            }
+            Tx::Postgres(tx) => {
+                sqlx::raw_sql(sql).execute(&mut ***tx).await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn commit(self) -> Result<(), sqlx::Error> {
+        match self {
+            Tx::Sqlite(tx) => tx.commit().await,
+            Tx::Postgres(tx) => tx.commit().await,
+        }
+    }
+}
+
+/// Databases for tests that must pass on every backend.
+///
+/// SQLite always runs in memory. PostgreSQL runs too when
+/// `KNOWCODE_TEST_POSTGRES_URL` is set, each call getting a scratch schema
+/// that `TestDb::cleanup` drops again.
+#[cfg(test)]
+pub mod testing {
+    use super::Db;
+    use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+    use sqlx::Executor;
+
+    pub const POSTGRES_URL_VAR: &str = "KNOWCODE_TEST_POSTGRES_URL";
+
+    pub struct TestDb {
+        pub db: Db,
+        schema: Option<(String, String)>,
+    }
+
+    impl TestDb {
+        /// Drop the scratch schema, if any
+        pub async fn cleanup(self) {
+            self.db.close().await;
+            if let Some((url, schema)) = self.schema {
+                let pool = PgPoolOptions::new().max_connections(1).connect(&url).await.unwrap();
+                pool.execute(format!("DROP SCHEMA {} CASCADE", schema).as_str())
+                    .await
+                    .unwrap();
+                pool.close().await;
+            }
+        }
+    }
+
+    /// In-memory SQLite with the schema and seed data in place
+    pub async fn sqlite() -> Db {
+        let db = Db::connect("sqlite::memory:", 1).await.unwrap();
+        crate::setup_database(&db).await.unwrap();
+        db
+    }
+
+    async fn postgres(url: &str) -> TestDb {
+        let schema = format!("kce_test_{}", uuid::Uuid::new_v4().simple());
+        let admin = PgPoolOptions::new().max_connections(1).connect(url).await.unwrap();
+        admin
+            .execute(format!("CREATE SCHEMA {}", schema).as_str())
+            .await
+            .unwrap();
+        admin.close().await;
+
+        let options = url
+            .parse::<PgConnectOptions>()
+            .unwrap()
+            .options([("search_path", schema.as_str())]);
+        let db = Db::Postgres(
+            PgPoolOptions::new()
+                .max_connections(2)
+                .connect_with(options)
+                .await
+                .unwrap(),
+        );
+        crate::setup_database(&db).await.unwrap();
+        TestDb {
+            db,
+            schema: Some((url.to_string(), schema)),
+        }
+    }
+
+    /// Remove a test with its questions and revisions, as a fresh server
+    /// without it would be
+    pub async fn delete_test(db: &Db, id: &str) {
+        db.tests().purge(id).await.unwrap().unwrap();
+    }
+
+    /// SQLite, plus PostgreSQL when configured
+    pub async fn all_backends() -> Vec<TestDb> {
+        let mut dbs = vec![TestDb {
+            db: sqlite().await,
+            schema: None,
+        }];
+        if let Ok(url) = std::env::var(POSTGRES_URL_VAR) {
+            dbs.push(postgres(&url).await);
+        }
+        dbs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_from_url() {
+        assert_eq!(Backend::from_url("sqlite:knowcodeextra.db"), Backend::Sqlite);
+        assert_eq!(Backend::from_url("sqlite::memory:"), Backend::Sqlite);
+        assert_eq!(Backend::from_url("postgres://kce@localhost/kce"), Backend::Postgres);
+        assert_eq!(Backend::from_url("postgresql://localhost/kce"), Backend::Postgres);
+    }
+
+    #[test]
+    fn test_number_placeholders() {
+        assert_eq!(
+            number_placeholders("SELECT * FROM t WHERE a = ? AND b IN ('?', ?)"),
+            "SELECT * FROM t WHERE a = $1 AND b IN ('?', $2)"
+        );
+        assert_eq!(number_placeholders("SELECT 1"), "SELECT 1");
+    }
+
+    #[test]
+    fn test_filter_builds_where_clause() {
+        let filter = Filter::new()
+            .and_some("test_speed = ?", Some(20))
+            .and_some::<bool>("passed = ?", None)
+            .and_if(true, "validation_status IS NOT NULL")
+            .and_if(false, "reached_out = TRUE")
+            .and("callsign LIKE ?", "%W1%");
+        let q = filter.query("SELECT id FROM attempts {where} LIMIT ?").bind(10);
+
+        assert_eq!(
+            q.sql,
+            "SELECT id FROM attempts WHERE test_speed = ? AND validation_status IS NOT NULL AND callsign LIKE ? LIMIT ?"
+        );
+        assert_eq!(
+            q.args,
+            vec![Value::from(20), Value::from("%W1%"), Value::from(10)]
+        );
+
+        let empty = Filter::new().query("SELECT id FROM attempts {where}");
+        assert_eq!(empty.sql, "SELECT id FROM attempts ");
+        assert!(empty.args.is_empty());
+    }
+}
@@ -0,0 +1,104 @@
+//! Drawing an exam's questions from a test's question bank
+//!
+//! A test with `draw_count` set asks each exam session only that many of its
+//! questions. Questions are grouped by topic and drawn one topic at a time, in
+//! random order of topics and of questions within each, so a draw covers
+//! every topic when it is at least as large as the number of topics, and no
+//! topic gets a second question while another has none. Questions without a
+//! topic are drawn as one more topic.
+
+use crate::QuestionWithAnswer;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::BTreeMap;
+
+/// Ids of `count` questions drawn across topics, in question number order.
+/// Draws every question when there are no more than `count`.
+pub fn draw(questions: &[QuestionWithAnswer], count: usize, rng: &mut impl Rng) -> Vec<String> {
+    let mut topics: BTreeMap<Option<&str>, Vec<&QuestionWithAnswer>> = BTreeMap::new();
+    for question in questions {
+        topics.entry(question.topic.as_deref()).or_default().push(question);
+    }
+    let mut topics: Vec<_> = topics.into_values().collect();
+    for topic in &mut topics {
+        topic.shuffle(rng);
+    }
+    topics.shuffle(rng);
+
+    let mut drawn: Vec<&QuestionWithAnswer> = Vec::with_capacity(count);
+    for round in 0.. {
+        let picks: Vec<_> = topics.iter().filter_map(|topic| topic.get(round).copied()).collect();
+        if picks.is_empty() {
+            break;
+        }
+        drawn.extend(picks.into_iter().take(count - drawn.len()));
+        if drawn.len() == count {
+            break;
+        }
+    }
+
+    drawn.sort_by_key(|question| question.question_number);
+    drawn.into_iter().map(|question| question.id.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::JsonList;
+    use crate::grading::QuestionType;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn bank(topics: &[(&str, usize)]) -> Vec<QuestionWithAnswer> {
+        let mut questions = Vec::new();
+        for (topic, count) in topics {
+            for _ in 0..*count {
+                let number = questions.len() as i32 + 1;
+                questions.push(QuestionWithAnswer {
+                    id: format!("{}-{}", topic, number),
+                    question_number: number,
+                    question_text: format!("Question {}", number),
+                    question_type: QuestionType::Choice,
+                    options: JsonList(vec!["W1AW".to_string(), "K1ABC".to_string()]),
+                    correct_option: "A".to_string(),
+                    accepted_answers: JsonList::default(),
+                    topic: (!topic.is_empty()).then(|| topic.to_string()),
+                });
+            }
+        }
+        questions
+    }
+
+    fn topics_of(drawn: &[String]) -> Vec<&str> {
+        let mut topics: Vec<&str> = drawn.iter().map(|id| id.split('-').next().unwrap()).collect();
+        topics.sort_unstable();
+        topics
+    }
+
+    #[test]
+    fn test_draw_covers_every_topic() {
+        let questions = bank(&[("name", 6), ("rig", 2), ("weather", 4), ("", 3)]);
+        for seed in 0..20 {
+            let drawn = draw(&questions, 6, &mut StdRng::seed_from_u64(seed));
+            assert_eq!(drawn.len(), 6);
+            // Four topics, then two more from topics that still have questions
+            let topics = topics_of(&drawn);
+            for topic in ["", "name", "rig", "weather"] {
+                let n = topics.iter().filter(|t| **t == topic).count();
+                assert!((1..=2).contains(&n), "{:?}", topics);
+            }
+
+            // Ids come back in question number order
+            let numbers: Vec<i32> = drawn.iter().map(|id| id.rsplit('-').next().unwrap().parse().unwrap()).collect();
+            assert!(numbers.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+    }
+
+    #[test]
+    fn test_draw_takes_every_question_of_a_small_bank() {
+        let questions = bank(&[("name", 2), ("rig", 1)]);
+        let drawn = draw(&questions, 10, &mut StdRng::seed_from_u64(1));
+        assert_eq!(drawn, vec!["name-1", "name-2", "rig-3"]);
+        assert!(draw(&questions, 0, &mut StdRng::seed_from_u64(1)).is_empty());
+    }
+}
@@ -0,0 +1,1305 @@
+//! End-to-end tests: the full router served over HTTP
+//!
+//! Each `Harness` serves `build_router` on a random localhost port, backed by
+//! an in-memory SQLite database set up with `setup_database`, a scratch
+//! static directory, and stub QRZ and ntfy servers that answer on localhost.
+
+use crate::certificate::CertificateSignature;
+use crate::{build_router, build_state, db::testing, grading, qrz::QrzClient, signing, AppState, Config};
+use axum::{
+    extract::{Path, Query},
+    http::HeaderMap,
+    routing::{get, post},
+    Router,
+};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const TEST_ID: &str = "20wpm-extra-1991";
+const ADMIN_PASSWORD: &str = "correct horse battery staple";
+/// Ed25519 seed for certificate signatures (32 bytes of 0x01)
+const SIGNING_KEY: &str = "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE";
+
+/// A notification received by the stub ntfy server
+#[derive(Debug, Clone)]
+struct Notification {
+    topic: String,
+    title: Option<String>,
+    body: String,
+}
+
+struct Harness {
+    base_url: String,
+    http: reqwest::Client,
+    static_dir: std::path::PathBuf,
+    notifications: Arc<Mutex<Vec<Notification>>>,
+    state: Arc<AppState>,
+}
+
+/// Serve a router on an ephemeral localhost port, returning its base URL
+async fn spawn(app: Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    format!("http://{}", addr)
+}
+
+/// Stub of the QRZ XML API: any login succeeds, and every callsign has an
+/// email address of `<callsign>@example.com`
+async fn spawn_qrz_stub() -> String {
+    async fn xml(Query(params): Query<HashMap<String, String>>) -> String {
+        if params.contains_key("username") {
+            return "<QRZDatabase><Session><Key>stub-session</Key></Session></QRZDatabase>"
+                .to_string();
+        }
+        let callsign = params.get("callsign").cloned().unwrap_or_default();
+        format!(
+            "<QRZDatabase><Callsign><call>{}</call><fname>Hiram</fname><name>Maxim</name><email>{}@example.com</email></Callsign></QRZDatabase>",
+            callsign,
+            callsign.to_lowercase()
+        )
+    }
+
+    spawn(Router::new().route("/xml/current/", get(xml))).await
+}
+
+/// Stub ntfy server recording every published message
+async fn spawn_ntfy_stub(notifications: Arc<Mutex<Vec<Notification>>>) -> String {
+    let app = Router::new().route(
+        "/:topic",
+        post(move |Path(topic): Path<String>, headers: HeaderMap, body: String| {
+            let notifications = notifications.clone();
+            async move {
+                let title = headers
+                    .get("Title")
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::to_string);
+                notifications
+                    .lock()
+                    .unwrap()
+                    .push(Notification { topic, title, body });
+                "{}"
+            }
+        }),
+    );
+    spawn(app).await
+}
+
+impl Harness {
+    async fn start() -> Self {
+        let static_dir = std::env::temp_dir().join(format!("kce-e2e-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&static_dir).unwrap();
+
+        let notifications = Arc::new(Mutex::new(Vec::new()));
+        let ntfy_url = spawn_ntfy_stub(notifications.clone()).await;
+        let qrz_url = spawn_qrz_stub().await;
+
+        let mut config: Config = serde_json::from_str("{}").unwrap();
+        config.static_dir = static_dir.to_string_lossy().into_owned();
+        config.admin_password = ADMIN_PASSWORD.to_string();
+        config.admin_jwt_secret = "0123456789abcdef0123456789abcdef".to_string();
+        config.rate_limit_enabled = false;
+        config.ntfy_url = Some(ntfy_url);
+        config.ntfy_topic = Some("kce-test".to_string());
+        config.ntfy_username = Some("ntfy".to_string());
+        config.ntfy_password = Some("ntfy-password".to_string());
+        config.certificate_signing_key = Some(SIGNING_KEY.to_string());
+        config.validate().unwrap();
+
+        let db = testing::sqlite().await;
+        let mut state = AppState::clone(&build_state(&config, db).unwrap());
+        state.qrz_client = Some(QrzClient::with_base_url(
+            "qrz".to_string(),
+            "qrz-password".to_string(),
+            format!("{}/xml/current/", qrz_url),
+        ));
+        let state = Arc::new(state);
+
+        let base_url = spawn(build_router(state.clone())).await;
+
+        Harness {
+            base_url,
+            http: reqwest::Client::new(),
+            static_dir,
+            notifications,
+            state,
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    async fn get(&self, path: &str) -> reqwest::Response {
+        self.http.get(self.url(path)).send().await.unwrap()
+    }
+
+    async fn post_json(&self, path: &str, body: Value) -> reqwest::Response {
+        self.http.post(self.url(path)).json(&body).send().await.unwrap()
+    }
+
+    /// Log in as the configured admin and return a bearer token
+    async fn admin_token(&self) -> String {
+        let response = self
+            .post_json(
+                "/api/admin/login",
+                json!({ "username": "admin", "password": ADMIN_PASSWORD }),
+            )
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = response.json().await.unwrap();
+        body["token"].as_str().unwrap().to_string()
+    }
+
+    async fn admin_get(&self, token: &str, path: &str) -> reqwest::Response {
+        self.http
+            .get(self.url(path))
+            .bearer_auth(token)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    async fn admin_post(&self, token: &str, path: &str, body: Value) -> reqwest::Response {
+        self.http
+            .post(self.url(path))
+            .bearer_auth(token)
+            .json(&body)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    async fn admin_put(&self, token: &str, path: &str, body: Value) -> reqwest::Response {
+        self.http
+            .put(self.url(path))
+            .bearer_auth(token)
+            .json(&body)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    async fn admin_delete(&self, token: &str, path: &str) -> reqwest::Response {
+        self.http
+            .delete(self.url(path))
+            .bearer_auth(token)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    /// Add ten questions whose correct answer is always C
+    async fn add_questions(&self, token: &str) {
+        for n in 1..=10 {
+            let response = self
+                .admin_post(
+                    token,
+                    &format!("/api/admin/tests/{}/questions", TEST_ID),
+                    json!({
+                        "question_number": n,
+                        "question_text": format!("Question {}", n),
+                        "options": ["W1AW", "K1ABC", "N0CALL", "AA1AA"],
+                        "correct_option": "C",
+                    }),
+                )
+                .await;
+            assert!(response.status().is_success(), "{}", response.text().await.unwrap());
+        }
+        self.publish(token, TEST_ID).await;
+    }
+
+    /// Publish a test's draft so candidates see it
+    async fn publish(&self, token: &str, test_id: &str) {
+        let response = self
+            .admin_post(token, &format!("/api/admin/tests/{}/publish", test_id), json!({}))
+            .await;
+        assert_eq!(response.status(), StatusCode::CREATED, "{}", response.text().await.unwrap());
+    }
+
+    /// Submit the exam, answering `correct` questions correctly
+    async fn submit(&self, callsign: &str, correct: usize) -> reqwest::Response {
+        let questions: Vec<Value> = self
+            .get(&format!("/api/tests/{}/questions", TEST_ID))
+            .await
+            .json()
+            .await
+            .unwrap();
+        let answers: HashMap<String, &str> = questions
+            .iter()
+            .enumerate()
+            .map(|(i, q)| {
+                let id = q["id"].as_str().unwrap().to_string();
+                (id, if i < correct { "C" } else { "A" })
+            })
+            .collect();
+
+        self.post_json(
+            &format!("/api/tests/{}/submit", TEST_ID),
+            json!({ "callsign": callsign, "answers": answers, "audio_progress": 100.0 }),
+        )
+        .await
+    }
+
+    /// Wait for background notifications to arrive at the ntfy stub
+    async fn wait_for_notifications(&self, count: usize) -> Vec<Notification> {
+        for _ in 0..50 {
+            let received = self.notifications.lock().unwrap().clone();
+            if received.len() >= count {
+                return received;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("expected {} ntfy notifications", count);
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.static_dir);
+    }
+}
+
+#[tokio::test]
+async fn test_submit_approve_certificate_and_members_txt() {
+    let harness = Harness::start().await;
+    let token = harness.admin_token().await;
+    harness.add_questions(&token).await;
+
+    let tests: Vec<Value> = harness.get("/api/tests").await.json().await.unwrap();
+    assert_eq!(tests.len(), 1);
+    assert_eq!(tests[0]["id"], TEST_ID);
+
+    // Submit a passing exam
+    let response = harness.submit("w1aw", 9).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let result: Value = response.json().await.unwrap();
+    assert_eq!(result["passed"], true);
+    assert_eq!(result["score"], 9);
+    let attempt_id = result["certificate_id"].as_str().unwrap().to_string();
+
+    let notifications = harness.wait_for_notifications(1).await;
+    assert_eq!(notifications[0].topic, "kce-test");
+    assert_eq!(notifications[0].title.as_deref(), Some("KnowCodeExtra"));
+    assert_eq!(notifications[0].body, "W1AW submitted - PASSED");
+
+    // It waits in the validation queue, without a certificate
+    let queue: Vec<Value> = harness
+        .admin_get(&token, "/api/admin/queue")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(queue.len(), 1);
+    assert_eq!(queue[0]["callsign"], "W1AW");
+    let response = harness.get(&format!("/api/certificate/{}", attempt_id)).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Approve: certificate #1, email looked up on the QRZ stub
+    let response = harness
+        .admin_post(&token, &format!("/api/admin/queue/{}/approve", attempt_id), json!({}))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let approval: Value = response.json().await.unwrap();
+    assert_eq!(approval["certificate_number"], 1);
+
+    let approved: Value = harness
+        .admin_get(&token, "/api/admin/approved")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(approved["total"], 1);
+    assert_eq!(approved["items"][0]["email"], "w1aw@example.com");
+
+    // The certificate is now available
+    let response = harness.get(&format!("/api/certificate/{}", attempt_id)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "image/svg+xml");
+    let svg = response.text().await.unwrap();
+    assert!(svg.contains("W1AW"));
+    assert!(svg.contains("#1"));
+    assert!(svg.contains("https://knowcodeextra.com/verify/1?sig="));
+
+    // Printable and shareable renders of the same certificate
+    let response = harness.get(&format!("/api/certificate/{}?format=pdf", attempt_id)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/pdf");
+    assert!(response.bytes().await.unwrap().starts_with(b"%PDF-"));
+    let response = harness.get(&format!("/api/certificate/{}?format=png", attempt_id)).await;
+    assert_eq!(response.headers()["content-type"], "image/png");
+    assert!(response.bytes().await.unwrap().starts_with(b"\x89PNG"));
+    let response = harness.get(&format!("/api/certificate/{}?format=gif", attempt_id)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // The signature checks out offline against the published key
+    let key: Value = harness.get("/api/certificate-key").await.json().await.unwrap();
+    let public_key = key["public_key"].as_str().unwrap();
+    let signature = CertificateSignature::from_svg(&svg).unwrap();
+    assert_eq!(signature.fields.callsign, "W1AW");
+    assert_eq!(signature.fields.test_id.as_deref(), Some(TEST_ID));
+    assert!(signing::verify(public_key, &signature.fields, &signature.value).is_ok());
+
+    // Anyone can check the number the QR code links to
+    let verification: Value = harness
+        .http
+        .get(harness.url("/verify/1"))
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(verification["callsign"], "W1AW");
+    assert_eq!(verification["status"], "valid");
+    assert_eq!(verification["test_id"], TEST_ID);
+    assert_eq!(verification["test_speed"], 20);
+    assert!(verification.get("signature_valid").is_none());
+    let signed: Value = harness
+        .http
+        .get(harness.url(&format!("/verify/1?sig={}", signature.value)))
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(signed["signature_valid"], true);
+    let response = harness.get("/verify/1").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers()["content-type"].to_str().unwrap().starts_with("text/html"));
+    assert!(response.text().await.unwrap().contains("VALID"));
+    assert_eq!(harness.get("/verify/2").await.status(), StatusCode::NOT_FOUND);
+
+    // members.txt was regenerated on approval
+    let response = harness.get("/members.txt").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/plain; charset=utf-8");
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("W1AW 📜 Know Code Extra #1"));
+
+    let roster: Vec<Value> = harness.get("/api/roster").await.json().await.unwrap();
+    assert_eq!(roster.len(), 1);
+    assert_eq!(roster[0]["certificate_number"], 1);
+}
+
+#[tokio::test]
+async fn test_failed_and_rejected_attempts() {
+    let harness = Harness::start().await;
+    let token = harness.admin_token().await;
+    harness.add_questions(&token).await;
+
+    let result: Value = harness.submit("K1ABC", 3).await.json().await.unwrap();
+    assert_eq!(result["passed"], false);
+    assert!(result["certificate_id"].is_null());
+    assert!(result["correct_answers"].is_null());
+
+    // Once per day
+    let response = harness.submit("K1ABC", 10).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let result: Value = harness.submit("N0CALL", 10).await.json().await.unwrap();
+    let attempt_id = result["certificate_id"].as_str().unwrap().to_string();
+    let response = harness
+        .admin_post(
+            &token,
+            &format!("/api/admin/queue/{}/reject", attempt_id),
+            json!({ "note": "Audio not played" }),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = harness
+        .admin_post(&token, &format!("/api/admin/queue/{}/approve", attempt_id), json!({}))
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let notifications = harness.wait_for_notifications(2).await;
+    let mut bodies: Vec<&str> = notifications.iter().map(|n| n.body.as_str()).collect();
+    bodies.sort();
+    assert_eq!(bodies, ["K1ABC submitted - FAILED", "N0CALL submitted - PASSED"]);
+
+    let attempts: Value = harness
+        .admin_get(&token, "/api/admin/attempts?passed=true")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(attempts["total"], 1);
+    assert_eq!(attempts["items"][0]["validation_status"], "rejected");
+    assert_eq!(harness.state.db.attempts().count_by_status("rejected").await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn test_revoke_reinstate_and_reissue() {
+    let harness = Harness::start().await;
+    let token = harness.admin_token().await;
+    harness.add_questions(&token).await;
+
+    let result: Value = harness.submit("KD9ABC", 10).await.json().await.unwrap();
+    let attempt_id = result["certificate_id"].as_str().unwrap().to_string();
+    harness
+        .admin_post(&token, &format!("/api/admin/queue/{}/approve", attempt_id), json!({}))
+        .await;
+    let certificate = format!("/api/certificate/{}", attempt_id);
+    let action = |name: &str| format!("/api/admin/certificates/{}/{}", attempt_id, name);
+
+    // A reason is required
+    let response = harness.admin_post(&token, &action("revoke"), json!({})).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = harness
+        .admin_post(&token, &action("revoke"), json!({ "reason": "Duplicate account" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(harness.get(&certificate).await.status(), StatusCode::GONE);
+    assert!(harness.get("/verify/1").await.text().await.unwrap().contains("REVOKED"));
+    let roster: Vec<Value> = harness.get("/api/roster").await.json().await.unwrap();
+    assert!(roster.is_empty());
+    assert!(!harness.get("/members.txt").await.text().await.unwrap().contains("KD9ABC"));
+
+    let response = harness.admin_post(&token, &action("reinstate"), json!({})).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(harness.get(&certificate).await.status(), StatusCode::OK);
+
+    let response = harness
+        .admin_post(&token, &action("reissue"), json!({ "callsign": "aa9a", "reason": "Vanity call" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let svg = harness.get(&certificate).await.text().await.unwrap();
+    assert!(svg.contains("AA9A"));
+    assert!(svg.contains("#1"));
+    assert!(harness
+        .get("/members.txt")
+        .await
+        .text()
+        .await
+        .unwrap()
+        .contains("AA9A 📜 Know Code Extra #1"));
+
+    let history: Vec<Value> = harness
+        .admin_get(&token, &action("history"))
+        .await
+        .json()
+        .await
+        .unwrap();
+    let actions: Vec<&str> = history.iter().map(|e| e["action"].as_str().unwrap()).collect();
+    assert_eq!(actions, ["revoked", "reinstated", "reissued"]);
+    assert_eq!(history[0]["reason"], "Duplicate account");
+    assert_eq!(history[2]["old_callsign"], "KD9ABC");
+}
+
+#[tokio::test]
+async fn test_certificate_templates_bound_to_tests() {
+    let harness = Harness::start().await;
+    let token = harness.admin_token().await;
+    harness.add_questions(&token).await;
+
+    let template = |label: &str| {
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 800 600" width="800" height="600">{{{{METADATA}}}}<text x="10" y="50">{} {{{{CALLSIGN}}}} {{{{NAME}}}} {{{{SPEED}}}} WPM {{{{YEAR}}}} {{{{CERT_NO}}}}</text>{{{{QR_CODE}}}}</svg>"#,
+            label
+        )
+    };
+
+    let response = harness
+        .admin_post(&token, "/api/admin/certificate-templates", json!({ "id": "extra", "name": "Extra", "svg": "<svg/>" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = harness
+        .admin_post(&token, "/api/admin/certificate-templates", json!({ "id": "preview", "name": "Extra", "svg": template("v1") }))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = harness
+        .admin_post(
+            &token,
+            "/api/admin/certificate-templates/preview",
+            json!({ "svg": template("Draft"), "test_id": "20wpm-extra-1991" }),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let preview = response.text().await.unwrap();
+    assert!(preview.contains("Draft W1AW Hiram Percy Maxim 20 WPM 1991 #1234"));
+    let response = harness
+        .admin_post(&token, "/api/admin/certificate-templates/preview", json!({ "svg": template("Draft"), "format": "png" }))
+        .await;
+    assert_eq!(response.headers()["content-type"], "image/png");
+
+    let response = harness
+        .admin_post(&token, "/api/admin/certificate-templates", json!({ "id": "extra", "name": "Extra", "svg": template("v1") }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = harness
+        .admin_put(&token, "/api/admin/tests/20wpm-extra-1991", json!({ "certificate_template_id": "missing" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = harness
+        .admin_put(&token, "/api/admin/tests/20wpm-extra-1991", json!({ "certificate_template_id": "extra" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let result: Value = harness.submit("W1AW", 10).await.json().await.unwrap();
+    let attempt_id = result["certificate_id"].as_str().unwrap().to_string();
+    harness
+        .admin_post(&token, &format!("/api/admin/queue/{}/approve", attempt_id), json!({}))
+        .await;
+    let certificate = format!("/api/certificate/{}", attempt_id);
+    let svg = harness.get(&certificate).await.text().await.unwrap();
+    assert!(svg.contains("v1 W1AW Hiram Maxim 20 WPM 1991 #1"));
+
+    // Saving a new version changes certificates already issued
+    let response = harness
+        .admin_put(
+            &token,
+            "/api/admin/certificate-templates/extra",
+            json!({ "name": "Extra", "svg": template("v2"), "note": "Relabel" }),
+        )
+        .await;
+    let saved: Value = response.json().await.unwrap();
+    assert_eq!(saved["version"], 2);
+    assert!(harness.get(&certificate).await.text().await.unwrap().contains("v2 W1AW"));
+
+    let detail: Value = harness
+        .admin_get(&token, "/api/admin/certificate-templates/extra")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(detail["version"], 2);
+    assert_eq!(detail["history"].as_array().unwrap().len(), 2);
+    let first: Value = harness
+        .admin_get(&token, "/api/admin/certificate-templates/extra/versions/1")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert!(first["svg"].as_str().unwrap().contains("v1"));
+
+    // Unbinding falls back to the built-in design
+    harness
+        .admin_put(&token, "/api/admin/tests/20wpm-extra-1991", json!({ "certificate_template_id": "" }))
+        .await;
+    assert!(harness.get(&certificate).await.text().await.unwrap().contains("KNOW-CODE"));
+    let builtin: Value = harness
+        .admin_get(&token, "/api/admin/certificate-templates/builtin")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert!(builtin["svg"].as_str().unwrap().contains("{{SPEED}} WPM"));
+}
+
+#[tokio::test]
+async fn test_test_bundle_moves_between_servers() {
+    let staging = Harness::start().await;
+    let token = staging.admin_token().await;
+    staging.add_questions(&token).await;
+    std::fs::create_dir_all(staging.static_dir.join("audio/20wpm")).unwrap();
+    std::fs::write(staging.static_dir.join("audio/20wpm/test.mp3"), b"ID3 staging audio").unwrap();
+
+    let response = staging
+        .admin_get(&token, &format!("/api/admin/tests/{}/bundle", TEST_ID))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/gzip");
+    let bundle = response.bytes().await.unwrap();
+
+    // Production was seeded with the same test id; clear it for the import
+    let production = Harness::start().await;
+    let token = production.admin_token().await;
+    let import = |query: &'static str| {
+        production
+            .http
+            .post(production.url(&format!("/api/admin/test-bundles{}", query)))
+            .bearer_auth(&token)
+            .body(bundle.clone())
+            .send()
+    };
+    assert_eq!(import("").await.unwrap().status(), StatusCode::CONFLICT);
+    crate::db::testing::delete_test(&production.state.db, TEST_ID).await;
+
+    let response = import("?dry_run=true").await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let summary: Value = response.json().await.unwrap();
+    assert_eq!(summary["questions"], 10);
+    assert!(!production.static_dir.join("audio/20wpm/test.mp3").exists());
+
+    let response = import("").await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(
+        std::fs::read(production.static_dir.join("audio/20wpm/test.mp3")).unwrap(),
+        b"ID3 staging audio"
+    );
+
+    // Imported tests are drafts until published
+    let path = format!("/api/tests/{}/questions", TEST_ID);
+    assert_eq!(production.get(&path).await.status(), StatusCode::NOT_FOUND);
+    production.publish(&token, TEST_ID).await;
+    let questions: Vec<Value> = production
+        .get(&path)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(questions.len(), 10);
+
+    let response = production
+        .http
+        .post(production.url("/api/admin/test-bundles"))
+        .bearer_auth(&token)
+        .body("not a bundle")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_segment_markers_preview_and_save() {
+    let harness = Harness::start().await;
+    let token = harness.admin_token().await;
+
+    let response = harness
+        .admin_post(
+            &token,
+            "/api/admin/segments/preview",
+            json!({ "markers": "start-1:02 - intro\n1:02-8:51 - test\n8:51-end - outro\n" }),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let preview: Value = response.json().await.unwrap();
+    assert_eq!(preview["format"], "marks");
+    assert_eq!(preview["segments"][1]["start_time"], 62);
+    assert_eq!(preview["segments"][1]["enables_copy"], true);
+    assert_eq!(preview["segments"][2]["end_time"], Value::Null);
+
+    // Nothing is saved until the previewed segments are put on the test
+    let response = harness
+        .admin_put(
+            &token,
+            &format!("/api/admin/tests/{}", TEST_ID),
+            json!({ "segments": preview["segments"] }),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    harness.publish(&token, TEST_ID).await;
+    let tests: Vec<Value> = harness.get("/api/tests").await.json().await.unwrap();
+    assert_eq!(tests[0]["segments"].as_array().unwrap().len(), 3);
+
+    let response = harness
+        .admin_post(&token, "/api/admin/segments/preview", json!({ "markers": "1:02 practice" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(response.text().await.unwrap().contains("Line 1"));
+
+    let overlapping = json!([
+        { "name": "intro", "start_time": 0, "end_time": 90, "enables_copy": false, "enables_questions": false },
+        { "name": "test", "start_time": 60, "end_time": null, "enables_copy": true, "enables_questions": true },
+    ]);
+    let response = harness
+        .admin_put(&token, &format!("/api/admin/tests/{}", TEST_ID), json!({ "segments": overlapping }))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_admin_routes_require_auth() {
+    let harness = Harness::start().await;
+
+    let response = harness.get("/api/admin/queue").await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = harness
+        .post_json(
+            "/api/admin/login",
+            json!({ "username": "admin", "password": "wrong" }),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = harness.get("/health").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_scoped_api_tokens() {
+    let harness = Harness::start().await;
+    let admin = harness.admin_token().await;
+
+    let response = harness
+        .admin_post(&admin, "/api/admin/tokens", json!({ "name": "roster script", "scopes": ["roster:read"] }))
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let created: Value = response.json().await.unwrap();
+    let id = created["id"].as_str().unwrap();
+    let token = created["token"].as_str().unwrap();
+    assert!(token.starts_with("kce_"));
+
+    // Only the routes its scopes cover
+    let response = harness.admin_get(token, "/api/admin/approved").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    for response in [
+        harness.admin_get(token, "/api/admin/queue").await,
+        harness.admin_get(token, "/api/admin/tokens").await,
+        harness.admin_post(token, "/api/admin/queue/a1/approve", json!({})).await,
+    ] {
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    let tokens: Vec<Value> = harness.admin_get(&admin, "/api/admin/tokens").await.json().await.unwrap();
+    assert_eq!(tokens.len(), 1);
+    assert!(tokens[0]["last_used_at"].is_string());
+    assert!(tokens[0].get("token").is_none());
+
+    let response = harness.admin_delete(&admin, &format!("/api/admin/tokens/{}", id)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = harness.admin_get(token, "/api/admin/approved").await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = harness.admin_delete(&admin, &format!("/api/admin/tokens/{}", id)).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_draft_edits_wait_for_publish() {
+    let harness = Harness::start().await;
+    let token = harness.admin_token().await;
+    harness.add_questions(&token).await;
+
+    // Change an answer in the draft; candidates are still graded on revision 2
+    let questions: Vec<Value> = harness
+        .admin_get(&token, &format!("/api/admin/tests/{}/questions", TEST_ID))
+        .await
+        .json()
+        .await
+        .unwrap();
+    let first = questions[0]["id"].as_str().unwrap();
+    let response = harness
+        .admin_put(&token, &format!("/api/admin/questions/{}", first), json!({ "correct_option": "A" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let result: Value = harness.submit("W1AW", 10).await.json().await.unwrap();
+    assert_eq!(result["score"], 10);
+    let attempts: Value = harness
+        .admin_get(&token, "/api/admin/attempts")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(attempts["items"][0]["revision_id"], format!("{}@2", TEST_ID));
+
+    harness.publish(&token, TEST_ID).await;
+    let revisions: Vec<Value> = harness
+        .admin_get(&token, &format!("/api/admin/tests/{}/revisions", TEST_ID))
+        .await
+        .json()
+        .await
+        .unwrap();
+    let summary: Vec<(i64, i64, bool)> = revisions
+        .iter()
+        .map(|r| {
+            (
+                r["revision"].as_i64().unwrap(),
+                r["attempt_count"].as_i64().unwrap(),
+                r["live"].as_bool().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(summary, vec![(3, 0, true), (2, 1, false), (1, 0, false)]);
+
+    let old: Value = harness
+        .admin_get(&token, &format!("/api/admin/tests/{}/revisions/2", TEST_ID))
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(old["questions"][0]["correct_option"], "C");
+
+    // A draft that would not import cannot be published
+    let response = harness
+        .admin_post(
+            &token,
+            &format!("/api/admin/tests/{}/questions", TEST_ID),
+            json!({
+                "question_number": 12,
+                "question_text": "Question 12",
+                "options": ["W1AW", "K1ABC", "N0CALL", "AA1AA"],
+                "correct_option": "C",
+            }),
+        )
+        .await;
+    assert!(response.status().is_success());
+    let response = harness
+        .admin_post(&token, &format!("/api/admin/tests/{}/publish", TEST_ID), json!({}))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(response.text().await.unwrap().contains("without gaps"));
+}
+
+#[tokio::test]
+async fn test_scheduled_availability() {
+    let harness = Harness::start().await;
+    let token = harness.admin_token().await;
+    harness.add_questions(&token).await;
+    let path = format!("/api/admin/tests/{}", TEST_ID);
+    let schedule = |schedule: Value| harness.admin_put(&token, &path, json!({ "schedule": schedule }));
+    let admin_status = || async {
+        let tests: Vec<Value> = harness.admin_get(&token, "/api/admin/tests").await.json().await.unwrap();
+        tests[0]["availability"].clone()
+    };
+
+    let now = chrono::Utc::now();
+    let opens_at = now + chrono::Duration::days(1);
+    assert_eq!(schedule(json!({ "available_from": opens_at })).await.status(), StatusCode::OK);
+    let tests: Vec<Value> = harness.get("/api/tests").await.json().await.unwrap();
+    assert!(tests.is_empty());
+    let response = harness.get(&format!("/api/tests/{}/questions", TEST_ID)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(response.text().await.unwrap().contains("This test opens at"));
+    assert_eq!(admin_status().await["status"], "upcoming");
+
+    // A weekly window open now lists the test with the time it closes
+    let window = json!({
+        "start": now - chrono::Duration::weeks(3) - chrono::Duration::hours(1),
+        "end": now - chrono::Duration::weeks(3) + chrono::Duration::hours(1),
+        "repeat": "weekly",
+    });
+    assert_eq!(schedule(json!({ "windows": [window] })).await.status(), StatusCode::OK);
+    let tests: Vec<Value> = harness.get("/api/tests").await.json().await.unwrap();
+    assert_eq!(tests.len(), 1);
+    assert!(tests[0]["closes_at"].is_string());
+    assert_eq!(harness.submit("W1AW", 10).await.status(), StatusCode::OK);
+
+    assert_eq!(
+        schedule(json!({ "available_until": now - chrono::Duration::hours(1) })).await.status(),
+        StatusCode::OK
+    );
+    let response = harness
+        .post_json(
+            &format!("/api/tests/{}/submit", TEST_ID),
+            json!({ "callsign": "K1ABC", "answers": {}, "audio_progress": 100.0 }),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(admin_status().await["status"], "expired");
+
+    let response = schedule(json!({ "available_from": now, "available_until": now })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_shuffled_exam_sessions() {
+    let harness = Harness::start().await;
+    let token = harness.admin_token().await;
+    harness.add_questions(&token).await;
+    let response = harness
+        .admin_put(&token, &format!("/api/admin/tests/{}", TEST_ID), json!({ "shuffle": true }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let questions_path = format!("/api/tests/{}/questions", TEST_ID);
+    let response = harness.get(&questions_path).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let sessions_path = format!("/api/tests/{}/sessions", TEST_ID);
+    let response = harness.post_json(&sessions_path, json!({})).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let session: Value = response.json().await.unwrap();
+    assert_eq!(session["shuffled"], true);
+    let session_id = session["session_id"].as_str().unwrap().to_string();
+
+    let session_path = format!("{}?session={}", questions_path, session_id);
+    let questions: Vec<Value> = harness.get(&session_path).await.json().await.unwrap();
+    let again: Vec<Value> = harness.get(&session_path).await.json().await.unwrap();
+    assert_eq!(questions, again);
+    assert_eq!(questions[0]["question_number"], 1);
+
+    // Answer with whichever letter shows the correct call
+    let answers: HashMap<String, String> = questions
+        .iter()
+        .map(|q| {
+            let shown = q["options"].as_array().unwrap().iter().position(|o| o == "N0CALL").unwrap();
+            (q["id"].as_str().unwrap().to_string(), grading::option_letter(shown))
+        })
+        .collect();
+    let submit_path = format!("/api/tests/{}/submit", TEST_ID);
+    let body = json!({
+        "callsign": "W1AW",
+        "answers": answers,
+        "audio_progress": 100.0,
+        "session_id": session_id,
+    });
+    let response = harness.post_json(&submit_path, body.clone()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let result: Value = response.json().await.unwrap();
+    assert_eq!(result["score"], 10);
+    // The questions give away nothing but the shown options, and results
+    // come back in the letters the candidate saw
+    let fields = |q: &Value| q.as_object().unwrap().keys().cloned().collect::<Vec<_>>();
+    assert!(questions.iter().all(|q| fields(q) == fields(&questions[0])));
+    assert!(!fields(&questions[0]).iter().any(|f| f.contains("letter") || f.contains("correct")));
+    assert!(answers.values().any(|letter| letter != "C"));
+    for q in &questions {
+        let id = q["id"].as_str().unwrap();
+        assert_eq!(result["correct_answers"][id], answers[id]);
+    }
+    let results = result["question_results"].as_array().unwrap();
+    assert!(results.iter().all(|r| {
+        let id = r["question_id"].as_str().unwrap();
+        r["correct"] == true && r["user_answer"] == answers[id] && r["correct_answer"] == answers[id]
+    }));
+
+    // Only the admin view maps the shown letters to the answer key's
+    let detail: Value = harness
+        .admin_get(&token, &format!("/api/admin/exam-sessions/{}", session_id))
+        .await
+        .json()
+        .await
+        .unwrap();
+    for q in &questions {
+        let id = q["id"].as_str().unwrap();
+        assert_eq!(detail["correct_answers"][id], "C");
+        let shown = grading::option_index(&answers[id], 4).unwrap();
+        assert_eq!(detail["option_letters"][id][shown], "C");
+    }
+
+    let response = harness.post_json(&submit_path, body).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = harness.get(&format!("{}?session=unknown", questions_path)).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_question_bank_draws() {
+    let harness = Harness::start().await;
+    let token = harness.admin_token().await;
+    let topics = ["operator name", "rig", "weather"];
+    for n in 1..=12 {
+        let response = harness
+            .admin_post(
+                &token,
+                &format!("/api/admin/tests/{}/questions", TEST_ID),
+                json!({
+                    "question_number": n,
+                    "question_text": format!("Question {}", n),
+                    "options": ["W1AW", "K1ABC", "N0CALL", "AA1AA"],
+                    "correct_option": "C",
+                    "topic": topics[(n - 1) % 3],
+                }),
+            )
+            .await;
+        assert!(response.status().is_success());
+    }
+    let path = format!("/api/admin/tests/{}", TEST_ID);
+    let response = harness.admin_put(&token, &path, json!({ "draw_count": 3 })).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The default passing score of 7 cannot be reached with three questions
+    let publish_path = format!("/api/admin/tests/{}/publish", TEST_ID);
+    let response = harness.admin_post(&token, &publish_path, json!({})).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(response.text().await.unwrap().contains("passing_score 7"));
+    let response = harness.admin_put(&token, &path, json!({ "passing_score": 2 })).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    harness.publish(&token, TEST_ID).await;
+
+    let questions_path = format!("/api/tests/{}/questions", TEST_ID);
+    assert_eq!(harness.get(&questions_path).await.status(), StatusCode::BAD_REQUEST);
+
+    let bank: Vec<Value> = harness.admin_get(&token, &format!("{}/questions", path)).await.json().await.unwrap();
+    let topic_of = |id: &Value| bank.iter().find(|q| q["id"] == *id).unwrap()["topic"].clone();
+
+    let session: Value = harness
+        .post_json(&format!("/api/tests/{}/sessions", TEST_ID), json!({}))
+        .await
+        .json()
+        .await
+        .unwrap();
+    let session_id = session["session_id"].as_str().unwrap();
+    let questions: Vec<Value> = harness
+        .get(&format!("{}?session={}", questions_path, session_id))
+        .await
+        .json()
+        .await
+        .unwrap();
+    let numbers: Vec<_> = questions.iter().map(|q| q["question_number"].as_i64().unwrap()).collect();
+    assert_eq!(numbers, vec![1, 2, 3]);
+    let mut drawn_topics: Vec<_> = questions.iter().map(|q| topic_of(&q["id"])).collect();
+    drawn_topics.sort_by_key(|t| t.to_string());
+    assert_eq!(drawn_topics, topics.map(Value::from).to_vec());
+
+    let answers: HashMap<String, &str> = questions
+        .iter()
+        .map(|q| (q["id"].as_str().unwrap().to_string(), "C"))
+        .collect();
+    let response = harness
+        .post_json(
+            &format!("/api/tests/{}/submit", TEST_ID),
+            json!({ "callsign": "W1AW", "answers": answers, "audio_progress": 100.0, "session_id": session_id }),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let result: Value = response.json().await.unwrap();
+    assert_eq!((result["score"].as_i64(), result["passed"].as_bool()), (Some(3), Some(true)));
+
+    // The attempt leads back to the questions the candidate was asked
+    let attempts: Value = harness.admin_get(&token, "/api/admin/attempts").await.json().await.unwrap();
+    assert_eq!(attempts["items"][0]["session_id"], session_id);
+    let detail: Value = harness
+        .admin_get(&token, &format!("/api/admin/exam-sessions/{}", session_id))
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(detail["questions"], Value::from(questions));
+    assert_eq!(detail["correct_answers"].as_object().unwrap().len(), 3);
+    assert!(detail["submitted_at"].is_string());
+}
+
+#[tokio::test]
+async fn test_question_types() {
+    let harness = Harness::start().await;
+    let token = harness.admin_token().await;
+    let questions_path = format!("/api/admin/tests/{}/questions", TEST_ID);
+    let questions = [
+        json!({ "question_type": "choice", "options": ["W1AW", "K1ABC", "N0CALL"], "correct_option": "c" }),
+        json!({ "question_type": "true_false", "correct_option": "true" }),
+        json!({
+            "question_type": "text",
+            "correct_option": "Dayton",
+            "accepted_answers": ["Dayton, Ohio"],
+        }),
+    ];
+    for (n, question) in questions.into_iter().enumerate() {
+        let mut body = question;
+        body["question_number"] = json!(n + 1);
+        body["question_text"] = json!(format!("Question {}", n + 1));
+        let response = harness.admin_post(&token, &questions_path, body).await;
+        assert!(response.status().is_success(), "{}", response.text().await.unwrap());
+    }
+
+    // Each type rejects answers it cannot have
+    for (body, problem) in [
+        (json!({ "options": ["W1AW"], "correct_option": "A" }), "2 to 8 options"),
+        (json!({ "options": ["W1AW", "K1ABC"], "correct_option": "C" }), "A to B"),
+        (json!({ "question_type": "true_false", "correct_option": "maybe" }), "TRUE or FALSE"),
+        (json!({ "question_type": "text", "correct_option": " " }), "expected answer"),
+        (json!({ "question_type": "true_false", "options": ["W1AW", "K1ABC"], "correct_option": "TRUE" }), "options"),
+    ] {
+        let mut body = body;
+        body["question_number"] = json!(4);
+        body["question_text"] = json!("Question 4");
+        let response = harness.admin_post(&token, &questions_path, body).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let message = response.text().await.unwrap();
+        assert!(message.contains(problem), "{}", message);
+    }
+
+    // Changing the type drops the options and accepted answers left unsent
+    let admin_questions = || async {
+        let questions: Vec<Value> = harness.admin_get(&token, &questions_path).await.json().await.unwrap();
+        questions
+    };
+    let ids: Vec<String> = admin_questions().await.iter().map(|q| q["id"].as_str().unwrap().to_string()).collect();
+    for (id, body) in [
+        (&ids[0], json!({ "question_type": "text", "correct_option": "W1AW" })),
+        (&ids[2], json!({ "question_type": "true_false", "correct_option": "false" })),
+    ] {
+        let response = harness.admin_put(&token, &format!("/api/admin/questions/{}", id), body).await;
+        assert_eq!(response.status(), StatusCode::OK, "{}", response.text().await.unwrap());
+    }
+    let changed = admin_questions().await;
+    assert_eq!((changed[0]["question_type"].as_str(), changed[0]["correct_option"].as_str()), (Some("text"), Some("W1AW")));
+    assert_eq!(changed[0]["options"], json!([]));
+    assert_eq!((changed[2]["question_type"].as_str(), changed[2]["correct_option"].as_str()), (Some("true_false"), Some("FALSE")));
+    assert_eq!(changed[2]["accepted_answers"], json!([]));
+    // Back to the original types, sending what they need
+    for (id, body) in [
+        (&ids[0], json!({ "question_type": "choice", "options": ["W1AW", "K1ABC", "N0CALL"], "correct_option": "c" })),
+        (&ids[2], json!({ "question_type": "text", "correct_option": "Dayton", "accepted_answers": ["Dayton, Ohio"] })),
+    ] {
+        let response = harness.admin_put(&token, &format!("/api/admin/questions/{}", id), body).await;
+        assert_eq!(response.status(), StatusCode::OK, "{}", response.text().await.unwrap());
+    }
+
+    let response = harness.admin_put(&token, &format!("/api/admin/tests/{}", TEST_ID), json!({ "passing_score": 3 })).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    harness.publish(&token, TEST_ID).await;
+
+    let questions: Vec<Value> = harness
+        .get(&format!("/api/tests/{}/questions", TEST_ID))
+        .await
+        .json()
+        .await
+        .unwrap();
+    let types: Vec<_> = questions.iter().map(|q| q["question_type"].as_str().unwrap()).collect();
+    assert_eq!(types, vec!["choice", "true_false", "text"]);
+    assert_eq!(questions[0]["options"].as_array().unwrap().len(), 3);
+    assert!(questions[2].get("accepted_answers").is_none());
+
+    // Free text is compared ignoring case and spacing
+    let submit_path = format!("/api/tests/{}/submit", TEST_ID);
+    let submit = |callsign: &str, answers: [&str; 3]| {
+        let answers: HashMap<String, &str> = questions
+            .iter()
+            .zip(answers)
+            .map(|(q, answer)| (q["id"].as_str().unwrap().to_string(), answer))
+            .collect();
+        harness.post_json(
+            &submit_path,
+            json!({ "callsign": callsign, "answers": answers, "audio_progress": 100.0 }),
+        )
+    };
+    let result: Value = submit("W1AW", ["C", "True", "  dayton,  OHIO "]).await.json().await.unwrap();
+    assert_eq!((result["score"].as_i64(), result["passed"].as_bool()), (Some(3), Some(true)));
+    let results = result["question_results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(|r| r["correct"] == true));
+
+    let result: Value = submit("K1ABC", ["C", "FALSE", "Xenia"]).await.json().await.unwrap();
+    assert_eq!((result["score"].as_i64(), result["passed"].as_bool()), (Some(1), Some(false)));
+    assert!(result["question_results"].is_null());
+}
+
+#[tokio::test]
+async fn test_purge_tests_without_attempts() {
+    let harness = Harness::start().await;
+    let token = harness.admin_token().await;
+    std::fs::create_dir_all(harness.static_dir.join("audio")).unwrap();
+    std::fs::write(harness.static_dir.join("audio/practice.mp3"), b"ID3 practice audio").unwrap();
+
+    let response = harness
+        .admin_post(
+            &token,
+            "/api/admin/tests",
+            json!({
+                "id": "20wpm-practice",
+                "title": "Practice",
+                "speed_wpm": 20,
+                "year": "1991",
+                "audio_url": "/audio/practice.mp3",
+            }),
+        )
+        .await;
+    assert!(response.status().is_success());
+    let response = harness
+        .admin_post(
+            &token,
+            "/api/admin/tests/20wpm-practice/questions",
+            json!({
+                "question_number": 1,
+                "question_text": "Question 1",
+                "options": ["W1AW", "K1ABC"],
+                "correct_option": "A",
+            }),
+        )
+        .await;
+    assert!(response.status().is_success());
+    harness.publish(&token, "20wpm-practice").await;
+
+    let response = harness.admin_delete(&token, "/api/admin/tests/20wpm-practice?purge=true").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["audio_removed"], true);
+    assert!(!harness.static_dir.join("audio/practice.mp3").exists());
+    let tests: Vec<Value> = harness.admin_get(&token, "/api/admin/tests").await.json().await.unwrap();
+    assert!(tests.iter().all(|t| t["id"] != "20wpm-practice"));
+    let response = harness.admin_delete(&token, "/api/admin/tests/20wpm-practice?purge=true").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // A test with attempts is kept for the attempts' sake
+    harness.add_questions(&token).await;
+    assert!(harness.submit("W1AW", 8).await.status().is_success());
+    let path = format!("/api/admin/tests/{}?purge=true", TEST_ID);
+    let response = harness.admin_delete(&token, &path).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert!(response.text().await.unwrap().contains("Deactivate it instead"));
+    assert_eq!(harness.get(&format!("/api/tests/{}/questions", TEST_ID)).await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_clone_test() {
+    let harness = Harness::start().await;
+    let token = harness.admin_token().await;
+    harness.add_questions(&token).await;
+    std::fs::create_dir_all(harness.static_dir.join("audio/20wpm")).unwrap();
+    std::fs::write(harness.static_dir.join("audio/20wpm/test.mp3"), b"ID3 variant A").unwrap();
+    let clone_path = format!("/api/admin/tests/{}/clone", TEST_ID);
+
+    let response = harness
+        .admin_post(
+            &token,
+            &clone_path,
+            json!({ "id": "20wpm-extra-1991-b", "title": "Extra, variant B", "duplicate_audio": true }),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let cloned: Value = response.json().await.unwrap();
+    assert_eq!(cloned["questions"], 10);
+    assert_eq!(cloned["audio_url"], "/audio/20wpm/20wpm-extra-1991-b.mp3");
+    assert_eq!(
+        std::fs::read(harness.static_dir.join("audio/20wpm/20wpm-extra-1991-b.mp3")).unwrap(),
+        b"ID3 variant A"
+    );
+
+    // The copy is an inactive draft with the same questions
+    let tests: Vec<Value> = harness.admin_get(&token, "/api/admin/tests").await.json().await.unwrap();
+    let copy = tests.iter().find(|t| t["id"] == "20wpm-extra-1991-b").unwrap();
+    assert_eq!((copy["title"].as_str(), copy["active"].as_bool()), (Some("Extra, variant B"), Some(false)));
+    assert!(copy["published_revision"].is_null());
+    let questions: Vec<Value> = harness
+        .admin_get(&token, "/api/admin/tests/20wpm-extra-1991-b/questions")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(questions.len(), 10);
+    assert_eq!(questions[0]["options"], json!(["W1AW", "K1ABC", "N0CALL", "AA1AA"]));
+
+    // A copy sharing the audio leaves the file in place when purged
+    let response = harness
+        .admin_post(&token, &clone_path, json!({ "id": "20wpm-extra-1991-c", "title": "Extra, variant C" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let cloned: Value = response.json().await.unwrap();
+    assert_eq!(cloned["audio_url"], "/audio/20wpm/test.mp3");
+    let response = harness.admin_delete(&token, "/api/admin/tests/20wpm-extra-1991-c?purge=true").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(harness.static_dir.join("audio/20wpm/test.mp3").exists());
+
+    let response = harness
+        .admin_post(&token, &clone_path, json!({ "id": "20wpm-extra-1991-b", "title": "Again", "duplicate_audio": true }))
+        .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let response = harness
+        .admin_post(&token, "/api/admin/tests/missing/clone", json!({ "id": "missing-b", "title": "Missing" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Ids end up in URLs and audio file names
+    for id in ["../20wpm-extra", "20wpm extra", ""] {
+        let response = harness
+            .admin_post(&token, &clone_path, json!({ "id": id, "title": "Bad", "duplicate_audio": true }))
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(response.text().await.unwrap().contains("letters, digits"));
+    }
+    assert!(!harness.static_dir.join("audio/20wpm-extra.mp3").exists());
+}
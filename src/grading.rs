@@ -12,8 +12,84 @@ pub fn normalize_text(text: &str) -> String {
         .join(" ")
 }
 
+/// Most options a multiple-choice question may have, lettered A to H
+pub const MAX_OPTIONS: usize = 8;
+
+/// How a question is answered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestionType {
+    /// One of the question's options, answered with its letter
+    #[default]
+    Choice,
+    /// Answered TRUE or FALSE
+    TrueFalse,
+    /// A short answer typed in, such as the operator's name
+    Text,
+}
+
+impl QuestionType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QuestionType::Choice => "choice",
+            QuestionType::TrueFalse => "true_false",
+            QuestionType::Text => "text",
+        }
+    }
+}
+
+impl TryFrom<String> for QuestionType {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        match name.as_str() {
+            "choice" => Ok(QuestionType::Choice),
+            "true_false" => Ok(QuestionType::TrueFalse),
+            "text" => Ok(QuestionType::Text),
+            _ => Err(format!("Unknown question type '{}'", name)),
+        }
+    }
+}
+
+/// Letter of the option at `index`: A, B, C...
+pub fn option_letter(index: usize) -> String {
+    char::from(b'A' + index as u8).to_string()
+}
+
+/// Index of the option a letter names, if a question with `count` options has it
+pub fn option_index(letter: &str, count: usize) -> Option<usize> {
+    (0..count.min(MAX_OPTIONS)).find(|&i| option_letter(i).eq_ignore_ascii_case(letter.trim()))
+}
+
+/// What a question accepts as correct
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnswerKey {
+    pub question_type: QuestionType,
+    /// The option letter, TRUE or FALSE, or the expected text
+    pub correct: String,
+    /// Further free-text answers accepted, such as other spellings
+    pub accepted: Vec<String>,
+}
+
+impl AnswerKey {
+    pub fn accepts(&self, answer: &str) -> bool {
+        match self.question_type {
+            QuestionType::Choice | QuestionType::TrueFalse => {
+                answer.trim().eq_ignore_ascii_case(&self.correct)
+            }
+            QuestionType::Text => {
+                let answer = normalize_text(answer);
+                !answer.is_empty()
+                    && std::iter::once(&self.correct)
+                        .chain(&self.accepted)
+                        .any(|accepted| normalize_text(accepted) == answer)
+            }
+        }
+    }
+}
+
 /// Result of grading a single question
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct QuestionResult {
     pub question_id: String,
     pub correct: bool,
@@ -21,20 +97,17 @@ pub struct QuestionResult {
     pub correct_answer: String,
 }
 
-/// Grade question answers against correct answers
+/// Grade answers question by question, each as its type is answered
 pub fn grade_questions(
     answers: &HashMap<String, String>,
-    correct_answers: &HashMap<String, String>,
+    answer_key: &HashMap<String, AnswerKey>,
 ) -> (i32, Vec<QuestionResult>) {
     let mut score = 0;
     let mut results = Vec::new();
 
-    for (question_id, correct_answer) in correct_answers {
+    for (question_id, key) in answer_key {
         let user_answer = answers.get(question_id).cloned();
-        let is_correct = user_answer
-            .as_ref()
-            .map(|a| a.to_uppercase() == correct_answer.to_uppercase())
-            .unwrap_or(false);
+        let is_correct = user_answer.as_deref().is_some_and(|answer| key.accepts(answer));
 
         if is_correct {
             score += 1;
@@ -44,7 +117,7 @@ pub fn grade_questions(
             question_id: question_id.clone(),
             correct: is_correct,
             user_answer,
-            correct_answer: correct_answer.clone(),
+            correct_answer: key.correct.clone(),
         });
     }
 
@@ -207,6 +280,18 @@ mod tests {
         assert_eq!(normalize_text("CQ CQ de W6JSV"), "CQ CQ DE W6JSV");
     }
 
+    fn key(question_type: QuestionType, correct: &str, accepted: &[&str]) -> AnswerKey {
+        AnswerKey {
+            question_type,
+            correct: correct.to_string(),
+            accepted: accepted.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    fn choice(correct: &str) -> AnswerKey {
+        key(QuestionType::Choice, correct, &[])
+    }
+
     #[test]
     fn test_grade_questions_all_correct() {
         let answers: HashMap<String, String> = [
@@ -214,9 +299,9 @@ mod tests {
             ("q2".to_string(), "B".to_string()),
         ].into_iter().collect();
 
-        let correct: HashMap<String, String> = [
-            ("q1".to_string(), "A".to_string()),
-            ("q2".to_string(), "B".to_string()),
+        let correct: HashMap<String, AnswerKey> = [
+            ("q1".to_string(), choice("A")),
+            ("q2".to_string(), choice("B")),
         ].into_iter().collect();
 
         let (score, _) = grade_questions(&answers, &correct);
@@ -229,8 +314,8 @@ mod tests {
             ("q1".to_string(), "a".to_string()),
         ].into_iter().collect();
 
-        let correct: HashMap<String, String> = [
-            ("q1".to_string(), "A".to_string()),
+        let correct: HashMap<String, AnswerKey> = [
+            ("q1".to_string(), choice("A")),
         ].into_iter().collect();
 
         let (score, _) = grade_questions(&answers, &correct);
@@ -241,8 +326,8 @@ mod tests {
     fn test_grade_questions_missing_answer() {
         let answers: HashMap<String, String> = HashMap::new();
 
-        let correct: HashMap<String, String> = [
-            ("q1".to_string(), "A".to_string()),
+        let correct: HashMap<String, AnswerKey> = [
+            ("q1".to_string(), choice("A")),
         ].into_iter().collect();
 
         let (score, results) = grade_questions(&answers, &correct);
@@ -250,6 +335,39 @@ mod tests {
         assert!(!results[0].correct);
     }
 
+    #[test]
+    fn test_grade_questions_by_type() {
+        let correct: HashMap<String, AnswerKey> = [
+            ("choice".to_string(), choice("E")),
+            ("true_false".to_string(), key(QuestionType::TrueFalse, "FALSE", &[])),
+            ("name".to_string(), key(QuestionType::Text, "Bill", &["William"])),
+            ("qth".to_string(), key(QuestionType::Text, "San Jose", &[])),
+        ].into_iter().collect();
+        let answers: HashMap<String, String> = [
+            ("choice", "e"),
+            ("true_false", "false"),
+            ("name", "  william "),
+            ("qth", "SANJOSE"),
+        ].into_iter().map(|(q, a)| (q.to_string(), a.to_string())).collect();
+
+        let (score, results) = grade_questions(&answers, &correct);
+        assert_eq!(score, 3);
+        let wrong: Vec<_> = results.iter().filter(|r| !r.correct).map(|r| r.question_id.as_str()).collect();
+        assert_eq!(wrong, vec!["qth"]);
+
+        // A blank answer never matches
+        assert!(!key(QuestionType::Text, " ", &[]).accepts(""));
+    }
+
+    #[test]
+    fn test_option_letters() {
+        assert_eq!(option_letter(0), "A");
+        assert_eq!(option_letter(4), "E");
+        assert_eq!(option_index("e", 5), Some(4));
+        assert_eq!(option_index("E", 4), None);
+        assert_eq!(option_index("TRUE", 4), None);
+    }
+
     #[test]
     fn test_find_consecutive_correct_exact_match() {
         let result = find_consecutive_correct(
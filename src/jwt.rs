@@ -1,3 +1,7 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
 use axum::{
     extract::{Request, State},
     http::{header, StatusCode},
@@ -6,6 +10,7 @@ use axum::{
     Json,
 };
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use crate::db::{query, Db};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
@@ -35,9 +40,15 @@ pub async fn login(
     State(state): State<Arc<crate::AppState>>,
     Json(req): Json<LoginRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    // Validate credentials
-    if req.username != state.admin_username || req.password != state.admin_password {
-        return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
+    // Validate credentials: the configured admin, or an account from create-admin
+    let config_admin = req.username == state.admin_username && req.password == state.admin_password;
+    if !config_admin {
+        let valid = verify_admin_user(&state.db, &req.username, &req.password)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        if !valid {
+            return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
+        }
     }
 
     // Create JWT
@@ -63,7 +74,60 @@ pub async fn login(
     }))
 }
 
-/// Middleware to validate JWT on admin routes
+/// Create (or reset the password of) a database-backed admin account
+pub async fn create_admin_user(
+    db: &Db,
+    username: &str,
+    password: &str,
+) -> Result<(), String> {
+    let salt = SaltString::generate(&mut OsRng);
+    let password_hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| format!("Failed to hash password: {}", e))?
+        .to_string();
+
+    db.execute(
+        query(
+            "INSERT INTO admin_users (id, username, password_hash, created_at) VALUES (?, ?, ?, ?)
+             ON CONFLICT(username) DO UPDATE SET password_hash = excluded.password_hash",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(username)
+        .bind(&password_hash)
+        .bind(chrono::Utc::now()),
+    )
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(())
+}
+
+/// Check a username/password against the `admin_users` table
+async fn verify_admin_user(
+    db: &Db,
+    username: &str,
+    password: &str,
+) -> Result<bool, sqlx::Error> {
+    let row: Option<(String,)> = db
+        .fetch_optional(
+            query("SELECT password_hash FROM admin_users WHERE username = ?").bind(username),
+        )
+        .await?;
+
+    let Some((stored,)) = row else {
+        return Ok(false);
+    };
+
+    Ok(PasswordHash::new(&stored)
+        .map(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+        .unwrap_or(false))
+}
+
+/// Middleware to validate JWT (or a scoped API token) on admin routes
 pub async fn require_admin_auth(
     State(state): State<Arc<crate::AppState>>,
     request: Request,
@@ -86,6 +150,26 @@ pub async fn require_admin_auth(
         }
     };
 
+    // API tokens are only accepted on routes their scopes cover
+    if token.starts_with(crate::tokens::TOKEN_PREFIX) {
+        let scopes = match crate::tokens::authenticate(&state.db, token).await {
+            Ok(Some(scopes)) => scopes,
+            Ok(None) => {
+                return (StatusCode::UNAUTHORIZED, "Invalid or revoked API token").into_response()
+            }
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        };
+
+        return match crate::tokens::required_scope(request.method(), request.uri().path()) {
+            Some(scope) if scopes.contains(&scope) => next.run(request).await,
+            _ => (
+                StatusCode::FORBIDDEN,
+                "API token does not have the required scope",
+            )
+                .into_response(),
+        };
+    }
+
     // Validate JWT
     let validation = Validation::default();
     match decode::<Claims>(
@@ -0,0 +1,496 @@
+//! Versioned schema migrations
+//!
+//! Migrations are numbered SQL files under `migrations/sqlite/` and
+//! `migrations/postgres/`, embedded at compile time and applied in order.
+//! Both directories carry the same versions; each file describes the same
+//! schema in its backend's dialect. Each migration runs in its own
+//! transaction together with its `schema_version` row, so a failed migration
+//! leaves the database at the previous version.
+
+use crate::db::{query, Backend, Db};
+
+#[derive(Debug)]
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub sqlite: &'static str,
+    pub postgres: &'static str,
+}
+
+impl Migration {
+    pub fn sql(&self, backend: Backend) -> &'static str {
+        match backend {
+            Backend::Sqlite => self.sqlite,
+            Backend::Postgres => self.postgres,
+        }
+    }
+}
+
+/// All migrations, in order. Append new files here; never edit applied ones.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        sqlite: include_str!("../migrations/sqlite/0001_initial_schema.sql"),
+        postgres: include_str!("../migrations/postgres/0001_initial_schema.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "admin_users",
+        sqlite: include_str!("../migrations/sqlite/0002_admin_users.sql"),
+        postgres: include_str!("../migrations/postgres/0002_admin_users.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "certificate_numbers",
+        sqlite: include_str!("../migrations/sqlite/0003_certificate_numbers.sql"),
+        postgres: include_str!("../migrations/postgres/0003_certificate_numbers.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "certificate_events",
+        sqlite: include_str!("../migrations/sqlite/0004_certificate_events.sql"),
+        postgres: include_str!("../migrations/postgres/0004_certificate_events.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "certificate_templates",
+        sqlite: include_str!("../migrations/sqlite/0005_certificate_templates.sql"),
+        postgres: include_str!("../migrations/postgres/0005_certificate_templates.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "test_revisions",
+        sqlite: include_str!("../migrations/sqlite/0006_test_revisions.sql"),
+        postgres: include_str!("../migrations/postgres/0006_test_revisions.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "test_availability",
+        sqlite: include_str!("../migrations/sqlite/0007_test_availability.sql"),
+        postgres: include_str!("../migrations/postgres/0007_test_availability.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "exam_sessions",
+        sqlite: include_str!("../migrations/sqlite/0008_exam_sessions.sql"),
+        postgres: include_str!("../migrations/postgres/0008_exam_sessions.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "question_banks",
+        sqlite: include_str!("../migrations/sqlite/0009_question_banks.sql"),
+        postgres: include_str!("../migrations/postgres/0009_question_banks.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "question_types",
+        sqlite: include_str!("../migrations/sqlite/0010_question_types.sql"),
+        postgres: include_str!("../migrations/postgres/0010_question_types.sql"),
+    },
+];
+
+/// Columns that the pre-migrations `setup_database` added with
+/// `ALTER TABLE ... ADD COLUMN`. Databases from that era may lack any of them.
+/// Only SQLite databases predate migrations.
+const LEGACY_COLUMNS: &[(&str, &str, &str)] = &[
+    ("attempts", "test_id", "TEXT"),
+    ("attempts", "validation_status", "TEXT"),
+    ("attempts", "certificate_number", "INTEGER"),
+    ("attempts", "validated_at", "TEXT"),
+    ("attempts", "admin_note", "TEXT"),
+    ("attempts", "email", "TEXT"),
+    ("attempts", "reached_out", "INTEGER DEFAULT 0"),
+    ("attempts", "audio_progress", "REAL"),
+    ("attempts", "copy_text", "TEXT"),
+    ("attempts", "consecutive_correct", "INTEGER"),
+    ("tests", "segments", "TEXT"),
+    ("tests", "expected_copy_text", "TEXT"),
+];
+
+/// What `run` would do against the current database
+#[derive(Debug)]
+pub struct MigrationPlan {
+    pub current_version: i64,
+    /// Database predates versioned migrations and will be adopted at version 1
+    pub adopt_legacy: bool,
+    pub pending: Vec<&'static Migration>,
+}
+
+async fn table_exists(db: &Db, table: &str) -> Result<bool, sqlx::Error> {
+    let sql = match db.backend() {
+        Backend::Sqlite => "SELECT name FROM sqlite_master WHERE type = 'table' AND name = ?",
+        Backend::Postgres => {
+            "SELECT table_name::TEXT FROM information_schema.tables
+             WHERE table_schema = current_schema() AND table_name = ?"
+        }
+    };
+    let row: Option<(String,)> = db.fetch_optional(query(sql).bind(table)).await?;
+    Ok(row.is_some())
+}
+
+async fn current_version(db: &Db) -> Result<i64, sqlx::Error> {
+    if !table_exists(db, "schema_version").await? {
+        return Ok(0);
+    }
+    let version: (Option<i64>,) = db
+        .fetch_one(query("SELECT MAX(version) FROM schema_version"))
+        .await?;
+    Ok(version.0.unwrap_or(0))
+}
+
+/// Determine the current version and which migrations are pending
+pub async fn plan(db: &Db) -> Result<MigrationPlan, sqlx::Error> {
+    let current_version = current_version(db).await?;
+    let adopt_legacy = current_version == 0
+        && db.backend() == Backend::Sqlite
+        && table_exists(db, "attempts").await?;
+    let applied_through = if adopt_legacy { 1 } else { current_version };
+
+    let pending = MIGRATIONS
+        .iter()
+        .filter(|m| m.version > applied_through)
+        .collect();
+
+    Ok(MigrationPlan {
+        current_version,
+        adopt_legacy,
+        pending,
+    })
+}
+
+/// Bring a SQLite database created by the old `setup_database` up to the
+/// initial schema: add whichever legacy columns are missing, create any
+/// missing tables and indexes, and record version 1.
+async fn adopt_legacy_schema(db: &Db) -> Result<(), sqlx::Error> {
+    let mut tx = db.begin().await?;
+
+    for (table, column, definition) in LEGACY_COLUMNS {
+        let table_present: Option<(String,)> = tx
+            .fetch_optional(
+                query("SELECT name FROM sqlite_master WHERE type = 'table' AND name = ?")
+                    .bind(*table),
+            )
+            .await?;
+        if table_present.is_none() {
+            continue;
+        }
+
+        let existing: Option<(String,)> = tx
+            .fetch_optional(
+                query("SELECT name FROM pragma_table_info(?) WHERE name = ?")
+                    .bind(*table)
+                    .bind(*column),
+            )
+            .await?;
+        if existing.is_none() {
+            tracing::info!("Adding missing legacy column {}.{}", table, column);
+            tx.execute(query(format!(
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                table, column, definition
+            )))
+            .await?;
+        }
+    }
+
+    let initial = &MIGRATIONS[0];
+    tx.execute_script(initial.sqlite).await?;
+    tx.execute(record_version(initial)).await?;
+
+    tx.commit().await?;
+    tracing::info!("Adopted existing database at schema version {}", initial.version);
+    Ok(())
+}
+
+fn record_version(migration: &Migration) -> crate::db::Query {
+    query("INSERT INTO schema_version (version, name, applied_at) VALUES (?, ?, ?)")
+        .bind(migration.version)
+        .bind(migration.name)
+        .bind(chrono::Utc::now())
+}
+
+/// Apply all pending migrations, returning the resulting schema version
+pub async fn run(db: &Db) -> Result<i64, sqlx::Error> {
+    let create_version_table = match db.backend() {
+        Backend::Sqlite => {
+            r#"
+            CREATE TABLE IF NOT EXISTS schema_version (
+                version INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                applied_at TEXT NOT NULL
+            )
+            "#
+        }
+        Backend::Postgres => {
+            r#"
+            CREATE TABLE IF NOT EXISTS schema_version (
+                version BIGINT PRIMARY KEY,
+                name TEXT NOT NULL,
+                applied_at TIMESTAMPTZ NOT NULL
+            )
+            "#
+        }
+    };
+    db.execute(query(create_version_table)).await?;
+
+    let plan = plan(db).await?;
+
+    if plan.adopt_legacy {
+        adopt_legacy_schema(db).await?;
+    }
+
+    for migration in &plan.pending {
+        tracing::info!(
+            "Applying migration {:04}_{}",
+            migration.version,
+            migration.name
+        );
+        let mut tx = db.begin().await?;
+        tx.execute_script(migration.sql(db.backend())).await?;
+        tx.execute(record_version(migration)).await?;
+        tx.commit().await?;
+    }
+
+    current_version(db).await
+}
+
+/// Latest version known to this build
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use sqlx::SqlitePool;
+
+    /// Schema produced by `setup_database` before versioned migrations existed
+    const LEGACY_SCHEMA: &str = r#"
+        CREATE TABLE attempts (
+            id TEXT PRIMARY KEY,
+            callsign TEXT NOT NULL,
+            test_speed INTEGER NOT NULL,
+            questions_correct INTEGER NOT NULL,
+            copy_chars INTEGER NOT NULL,
+            passed BOOLEAN NOT NULL,
+            created_at TEXT NOT NULL
+        );
+        CREATE TABLE tests (
+            id TEXT PRIMARY KEY,
+            title TEXT NOT NULL,
+            speed_wpm INTEGER NOT NULL,
+            year TEXT NOT NULL,
+            audio_url TEXT NOT NULL,
+            passing_score INTEGER NOT NULL DEFAULT 7,
+            active INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL
+        );
+        CREATE TABLE questions (
+            id TEXT PRIMARY KEY,
+            test_id TEXT NOT NULL,
+            question_number INTEGER NOT NULL,
+            question_text TEXT NOT NULL,
+            option_a TEXT NOT NULL,
+            option_b TEXT NOT NULL,
+            option_c TEXT NOT NULL,
+            option_d TEXT NOT NULL,
+            correct_option TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (test_id) REFERENCES tests(id),
+            UNIQUE(test_id, question_number)
+        );
+        CREATE TABLE prosign_mappings (
+            id TEXT PRIMARY KEY,
+            prosign TEXT NOT NULL UNIQUE,
+            alternate TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        );
+        CREATE TABLE settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+        ALTER TABLE attempts ADD COLUMN test_id TEXT;
+        ALTER TABLE tests ADD COLUMN segments TEXT;
+        ALTER TABLE tests ADD COLUMN expected_copy_text TEXT;
+        CREATE INDEX idx_questions_test_id ON questions(test_id);
+        CREATE INDEX idx_callsign ON attempts(callsign);
+        CREATE INDEX idx_passed ON attempts(passed);
+        CREATE INDEX idx_test_speed ON attempts(test_speed);
+        CREATE INDEX idx_created_at ON attempts(created_at DESC);
+        ALTER TABLE attempts ADD COLUMN validation_status TEXT;
+        ALTER TABLE attempts ADD COLUMN certificate_number INTEGER;
+        ALTER TABLE attempts ADD COLUMN validated_at TEXT;
+        ALTER TABLE attempts ADD COLUMN admin_note TEXT;
+        ALTER TABLE attempts ADD COLUMN email TEXT;
+        ALTER TABLE attempts ADD COLUMN reached_out INTEGER DEFAULT 0;
+        ALTER TABLE attempts ADD COLUMN audio_progress REAL;
+        ALTER TABLE attempts ADD COLUMN copy_text TEXT;
+        ALTER TABLE attempts ADD COLUMN consecutive_correct INTEGER;
+        CREATE INDEX idx_validation_status ON attempts(validation_status);
+        INSERT INTO attempts (id, callsign, test_speed, questions_correct, copy_chars, passed, created_at, validation_status, certificate_number)
+        VALUES ('a1', 'W1AW', 20, 8, 120, 1, '2026-01-20T00:00:00+00:00', 'approved', 1);
+    "#;
+
+    fn sqlite(pool: &SqlitePool) -> Db {
+        Db::Sqlite(pool.clone())
+    }
+
+    async fn memory_pool() -> SqlitePool {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap()
+    }
+
+    /// Tables, columns and indexes (excluding `schema_version` bookkeeping)
+    async fn schema_shape(pool: &SqlitePool) -> Vec<String> {
+        let objects: Vec<(String, String)> = sqlx::query_as(
+            "SELECT type, name FROM sqlite_master
+             WHERE name NOT LIKE 'sqlite_%' AND name != 'schema_version'
+             ORDER BY type, name",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap();
+
+        let mut shape = Vec::new();
+        for (kind, name) in objects {
+            shape.push(format!("{} {}", kind, name));
+            if kind == "table" {
+                let columns: Vec<(String, String, bool, Option<String>, i64)> = sqlx::query_as(
+                    "SELECT name, type, \"notnull\", dflt_value, pk FROM pragma_table_info(?) ORDER BY name",
+                )
+                .bind(&name)
+                .fetch_all(pool)
+                .await
+                .unwrap();
+                for (column, ty, notnull, default, pk) in columns {
+                    shape.push(format!(
+                        "  {}.{} {} notnull={} default={:?} pk={}",
+                        name, column, ty, notnull, default, pk
+                    ));
+                }
+            }
+        }
+        shape
+    }
+
+    #[tokio::test]
+    async fn test_empty_and_legacy_databases_converge() {
+        let fresh = memory_pool().await;
+        assert_eq!(run(&sqlite(&fresh)).await.unwrap(), latest_version());
+
+        let legacy = memory_pool().await;
+        sqlx::raw_sql(LEGACY_SCHEMA).execute(&legacy).await.unwrap();
+        let plan = plan(&sqlite(&legacy)).await.unwrap();
+        assert!(plan.adopt_legacy);
+        assert_eq!(run(&sqlite(&legacy)).await.unwrap(), latest_version());
+
+        assert_eq!(schema_shape(&fresh).await, schema_shape(&legacy).await);
+
+        // Existing data survives adoption
+        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM attempts")
+            .fetch_one(&legacy)
+            .await
+            .unwrap();
+        assert_eq!(count.0, 1);
+    }
+
+    #[tokio::test]
+    async fn test_legacy_database_missing_columns_is_completed() {
+        let pool = memory_pool().await;
+        sqlx::raw_sql(
+            "CREATE TABLE attempts (
+                id TEXT PRIMARY KEY,
+                callsign TEXT NOT NULL,
+                test_speed INTEGER NOT NULL,
+                questions_correct INTEGER NOT NULL,
+                copy_chars INTEGER NOT NULL,
+                passed BOOLEAN NOT NULL,
+                created_at TEXT NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        run(&sqlite(&pool)).await.unwrap();
+
+        let fresh = memory_pool().await;
+        run(&sqlite(&fresh)).await.unwrap();
+        assert_eq!(schema_shape(&fresh).await, schema_shape(&pool).await);
+    }
+
+    #[tokio::test]
+    async fn test_certificate_ledger_backfill_keeps_earliest_duplicate() {
+        let pool = memory_pool().await;
+        sqlx::raw_sql(LEGACY_SCHEMA).execute(&pool).await.unwrap();
+        sqlx::raw_sql(
+            "UPDATE attempts SET validated_at = '2026-01-21T00:00:00+00:00' WHERE id = 'a1';
+             INSERT INTO attempts (id, callsign, test_speed, questions_correct, copy_chars, passed, created_at, validation_status, certificate_number, validated_at)
+             VALUES ('a0', 'K1ABC', 20, 8, 120, 1, '2026-01-19T00:00:00+00:00', 'approved', 1, '2026-01-20T00:00:00+00:00');",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        run(&sqlite(&pool)).await.unwrap();
+
+        let ledger: Vec<(i64, String)> =
+            sqlx::query_as("SELECT number, attempt_id FROM certificate_numbers")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(ledger, vec![(1, "a0".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_lettered_options_become_an_options_list() {
+        let pool = memory_pool().await;
+        sqlx::raw_sql(LEGACY_SCHEMA).execute(&pool).await.unwrap();
+        sqlx::raw_sql(
+            "INSERT INTO tests (id, title, speed_wpm, year, audio_url, created_at)
+             VALUES ('t1', 'Test', 20, '1991', '/audio/t1.mp3', '2026-01-19T00:00:00+00:00');
+             INSERT INTO questions (id, test_id, question_number, question_text, option_a, option_b, option_c, option_d, correct_option, created_at)
+             VALUES ('q1', 't1', 1, 'What was the call?', 'W1AW', 'K1ABC', 'N0CALL', 'AA1AA', 'C', '2026-01-19T00:00:00+00:00');",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        run(&sqlite(&pool)).await.unwrap();
+
+        let question: (String, String, String) =
+            sqlx::query_as("SELECT question_type, options, accepted_answers FROM questions")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            question,
+            (
+                "choice".to_string(),
+                r#"["W1AW","K1ABC","N0CALL","AA1AA"]"#.to_string(),
+                "[]".to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_is_idempotent() {
+        let pool = memory_pool().await;
+        run(&sqlite(&pool)).await.unwrap();
+        let plan = plan(&sqlite(&pool)).await.unwrap();
+        assert!(!plan.adopt_legacy);
+        assert!(plan.pending.is_empty());
+        assert_eq!(run(&sqlite(&pool)).await.unwrap(), latest_version());
+    }
+
+    #[test]
+    fn test_migration_versions_are_sequential() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, i as i64 + 1);
+        }
+    }
+}
@@ -36,3 +36,41 @@ pub async fn send_attempt_notification(
         }
     }
 }
+
+/// ntfy server and credentials for submission notifications
+#[derive(Clone)]
+pub struct Ntfy {
+    pub url: String,
+    pub topic: String,
+    pub username: String,
+    pub password: String,
+}
+
+impl Ntfy {
+    /// Notification settings, if all four ntfy values are configured
+    pub fn from_config(config: &crate::Config) -> Option<Self> {
+        Some(Ntfy {
+            url: config.ntfy_url.clone()?,
+            topic: config.ntfy_topic.clone()?,
+            username: config.ntfy_username.clone()?,
+            password: config.ntfy_password.clone()?,
+        })
+    }
+
+    /// Send an attempt notification in the background
+    pub fn spawn_attempt_notification(&self, callsign: &str, passed: bool) {
+        let ntfy = self.clone();
+        let callsign = callsign.to_string();
+        tokio::spawn(async move {
+            send_attempt_notification(
+                &ntfy.url,
+                &ntfy.topic,
+                &ntfy.username,
+                &ntfy.password,
+                &callsign,
+                passed,
+            )
+            .await;
+        });
+    }
+}
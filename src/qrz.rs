@@ -1,11 +1,15 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+/// QRZ XML data service endpoint
+const QRZ_API_URL: &str = "https://xmldata.qrz.com/xml/current/";
+
 /// QRZ API client with session caching
 #[derive(Clone)]
 pub struct QrzClient {
     username: String,
     password: String,
+    base_url: String,
     http: reqwest::Client,
     session_key: Arc<RwLock<Option<String>>>,
 }
@@ -15,16 +19,26 @@ impl QrzClient {
         Self {
             username,
             password,
+            base_url: QRZ_API_URL.to_string(),
             http: reqwest::Client::new(),
             session_key: Arc::new(RwLock::new(None)),
         }
     }
 
+    /// Client for another QRZ-compatible endpoint, such as a local stub
+    #[cfg(test)]
+    pub fn with_base_url(username: String, password: String, base_url: String) -> Self {
+        Self {
+            base_url,
+            ..Self::new(username, password)
+        }
+    }
+
     /// Login to QRZ and get session key
     async fn login(&self) -> Result<String, String> {
         let url = format!(
-            "https://xmldata.qrz.com/xml/current/?username={}&password={}",
-            self.username, self.password
+            "{}?username={}&password={}",
+            self.base_url, self.username, self.password
         );
 
         let response = self
@@ -85,13 +99,13 @@ impl QrzClient {
         *cached = None;
     }
 
-    /// Lookup callsign and return email if found
-    pub async fn lookup_email(&self, callsign: &str) -> Result<Option<String>, String> {
+    /// Fetch the QRZ record for a callsign as XML
+    async fn lookup(&self, callsign: &str) -> Result<String, String> {
         let session_key = self.get_session_key().await?;
 
         let url = format!(
-            "https://xmldata.qrz.com/xml/current/?s={}&callsign={}",
-            session_key, callsign
+            "{}?s={}&callsign={}",
+            self.base_url, session_key, callsign
         );
 
         let response = self
@@ -108,66 +122,68 @@ impl QrzClient {
 
         if text.contains("Session Timeout") || text.contains("Invalid session key") {
             self.clear_session().await;
-            return Box::pin(self.lookup_email(callsign)).await;
+            return Box::pin(self.lookup(callsign)).await;
         }
 
-        Ok(Self::extract_email(&text))
+        Ok(text)
+    }
+
+    /// Lookup callsign and return email if found
+    pub async fn lookup_email(&self, callsign: &str) -> Result<Option<String>, String> {
+        Ok(Self::extract_email(&self.lookup(callsign).await?))
     }
 
     fn extract_email(xml: &str) -> Option<String> {
-        if let Some(start) = xml.find("<email>") {
-            if let Some(end) = xml.find("</email>") {
-                let email = &xml[start + 7..end];
-                if !email.is_empty() {
-                    return Some(email.to_string());
-                }
-            }
-        }
-        None
+        Self::extract_field(xml, "email")
     }
 
     /// Lookup callsign and return first name if found
     pub async fn lookup_name(&self, callsign: &str) -> Result<Option<String>, String> {
-        let session_key = self.get_session_key().await?;
-
-        let url = format!(
-            "https://xmldata.qrz.com/xml/current/?s={}&callsign={}",
-            session_key, callsign
-        );
-
-        let response = self
-            .http
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| format!("QRZ lookup request failed: {}", e))?;
-
-        let text = response
-            .text()
-            .await
-            .map_err(|e| format!("QRZ lookup response read failed: {}", e))?;
+        Ok(Self::extract_fname(&self.lookup(callsign).await?))
+    }
 
-        if text.contains("Session Timeout") || text.contains("Invalid session key") {
-            self.clear_session().await;
-            return Box::pin(self.lookup_name(callsign)).await;
-        }
+    fn extract_fname(xml: &str) -> Option<String> {
+        Self::extract_field(xml, "fname")
+    }
 
-        Ok(Self::extract_fname(&text))
+    /// Lookup callsign and return email and full name, from a single request
+    pub async fn lookup_contact(&self, callsign: &str) -> Result<QrzContact, String> {
+        let xml = self.lookup(callsign).await?;
+        let name = [Self::extract_fname(&xml), Self::extract_field(&xml, "name")]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Ok(QrzContact {
+            email: Self::extract_email(&xml),
+            name: Some(name).filter(|name| !name.is_empty()),
+        })
     }
 
-    fn extract_fname(xml: &str) -> Option<String> {
-        if let Some(start) = xml.find("<fname>") {
-            if let Some(end) = xml[start..].find("</fname>") {
-                let fname = &xml[start + 7..start + end];
-                if !fname.is_empty() {
-                    return Some(fname.to_string());
-                }
-            }
+    /// Text of the first `<field>` element, if present and not empty
+    fn extract_field(xml: &str, field: &str) -> Option<String> {
+        let open = format!("<{}>", field);
+        let close = format!("</{}>", field);
+        let start = xml.find(&open)? + open.len();
+        let end = xml[start..].find(&close)?;
+        let value = &xml[start..start + end];
+        if value.is_empty() {
+            None
+        } else {
+            Some(value.to_string())
         }
-        None
     }
 }
 
+/// What a QRZ lookup tells us about an operator
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct QrzContact {
+    pub email: Option<String>,
+    /// First and last name
+    pub name: Option<String>,
+}
+
 /// Create QRZ client from environment variables, returns None if not configured
 pub fn create_client_from_env() -> Option<QrzClient> {
     let username = std::env::var("QRZ_USERNAME").ok()?;
@@ -179,3 +195,17 @@ pub fn create_client_from_env() -> Option<QrzClient> {
 
     Some(QrzClient::new(username, password))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_fields() {
+        let xml = "<QRZDatabase><Callsign><call>W1AW</call><fname>Hiram Percy</fname><name>Maxim</name><email></email></Callsign></QRZDatabase>";
+        assert_eq!(QrzClient::extract_fname(xml).as_deref(), Some("Hiram Percy"));
+        assert_eq!(QrzClient::extract_field(xml, "name").as_deref(), Some("Maxim"));
+        assert_eq!(QrzClient::extract_email(xml), None);
+        assert_eq!(QrzClient::extract_field(xml, "addr1"), None);
+    }
+}
@@ -0,0 +1,366 @@
+//! Per-IP rate limiting for public submission endpoints and admin login
+//!
+//! Each protected route has its own token bucket budget keyed by client IP.
+//! Failed admin logins additionally trigger an exponential lockout.
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Buckets idle for longer than this are dropped during pruning
+const IDLE_EXPIRY: Duration = Duration::from_secs(15 * 60);
+
+/// Prune stale entries once a map grows past this many keys
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Routes with their own request budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Route {
+    Login,
+    Attempts,
+    Submit,
+}
+
+/// Rate limiting settings, built from `Config`
+#[derive(Debug, Clone)]
+pub struct RateLimitSettings {
+    pub enabled: bool,
+    pub trusted_proxies: Vec<IpAddr>,
+    pub login_per_minute: u32,
+    pub attempts_per_minute: u32,
+    pub submit_per_minute: u32,
+    pub lockout_threshold: u32,
+    pub lockout_base: Duration,
+    pub lockout_max: Duration,
+}
+
+impl RateLimitSettings {
+    fn per_minute(&self, route: Route) -> u32 {
+        match route {
+            Route::Login => self.login_per_minute,
+            Route::Attempts => self.attempts_per_minute,
+            Route::Submit => self.submit_per_minute,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Debug)]
+struct LoginFailures {
+    count: u32,
+    locked_until: Option<Instant>,
+    updated: Instant,
+}
+
+/// Shared limiter state held in `AppState`
+pub struct RateLimiter {
+    settings: RateLimitSettings,
+    buckets: Mutex<HashMap<(Route, IpAddr), Bucket>>,
+    failures: Mutex<HashMap<IpAddr, LoginFailures>>,
+}
+
+impl RateLimiter {
+    pub fn new(settings: RateLimitSettings) -> Self {
+        Self {
+            settings,
+            buckets: Mutex::new(HashMap::new()),
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take one token from the route's bucket for this IP.
+    /// Returns the wait until a token is available when the budget is exhausted.
+    fn check(&self, route: Route, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        let capacity = self.settings.per_minute(route) as f64;
+        if capacity <= 0.0 {
+            return Ok(());
+        }
+        let refill_per_sec = capacity / 60.0;
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() > PRUNE_THRESHOLD {
+            buckets.retain(|_, b| now.duration_since(b.updated) < IDLE_EXPIRY);
+        }
+
+        let bucket = buckets.entry((route, ip)).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            let wait = (1.0 - bucket.tokens) / refill_per_sec;
+            Err(Duration::from_secs_f64(wait))
+        }
+    }
+
+    /// Remaining lockout for an IP after repeated failed logins
+    fn lockout_remaining(&self, ip: IpAddr, now: Instant) -> Option<Duration> {
+        let failures = self.failures.lock().unwrap();
+        failures
+            .get(&ip)
+            .and_then(|f| f.locked_until)
+            .filter(|until| *until > now)
+            .map(|until| until - now)
+    }
+
+    /// Record a failed login; locks the IP out once the threshold is reached,
+    /// doubling the lockout with every further failure. Failures idle for
+    /// `IDLE_EXPIRY`, with any lockout over, are forgotten.
+    fn record_failure(&self, ip: IpAddr, now: Instant) {
+        let mut failures = self.failures.lock().unwrap();
+        if failures.len() > PRUNE_THRESHOLD {
+            failures.retain(|_, f| {
+                now.duration_since(f.updated) < IDLE_EXPIRY
+                    || f.locked_until.is_some_and(|until| until > now)
+            });
+        }
+
+        let entry = failures.entry(ip).or_insert(LoginFailures {
+            count: 0,
+            locked_until: None,
+            updated: now,
+        });
+        let locked = entry.locked_until.is_some_and(|until| until > now);
+        if now.duration_since(entry.updated) >= IDLE_EXPIRY && !locked {
+            entry.count = 0;
+            entry.locked_until = None;
+        }
+        entry.count += 1;
+        entry.updated = now;
+
+        if entry.count >= self.settings.lockout_threshold {
+            let exponent = (entry.count - self.settings.lockout_threshold).min(16);
+            let lockout = self
+                .settings
+                .lockout_base
+                .saturating_mul(1u32 << exponent)
+                .min(self.settings.lockout_max);
+            entry.locked_until = Some(now + lockout);
+            tracing::warn!(
+                "Admin login locked out for {} after {} failures ({}s)",
+                ip,
+                entry.count,
+                lockout.as_secs()
+            );
+        }
+    }
+
+    /// Clear failure history after a successful login
+    fn record_success(&self, ip: IpAddr) {
+        self.failures.lock().unwrap().remove(&ip);
+    }
+}
+
+/// Determine the client IP, honoring `X-Forwarded-For` only when the
+/// connecting peer is a trusted proxy. Walks the header right to left and
+/// returns the first address that is not itself a trusted proxy.
+pub fn client_ip(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpAddr]) -> IpAddr {
+    if !trusted_proxies.contains(&peer) {
+        return peer;
+    }
+
+    let forwarded: Vec<IpAddr> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|s| s.trim().parse().ok())
+        .collect();
+
+    forwarded
+        .into_iter()
+        .rev()
+        .find(|ip| !trusted_proxies.contains(ip))
+        .unwrap_or(peer)
+}
+
+fn too_many_requests(retry_after: Duration) -> Response {
+    let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    let secs = secs.max(1);
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, secs.to_string())],
+        format!("Too many requests. Try again in {} seconds.", secs),
+    )
+        .into_response()
+}
+
+/// Middleware enforcing the per-route budget (and login lockout)
+pub async fn enforce(
+    State((state, route)): State<(Arc<crate::AppState>, Route)>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let limiter = &state.rate_limiter;
+    if !limiter.settings.enabled {
+        return next.run(request).await;
+    }
+
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ci| ci.0.ip())
+        .unwrap_or(IpAddr::from([127, 0, 0, 1]));
+    let ip = client_ip(peer, request.headers(), &limiter.settings.trusted_proxies);
+    let now = Instant::now();
+
+    if route == Route::Login {
+        if let Some(remaining) = limiter.lockout_remaining(ip, now) {
+            return too_many_requests(remaining);
+        }
+    }
+
+    if let Err(wait) = limiter.check(route, ip, now) {
+        tracing::warn!("Rate limit exceeded for {} on {:?}", ip, route);
+        return too_many_requests(wait);
+    }
+
+    let response = next.run(request).await;
+
+    if route == Route::Login {
+        match response.status() {
+            StatusCode::UNAUTHORIZED => limiter.record_failure(ip, Instant::now()),
+            s if s.is_success() => limiter.record_success(ip),
+            _ => {}
+        }
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> RateLimitSettings {
+        RateLimitSettings {
+            enabled: true,
+            trusted_proxies: vec![IpAddr::from([127, 0, 0, 1])],
+            login_per_minute: 3,
+            attempts_per_minute: 2,
+            submit_per_minute: 2,
+            lockout_threshold: 3,
+            lockout_base: Duration::from_secs(30),
+            lockout_max: Duration::from_secs(100),
+        }
+    }
+
+    #[test]
+    fn test_bucket_exhausts_and_refills() {
+        let limiter = RateLimiter::new(settings());
+        let ip = IpAddr::from([10, 0, 0, 1]);
+        let start = Instant::now();
+
+        assert!(limiter.check(Route::Submit, ip, start).is_ok());
+        assert!(limiter.check(Route::Submit, ip, start).is_ok());
+        let wait = limiter.check(Route::Submit, ip, start).unwrap_err();
+        assert!((wait.as_secs_f64() - 30.0).abs() < 0.01);
+
+        // Two per minute refills one token every 30 seconds
+        assert!(limiter
+            .check(Route::Submit, ip, start + Duration::from_secs(30))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_budgets_are_per_route_and_ip() {
+        let limiter = RateLimiter::new(settings());
+        let a = IpAddr::from([10, 0, 0, 1]);
+        let b = IpAddr::from([10, 0, 0, 2]);
+        let now = Instant::now();
+
+        assert!(limiter.check(Route::Attempts, a, now).is_ok());
+        assert!(limiter.check(Route::Attempts, a, now).is_ok());
+        assert!(limiter.check(Route::Attempts, a, now).is_err());
+        assert!(limiter.check(Route::Submit, a, now).is_ok());
+        assert!(limiter.check(Route::Attempts, b, now).is_ok());
+    }
+
+    #[test]
+    fn test_login_lockout_escalates() {
+        let limiter = RateLimiter::new(settings());
+        let ip = IpAddr::from([10, 0, 0, 1]);
+        let now = Instant::now();
+
+        limiter.record_failure(ip, now);
+        limiter.record_failure(ip, now);
+        assert!(limiter.lockout_remaining(ip, now).is_none());
+
+        limiter.record_failure(ip, now);
+        assert_eq!(limiter.lockout_remaining(ip, now), Some(Duration::from_secs(30)));
+
+        limiter.record_failure(ip, now);
+        assert_eq!(limiter.lockout_remaining(ip, now), Some(Duration::from_secs(60)));
+
+        // Capped at lockout_max
+        limiter.record_failure(ip, now);
+        assert_eq!(limiter.lockout_remaining(ip, now), Some(Duration::from_secs(100)));
+
+        limiter.record_success(ip);
+        assert!(limiter.lockout_remaining(ip, now).is_none());
+    }
+
+    #[test]
+    fn test_login_failures_expire_when_idle() {
+        let limiter = RateLimiter::new(settings());
+        let ip = IpAddr::from([10, 0, 0, 1]);
+        let now = Instant::now();
+
+        // Typos spread out over time never add up to a lockout
+        for day in 0..5 {
+            let later = now + Duration::from_secs(day * 24 * 60 * 60);
+            limiter.record_failure(ip, later);
+            assert!(limiter.lockout_remaining(ip, later).is_none());
+        }
+
+        // A lockout that has run out does not escalate the next one
+        let later = now + Duration::from_secs(10 * 24 * 60 * 60);
+        for _ in 0..3 {
+            limiter.record_failure(ip, later);
+        }
+        assert_eq!(limiter.lockout_remaining(ip, later), Some(Duration::from_secs(30)));
+        let idle = later + IDLE_EXPIRY;
+        limiter.record_failure(ip, idle);
+        assert!(limiter.lockout_remaining(ip, idle).is_none());
+    }
+
+    #[test]
+    fn test_client_ip_ignores_forwarded_from_untrusted_peer() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+        let peer = IpAddr::from([203, 0, 113, 9]);
+
+        assert_eq!(client_ip(peer, &headers, &settings().trusted_proxies), peer);
+    }
+
+    #[test]
+    fn test_client_ip_uses_rightmost_untrusted_forwarded() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "6.6.6.6, 1.2.3.4, 127.0.0.1".parse().unwrap());
+        let peer = IpAddr::from([127, 0, 0, 1]);
+
+        assert_eq!(
+            client_ip(peer, &headers, &settings().trusted_proxies),
+            IpAddr::from([1, 2, 3, 4])
+        );
+    }
+}
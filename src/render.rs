@@ -0,0 +1,253 @@
+//! Server-side PDF and PNG rendering of certificate SVGs
+//!
+//! Text is laid out with the DejaVu fonts bundled from `fonts/`, so output
+//! does not depend on what the host has installed. Rendered files are cached
+//! in memory by certificate number, template version and format; an entry is
+//! rendered again when the certificate's SVG has changed since, as it does
+//! after a reissue. Template previews are never cached.
+
+use axum::body::Bytes;
+use resvg::tiny_skia;
+use resvg::usvg::{self, fontdb, PostProcessingSteps, TreeParsing, TreePostProc};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+const FONTS: [&[u8]; 6] = [
+    include_bytes!("../fonts/DejaVuSerif.ttf"),
+    include_bytes!("../fonts/DejaVuSerif-Bold.ttf"),
+    include_bytes!("../fonts/DejaVuSerif-Italic.ttf"),
+    include_bytes!("../fonts/DejaVuSansMono.ttf"),
+    include_bytes!("../fonts/DejaVuSansMono-Bold.ttf"),
+    include_bytes!("../fonts/DejaVuSans.ttf"),
+];
+
+/// PNGs are rendered at twice the SVG's nominal size
+const PNG_SCALE: f32 = 2.0;
+
+/// The 800px-wide certificate fills 11 inches, landscape US Letter width
+const PDF_DPI: f32 = 800.0 / 11.0;
+
+/// Rendered certificates kept in memory before the cache is cleared
+const CACHE_CAPACITY: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RenderFormat {
+    Pdf,
+    Png,
+}
+
+impl RenderFormat {
+    pub fn parse(format: &str) -> Option<Self> {
+        match format.to_ascii_lowercase().as_str() {
+            "pdf" => Some(RenderFormat::Pdf),
+            "png" => Some(RenderFormat::Png),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            RenderFormat::Pdf => "application/pdf",
+            RenderFormat::Png => "image/png",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            RenderFormat::Pdf => "pdf",
+            RenderFormat::Png => "png",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    certificate_number: i32,
+    template_id: Option<String>,
+    template_version: i32,
+    format: RenderFormat,
+}
+
+struct CachedRender {
+    svg_sha256: Vec<u8>,
+    bytes: Bytes,
+}
+
+/// Renders certificates to PDF or PNG with the bundled fonts
+pub struct CertificateRenderer {
+    fontdb: Arc<fontdb::Database>,
+    cache: Mutex<HashMap<CacheKey, CachedRender>>,
+}
+
+impl CertificateRenderer {
+    pub fn new() -> Self {
+        let mut fontdb = fontdb::Database::new();
+        for font in FONTS {
+            fontdb.load_font_data(font.to_vec());
+        }
+        // Georgia and other named families fall back to these generics
+        fontdb.set_serif_family("DejaVu Serif");
+        fontdb.set_sans_serif_family("DejaVu Sans");
+        fontdb.set_monospace_family("DejaVu Sans Mono");
+
+        CertificateRenderer {
+            fontdb: Arc::new(fontdb),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Render a certificate's SVG, reusing the cached file while the SVG is unchanged
+    pub async fn render(
+        &self,
+        certificate_number: i32,
+        // None for the built-in template
+        template_id: Option<&str>,
+        template_version: i32,
+        format: RenderFormat,
+        svg: String,
+    ) -> Result<Bytes, String> {
+        let key = CacheKey {
+            certificate_number,
+            template_id: template_id.map(str::to_string),
+            template_version,
+            format,
+        };
+        let svg_sha256 = Sha256::digest(svg.as_bytes()).to_vec();
+
+        if let Some(cached) = self.cache.lock().unwrap().get(&key) {
+            if cached.svg_sha256 == svg_sha256 {
+                return Ok(cached.bytes.clone());
+            }
+        }
+
+        let bytes = self.render_uncached(format, svg).await?;
+
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= CACHE_CAPACITY && !cache.contains_key(&key) {
+            cache.clear();
+        }
+        cache.insert(
+            key,
+            CachedRender {
+                svg_sha256,
+                bytes: bytes.clone(),
+            },
+        );
+
+        Ok(bytes)
+    }
+
+    /// Render an SVG without touching the cache, for template previews
+    pub async fn render_uncached(&self, format: RenderFormat, svg: String) -> Result<Bytes, String> {
+        // Rasterizing takes long enough to keep off the async workers
+        let fontdb = self.fontdb.clone();
+        let bytes = tokio::task::spawn_blocking(move || render_svg(&svg, format, &fontdb))
+            .await
+            .map_err(|e| e.to_string())??;
+        Ok(Bytes::from(bytes))
+    }
+}
+
+/// Check that an SVG parses, without laying out text or drawing it
+pub fn parse_svg(svg: &str) -> Result<(), String> {
+    usvg::Tree::from_str(svg, &usvg::Options::default())
+        .map(|_| ())
+        .map_err(|e| format!("Invalid certificate SVG: {}", e))
+}
+
+fn render_svg(svg: &str, format: RenderFormat, fontdb: &fontdb::Database) -> Result<Vec<u8>, String> {
+    let options = usvg::Options {
+        font_family: "DejaVu Serif".to_string(),
+        ..usvg::Options::default()
+    };
+    let mut tree = usvg::Tree::from_str(svg, &options).map_err(|e| format!("Invalid certificate SVG: {}", e))?;
+    tree.postprocess(PostProcessingSteps::default(), fontdb);
+
+    match format {
+        RenderFormat::Pdf => Ok(svg2pdf::convert_tree(
+            &tree,
+            svg2pdf::Options {
+                dpi: PDF_DPI,
+                ..svg2pdf::Options::default()
+            },
+        )),
+        RenderFormat::Png => {
+            let size = tree.size.to_int_size().scale_by(PNG_SCALE).ok_or("Certificate is too large to render")?;
+            let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).ok_or("Certificate is too large to render")?;
+            resvg::render(
+                &tree,
+                tiny_skia::Transform::from_scale(PNG_SCALE, PNG_SCALE),
+                &mut pixmap.as_mut(),
+            );
+            pixmap.encode_png().map_err(|e| e.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVG: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 800 600" width="800" height="600">
+  <rect width="800" height="600" fill="#FFFBEB"/>
+  <text x="400" y="300" text-anchor="middle" font-family="Georgia, serif" font-size="44">W1AW ★</text>
+</svg>"##;
+
+    #[test]
+    fn test_format_parse() {
+        assert_eq!(RenderFormat::parse("PDF"), Some(RenderFormat::Pdf));
+        assert_eq!(RenderFormat::parse("png"), Some(RenderFormat::Png));
+        assert_eq!(RenderFormat::parse("gif"), None);
+    }
+
+    #[test]
+    fn test_render_png_and_pdf() {
+        let renderer = CertificateRenderer::new();
+
+        let png = render_svg(SVG, RenderFormat::Png, &renderer.fontdb).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        // IHDR: 1600x1200
+        assert_eq!(u32::from_be_bytes(png[16..20].try_into().unwrap()), 1600);
+        assert_eq!(u32::from_be_bytes(png[20..24].try_into().unwrap()), 1200);
+
+        // The bundled fonts draw the text, including the star from the fallback font
+        let without_star = render_svg(&SVG.replace(" ★", ""), RenderFormat::Png, &renderer.fontdb).unwrap();
+        let blank = render_svg(&SVG.replace("W1AW ★", ""), RenderFormat::Png, &renderer.fontdb).unwrap();
+        assert_ne!(png, without_star);
+        assert_ne!(without_star, blank);
+
+        let pdf = render_svg(SVG, RenderFormat::Pdf, &renderer.fontdb).unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+
+        assert!(parse_svg(SVG).is_ok());
+        assert!(parse_svg("<svg><g></svg>").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_render_is_cached_until_svg_changes() {
+        let renderer = CertificateRenderer::new();
+
+        let first = renderer.render(1, None, 1, RenderFormat::Png, SVG.to_string()).await.unwrap();
+        let again = renderer.render(1, None, 1, RenderFormat::Png, SVG.to_string()).await.unwrap();
+        assert_eq!(first.as_ptr(), again.as_ptr());
+
+        let changed = renderer
+            .render(1, None, 1, RenderFormat::Png, SVG.replace("W1AW", "K1ABC"))
+            .await
+            .unwrap();
+        assert_ne!(first.as_ptr(), changed.as_ptr());
+
+        let next_version = renderer
+            .render(1, None, 2, RenderFormat::Png, SVG.replace("W1AW", "K1ABC"))
+            .await
+            .unwrap();
+        assert_ne!(changed.as_ptr(), next_version.as_ptr());
+
+        let other_template = renderer
+            .render(1, Some("general"), 2, RenderFormat::Png, SVG.replace("W1AW", "K1ABC"))
+            .await
+            .unwrap();
+        assert_ne!(next_version.as_ptr(), other_template.as_ptr());
+    }
+}
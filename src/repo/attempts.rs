@@ -0,0 +1,559 @@
+use crate::admin::{
+    ApprovedAttempt, AttemptHistory, AttemptListItem, QueueItem, RecentActivity, RecentAttempt,
+    SearchResult,
+};
+use super::CertificateRepo;
+use crate::db::{query, Db, Filter, Query};
+use crate::{Attempt, LeaderboardEntry, SpeedStats, StatsResponse};
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+
+/// A graded attempt to record
+#[derive(Debug)]
+pub struct NewAttempt<'a> {
+    pub id: &'a str,
+    pub callsign: &'a str,
+    pub test_speed: i32,
+    pub questions_correct: i32,
+    pub copy_chars: i32,
+    pub passed: bool,
+    pub created_at: DateTime<Utc>,
+    pub validation_status: Option<&'a str>,
+    pub audio_progress: Option<f32>,
+    pub test_id: Option<&'a str>,
+    pub copy_text: Option<&'a str>,
+    pub consecutive_correct: Option<i32>,
+    /// The test revision the attempt was graded against
+    pub revision_id: Option<&'a str>,
+    /// The exam session the attempt was submitted in
+    pub session_id: Option<&'a str>,
+}
+
+impl NewAttempt<'_> {
+    fn insert_query(&self) -> Query {
+        query(
+            "INSERT INTO attempts (id, callsign, test_speed, questions_correct, copy_chars, passed, created_at, validation_status, audio_progress, test_id, copy_text, consecutive_correct, revision_id, session_id)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(self.id)
+        .bind(self.callsign)
+        .bind(self.test_speed)
+        .bind(self.questions_correct)
+        .bind(self.copy_chars)
+        .bind(self.passed)
+        .bind(self.created_at)
+        .bind(self.validation_status)
+        .bind(self.audio_progress)
+        .bind(self.test_id)
+        .bind(self.copy_text)
+        .bind(self.consecutive_correct)
+        .bind(self.revision_id)
+        .bind(self.session_id)
+    }
+}
+
+/// An approved certificate on the roster
+#[derive(Debug, Clone, PartialEq, FromRow)]
+pub struct RosterRow {
+    pub callsign: String,
+    pub test_speed: i32,
+    pub certificate_number: Option<i32>,
+    pub validated_at: Option<DateTime<Utc>>,
+}
+
+/// Filters for the admin attempt list
+#[derive(Debug, Default)]
+pub struct AttemptFilter {
+    pub passed: Option<bool>,
+    /// Substring of the callsign (matched upper-case)
+    pub callsign: Option<String>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+}
+
+impl AttemptFilter {
+    fn to_filter(&self) -> Filter {
+        Filter::new()
+            .and_some("passed = ?", self.passed)
+            .and_some(
+                "callsign LIKE ?",
+                self.callsign
+                    .as_ref()
+                    .map(|c| format!("%{}%", c.to_uppercase())),
+            )
+            .and_some("created_at >= ?", self.created_from)
+            .and_some("created_at < ?", self.created_before)
+    }
+}
+
+const ATTEMPT_COLUMNS: &str =
+    "id, callsign, test_speed, questions_correct, copy_chars, passed, created_at";
+
+/// Passed and approved by an admin
+const APPROVED_PASS: &str = "passed = TRUE AND validation_status = 'approved'";
+
+/// Exam attempts and their validation workflow
+pub struct AttemptRepo<'a> {
+    db: &'a Db,
+}
+
+impl<'a> AttemptRepo<'a> {
+    pub fn new(db: &'a Db) -> Self {
+        AttemptRepo { db }
+    }
+
+    pub async fn insert(&self, attempt: &NewAttempt<'_>) -> Result<(), sqlx::Error> {
+        self.db.execute(attempt.insert_query()).await?;
+        Ok(())
+    }
+
+    /// Record an attempt and mark its exam session submitted, if it has one,
+    /// in one transaction. Returns false, recording nothing, if the session
+    /// was already submitted.
+    pub async fn insert_submitted(&self, attempt: &NewAttempt<'_>) -> Result<bool, sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+        if let Some(session_id) = attempt.session_id {
+            let updated = tx
+                .execute(
+                    query("UPDATE exam_sessions SET submitted_at = ? WHERE id = ? AND submitted_at IS NULL")
+                        .bind(attempt.created_at)
+                        .bind(session_id),
+                )
+                .await?;
+            if updated == 0 {
+                return Ok(false);
+            }
+        }
+        tx.execute(attempt.insert_query()).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Whether the callsign has attempted the test at or after `since`
+    pub async fn has_attempt_since(
+        &self,
+        callsign: &str,
+        since: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error> {
+        let row: Option<(String,)> = self
+            .db
+            .fetch_optional(
+                query("SELECT id FROM attempts WHERE callsign = ? AND created_at >= ? LIMIT 1")
+                    .bind(callsign)
+                    .bind(since),
+            )
+            .await?;
+        Ok(row.is_some())
+    }
+
+    /// Whether the callsign has an attempt at this speed awaiting validation or already approved
+    pub async fn has_open_attempt(&self, callsign: &str, test_speed: i32) -> Result<bool, sqlx::Error> {
+        let row: Option<(String,)> = self
+            .db
+            .fetch_optional(
+                query(
+                    "SELECT id FROM attempts
+                     WHERE callsign = ? AND test_speed = ? AND validation_status IN ('pending', 'approved')
+                     LIMIT 1",
+                )
+                .bind(callsign)
+                .bind(test_speed),
+            )
+            .await?;
+        Ok(row.is_some())
+    }
+
+    /// Public attempt list, newest first
+    pub async fn list(
+        &self,
+        speed: Option<i32>,
+        passed_only: bool,
+        limit: i32,
+        offset: i32,
+    ) -> Result<Vec<Attempt>, sqlx::Error> {
+        let filter = Filter::new()
+            .and_some("test_speed = ?", speed)
+            .and_if(passed_only, "passed = TRUE");
+        let q = filter.query(&format!(
+            "SELECT {} FROM attempts {{where}} ORDER BY created_at DESC LIMIT ? OFFSET ?",
+            ATTEMPT_COLUMNS
+        ));
+        self.db.fetch_all(q.bind(limit).bind(offset)).await
+    }
+
+    pub async fn list_for_callsign(
+        &self,
+        callsign: &str,
+        speed: Option<i32>,
+    ) -> Result<Vec<Attempt>, sqlx::Error> {
+        let filter = Filter::new()
+            .and("callsign = ?", callsign)
+            .and_some("test_speed = ?", speed);
+        let q = filter.query(&format!(
+            "SELECT {} FROM attempts {{where}} ORDER BY created_at DESC",
+            ATTEMPT_COLUMNS
+        ));
+        self.db.fetch_all(q).await
+    }
+
+    /// Operators ranked by highest approved speed, then by who passed first
+    pub async fn leaderboard(&self, limit: i32) -> Result<Vec<LeaderboardEntry>, sqlx::Error> {
+        let highest =
+            format!("MAX(CASE WHEN {} THEN test_speed ELSE 0 END)", APPROVED_PASS);
+        self.db
+            .fetch_all(
+                query(format!(
+                    r#"
+                    SELECT
+                        callsign,
+                        {highest} as highest_speed_passed,
+                        CAST(COUNT(*) AS INTEGER) as total_attempts,
+                        CAST(SUM(CASE WHEN {pass} THEN 1 ELSE 0 END) AS INTEGER) as total_passes,
+                        MIN(CASE WHEN {pass} THEN created_at ELSE NULL END) as first_passed_at
+                    FROM attempts
+                    GROUP BY callsign
+                    HAVING {highest} > 0
+                    ORDER BY highest_speed_passed DESC, first_passed_at ASC
+                    LIMIT ?
+                    "#,
+                    highest = highest,
+                    pass = APPROVED_PASS
+                ))
+                .bind(limit),
+            )
+            .await
+    }
+
+    /// Public totals and per-speed breakdown
+    pub async fn stats(&self) -> Result<StatsResponse, sqlx::Error> {
+        let total_attempts: (i64,) = self.db.fetch_one(query("SELECT COUNT(*) FROM attempts")).await?;
+        let total_passes: (i64,) = self
+            .db
+            .fetch_one(query(format!(
+                "SELECT COUNT(*) FROM attempts WHERE {}",
+                APPROVED_PASS
+            )))
+            .await?;
+        let unique_callsigns: (i64,) = self
+            .db
+            .fetch_one(query("SELECT COUNT(DISTINCT callsign) FROM attempts"))
+            .await?;
+        let attempts_by_speed: Vec<SpeedStats> = self
+            .db
+            .fetch_all(query(format!(
+                r#"
+                SELECT
+                    test_speed,
+                    COUNT(*) as attempts,
+                    CAST(SUM(CASE WHEN {} THEN 1 ELSE 0 END) AS BIGINT) as passes
+                FROM attempts
+                GROUP BY test_speed
+                ORDER BY test_speed
+                "#,
+                APPROVED_PASS
+            )))
+            .await?;
+
+        Ok(StatsResponse {
+            total_attempts: total_attempts.0,
+            total_passes: total_passes.0,
+            unique_callsigns: unique_callsigns.0,
+            attempts_by_speed,
+        })
+    }
+
+    /// Approved certificates, every class of every member, in certificate order
+    pub async fn roster(&self) -> Result<Vec<RosterRow>, sqlx::Error> {
+        self.db
+            .fetch_all(query(
+                "SELECT callsign, test_speed, certificate_number, validated_at
+                 FROM attempts
+                 WHERE validation_status = 'approved'
+                 ORDER BY certificate_number ASC",
+            ))
+            .await
+    }
+
+    pub async fn count_by_status(&self, status: &str) -> Result<i64, sqlx::Error> {
+        let row: (i64,) = self
+            .db
+            .fetch_one(query("SELECT COUNT(*) FROM attempts WHERE validation_status = ?").bind(status))
+            .await?;
+        Ok(row.0)
+    }
+
+    /// Approvals with `from <= validated_at < until`
+    pub async fn count_approved_between(
+        &self,
+        from: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<i64, sqlx::Error> {
+        let row: (i64,) = self
+            .db
+            .fetch_one(
+                query(
+                    "SELECT COUNT(*) FROM attempts
+                     WHERE validation_status = 'approved' AND validated_at >= ? AND validated_at < ?",
+                )
+                .bind(from)
+                .bind(until),
+            )
+            .await?;
+        Ok(row.0)
+    }
+
+    /// Latest approvals and rejections
+    pub async fn recent_activity(&self, limit: i32) -> Result<Vec<RecentActivity>, sqlx::Error> {
+        self.db
+            .fetch_all(
+                query(
+                    "SELECT id, callsign, validation_status as action, validated_at as created_at
+                     FROM attempts
+                     WHERE validation_status IN ('approved', 'rejected')
+                     ORDER BY validated_at DESC
+                     LIMIT ?",
+                )
+                .bind(limit),
+            )
+            .await
+    }
+
+    /// Latest attempts, passed or not
+    pub async fn recent(&self, limit: i32) -> Result<Vec<RecentAttempt>, sqlx::Error> {
+        self.db
+            .fetch_all(
+                query(
+                    "SELECT id, callsign, questions_correct, consecutive_correct, passed, created_at
+                     FROM attempts
+                     ORDER BY created_at DESC
+                     LIMIT ?",
+                )
+                .bind(limit),
+            )
+            .await
+    }
+
+    /// Attempts awaiting validation, oldest first
+    pub async fn pending_queue(&self) -> Result<Vec<QueueItem>, sqlx::Error> {
+        self.db
+            .fetch_all(query(
+                "SELECT id, callsign, questions_correct, copy_chars, created_at
+                 FROM attempts
+                 WHERE validation_status = 'pending'
+                 ORDER BY created_at ASC",
+            ))
+            .await
+    }
+
+    pub async fn history(&self, callsign: &str) -> Result<Vec<AttemptHistory>, sqlx::Error> {
+        self.db
+            .fetch_all(
+                query(
+                    "SELECT id, questions_correct, copy_chars, passed, validation_status, created_at
+                     FROM attempts
+                     WHERE callsign = ?
+                     ORDER BY created_at DESC",
+                )
+                .bind(callsign),
+            )
+            .await
+    }
+
+    pub async fn callsign(&self, id: &str) -> Result<Option<String>, sqlx::Error> {
+        let row: Option<(String,)> = self
+            .db
+            .fetch_optional(query("SELECT callsign FROM attempts WHERE id = ?").bind(id))
+            .await?;
+        Ok(row.map(|r| r.0))
+    }
+
+    /// Approve a pending attempt under the next certificate number.
+    /// Returns None if the attempt does not exist or is not pending.
+    pub async fn approve(
+        &self,
+        id: &str,
+        email: Option<&str>,
+        name: Option<&str>,
+        validated_at: DateTime<Utc>,
+    ) -> Result<Option<i32>, sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+
+        // Writing first takes SQLite's write lock before anything is read,
+        // so concurrent approvals queue here instead of racing
+        let updated = tx
+            .execute(
+                query(
+                    "UPDATE attempts SET validation_status = 'approved', validated_at = ?, email = ?, name = ?
+                     WHERE id = ? AND validation_status = 'pending'",
+                )
+                .bind(validated_at)
+                .bind(email)
+                .bind(name)
+                .bind(id),
+            )
+            .await?;
+        if updated == 0 {
+            return Ok(None);
+        }
+
+        let number = CertificateRepo::allocate_in(&mut tx, id, validated_at).await?;
+        tx.execute(
+            query("UPDATE attempts SET certificate_number = ? WHERE id = ?")
+                .bind(number)
+                .bind(id),
+        )
+        .await?;
+
+        tx.commit().await?;
+        Ok(Some(number))
+    }
+
+    /// Reject a pending attempt. Returns false if it was not pending.
+    pub async fn reject(
+        &self,
+        id: &str,
+        note: Option<&str>,
+        validated_at: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error> {
+        let updated = self
+            .db
+            .execute(
+                query(
+                    "UPDATE attempts SET validation_status = 'rejected', validated_at = ?, admin_note = ?
+                     WHERE id = ? AND validation_status = 'pending'",
+                )
+                .bind(validated_at)
+                .bind(note)
+                .bind(id),
+            )
+            .await?;
+        Ok(updated > 0)
+    }
+
+    /// A page of approved members (newest certificate first) and the total count
+    pub async fn approved(
+        &self,
+        reached_out: Option<bool>,
+        limit: i32,
+        offset: i32,
+    ) -> Result<(Vec<ApprovedAttempt>, i64), sqlx::Error> {
+        let filter = if reached_out.is_some() {
+            "validation_status = 'approved' AND reached_out = ?"
+        } else {
+            "validation_status = 'approved'"
+        };
+
+        let mut items_query = query(format!(
+            "SELECT id, callsign, certificate_number, validated_at, email, reached_out
+             FROM attempts
+             WHERE {}
+             ORDER BY certificate_number DESC
+             LIMIT ? OFFSET ?",
+            filter
+        ));
+        let mut count_query = query(format!("SELECT COUNT(*) FROM attempts WHERE {}", filter));
+        if let Some(reached_out) = reached_out {
+            items_query = items_query.bind(reached_out);
+            count_query = count_query.bind(reached_out);
+        }
+
+        let items = self.db.fetch_all(items_query.bind(limit).bind(offset)).await?;
+        let total: (i64,) = self.db.fetch_one(count_query).await?;
+        Ok((items, total.0))
+    }
+
+    pub async fn mark_reached_out(&self, ids: &[String]) -> Result<u64, sqlx::Error> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        let placeholders = vec!["?"; ids.len()].join(", ");
+        let mut q = query(format!(
+            "UPDATE attempts SET reached_out = TRUE WHERE id IN ({})",
+            placeholders
+        ));
+        for id in ids {
+            q = q.bind(id);
+        }
+        self.db.execute(q).await
+    }
+
+    /// Attempts whose callsign contains `term` (case-insensitive)
+    pub async fn search(&self, term: &str) -> Result<Vec<SearchResult>, sqlx::Error> {
+        self.db
+            .fetch_all(
+                query(
+                    "SELECT id, callsign, questions_correct, copy_chars, passed, validation_status, certificate_number, admin_note, created_at
+                     FROM attempts
+                     WHERE callsign LIKE ?
+                     ORDER BY callsign, created_at DESC
+                     LIMIT 100",
+                )
+                .bind(format!("%{}%", term.to_uppercase())),
+            )
+            .await
+    }
+
+    /// A page of attempts matching `filter`, newest first, and the total match count
+    pub async fn list_filtered(
+        &self,
+        filter: &AttemptFilter,
+        limit: i32,
+        offset: i32,
+    ) -> Result<(i64, Vec<AttemptListItem>), sqlx::Error> {
+        let filter = filter.to_filter();
+        let count_query = filter.query("SELECT COUNT(*) FROM attempts {where}");
+        let items_query = filter.query(
+            "SELECT id, callsign, test_speed, questions_correct, copy_chars, consecutive_correct, passed, validation_status, created_at, copy_text, revision_id, session_id
+             FROM attempts {where} ORDER BY created_at DESC LIMIT ? OFFSET ?",
+        );
+
+        let total: (i64,) = self.db.fetch_one(count_query).await?;
+        let items = self.db.fetch_all(items_query.bind(limit).bind(offset)).await?;
+        Ok((total.0, items))
+    }
+
+    /// Speeds of the classes a callsign holds approved certificates for, slowest first
+    pub async fn approved_speeds(&self, callsign: &str) -> Result<Vec<i32>, sqlx::Error> {
+        let rows: Vec<(i32,)> = self
+            .db
+            .fetch_all(
+                query(
+                    "SELECT DISTINCT test_speed FROM attempts
+                     WHERE callsign = ? AND validation_status = 'approved'
+                     ORDER BY test_speed",
+                )
+                .bind(callsign),
+            )
+            .await?;
+        Ok(rows.into_iter().map(|(speed,)| speed).collect())
+    }
+
+    /// (callsign, certificate number, email) of an approved member
+    pub async fn approved_member(
+        &self,
+        id: &str,
+    ) -> Result<Option<(String, Option<i32>, Option<String>)>, sqlx::Error> {
+        self.db
+            .fetch_optional(
+                query(
+                    "SELECT callsign, certificate_number, email FROM attempts WHERE id = ? AND validation_status = 'approved'",
+                )
+                .bind(id),
+            )
+            .await
+    }
+
+    /// A passed and approved attempt, for certificate rendering
+    pub async fn find_certified(&self, id: &str) -> Result<Option<Attempt>, sqlx::Error> {
+        self.db
+            .fetch_optional(
+                query(format!(
+                    "SELECT {}, validation_status, certificate_number, validated_at, admin_note, test_id, name
+                     FROM attempts WHERE id = ? AND {}",
+                    ATTEMPT_COLUMNS, APPROVED_PASS
+                ))
+                .bind(id),
+            )
+            .await
+    }
+}
@@ -0,0 +1,509 @@
+use crate::db::{query, Backend, Db, Tx};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use uuid::Uuid;
+
+/// How `repair` brings an approved attempt back in line with the ledger
+#[derive(Debug, Clone, PartialEq)]
+pub enum CertificateFix {
+    /// The attempt holds a free number the ledger never recorded: record it
+    Record(i32),
+    /// The ledger gives the attempt a different number: the ledger wins
+    Restore(i32),
+    /// The attempt has no number, or one the ledger gave to another attempt:
+    /// allocate a fresh one
+    Renumber,
+}
+
+/// An approved or revoked attempt whose certificate number disagrees with the ledger
+#[derive(Debug, Clone, PartialEq)]
+pub struct CertificateProblem {
+    pub attempt_id: String,
+    pub callsign: String,
+    pub certificate_number: Option<i32>,
+    pub fix: CertificateFix,
+}
+
+/// Consistency report for issued certificate numbers
+#[derive(Debug, Default, PartialEq)]
+pub struct CertificateAudit {
+    /// Numbers held by more than one issued certificate, with their callsigns
+    pub duplicates: Vec<(i32, Vec<String>)>,
+    /// Numbers below the highest issued one that no certificate holds,
+    /// revoked ones included
+    pub gaps: Vec<i32>,
+    pub problems: Vec<CertificateProblem>,
+}
+
+impl CertificateAudit {
+    pub fn is_clean(&self) -> bool {
+        self.duplicates.is_empty() && self.gaps.is_empty() && self.problems.is_empty()
+    }
+}
+
+/// A certificate number changed by `repair`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Renumbered {
+    pub attempt_id: String,
+    pub callsign: String,
+    pub old: Option<i32>,
+    pub new: i32,
+}
+
+/// A revocation, reinstatement or reissue of a certificate
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct CertificateEvent {
+    pub id: String,
+    pub attempt_id: String,
+    pub certificate_number: Option<i32>,
+    /// `revoked`, `reinstated` or `reissued`
+    pub action: String,
+    pub reason: Option<String>,
+    pub old_callsign: Option<String>,
+    pub new_callsign: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An issued certificate as looked up by its number, for public verification
+#[derive(Debug, Clone, FromRow)]
+pub struct IssuedCertificate {
+    pub attempt_id: String,
+    pub certificate_number: i32,
+    pub callsign: String,
+    /// The attempt date, printed on the certificate as the issue date
+    pub created_at: DateTime<Utc>,
+    pub validated_at: Option<DateTime<Utc>>,
+    pub test_speed: i32,
+    pub test_id: Option<String>,
+    pub test_title: Option<String>,
+    /// `approved` or `revoked`
+    pub validation_status: String,
+}
+
+/// Why a reissue did not happen
+#[derive(Debug, PartialEq)]
+pub enum ReissueError {
+    /// The attempt does not exist or is not approved
+    NotApproved,
+    /// The new callsign already holds an approved certificate for the same class
+    CallsignCertified,
+}
+
+/// The ledger of issued certificate numbers (`certificate_numbers`)
+pub struct CertificateRepo<'a> {
+    db: &'a Db,
+}
+
+impl<'a> CertificateRepo<'a> {
+    pub fn new(db: &'a Db) -> Self {
+        CertificateRepo { db }
+    }
+
+    /// Take the next certificate number for an attempt inside `tx`.
+    ///
+    /// The number is `MAX(number) + 1`, so numbers stay gap-free as long as
+    /// the transaction either commits or rolls back as a whole. Callers must
+    /// already hold a write lock on SQLite (their first statement should be a
+    /// write) and must not await anything slow, such as a QRZ lookup, while
+    /// the transaction is open.
+    pub async fn allocate_in(
+        tx: &mut Tx,
+        attempt_id: &str,
+        at: DateTime<Utc>,
+    ) -> Result<i32, sqlx::Error> {
+        if tx.backend() == Backend::Postgres {
+            // SQLite serializes writers already; PostgreSQL needs the lock
+            tx.execute(query("LOCK TABLE certificate_numbers IN EXCLUSIVE MODE"))
+                .await?;
+        }
+
+        let row: (i32,) = tx
+            .fetch_one(
+                query(
+                    "INSERT INTO certificate_numbers (number, attempt_id, allocated_at)
+                     SELECT COALESCE(MAX(number), 0) + 1, ?, ? FROM certificate_numbers
+                     RETURNING number",
+                )
+                .bind(attempt_id)
+                .bind(at),
+            )
+            .await?;
+        Ok(row.0)
+    }
+
+    /// Revoke an approved certificate. The attempt keeps its number and the
+    /// ledger keeps its row, so the number is retired rather than reused.
+    /// Returns None if the attempt is not approved.
+    pub async fn revoke(
+        &self,
+        attempt_id: &str,
+        reason: &str,
+        at: DateTime<Utc>,
+    ) -> Result<Option<CertificateEvent>, sqlx::Error> {
+        self.change_status(attempt_id, "approved", "revoked", Some(reason), at)
+            .await
+    }
+
+    /// Restore a revoked certificate under its original number.
+    /// Returns None if the attempt is not revoked.
+    pub async fn reinstate(
+        &self,
+        attempt_id: &str,
+        reason: Option<&str>,
+        at: DateTime<Utc>,
+    ) -> Result<Option<CertificateEvent>, sqlx::Error> {
+        self.change_status(attempt_id, "revoked", "approved", reason, at)
+            .await
+    }
+
+    async fn change_status(
+        &self,
+        attempt_id: &str,
+        from: &str,
+        to: &str,
+        reason: Option<&str>,
+        at: DateTime<Utc>,
+    ) -> Result<Option<CertificateEvent>, sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+
+        let updated = tx
+            .execute(
+                query("UPDATE attempts SET validation_status = ? WHERE id = ? AND validation_status = ?")
+                    .bind(to)
+                    .bind(attempt_id)
+                    .bind(from),
+            )
+            .await?;
+        if updated == 0 {
+            return Ok(None);
+        }
+
+        let (number,): (Option<i32>,) = tx
+            .fetch_one(query("SELECT certificate_number FROM attempts WHERE id = ?").bind(attempt_id))
+            .await?;
+        let event = CertificateEvent {
+            id: Uuid::new_v4().to_string(),
+            attempt_id: attempt_id.to_string(),
+            certificate_number: number,
+            action: if to == "revoked" { "revoked" } else { "reinstated" }.to_string(),
+            reason: reason.map(str::to_string),
+            old_callsign: None,
+            new_callsign: None,
+            created_at: at,
+        };
+        insert_event(&mut tx, &event).await?;
+        tx.commit().await?;
+
+        Ok(Some(event))
+    }
+
+    /// Move an approved certificate to a new callsign under the same number
+    pub async fn reissue(
+        &self,
+        attempt_id: &str,
+        new_callsign: &str,
+        reason: Option<&str>,
+        at: DateTime<Utc>,
+    ) -> Result<Result<CertificateEvent, ReissueError>, sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+
+        // Touch the row first so SQLite takes its write lock before reading
+        let updated = tx
+            .execute(
+                query("UPDATE attempts SET validated_at = validated_at WHERE id = ? AND validation_status = 'approved'")
+                    .bind(attempt_id),
+            )
+            .await?;
+        if updated == 0 {
+            return Ok(Err(ReissueError::NotApproved));
+        }
+
+        // Each callsign holds at most one certificate per class
+        let holder: Option<(String,)> = tx
+            .fetch_optional(
+                query(
+                    "SELECT id FROM attempts
+                     WHERE callsign = ? AND id <> ? AND validation_status = 'approved'
+                       AND test_speed = (SELECT test_speed FROM attempts WHERE id = ?)
+                     LIMIT 1",
+                )
+                .bind(new_callsign)
+                .bind(attempt_id)
+                .bind(attempt_id),
+            )
+            .await?;
+        if holder.is_some() {
+            return Ok(Err(ReissueError::CallsignCertified));
+        }
+
+        let (old_callsign, number): (String, Option<i32>) = tx
+            .fetch_one(
+                query("SELECT callsign, certificate_number FROM attempts WHERE id = ?").bind(attempt_id),
+            )
+            .await?;
+        tx.execute(
+            query("UPDATE attempts SET callsign = ? WHERE id = ?")
+                .bind(new_callsign)
+                .bind(attempt_id),
+        )
+        .await?;
+        let event = CertificateEvent {
+            id: Uuid::new_v4().to_string(),
+            attempt_id: attempt_id.to_string(),
+            certificate_number: number,
+            action: "reissued".to_string(),
+            reason: reason.map(str::to_string),
+            old_callsign: Some(old_callsign),
+            new_callsign: Some(new_callsign.to_string()),
+            created_at: at,
+        };
+        insert_event(&mut tx, &event).await?;
+        tx.commit().await?;
+
+        Ok(Ok(event))
+    }
+
+    /// Revocations, reinstatements and reissues of an attempt, oldest first
+    pub async fn history(&self, attempt_id: &str) -> Result<Vec<CertificateEvent>, sqlx::Error> {
+        self.db
+            .fetch_all(
+                query(
+                    "SELECT id, attempt_id, certificate_number, action, reason, old_callsign, new_callsign, created_at
+                     FROM certificate_events WHERE attempt_id = ? ORDER BY created_at, id",
+                )
+                .bind(attempt_id),
+            )
+            .await
+    }
+
+    /// The certificate holding a ledger number, whether valid or revoked
+    pub async fn find_by_number(&self, number: i32) -> Result<Option<IssuedCertificate>, sqlx::Error> {
+        self.db
+            .fetch_optional(
+                query(
+                    "SELECT a.id AS attempt_id, n.number AS certificate_number, a.callsign, a.created_at,
+                            a.validated_at, a.test_speed, a.test_id, t.title AS test_title, a.validation_status
+                     FROM certificate_numbers n
+                     JOIN attempts a ON a.id = n.attempt_id
+                     LEFT JOIN tests t ON t.id = a.test_id
+                     WHERE n.number = ? AND a.validation_status IN ('approved', 'revoked')",
+                )
+                .bind(number),
+            )
+            .await
+    }
+
+    /// Whether the attempt held a certificate that has since been revoked
+    pub async fn is_revoked(&self, attempt_id: &str) -> Result<bool, sqlx::Error> {
+        let row: Option<(String,)> = self
+            .db
+            .fetch_optional(
+                query("SELECT id FROM attempts WHERE id = ? AND validation_status = 'revoked'")
+                    .bind(attempt_id),
+            )
+            .await?;
+        Ok(row.is_some())
+    }
+
+    /// Compare issued certificates, including revoked ones whose numbers
+    /// are retired, with the ledger
+    pub async fn audit(&self) -> Result<CertificateAudit, sqlx::Error> {
+        let approved: Vec<(String, String, Option<i32>)> = self
+            .db
+            .fetch_all(query(
+                "SELECT id, callsign, certificate_number FROM attempts
+                 WHERE validation_status IN ('approved', 'revoked')
+                 ORDER BY validated_at, created_at",
+            ))
+            .await?;
+        let ledger: Vec<(i32, String)> = self
+            .db
+            .fetch_all(query("SELECT number, attempt_id FROM certificate_numbers"))
+            .await?;
+
+        Ok(audit(&approved, &ledger))
+    }
+
+    /// Apply the fixes from `audit` in one transaction, returning the
+    /// attempts whose certificate number changed. Gaps are left alone:
+    /// issued certificates keep the numbers printed on them.
+    pub async fn repair(&self) -> Result<Vec<Renumbered>, sqlx::Error> {
+        let report = self.audit().await?;
+        let mut renumbered = Vec::new();
+        if report.problems.is_empty() {
+            return Ok(renumbered);
+        }
+
+        // Settle numbers that are already taken before handing out new ones
+        let mut problems = report.problems;
+        problems.sort_by_key(|p| p.fix == CertificateFix::Renumber);
+
+        let now = Utc::now();
+        let mut tx = self.db.begin().await?;
+        for problem in problems {
+            let new = match problem.fix {
+                CertificateFix::Record(number) => {
+                    tx.execute(
+                        query(
+                            "INSERT INTO certificate_numbers (number, attempt_id, allocated_at) VALUES (?, ?, ?)",
+                        )
+                        .bind(number)
+                        .bind(&problem.attempt_id)
+                        .bind(now),
+                    )
+                    .await?;
+                    continue;
+                }
+                CertificateFix::Restore(number) => number,
+                CertificateFix::Renumber => {
+                    Self::allocate_in(&mut tx, &problem.attempt_id, now).await?
+                }
+            };
+
+            tx.execute(
+                query("UPDATE attempts SET certificate_number = ? WHERE id = ?")
+                    .bind(new)
+                    .bind(&problem.attempt_id),
+            )
+            .await?;
+            renumbered.push(Renumbered {
+                attempt_id: problem.attempt_id,
+                callsign: problem.callsign,
+                old: problem.certificate_number,
+                new,
+            });
+        }
+        tx.commit().await?;
+
+        Ok(renumbered)
+    }
+}
+
+async fn insert_event(tx: &mut Tx, event: &CertificateEvent) -> Result<(), sqlx::Error> {
+    tx.execute(
+        query(
+            "INSERT INTO certificate_events
+             (id, attempt_id, certificate_number, action, reason, old_callsign, new_callsign, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&event.id)
+        .bind(&event.attempt_id)
+        .bind(event.certificate_number)
+        .bind(&event.action)
+        .bind(&event.reason)
+        .bind(&event.old_callsign)
+        .bind(&event.new_callsign)
+        .bind(event.created_at),
+    )
+    .await?;
+    Ok(())
+}
+
+/// `approved` is (id, callsign, number) in approval order; `ledger` is (number, attempt id)
+fn audit(approved: &[(String, String, Option<i32>)], ledger: &[(i32, String)]) -> CertificateAudit {
+    let mut taken: HashSet<i32> = ledger.iter().map(|(n, _)| *n).collect();
+    let by_attempt: HashMap<&str, i32> = ledger.iter().map(|(n, id)| (id.as_str(), *n)).collect();
+
+    let mut holders: BTreeMap<i32, Vec<String>> = BTreeMap::new();
+    for (_, callsign, number) in approved {
+        if let Some(number) = number {
+            holders.entry(*number).or_default().push(callsign.clone());
+        }
+    }
+
+    let duplicates = holders
+        .iter()
+        .filter(|(_, callsigns)| callsigns.len() > 1)
+        .map(|(number, callsigns)| (*number, callsigns.clone()))
+        .collect();
+
+    let highest = holders.keys().next_back().copied().unwrap_or(0);
+    let gaps = (1..highest).filter(|n| !holders.contains_key(n)).collect();
+
+    let problems = approved
+        .iter()
+        .filter_map(|(id, callsign, number)| {
+            let fix = match (*number, by_attempt.get(id.as_str())) {
+                (Some(held), Some(&recorded)) if held == recorded => return None,
+                (_, Some(&recorded)) => CertificateFix::Restore(recorded),
+                // The first unrecorded holder of a free number keeps it
+                (Some(held), None) if taken.insert(held) => CertificateFix::Record(held),
+                (_, None) => CertificateFix::Renumber,
+            };
+            Some(CertificateProblem {
+                attempt_id: id.clone(),
+                callsign: callsign.clone(),
+                certificate_number: *number,
+                fix,
+            })
+        })
+        .collect();
+
+    CertificateAudit {
+        duplicates,
+        gaps,
+        problems,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approved(rows: &[(&str, Option<i32>)]) -> Vec<(String, String, Option<i32>)> {
+        rows.iter()
+            .map(|(id, n)| (id.to_string(), id.to_uppercase(), *n))
+            .collect()
+    }
+
+    fn ledger(rows: &[(i32, &str)]) -> Vec<(i32, String)> {
+        rows.iter().map(|(n, id)| (*n, id.to_string())).collect()
+    }
+
+    #[test]
+    fn test_audit_clean() {
+        let report = audit(
+            &approved(&[("a", Some(1)), ("b", Some(2))]),
+            &ledger(&[(1, "a"), (2, "b")]),
+        );
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn test_audit_finds_duplicates_and_gaps() {
+        let report = audit(
+            &approved(&[("a", Some(1)), ("b", Some(1)), ("c", Some(4)), ("d", None)]),
+            &ledger(&[(1, "a")]),
+        );
+        assert_eq!(report.duplicates, vec![(1, vec!["A".to_string(), "B".to_string()])]);
+        assert_eq!(report.gaps, vec![2, 3]);
+        let fixes: Vec<(&str, CertificateFix)> = report
+            .problems
+            .iter()
+            .map(|p| (p.attempt_id.as_str(), p.fix.clone()))
+            .collect();
+        assert_eq!(
+            fixes,
+            vec![
+                ("b", CertificateFix::Renumber),
+                ("c", CertificateFix::Record(4)),
+                ("d", CertificateFix::Renumber),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_audit_unrecorded_duplicates_keep_one_holder() {
+        let report = audit(&approved(&[("a", Some(3)), ("b", Some(3))]), &ledger(&[]));
+        let fixes: Vec<CertificateFix> = report.problems.iter().map(|p| p.fix.clone()).collect();
+        assert_eq!(fixes, vec![CertificateFix::Record(3), CertificateFix::Renumber]);
+    }
+
+    #[test]
+    fn test_audit_prefers_ledger_number() {
+        let report = audit(&approved(&[("a", Some(7))]), &ledger(&[(1, "a")]));
+        assert_eq!(report.problems[0].fix, CertificateFix::Restore(1));
+    }
+}
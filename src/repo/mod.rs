@@ -0,0 +1,80 @@
+//! Repositories holding the SQL for each table
+//!
+//! Handlers reach the database through `state.db.attempts()`,
+//! `.certificates()`, `.certificate_templates()`, `.tests()`,
+//! `.test_revisions()`, `.exam_sessions()`, `.questions()`, `.prosigns()` and
+//! `.settings()`. Every query here must run unchanged on SQLite and
+//! PostgreSQL: bind timestamps as `DateTime<Utc>` rather than strings, compare
+//! against `TRUE`/`FALSE`, and avoid backend-specific functions such as
+//! SQLite's `date()`.
+
+mod attempts;
+mod certificates;
+mod prosigns;
+mod questions;
+mod revisions;
+mod sessions;
+mod settings;
+mod templates;
+mod test;
+
+pub use attempts::{AttemptFilter, AttemptRepo, NewAttempt, RosterRow};
+pub use certificates::{CertificateEvent, CertificateFix, CertificateRepo, IssuedCertificate, ReissueError};
+pub use prosigns::ProsignRepo;
+pub use questions::{NewQuestion, QuestionRepo};
+pub use revisions::{TestRevisionRepo, TestRevisionRow};
+pub use sessions::{ExamSession, ExamSessionRepo};
+pub use settings::SettingsRepo;
+pub use templates::{CertificateTemplateRepo, CertificateTemplateRevision, CertificateTemplateVersion};
+pub use test::{CloneError, NewTest, PurgeError, TestRepo};
+
+use crate::db::Db;
+use chrono::{DateTime, NaiveDate, Utc};
+
+impl Db {
+    pub fn attempts(&self) -> AttemptRepo<'_> {
+        AttemptRepo::new(self)
+    }
+
+    pub fn certificates(&self) -> CertificateRepo<'_> {
+        CertificateRepo::new(self)
+    }
+
+    pub fn certificate_templates(&self) -> CertificateTemplateRepo<'_> {
+        CertificateTemplateRepo::new(self)
+    }
+
+    pub fn tests(&self) -> TestRepo<'_> {
+        TestRepo::new(self)
+    }
+
+    pub fn test_revisions(&self) -> TestRevisionRepo<'_> {
+        TestRevisionRepo::new(self)
+    }
+
+    pub fn exam_sessions(&self) -> ExamSessionRepo<'_> {
+        ExamSessionRepo::new(self)
+    }
+
+    pub fn questions(&self) -> QuestionRepo<'_> {
+        QuestionRepo::new(self)
+    }
+
+    pub fn prosigns(&self) -> ProsignRepo<'_> {
+        ProsignRepo::new(self)
+    }
+
+    pub fn settings(&self) -> SettingsRepo<'_> {
+        SettingsRepo::new(self)
+    }
+}
+
+/// Midnight UTC at the start of `date`, for day-based range filters
+pub fn day_start(date: NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0)
+        .expect("midnight is a valid time")
+        .and_utc()
+}
+
+#[cfg(test)]
+mod suite;
@@ -0,0 +1,71 @@
+use crate::admin::ProsignMapping;
+use crate::db::{query, Db};
+use chrono::Utc;
+
+/// Prosign to alternate-character mappings used when grading copy
+pub struct ProsignRepo<'a> {
+    db: &'a Db,
+}
+
+impl<'a> ProsignRepo<'a> {
+    pub fn new(db: &'a Db) -> Self {
+        ProsignRepo { db }
+    }
+
+    pub async fn count(&self) -> Result<i64, sqlx::Error> {
+        let row: (i64,) = self
+            .db
+            .fetch_one(query("SELECT COUNT(*) FROM prosign_mappings"))
+            .await?;
+        Ok(row.0)
+    }
+
+    pub async fn list(&self) -> Result<Vec<ProsignMapping>, sqlx::Error> {
+        self.db
+            .fetch_all(query(
+                "SELECT id, prosign, alternate, created_at FROM prosign_mappings ORDER BY prosign",
+            ))
+            .await
+    }
+
+    /// (prosign, alternate) pairs for `grading::find_consecutive_correct`
+    pub async fn pairs(&self) -> Result<Vec<(String, String)>, sqlx::Error> {
+        self.db
+            .fetch_all(query("SELECT prosign, alternate FROM prosign_mappings"))
+            .await
+    }
+
+    /// Insert a mapping, returning its new id
+    pub async fn insert(&self, prosign: &str, alternate: &str) -> Result<String, sqlx::Error> {
+        let id = uuid::Uuid::new_v4().to_string();
+        self.db
+            .execute(
+                query(
+                    "INSERT INTO prosign_mappings (id, prosign, alternate, created_at) VALUES (?, ?, ?, ?)",
+                )
+                .bind(&id)
+                .bind(prosign)
+                .bind(alternate)
+                .bind(Utc::now()),
+            )
+            .await?;
+        Ok(id)
+    }
+
+    pub async fn update(&self, id: &str, prosign: &str, alternate: &str) -> Result<u64, sqlx::Error> {
+        self.db
+            .execute(
+                query("UPDATE prosign_mappings SET prosign = ?, alternate = ? WHERE id = ?")
+                    .bind(prosign)
+                    .bind(alternate)
+                    .bind(id),
+            )
+            .await
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<u64, sqlx::Error> {
+        self.db
+            .execute(query("DELETE FROM prosign_mappings WHERE id = ?").bind(id))
+            .await
+    }
+}
@@ -0,0 +1,136 @@
+use crate::admin::{AdminQuestion, ExportedQuestion};
+use crate::db::{query, Db, Query, Tx, Value};
+use crate::grading::QuestionType;
+use chrono::Utc;
+
+/// A question to insert; the repository assigns its id
+#[derive(Debug)]
+pub struct NewQuestion<'a> {
+    pub test_id: &'a str,
+    pub question_number: i32,
+    pub question_text: &'a str,
+    pub question_type: QuestionType,
+    /// Multiple-choice options, lettered A, B, C... in order
+    pub options: &'a [&'a str],
+    /// The option letter, TRUE or FALSE, or the expected text
+    pub correct_option: &'a str,
+    /// Further free-text answers accepted
+    pub accepted_answers: &'a [&'a str],
+    /// What the question asks about, for drawing across topics
+    pub topic: Option<&'a str>,
+}
+
+impl NewQuestion<'_> {
+    pub(super) fn insert_query(&self, id: &str) -> Query {
+        query(
+            "INSERT INTO questions (id, test_id, question_number, question_text, question_type, options, correct_option, accepted_answers, topic, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(id)
+        .bind(self.test_id)
+        .bind(self.question_number)
+        .bind(self.question_text)
+        .bind(self.question_type.as_str())
+        .bind(self.options)
+        .bind(self.correct_option)
+        .bind(self.accepted_answers)
+        .bind(self.topic)
+        .bind(Utc::now())
+    }
+
+    pub(super) async fn insert_in(&self, tx: &mut Tx) -> Result<String, sqlx::Error> {
+        let id = uuid::Uuid::new_v4().to_string();
+        tx.execute(self.insert_query(&id)).await?;
+        Ok(id)
+    }
+}
+
+/// Multiple-choice questions belonging to a test
+pub struct QuestionRepo<'a> {
+    db: &'a Db,
+}
+
+impl<'a> QuestionRepo<'a> {
+    pub fn new(db: &'a Db) -> Self {
+        QuestionRepo { db }
+    }
+
+    pub async fn list_admin(&self, test_id: &str) -> Result<Vec<AdminQuestion>, sqlx::Error> {
+        self.db
+            .fetch_all(
+                query(
+                    "SELECT id, test_id, question_number, question_text, question_type, options, correct_option, accepted_answers, topic, created_at
+                     FROM questions WHERE test_id = ? ORDER BY question_number",
+                )
+                .bind(test_id),
+            )
+            .await
+    }
+
+    pub async fn find_admin(&self, id: &str) -> Result<Option<AdminQuestion>, sqlx::Error> {
+        self.db
+            .fetch_optional(
+                query(
+                    "SELECT id, test_id, question_number, question_text, question_type, options, correct_option, accepted_answers, topic, created_at
+                     FROM questions WHERE id = ?",
+                )
+                .bind(id),
+            )
+            .await
+    }
+
+    pub async fn list_exported(&self, test_id: &str) -> Result<Vec<ExportedQuestion>, sqlx::Error> {
+        self.db
+            .fetch_all(
+                query(
+                    "SELECT question_number, question_text, question_type, options, correct_option, accepted_answers, topic
+                     FROM questions WHERE test_id = ? ORDER BY question_number",
+                )
+                .bind(test_id),
+            )
+            .await
+    }
+
+    /// Insert a question, returning its new id
+    pub async fn insert(&self, question: &NewQuestion<'_>) -> Result<String, sqlx::Error> {
+        let id = uuid::Uuid::new_v4().to_string();
+        self.db.execute(question.insert_query(&id)).await?;
+        Ok(id)
+    }
+
+    /// Set the given columns. Column names must come from code, never from input.
+    pub async fn update(
+        &self,
+        id: &str,
+        changes: Vec<(&'static str, Value)>,
+    ) -> Result<u64, sqlx::Error> {
+        self.db.execute(update_query("questions", id, changes)).await
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<u64, sqlx::Error> {
+        self.db
+            .execute(query("DELETE FROM questions WHERE id = ?").bind(id))
+            .await
+    }
+}
+
+/// `UPDATE <table> SET a = ?, b = ? WHERE id = ?` for a list of column changes
+pub(super) fn update_query(
+    table: &str,
+    id: &str,
+    changes: Vec<(&'static str, Value)>,
+) -> Query {
+    let assignments: Vec<String> = changes
+        .iter()
+        .map(|(column, _)| format!("{} = ?", column))
+        .collect();
+    let mut q = query(format!(
+        "UPDATE {} SET {} WHERE id = ?",
+        table,
+        assignments.join(", ")
+    ));
+    for (_, value) in changes {
+        q = q.bind(value);
+    }
+    q.bind(id)
+}
@@ -0,0 +1,193 @@
+use crate::admin::ExportedQuestion;
+use crate::db::{query, Db};
+use crate::{PublicQuestion, QuestionWithAnswer};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+/// A published revision as listed in the admin portal
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct TestRevisionSummary {
+    pub id: String,
+    pub revision: i32,
+    pub title: String,
+    pub note: Option<String>,
+    pub published_at: DateTime<Utc>,
+    pub question_count: i64,
+    pub attempt_count: i64,
+    /// Whether candidates currently see this revision
+    pub live: bool,
+}
+
+/// The test fields of a published revision
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct TestRevisionRow {
+    pub id: String,
+    pub test_id: String,
+    pub revision: i32,
+    pub title: String,
+    pub speed_wpm: i32,
+    pub year: String,
+    pub audio_url: String,
+    pub passing_score: i32,
+    /// Questions drawn per exam session, or None to ask them all
+    pub draw_count: Option<i32>,
+    /// Segments serialized as JSON
+    #[serde(skip_serializing)]
+    pub segments: Option<String>,
+    pub expected_copy_text: Option<String>,
+    pub note: Option<String>,
+    pub published_at: DateTime<Utc>,
+}
+
+/// Immutable snapshots of tests and their questions, taken on publish
+pub struct TestRevisionRepo<'a> {
+    db: &'a Db,
+}
+
+impl<'a> TestRevisionRepo<'a> {
+    pub fn new(db: &'a Db) -> Self {
+        TestRevisionRepo { db }
+    }
+
+    /// Snapshot a test's draft and its questions as the next revision and make
+    /// it the one candidates see. Returns the revision number and id, or None
+    /// if the test does not exist.
+    pub async fn publish(
+        &self,
+        test_id: &str,
+        note: Option<&str>,
+        at: DateTime<Utc>,
+    ) -> Result<Option<(i32, String)>, sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+
+        // Writing first locks the test, so concurrent publishes number their
+        // revisions one after the other
+        let updated = tx
+            .execute(
+                query("UPDATE tests SET published_revision_id = published_revision_id WHERE id = ?")
+                    .bind(test_id),
+            )
+            .await?;
+        if updated == 0 {
+            return Ok(None);
+        }
+
+        let (latest,): (i32,) = tx
+            .fetch_one(
+                query("SELECT COALESCE(MAX(revision), 0) FROM test_revisions WHERE test_id = ?").bind(test_id),
+            )
+            .await?;
+        let revision = latest + 1;
+        let revision_id = format!("{}@{}", test_id, revision);
+
+        tx.execute(
+            query(
+                "INSERT INTO test_revisions (id, test_id, revision, title, speed_wpm, year, audio_url, passing_score, draw_count, segments, expected_copy_text, note, published_at)
+                 SELECT ?, id, ?, title, speed_wpm, year, audio_url, passing_score, draw_count, segments, expected_copy_text, ?, ?
+                 FROM tests WHERE id = ?",
+            )
+            .bind(&revision_id)
+            .bind(revision)
+            .bind(note)
+            .bind(at)
+            .bind(test_id),
+        )
+        .await?;
+        tx.execute(
+            query(
+                "INSERT INTO test_revision_questions (revision_id, question_id, question_number, question_text, question_type, options, correct_option, accepted_answers, topic)
+                 SELECT ?, id, question_number, question_text, question_type, options, correct_option, accepted_answers, topic
+                 FROM questions WHERE test_id = ?",
+            )
+            .bind(&revision_id)
+            .bind(test_id),
+        )
+        .await?;
+        tx.execute(
+            query("UPDATE tests SET published_revision_id = ? WHERE id = ?")
+                .bind(&revision_id)
+                .bind(test_id),
+        )
+        .await?;
+
+        tx.commit().await?;
+        Ok(Some((revision, revision_id)))
+    }
+
+    /// Every revision of a test, newest first
+    pub async fn list(&self, test_id: &str) -> Result<Vec<TestRevisionSummary>, sqlx::Error> {
+        self.db
+            .fetch_all(
+                query(
+                    "SELECT r.id, r.revision, r.title, r.note, r.published_at,
+                            CAST((SELECT COUNT(*) FROM test_revision_questions WHERE revision_id = r.id) AS BIGINT) AS question_count,
+                            CAST((SELECT COUNT(*) FROM attempts WHERE revision_id = r.id) AS BIGINT) AS attempt_count,
+                            (t.published_revision_id IS NOT NULL AND t.published_revision_id = r.id) AS live
+                     FROM test_revisions r
+                     JOIN tests t ON t.id = r.test_id
+                     WHERE r.test_id = ?
+                     ORDER BY r.revision DESC",
+                )
+                .bind(test_id),
+            )
+            .await
+    }
+
+    pub async fn find(&self, test_id: &str, revision: i32) -> Result<Option<TestRevisionRow>, sqlx::Error> {
+        self.db
+            .fetch_optional(
+                query(
+                    "SELECT id, test_id, revision, title, speed_wpm, year, audio_url, passing_score, draw_count, segments, expected_copy_text, note, published_at
+                     FROM test_revisions WHERE test_id = ? AND revision = ?",
+                )
+                .bind(test_id)
+                .bind(revision),
+            )
+            .await
+    }
+
+    /// A revision's questions for the exam page, without answers
+    pub async fn public_questions(&self, revision_id: &str) -> Result<Vec<PublicQuestion>, sqlx::Error> {
+        self.db
+            .fetch_all(
+                query(
+                    "SELECT q.question_id AS id, r.test_id, q.question_number, q.question_text, q.question_type, q.options
+                     FROM test_revision_questions q
+                     JOIN test_revisions r ON r.id = q.revision_id
+                     WHERE q.revision_id = ? ORDER BY q.question_number",
+                )
+                .bind(revision_id),
+            )
+            .await
+    }
+
+    /// A revision's questions with their correct options, for grading
+    pub async fn questions_with_answers(
+        &self,
+        revision_id: &str,
+    ) -> Result<Vec<QuestionWithAnswer>, sqlx::Error> {
+        self.db
+            .fetch_all(
+                query(
+                    "SELECT question_id AS id, question_number, question_text, question_type, options, correct_option, accepted_answers, topic
+                     FROM test_revision_questions WHERE revision_id = ? ORDER BY question_number",
+                )
+                .bind(revision_id),
+            )
+            .await
+    }
+
+    /// A revision's questions as the admin portal shows them
+    pub async fn exported_questions(&self, revision_id: &str) -> Result<Vec<ExportedQuestion>, sqlx::Error> {
+        self.db
+            .fetch_all(
+                query(
+                    "SELECT question_number, question_text, question_type, options, correct_option, accepted_answers, topic
+                     FROM test_revision_questions WHERE revision_id = ? ORDER BY question_number",
+                )
+                .bind(revision_id),
+            )
+            .await
+    }
+}
@@ -0,0 +1,72 @@
+use crate::db::{query, Db};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+/// An exam session as stored
+#[derive(Debug, Clone, PartialEq, Serialize, FromRow)]
+pub struct ExamSession {
+    pub id: String,
+    pub test_id: String,
+    pub revision_id: String,
+    pub shuffled: bool,
+    /// Ids of the questions drawn for the session as a JSON array, or None
+    /// when it asks all of the revision's questions
+    #[serde(skip_serializing)]
+    pub question_ids: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub submitted_at: Option<DateTime<Utc>>,
+}
+
+impl ExamSession {
+    /// The questions drawn for the session, or None if it asks them all
+    pub fn drawn_questions(&self) -> Option<Vec<String>> {
+        self.question_ids.as_deref().and_then(|json| {
+            serde_json::from_str(json)
+                .map_err(|e| tracing::warn!("Failed to parse drawn questions of session {}: {}", self.id, e))
+                .ok()
+        })
+    }
+}
+
+/// Exam sessions, one per sitting of a test
+pub struct ExamSessionRepo<'a> {
+    db: &'a Db,
+}
+
+impl<'a> ExamSessionRepo<'a> {
+    pub fn new(db: &'a Db) -> Self {
+        ExamSessionRepo { db }
+    }
+
+    pub async fn insert(&self, session: &ExamSession) -> Result<(), sqlx::Error> {
+        self.db
+            .execute(
+                query(
+                    "INSERT INTO exam_sessions (id, test_id, revision_id, shuffled, question_ids, created_at, submitted_at)
+                     VALUES (?, ?, ?, ?, ?, ?, ?)",
+                )
+                .bind(&session.id)
+                .bind(&session.test_id)
+                .bind(&session.revision_id)
+                .bind(session.shuffled)
+                .bind(session.question_ids.as_deref())
+                .bind(session.created_at)
+                .bind(session.submitted_at),
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn find(&self, id: &str) -> Result<Option<ExamSession>, sqlx::Error> {
+        self.db
+            .fetch_optional(
+                query(
+                    "SELECT id, test_id, revision_id, shuffled, question_ids, created_at, submitted_at
+                     FROM exam_sessions WHERE id = ?",
+                )
+                .bind(id),
+            )
+            .await
+    }
+}
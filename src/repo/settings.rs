@@ -0,0 +1,34 @@
+use crate::db::{query, Db};
+
+/// Key/value settings (email template and subject)
+pub struct SettingsRepo<'a> {
+    db: &'a Db,
+}
+
+impl<'a> SettingsRepo<'a> {
+    pub fn new(db: &'a Db) -> Self {
+        SettingsRepo { db }
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<String>, sqlx::Error> {
+        let row: Option<(String,)> = self
+            .db
+            .fetch_optional(query("SELECT value FROM settings WHERE key = ?").bind(key))
+            .await?;
+        Ok(row.map(|r| r.0))
+    }
+
+    pub async fn set(&self, key: &str, value: &str) -> Result<(), sqlx::Error> {
+        self.db
+            .execute(
+                query(
+                    "INSERT INTO settings (key, value) VALUES (?, ?)
+                     ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+                )
+                .bind(key)
+                .bind(value),
+            )
+            .await?;
+        Ok(())
+    }
+}
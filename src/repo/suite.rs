@@ -0,0 +1,756 @@
+//! Repository tests run against every configured backend
+//!
+//! Always runs on in-memory SQLite; set `KNOWCODE_TEST_POSTGRES_URL` to a
+//! database the tests may create schemas in to run on PostgreSQL as well.
+
+use super::{AttemptFilter, CloneError, ExamSession, NewAttempt, NewQuestion, NewTest, PurgeError};
+use crate::db::{is_unique_violation, testing, Db, Value};
+use crate::grading::QuestionType;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+
+fn at(day: u32, hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 3, day, hour, 0, 0).unwrap()
+}
+
+fn attempt<'a>(id: &'a str, callsign: &'a str, passed: bool, created_at: DateTime<Utc>) -> NewAttempt<'a> {
+    NewAttempt {
+        id,
+        callsign,
+        test_speed: 20,
+        questions_correct: if passed { 8 } else { 3 },
+        copy_chars: 0,
+        passed,
+        created_at,
+        validation_status: passed.then_some("pending"),
+        audio_progress: Some(100.0),
+        test_id: Some("20wpm-extra-1991"),
+        copy_text: None,
+        consecutive_correct: Some(if passed { 120 } else { 10 }),
+        revision_id: None,
+        session_id: None,
+    }
+}
+
+fn question(test_id: &str, number: i32) -> NewQuestion<'_> {
+    NewQuestion {
+        test_id,
+        question_number: number,
+        question_text: "What was the call?",
+        question_type: QuestionType::Choice,
+        options: &["W1AW", "K1ABC", "N0CALL", "AA1AA"],
+        correct_option: "B",
+        accepted_answers: &[],
+        topic: None,
+    }
+}
+
+async fn for_each_backend<F, Fut>(check: F)
+where
+    F: Fn(Db) -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    for test_db in testing::all_backends().await {
+        check(test_db.db.clone()).await;
+        test_db.cleanup().await;
+    }
+}
+
+#[tokio::test]
+async fn test_attempt_limits() {
+    for_each_backend(|db| async move {
+        let attempts = db.attempts();
+        attempts.insert(&attempt("a1", "W1AW", false, at(10, 9))).await.unwrap();
+
+        assert!(attempts.has_attempt_since("W1AW", at(10, 0)).await.unwrap());
+        assert!(!attempts.has_attempt_since("W1AW", at(11, 0)).await.unwrap());
+        assert!(!attempts.has_open_attempt("W1AW", 20).await.unwrap());
+
+        attempts.insert(&attempt("a2", "W1AW", true, at(11, 9))).await.unwrap();
+        assert!(attempts.has_open_attempt("W1AW", 20).await.unwrap());
+        // The pending Extra attempt does not hold up the other classes
+        assert!(!attempts.has_open_attempt("W1AW", 13).await.unwrap());
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_approve_and_reject() {
+    for_each_backend(|db| async move {
+        let attempts = db.attempts();
+        attempts.insert(&attempt("a1", "W1AW", true, at(1, 9))).await.unwrap();
+        attempts.insert(&attempt("a2", "K1ABC", true, at(2, 9))).await.unwrap();
+        attempts.insert(&attempt("a3", "N0CALL", true, at(3, 9))).await.unwrap();
+
+        assert_eq!(attempts.count_by_status("pending").await.unwrap(), 3);
+        assert_eq!(attempts.pending_queue().await.unwrap().len(), 3);
+
+        let first = attempts.approve("a1", Some("w1aw@example.com"), None, at(4, 9)).await.unwrap();
+        let second = attempts.approve("a2", None, None, at(5, 9)).await.unwrap();
+        assert_eq!(first, Some(1));
+        assert_eq!(second, Some(2));
+        // Already approved
+        assert_eq!(attempts.approve("a1", None, None, at(5, 9)).await.unwrap(), None);
+
+        assert!(attempts.reject("a3", Some("Bad copy"), at(6, 9)).await.unwrap());
+        assert!(!attempts.reject("a3", None, at(6, 9)).await.unwrap());
+
+        assert_eq!(attempts.count_approved_between(at(4, 0), at(5, 0)).await.unwrap(), 1);
+        assert_eq!(attempts.approved_speeds("W1AW").await.unwrap(), vec![20]);
+        assert!(attempts.approved_speeds("N0CALL").await.unwrap().is_empty());
+        assert_eq!(
+            attempts.approved_member("a1").await.unwrap(),
+            Some(("W1AW".to_string(), Some(1), Some("w1aw@example.com".to_string())))
+        );
+        assert!(attempts.approved_member("a3").await.unwrap().is_none());
+        assert!(attempts.find_certified("a2").await.unwrap().is_some());
+
+        let roster = attempts.roster().await.unwrap();
+        assert_eq!(roster.len(), 2);
+        assert_eq!(roster[0].callsign, "W1AW");
+        assert_eq!(roster[0].test_speed, 20);
+        assert_eq!(roster[0].validated_at, Some(at(4, 9)));
+
+        let (_, total) = attempts.approved(None, 10, 0).await.unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(attempts.mark_reached_out(&["a1".to_string()]).await.unwrap(), 1);
+        let (_, reached) = attempts.approved(Some(true), 10, 0).await.unwrap();
+        let (_, not_reached) = attempts.approved(Some(false), 10, 0).await.unwrap();
+        assert_eq!((reached, not_reached), (1, 1));
+
+        assert_eq!(attempts.history("N0CALL").await.unwrap().len(), 1);
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_leaderboard_and_stats() {
+    for_each_backend(|db| async move {
+        let attempts = db.attempts();
+        attempts.insert(&attempt("a1", "W1AW", false, at(1, 9))).await.unwrap();
+        attempts.insert(&attempt("a2", "W1AW", true, at(2, 9))).await.unwrap();
+        attempts.insert(&attempt("a3", "K1ABC", false, at(2, 10))).await.unwrap();
+        attempts.approve("a2", None, None, at(3, 9)).await.unwrap();
+
+        let leaders = attempts.leaderboard(10).await.unwrap();
+        assert_eq!(leaders.len(), 1);
+        assert_eq!(leaders[0].callsign, "W1AW");
+        assert_eq!(leaders[0].highest_speed_passed, 20);
+        assert_eq!(leaders[0].total_attempts, 2);
+        assert_eq!(leaders[0].total_passes, 1);
+        assert_eq!(leaders[0].first_passed_at, Some(at(2, 9)));
+
+        let stats = attempts.stats().await.unwrap();
+        assert_eq!(stats.total_attempts, 3);
+        assert_eq!(stats.total_passes, 1);
+        assert_eq!(stats.unique_callsigns, 2);
+        assert_eq!(stats.attempts_by_speed.len(), 1);
+        assert_eq!(stats.attempts_by_speed[0].passes, 1);
+
+        assert_eq!(attempts.list(Some(20), true, 10, 0).await.unwrap().len(), 1);
+        assert_eq!(attempts.list_for_callsign("W1AW", None).await.unwrap().len(), 2);
+        assert_eq!(attempts.search("w1").await.unwrap().len(), 2);
+        assert_eq!(attempts.recent(2).await.unwrap().len(), 2);
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_list_filtered() {
+    for_each_backend(|db| async move {
+        let attempts = db.attempts();
+        attempts.insert(&attempt("a1", "W1AW", false, at(1, 9))).await.unwrap();
+        attempts.insert(&attempt("a2", "W1AW", true, at(2, 9))).await.unwrap();
+        attempts.insert(&attempt("a3", "K1ABC", true, at(3, 9))).await.unwrap();
+
+        let (total, items) = attempts.list_filtered(&AttemptFilter::default(), 2, 0).await.unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].id, "a3");
+
+        let filter = AttemptFilter {
+            passed: Some(true),
+            callsign: Some("w1".to_string()),
+            ..Default::default()
+        };
+        let (total, items) = attempts.list_filtered(&filter, 10, 0).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(items[0].id, "a2");
+
+        let filter = AttemptFilter {
+            created_from: Some(at(2, 0)),
+            created_before: Some(at(2, 0) + Duration::days(1)),
+            ..Default::default()
+        };
+        let (total, _) = attempts.list_filtered(&filter, 10, 0).await.unwrap();
+        assert_eq!(total, 1);
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_tests_and_questions() {
+    for_each_backend(|db| async move {
+        let tests = db.tests();
+        assert_eq!(tests.list_active().await.unwrap().len(), 1);
+
+        let new_test = NewTest {
+            id: "13wpm-general",
+            title: "General",
+            speed_wpm: 13,
+            year: "1990",
+            audio_url: "/audio/13wpm.mp3",
+            passing_score: 7,
+            draw_count: None,
+            active: true,
+            segments: None,
+            expected_copy_text: Some("CQ CQ"),
+        };
+        let questions = [question("13wpm-general", 1), question("13wpm-general", 2)];
+        assert!(tests.insert_with_questions(&new_test, &questions).await.unwrap());
+        assert!(!tests.insert_with_questions(&new_test, &questions).await.unwrap());
+
+        let row = tests.find_admin("13wpm-general").await.unwrap().unwrap();
+        assert_eq!(row.question_count, 2);
+        assert_eq!(row.expected_copy_text.as_deref(), Some("CQ CQ"));
+
+        let changes: Vec<(&'static str, Value)> = vec![
+            ("title", "General Class".into()),
+            ("passing_score", 8.into()),
+        ];
+        assert_eq!(tests.update("13wpm-general", changes).await.unwrap(), 1);
+        assert_eq!(tests.find_admin("13wpm-general").await.unwrap().unwrap().title, "General Class");
+
+        let questions = db.questions();
+        let id = questions.insert(&question("13wpm-general", 3)).await.unwrap();
+        assert_eq!(questions.list_admin("13wpm-general").await.unwrap().len(), 3);
+        assert_eq!(
+            questions
+                .update(&id, vec![("correct_option", "C".into())])
+                .await
+                .unwrap(),
+            1
+        );
+        let listed = questions.list_admin("13wpm-general").await.unwrap();
+        assert_eq!(listed[2].correct_option, "C");
+        assert_eq!(questions.delete(&id).await.unwrap(), 1);
+        assert_eq!(questions.list_admin("13wpm-general").await.unwrap().len(), 2);
+
+        let schedule: Vec<(&'static str, Value)> = vec![
+            ("available_from", at(1, 9).into()),
+            ("available_until", Value::Timestamp(None)),
+            ("availability_windows", "[]".into()),
+        ];
+        assert_eq!(tests.update("13wpm-general", schedule).await.unwrap(), 1);
+        let row = tests.find_admin("13wpm-general").await.unwrap().unwrap();
+        assert_eq!((row.available_from, row.available_until), (Some(at(1, 9)), None));
+
+        assert_eq!(tests.deactivate("13wpm-general").await.unwrap(), 1);
+        assert!(tests.find_active("13wpm-general").await.unwrap().is_none());
+        assert_eq!(tests.list_admin().await.unwrap().len(), 2);
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_published_revisions_are_snapshots() {
+    for_each_backend(|db| async move {
+        let new_test = NewTest {
+            id: "13wpm-general",
+            title: "General",
+            speed_wpm: 13,
+            year: "1990",
+            audio_url: "/audio/13wpm.mp3",
+            passing_score: 7,
+            draw_count: Some(2),
+            active: true,
+            segments: None,
+            expected_copy_text: None,
+        };
+        let questions = [
+            NewQuestion { topic: Some("rig"), ..question("13wpm-general", 1) },
+            question("13wpm-general", 2),
+        ];
+        assert!(db.tests().insert_with_questions(&new_test, &questions).await.unwrap());
+
+        // Drafts stay hidden until published
+        assert!(db.tests().find_active("13wpm-general").await.unwrap().is_none());
+        let revisions = db.test_revisions();
+        assert_eq!(
+            revisions.publish("13wpm-general", Some("First"), at(1, 9)).await.unwrap(),
+            Some((1, "13wpm-general@1".to_string()))
+        );
+        assert!(revisions.publish("missing", None, at(1, 9)).await.unwrap().is_none());
+
+        // Editing the draft changes nothing candidates see
+        db.tests().update("13wpm-general", vec![("title", "General Class".into())]).await.unwrap();
+        let id = db.questions().insert(&question("13wpm-general", 3)).await.unwrap();
+        db.questions().update(&id, vec![("correct_option", "D".into())]).await.unwrap();
+        let live = db.tests().find_active("13wpm-general").await.unwrap().unwrap();
+        assert_eq!((live.title.as_str(), live.revision_id.as_str()), ("General", "13wpm-general@1"));
+        assert_eq!(live.draw_count, Some(2));
+        assert_eq!(revisions.public_questions(&live.revision_id).await.unwrap().len(), 2);
+        let answers = revisions.questions_with_answers(&live.revision_id).await.unwrap();
+        assert_eq!(answers[0].topic.as_deref(), Some("rig"));
+
+        let mut graded = attempt("a1", "W1AW", true, at(2, 9));
+        graded.revision_id = Some("13wpm-general@1");
+        db.attempts().insert(&graded).await.unwrap();
+
+        assert_eq!(
+            revisions.publish("13wpm-general", None, at(3, 9)).await.unwrap(),
+            Some((2, "13wpm-general@2".to_string()))
+        );
+        let live = db.tests().find_active("13wpm-general").await.unwrap().unwrap();
+        assert_eq!(live.title, "General Class");
+        let answers = revisions.questions_with_answers(&live.revision_id).await.unwrap();
+        assert_eq!(answers[2].correct_option, "D");
+
+        let listed = revisions.list("13wpm-general").await.unwrap();
+        let summary: Vec<_> = listed
+            .iter()
+            .map(|r| (r.revision, r.question_count, r.attempt_count, r.live, r.note.as_deref()))
+            .collect();
+        assert_eq!(summary, vec![(2, 3, 0, true, None), (1, 2, 1, false, Some("First"))]);
+
+        let first = revisions.find("13wpm-general", 1).await.unwrap().unwrap();
+        assert_eq!((first.title.as_str(), first.published_at), ("General", at(1, 9)));
+        assert_eq!(revisions.exported_questions(&first.id).await.unwrap().len(), 2);
+        assert_eq!(db.tests().find_admin("13wpm-general").await.unwrap().unwrap().published_revision, Some(2));
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_exam_sessions_submit_once() {
+    for_each_backend(|db| async move {
+        db.questions().insert(&question("20wpm-extra-1991", 1)).await.unwrap();
+        let (_, revision_id) = db
+            .test_revisions()
+            .publish("20wpm-extra-1991", None, at(1, 9))
+            .await
+            .unwrap()
+            .unwrap();
+        let session = ExamSession {
+            id: "s1".to_string(),
+            test_id: "20wpm-extra-1991".to_string(),
+            revision_id,
+            shuffled: true,
+            question_ids: Some(r#"["q1","q3"]"#.to_string()),
+            created_at: at(2, 9),
+            submitted_at: None,
+        };
+        db.exam_sessions().insert(&session).await.unwrap();
+        assert_eq!(db.exam_sessions().find("s1").await.unwrap(), Some(session.clone()));
+        assert_eq!(session.drawn_questions(), Some(vec!["q1".to_string(), "q3".to_string()]));
+        assert!(db.exam_sessions().find("s2").await.unwrap().is_none());
+
+        let attempts = db.attempts();
+        let submit = |id, session_id, created_at| NewAttempt {
+            session_id: Some(session_id),
+            ..attempt(id, "W1AW", false, created_at)
+        };
+        assert!(attempts.insert_submitted(&submit("a1", "s1", at(2, 10))).await.unwrap());
+        assert!(!attempts.insert_submitted(&submit("a2", "s1", at(2, 11))).await.unwrap());
+        let submitted = db.exam_sessions().find("s1").await.unwrap().unwrap();
+        assert_eq!(submitted.submitted_at, Some(at(2, 10)));
+        assert_eq!(attempts.callsign("a2").await.unwrap(), None);
+
+        // An attempt that fails to insert leaves its session open
+        let retry = ExamSession { id: "s2".to_string(), ..session };
+        db.exam_sessions().insert(&retry).await.unwrap();
+        assert!(attempts.insert_submitted(&submit("a1", "s2", at(2, 12))).await.is_err());
+        assert_eq!(db.exam_sessions().find("s2").await.unwrap().unwrap().submitted_at, None);
+        assert!(attempts.insert_submitted(&submit("a3", "s2", at(2, 13))).await.unwrap());
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_purge_removes_tests_without_attempts() {
+    for_each_backend(|db| async move {
+        let tests = db.tests();
+        // Foreign keys are enforced
+        assert!(db.questions().insert(&question("missing", 1)).await.is_err());
+        assert_eq!(tests.purge("missing").await.unwrap(), Err(PurgeError::NotFound));
+
+        for id in ["13wpm-general", "13wpm-general-b"] {
+            let new_test = NewTest {
+                id,
+                title: "General",
+                speed_wpm: 13,
+                year: "1990",
+                audio_url: "/audio/13wpm.mp3",
+                passing_score: 7,
+                draw_count: None,
+                active: true,
+                segments: None,
+                expected_copy_text: None,
+            };
+            assert!(tests.insert_with_questions(&new_test, &[question(id, 1), question(id, 2)]).await.unwrap());
+        }
+        let (_, revision_id) = db
+            .test_revisions()
+            .publish("13wpm-general", None, at(1, 9))
+            .await
+            .unwrap()
+            .unwrap();
+        let session = ExamSession {
+            id: "s1".to_string(),
+            test_id: "13wpm-general".to_string(),
+            revision_id,
+            shuffled: false,
+            question_ids: None,
+            created_at: at(2, 9),
+            submitted_at: None,
+        };
+        db.exam_sessions().insert(&session).await.unwrap();
+
+        // The audio stays while another test uses it
+        assert_eq!(tests.purge("13wpm-general").await.unwrap(), Ok(None));
+        assert!(tests.find_admin("13wpm-general").await.unwrap().is_none());
+        assert!(db.questions().list_admin("13wpm-general").await.unwrap().is_empty());
+        assert!(db.exam_sessions().find("s1").await.unwrap().is_none());
+        assert!(db.test_revisions().list("13wpm-general").await.unwrap().is_empty());
+        assert_eq!(
+            tests.purge("13wpm-general-b").await.unwrap(),
+            Ok(Some("/audio/13wpm.mp3".to_string()))
+        );
+
+        db.attempts().insert(&attempt("a1", "W1AW", false, at(3, 9))).await.unwrap();
+        assert_eq!(tests.purge("20wpm-extra-1991").await.unwrap(), Err(PurgeError::HasAttempts(1)));
+        assert!(tests.find_admin("20wpm-extra-1991").await.unwrap().is_some());
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_clone_copies_the_draft() {
+    for_each_backend(|db| async move {
+        let tests = db.tests();
+        for n in 1..=3 {
+            db.questions().insert(&question("20wpm-extra-1991", n)).await.unwrap();
+        }
+        let changes: Vec<(&'static str, Value)> = vec![
+            ("segments", r#"[{"name":"Test","start_time":0,"end_time":null}]"#.into()),
+            ("expected_copy_text", "VVV DE W1AW".into()),
+            ("draw_count", 2.into()),
+        ];
+        assert_eq!(tests.update("20wpm-extra-1991", changes).await.unwrap(), 1);
+        db.test_revisions().publish("20wpm-extra-1991", None, at(1, 9)).await.unwrap().unwrap();
+
+        let cloned = tests
+            .clone_test("20wpm-extra-1991", "20wpm-extra-1991-b", "Extra, variant B", "/audio/b.mp3")
+            .await
+            .unwrap();
+        assert_eq!(cloned, Ok(3));
+        let source = tests.find_admin("20wpm-extra-1991").await.unwrap().unwrap();
+        let copy = tests.find_admin("20wpm-extra-1991-b").await.unwrap().unwrap();
+        assert_eq!((copy.title.as_str(), copy.audio_url.as_str()), ("Extra, variant B", "/audio/b.mp3"));
+        assert!(!copy.active);
+        assert_eq!(copy.published_revision, None);
+        assert_eq!(copy.question_count, 3);
+        assert_eq!((copy.speed_wpm, copy.draw_count), (source.speed_wpm, source.draw_count));
+        assert_eq!(copy.segments, source.segments);
+        assert_eq!(copy.expected_copy_text.as_deref(), Some("VVV DE W1AW"));
+
+        let originals = db.questions().list_exported("20wpm-extra-1991").await.unwrap();
+        let copies = db.questions().list_exported("20wpm-extra-1991-b").await.unwrap();
+        assert_eq!(serde_json::to_value(copies).unwrap(), serde_json::to_value(originals).unwrap());
+
+        assert_eq!(
+            tests.clone_test("missing", "missing-b", "Missing", "/audio/b.mp3").await.unwrap(),
+            Err(CloneError::NotFound)
+        );
+        assert_eq!(
+            tests
+                .clone_test("20wpm-extra-1991", "20wpm-extra-1991-b", "Again", "/audio/b.mp3")
+                .await
+                .unwrap(),
+            Err(CloneError::IdTaken)
+        );
+        assert!(tests.find_admin("missing-b").await.unwrap().is_none());
+
+        // Racing clones to one id: one wins, the others are told it is taken
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let db = db.clone();
+                tokio::spawn(async move {
+                    db.tests()
+                        .clone_test("20wpm-extra-1991", "20wpm-extra-1991-c", "Variant C", "/audio/c.mp3")
+                        .await
+                })
+            })
+            .collect();
+        let mut outcomes = Vec::new();
+        for handle in handles {
+            outcomes.push(handle.await.unwrap().unwrap());
+        }
+        assert_eq!(outcomes.iter().filter(|o| **o == Ok(3)).count(), 1);
+        assert_eq!(outcomes.iter().filter(|o| **o == Err(CloneError::IdTaken)).count(), 3);
+        assert_eq!(tests.find_admin("20wpm-extra-1991-c").await.unwrap().unwrap().question_count, 3);
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_prosigns_and_settings() {
+    for_each_backend(|db| async move {
+        let prosigns = db.prosigns();
+        assert_eq!(prosigns.count().await.unwrap(), 2);
+
+        let id = prosigns.insert("<SK>", "%").await.unwrap();
+        let err = prosigns.insert("<SK>", "#").await.unwrap_err();
+        assert!(is_unique_violation(&err));
+        assert_eq!(prosigns.update(&id, "<SK>", "#").await.unwrap(), 1);
+        assert!(prosigns
+            .pairs()
+            .await
+            .unwrap()
+            .contains(&("<SK>".to_string(), "#".to_string())));
+        assert_eq!(prosigns.delete(&id).await.unwrap(), 1);
+        assert_eq!(prosigns.list().await.unwrap().len(), 2);
+
+        let settings = db.settings();
+        assert_eq!(settings.get("email_template").await.unwrap(), None);
+        settings.set("email_template", "Hello").await.unwrap();
+        settings.set("email_template", "Hi {callsign}").await.unwrap();
+        assert_eq!(
+            settings.get("email_template").await.unwrap().as_deref(),
+            Some("Hi {callsign}")
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_concurrent_approvals_get_distinct_numbers() {
+    for_each_backend(|db| async move {
+        let ids: Vec<String> = (0..8).map(|i| format!("a{}", i)).collect();
+        for (i, id) in ids.iter().enumerate() {
+            let callsign = format!("W{}AW", i);
+            db.attempts().insert(&attempt(id, &callsign, true, at(1, 9))).await.unwrap();
+        }
+
+        let handles: Vec<_> = ids
+            .iter()
+            .map(|id| {
+                let db = db.clone();
+                let id = id.clone();
+                tokio::spawn(async move { db.attempts().approve(&id, None, None, at(2, 9)).await })
+            })
+            .collect();
+        let mut numbers = Vec::new();
+        for handle in handles {
+            numbers.push(handle.await.unwrap().unwrap().unwrap());
+        }
+        numbers.sort();
+        assert_eq!(numbers, (1..=8).collect::<Vec<i32>>());
+        assert!(db.certificates().audit().await.unwrap().is_clean());
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_failed_approval_leaves_no_gap() {
+    for_each_backend(|db| async move {
+        let attempts = db.attempts();
+        attempts.insert(&attempt("a1", "W1AW", true, at(1, 9))).await.unwrap();
+        attempts.insert(&attempt("a2", "K1ABC", false, at(1, 10))).await.unwrap();
+        attempts.insert(&attempt("a3", "N0CALL", true, at(1, 11))).await.unwrap();
+
+        assert_eq!(attempts.approve("a1", None, None, at(2, 9)).await.unwrap(), Some(1));
+        // Not pending: no number is consumed
+        assert_eq!(attempts.approve("a2", None, None, at(2, 9)).await.unwrap(), None);
+        assert_eq!(attempts.approve("missing", None, None, at(2, 9)).await.unwrap(), None);
+        assert_eq!(attempts.approve("a3", None, None, at(2, 9)).await.unwrap(), Some(2));
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_repair_renumbers_duplicates() {
+    for_each_backend(|db| async move {
+        let attempts = db.attempts();
+        attempts.insert(&attempt("a1", "W1AW", true, at(1, 9))).await.unwrap();
+        attempts.insert(&attempt("a2", "K1ABC", true, at(1, 10))).await.unwrap();
+        attempts.approve("a1", None, None, at(2, 9)).await.unwrap();
+        attempts.approve("a2", None, None, at(2, 10)).await.unwrap();
+
+        // Simulate data from before the ledger: a duplicate and a gap
+        db.execute(
+            crate::db::query("UPDATE attempts SET certificate_number = 1 WHERE id = 'a2'"),
+        )
+        .await
+        .unwrap();
+        db.execute(crate::db::query("DELETE FROM certificate_numbers WHERE number = 2"))
+            .await
+            .unwrap();
+        attempts.insert(&attempt("a3", "N0CALL", true, at(1, 11))).await.unwrap();
+        db.execute(
+            crate::db::query(
+                "UPDATE attempts SET validation_status = 'approved', certificate_number = 4, validated_at = ? WHERE id = 'a3'",
+            )
+            .bind(at(2, 11)),
+        )
+        .await
+        .unwrap();
+
+        let certificates = db.certificates();
+        let report = certificates.audit().await.unwrap();
+        assert_eq!(report.duplicates, vec![(1, vec!["W1AW".to_string(), "K1ABC".to_string()])]);
+        assert_eq!(report.gaps, vec![2, 3]);
+
+        let renumbered = certificates.repair().await.unwrap();
+        assert_eq!(renumbered.len(), 1);
+        assert_eq!(renumbered[0].attempt_id, "a2");
+        assert_eq!((renumbered[0].old, renumbered[0].new), (Some(1), 5));
+
+        let report = certificates.audit().await.unwrap();
+        assert!(report.duplicates.is_empty());
+        assert!(report.problems.is_empty());
+        assert_eq!(report.gaps, vec![2, 3]);
+    })
+    .await;
+}
+
+
+#[tokio::test]
+async fn test_revoke_retires_number() {
+    for_each_backend(|db| async move {
+        let attempts = db.attempts();
+        let certificates = db.certificates();
+        attempts.insert(&attempt("a1", "W1AW", true, at(1, 9))).await.unwrap();
+        attempts.insert(&attempt("a2", "K1ABC", true, at(1, 10))).await.unwrap();
+        attempts.approve("a1", None, None, at(2, 9)).await.unwrap();
+
+        let event = certificates.revoke("a1", "Duplicate account", at(3, 9)).await.unwrap().unwrap();
+        assert_eq!(event.certificate_number, Some(1));
+        assert!(certificates.is_revoked("a1").await.unwrap());
+        let issued = certificates.find_by_number(1).await.unwrap().unwrap();
+        assert_eq!((issued.callsign.as_str(), issued.validation_status.as_str()), ("W1AW", "revoked"));
+        assert!(certificates.find_by_number(2).await.unwrap().is_none());
+        assert!(attempts.find_certified("a1").await.unwrap().is_none());
+        assert!(attempts.roster().await.unwrap().is_empty());
+        // Only approved certificates can be revoked
+        assert!(certificates.revoke("a1", "Again", at(3, 9)).await.unwrap().is_none());
+        assert!(certificates.revoke("a2", "Pending", at(3, 9)).await.unwrap().is_none());
+
+        // The revoked number is not handed out again, and is not a gap
+        assert_eq!(attempts.approve("a2", None, None, at(3, 10)).await.unwrap(), Some(2));
+        assert!(certificates.audit().await.unwrap().is_clean());
+
+        let event = certificates.reinstate("a1", None, at(4, 9)).await.unwrap().unwrap();
+        assert_eq!(event.certificate_number, Some(1));
+        assert!(certificates.reinstate("a1", None, at(4, 9)).await.unwrap().is_none());
+        assert_eq!(
+            attempts.find_certified("a1").await.unwrap().unwrap().certificate_number,
+            Some(1)
+        );
+
+        let actions: Vec<(String, Option<String>)> = certificates
+            .history("a1")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.action, e.reason))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                ("revoked".to_string(), Some("Duplicate account".to_string())),
+                ("reinstated".to_string(), None),
+            ]
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_reissue_moves_certificate_to_new_callsign() {
+    for_each_backend(|db| async move {
+        let attempts = db.attempts();
+        let certificates = db.certificates();
+        attempts.insert(&attempt("a1", "KD9ABC", true, at(1, 9))).await.unwrap();
+        attempts.insert(&attempt("a2", "W1AW", true, at(1, 10))).await.unwrap();
+        attempts.approve("a1", None, None, at(2, 9)).await.unwrap();
+        attempts.approve("a2", None, None, at(2, 10)).await.unwrap();
+
+        let event = certificates
+            .reissue("a1", "AA9A", Some("Vanity call"), at(3, 9))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.old_callsign.as_deref(), Some("KD9ABC"));
+        assert_eq!(event.new_callsign.as_deref(), Some("AA9A"));
+        let certified = attempts.find_certified("a1").await.unwrap().unwrap();
+        assert_eq!((certified.callsign.as_str(), certified.certificate_number), ("AA9A", Some(1)));
+
+        assert_eq!(
+            certificates.reissue("a1", "W1AW", None, at(3, 9)).await.unwrap().unwrap_err(),
+            super::ReissueError::CallsignCertified
+        );
+        assert_eq!(
+            certificates.reissue("missing", "N0CALL", None, at(3, 9)).await.unwrap().unwrap_err(),
+            super::ReissueError::NotApproved
+        );
+        assert_eq!(certificates.history("a1").await.unwrap().len(), 1);
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_certificate_templates_are_versioned() {
+    for_each_backend(|db| async move {
+        let templates = db.certificate_templates();
+        assert!(templates.create("extra", "Extra", "<svg>v1</svg>", None, at(1, 9)).await.unwrap());
+        assert!(!templates.create("extra", "Other", "<svg/>", None, at(1, 9)).await.unwrap());
+
+        assert_eq!(
+            templates
+                .save_version("extra", "Extra Class", "<svg>v2</svg>", Some("New seal"), at(2, 9))
+                .await
+                .unwrap(),
+            Some(2)
+        );
+        assert_eq!(templates.save_version("missing", "X", "<svg/>", None, at(2, 9)).await.unwrap(), None);
+
+        let latest = templates.find("extra").await.unwrap().unwrap();
+        assert_eq!((latest.name.as_str(), latest.version, latest.svg.as_str()), ("Extra Class", 2, "<svg>v2</svg>"));
+        let first = templates.find_version("extra", 1).await.unwrap().unwrap();
+        assert_eq!(first.svg, "<svg>v1</svg>");
+        let history: Vec<(i32, Option<String>)> = templates
+            .history("extra")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|revision| (revision.version, revision.note))
+            .collect();
+        assert_eq!(history, vec![(2, Some("New seal".to_string())), (1, None)]);
+
+        assert!(templates.for_test("20wpm-extra-1991").await.unwrap().is_none());
+        db.tests()
+            .update("20wpm-extra-1991", vec![("certificate_template_id", "extra".into())])
+            .await
+            .unwrap();
+        assert_eq!(templates.for_test("20wpm-extra-1991").await.unwrap().unwrap().version, 2);
+
+        let listed = templates.list().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!((listed[0].current_version, listed[0].test_count), (2, 1));
+        assert_eq!(listed[0].updated_at, at(2, 9));
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_approval_records_operator_name() {
+    for_each_backend(|db| async move {
+        let attempts = db.attempts();
+        attempts.insert(&attempt("a1", "W1AW", true, at(1, 9))).await.unwrap();
+        attempts.approve("a1", None, Some("Hiram Maxim"), at(2, 9)).await.unwrap();
+
+        let certified = attempts.find_certified("a1").await.unwrap().unwrap();
+        assert_eq!(certified.name.as_deref(), Some("Hiram Maxim"));
+    })
+    .await;
+}
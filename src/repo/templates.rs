@@ -0,0 +1,217 @@
+use crate::db::{query, Db};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+/// A certificate template with its latest version number
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct CertificateTemplateSummary {
+    pub id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub current_version: i32,
+    pub updated_at: DateTime<Utc>,
+    /// Tests whose certificates use this template
+    pub test_count: i64,
+}
+
+/// One saved version of a certificate template
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct CertificateTemplateVersion {
+    pub template_id: String,
+    pub name: String,
+    pub version: i32,
+    pub svg: String,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A version in a template's history, without its SVG
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct CertificateTemplateRevision {
+    pub version: i32,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+const VERSION_SELECT: &str = r#"
+    SELECT v.template_id, t.name, v.version, v.svg, v.note, v.created_at
+    FROM certificate_template_versions v
+    JOIN certificate_templates t ON t.id = v.template_id
+"#;
+
+const LATEST_VERSION: &str =
+    "v.version = (SELECT MAX(version) FROM certificate_template_versions WHERE template_id = v.template_id)";
+
+/// Versioned certificate designs (`certificate_templates`)
+pub struct CertificateTemplateRepo<'a> {
+    db: &'a Db,
+}
+
+impl<'a> CertificateTemplateRepo<'a> {
+    pub fn new(db: &'a Db) -> Self {
+        CertificateTemplateRepo { db }
+    }
+
+    pub async fn list(&self) -> Result<Vec<CertificateTemplateSummary>, sqlx::Error> {
+        self.db
+            .fetch_all(query(format!(
+                "SELECT t.id, t.name, t.created_at, v.version AS current_version, v.created_at AS updated_at,
+                        CAST((SELECT COUNT(*) FROM tests WHERE certificate_template_id = t.id) AS BIGINT) AS test_count
+                 FROM certificate_templates t
+                 JOIN certificate_template_versions v ON v.template_id = t.id
+                 WHERE {}
+                 ORDER BY t.name",
+                LATEST_VERSION
+            )))
+            .await
+    }
+
+    /// The latest version of a template
+    pub async fn find(&self, id: &str) -> Result<Option<CertificateTemplateVersion>, sqlx::Error> {
+        self.db
+            .fetch_optional(
+                query(format!("{} WHERE v.template_id = ? AND {}", VERSION_SELECT, LATEST_VERSION)).bind(id),
+            )
+            .await
+    }
+
+    pub async fn find_version(
+        &self,
+        id: &str,
+        version: i32,
+    ) -> Result<Option<CertificateTemplateVersion>, sqlx::Error> {
+        self.db
+            .fetch_optional(
+                query(format!("{} WHERE v.template_id = ? AND v.version = ?", VERSION_SELECT))
+                    .bind(id)
+                    .bind(version),
+            )
+            .await
+    }
+
+    /// Every saved version of a template, newest first
+    pub async fn history(&self, id: &str) -> Result<Vec<CertificateTemplateRevision>, sqlx::Error> {
+        self.db
+            .fetch_all(
+                query(
+                    "SELECT version, note, created_at FROM certificate_template_versions
+                     WHERE template_id = ? ORDER BY version DESC",
+                )
+                .bind(id),
+            )
+            .await
+    }
+
+    /// The latest version of the template bound to a test, if it has one
+    pub async fn for_test(&self, test_id: &str) -> Result<Option<CertificateTemplateVersion>, sqlx::Error> {
+        self.db
+            .fetch_optional(
+                query(format!(
+                    "{} JOIN tests ON tests.certificate_template_id = v.template_id
+                     WHERE tests.id = ? AND {}",
+                    VERSION_SELECT, LATEST_VERSION
+                ))
+                .bind(test_id),
+            )
+            .await
+    }
+
+    /// Create a template with its first version.
+    /// Returns false, changing nothing, if the id is already taken.
+    pub async fn create(
+        &self,
+        id: &str,
+        name: &str,
+        svg: &str,
+        note: Option<&str>,
+        at: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+
+        let existing: Option<(String,)> = tx
+            .fetch_optional(query("SELECT id FROM certificate_templates WHERE id = ?").bind(id))
+            .await?;
+        if existing.is_some() {
+            return Ok(false);
+        }
+
+        tx.execute(
+            query("INSERT INTO certificate_templates (id, name, created_at) VALUES (?, ?, ?)")
+                .bind(id)
+                .bind(name)
+                .bind(at),
+        )
+        .await?;
+        tx.execute(
+            query(
+                "INSERT INTO certificate_template_versions (template_id, version, svg, note, created_at)
+                 VALUES (?, 1, ?, ?, ?)",
+            )
+            .bind(id)
+            .bind(svg)
+            .bind(note)
+            .bind(at),
+        )
+        .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Save a new version of a template, renaming it at the same time.
+    /// Returns the new version number, or None if the template does not exist.
+    pub async fn save_version(
+        &self,
+        id: &str,
+        name: &str,
+        svg: &str,
+        note: Option<&str>,
+        at: DateTime<Utc>,
+    ) -> Result<Option<i32>, sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+
+        // Writing first locks the template, so concurrent saves number their
+        // versions one after the other
+        let updated = tx
+            .execute(
+                query("UPDATE certificate_templates SET name = ? WHERE id = ?")
+                    .bind(name)
+                    .bind(id),
+            )
+            .await?;
+        if updated == 0 {
+            return Ok(None);
+        }
+
+        let (latest,): (i32,) = tx
+            .fetch_one(
+                query("SELECT MAX(version) FROM certificate_template_versions WHERE template_id = ?").bind(id),
+            )
+            .await?;
+        let version = latest + 1;
+        tx.execute(
+            query(
+                "INSERT INTO certificate_template_versions (template_id, version, svg, note, created_at)
+                 VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(id)
+            .bind(version)
+            .bind(svg)
+            .bind(note)
+            .bind(at),
+        )
+        .await?;
+
+        tx.commit().await?;
+        Ok(Some(version))
+    }
+
+    pub async fn exists(&self, id: &str) -> Result<bool, sqlx::Error> {
+        let row: Option<(String,)> = self
+            .db
+            .fetch_optional(query("SELECT id FROM certificate_templates WHERE id = ?").bind(id))
+            .await?;
+        Ok(row.is_some())
+    }
+}
@@ -0,0 +1,293 @@
+use super::questions::update_query;
+use super::NewQuestion;
+use crate::admin::{AdminTestRow, ExportedQuestion};
+use crate::db::{is_unique_violation, query, Db, Query, Value};
+use crate::TestRow;
+use chrono::Utc;
+
+/// A test definition to insert
+#[derive(Debug)]
+pub struct NewTest<'a> {
+    pub id: &'a str,
+    pub title: &'a str,
+    pub speed_wpm: i32,
+    pub year: &'a str,
+    pub audio_url: &'a str,
+    pub passing_score: i32,
+    /// Questions drawn per exam session, or None to ask them all
+    pub draw_count: Option<i32>,
+    pub active: bool,
+    /// Segments serialized as JSON
+    pub segments: Option<String>,
+    pub expected_copy_text: Option<&'a str>,
+}
+
+impl NewTest<'_> {
+    fn insert_query(&self) -> Query {
+        query(
+            "INSERT INTO tests (id, title, speed_wpm, year, audio_url, passing_score, draw_count, active, created_at, segments, expected_copy_text)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(self.id)
+        .bind(self.title)
+        .bind(self.speed_wpm)
+        .bind(self.year)
+        .bind(self.audio_url)
+        .bind(self.passing_score)
+        .bind(self.draw_count)
+        .bind(self.active)
+        .bind(Utc::now())
+        .bind(self.segments.clone())
+        .bind(self.expected_copy_text)
+    }
+}
+
+/// Why a test was not purged
+#[derive(Debug, PartialEq)]
+pub enum PurgeError {
+    NotFound,
+    /// Attempts were graded against the test, which must keep it to show them
+    HasAttempts(i64),
+}
+
+/// Why a test was not cloned
+#[derive(Debug, PartialEq)]
+pub enum CloneError {
+    NotFound,
+    IdTaken,
+}
+
+/// Active tests as candidates see them: the published revision's fields.
+/// Tests that were never published are left out.
+const PUBLISHED_TEST_SELECT: &str = r#"
+    SELECT t.id, r.title, r.speed_wpm, r.year, r.audio_url, r.passing_score, r.draw_count, t.active, t.created_at,
+           r.segments, r.expected_copy_text, t.shuffle, r.id AS revision_id,
+           t.available_from, t.available_until, t.availability_windows
+    FROM tests t
+    JOIN test_revisions r ON r.id = t.published_revision_id
+    WHERE t.active = TRUE
+"#;
+
+const ADMIN_TEST_SELECT: &str = r#"
+    SELECT t.id, t.title, t.speed_wpm, t.year, t.audio_url, t.passing_score, t.draw_count, t.active, t.created_at, t.segments,
+           (SELECT COUNT(*) FROM questions WHERE test_id = t.id) as question_count,
+           t.expected_copy_text, t.certificate_template_id, t.shuffle,
+           (SELECT revision FROM test_revisions WHERE id = t.published_revision_id) as published_revision,
+           t.available_from, t.available_until, t.availability_windows
+    FROM tests t
+"#;
+
+/// Morse code tests (audio, passing score, segments)
+pub struct TestRepo<'a> {
+    db: &'a Db,
+}
+
+impl<'a> TestRepo<'a> {
+    pub fn new(db: &'a Db) -> Self {
+        TestRepo { db }
+    }
+
+    pub async fn count(&self) -> Result<i64, sqlx::Error> {
+        let row: (i64,) = self.db.fetch_one(query("SELECT COUNT(*) FROM tests")).await?;
+        Ok(row.0)
+    }
+
+    pub async fn list_active(&self) -> Result<Vec<TestRow>, sqlx::Error> {
+        self.db
+            .fetch_all(query(format!("{} ORDER BY r.speed_wpm", PUBLISHED_TEST_SELECT)))
+            .await
+    }
+
+    pub async fn find_active(&self, id: &str) -> Result<Option<TestRow>, sqlx::Error> {
+        self.db
+            .fetch_optional(
+                query(format!("{} AND t.id = ?", PUBLISHED_TEST_SELECT)).bind(id),
+            )
+            .await
+    }
+
+    /// All tests including inactive ones, with question counts
+    pub async fn list_admin(&self) -> Result<Vec<AdminTestRow>, sqlx::Error> {
+        self.db
+            .fetch_all(query(format!(
+                "{} ORDER BY t.speed_wpm, t.title",
+                ADMIN_TEST_SELECT
+            )))
+            .await
+    }
+
+    pub async fn find_admin(&self, id: &str) -> Result<Option<AdminTestRow>, sqlx::Error> {
+        self.db
+            .fetch_optional(query(format!("{} WHERE t.id = ?", ADMIN_TEST_SELECT)).bind(id))
+            .await
+    }
+
+    pub async fn insert(&self, test: &NewTest<'_>) -> Result<(), sqlx::Error> {
+        self.db.execute(test.insert_query()).await?;
+        Ok(())
+    }
+
+    /// Insert a test and its questions in one transaction.
+    /// Returns false, changing nothing, if the test id is already taken.
+    pub async fn insert_with_questions(
+        &self,
+        test: &NewTest<'_>,
+        questions: &[NewQuestion<'_>],
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+
+        let existing: Option<(String,)> = tx
+            .fetch_optional(query("SELECT id FROM tests WHERE id = ?").bind(test.id))
+            .await?;
+        if existing.is_some() {
+            return Ok(false);
+        }
+
+        tx.execute(test.insert_query()).await?;
+        for question in questions {
+            question.insert_in(&mut tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Copy a test's draft and its questions under a new id, title and audio
+    /// URL in one transaction. The copy starts inactive and unpublished.
+    /// Returns the number of questions copied.
+    pub async fn clone_test(
+        &self,
+        source_id: &str,
+        id: &str,
+        title: &str,
+        audio_url: &str,
+    ) -> Result<Result<usize, CloneError>, sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+
+        // Insert before reading so SQLite takes its write lock first; a
+        // concurrent clone to the same id then fails on the primary key
+        let inserted = tx
+            .execute(
+                query(
+                    "INSERT INTO tests (id, title, speed_wpm, year, audio_url, passing_score, draw_count, active, created_at,
+                                        segments, expected_copy_text, certificate_template_id, shuffle,
+                                        available_from, available_until, availability_windows)
+                     SELECT ?, ?, speed_wpm, year, ?, passing_score, draw_count, FALSE, ?,
+                            segments, expected_copy_text, certificate_template_id, shuffle,
+                            available_from, available_until, availability_windows
+                     FROM tests WHERE id = ?",
+                )
+                .bind(id)
+                .bind(title)
+                .bind(audio_url)
+                .bind(Utc::now())
+                .bind(source_id),
+            )
+            .await;
+        let inserted = match inserted {
+            Err(e) if is_unique_violation(&e) => return Ok(Err(CloneError::IdTaken)),
+            inserted => inserted?,
+        };
+        if inserted == 0 {
+            return Ok(Err(CloneError::NotFound));
+        }
+
+        let questions: Vec<ExportedQuestion> = tx
+            .fetch_all(
+                query(
+                    "SELECT question_number, question_text, question_type, options, correct_option, accepted_answers, topic
+                     FROM questions WHERE test_id = ? ORDER BY question_number",
+                )
+                .bind(source_id),
+            )
+            .await?;
+        for q in &questions {
+            let (options, accepted_answers) = (q.options.as_strs(), q.accepted_answers.as_strs());
+            let question = NewQuestion {
+                test_id: id,
+                question_number: q.question_number,
+                question_text: &q.question_text,
+                question_type: q.question_type,
+                options: &options,
+                correct_option: &q.correct_option,
+                accepted_answers: &accepted_answers,
+                topic: q.topic.as_deref(),
+            };
+            question.insert_in(&mut tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(Ok(questions.len()))
+    }
+
+    /// Set the given columns. Column names must come from code, never from input.
+    pub async fn update(
+        &self,
+        id: &str,
+        changes: Vec<(&'static str, Value)>,
+    ) -> Result<u64, sqlx::Error> {
+        self.db.execute(update_query("tests", id, changes)).await
+    }
+
+    /// Remove a test that no attempt was graded against, with its questions,
+    /// revisions and exam sessions, in one transaction. Returns the test's
+    /// audio URL if no other test or revision still uses it.
+    pub async fn purge(&self, id: &str) -> Result<Result<Option<String>, PurgeError>, sqlx::Error> {
+        let mut tx = self.db.begin().await?;
+
+        // Touch the row first so SQLite takes its write lock before reading
+        let updated = tx
+            .execute(query("UPDATE tests SET published_revision_id = NULL WHERE id = ?").bind(id))
+            .await?;
+        if updated == 0 {
+            return Ok(Err(PurgeError::NotFound));
+        }
+
+        let (attempts,): (i64,) = tx
+            .fetch_one(
+                query(
+                    "SELECT COUNT(*) FROM attempts
+                     WHERE test_id = ? OR revision_id IN (SELECT id FROM test_revisions WHERE test_id = ?)",
+                )
+                .bind(id)
+                .bind(id),
+            )
+            .await?;
+        if attempts > 0 {
+            return Ok(Err(PurgeError::HasAttempts(attempts)));
+        }
+
+        let (audio_url,): (String,) = tx
+            .fetch_one(query("SELECT audio_url FROM tests WHERE id = ?").bind(id))
+            .await?;
+        for sql in [
+            "DELETE FROM exam_sessions WHERE test_id = ?",
+            "DELETE FROM test_revision_questions WHERE revision_id IN (SELECT id FROM test_revisions WHERE test_id = ?)",
+            "DELETE FROM test_revisions WHERE test_id = ?",
+            "DELETE FROM questions WHERE test_id = ?",
+            "DELETE FROM tests WHERE id = ?",
+        ] {
+            tx.execute(query(sql).bind(id)).await?;
+        }
+
+        let (users,): (i64,) = tx
+            .fetch_one(
+                query(
+                    "SELECT (SELECT COUNT(*) FROM tests WHERE audio_url = ?)
+                          + (SELECT COUNT(*) FROM test_revisions WHERE audio_url = ?)",
+                )
+                .bind(audio_url.as_str())
+                .bind(audio_url.as_str()),
+            )
+            .await?;
+        tx.commit().await?;
+
+        Ok(Ok((users == 0).then_some(audio_url)))
+    }
+
+    pub async fn deactivate(&self, id: &str) -> Result<u64, sqlx::Error> {
+        self.db
+            .execute(query("UPDATE tests SET active = FALSE WHERE id = ?").bind(id))
+            .await
+    }
+}
@@ -0,0 +1,353 @@
+//! Scheduled availability of tests
+//!
+//! On top of the manual `active` switch, a test can be limited to a date range
+//! (`available_from`, `available_until`) and to recurring windows, such as a
+//! Straight Key Night exam open for 24 hours every 1 January. A window is
+//! given by its first occurrence and how often it repeats; later occurrences
+//! last as long as the first.
+
+use chrono::{DateTime, Duration, Months, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Recurrence {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Recurrence {
+    /// The `n`th repeat of `first`. Monthly and yearly repeats of a day the
+    /// month lacks fall on its last day.
+    fn nth(self, first: DateTime<Utc>, n: u32) -> Option<DateTime<Utc>> {
+        match self {
+            Recurrence::Daily => first.checked_add_signed(Duration::days(n.into())),
+            Recurrence::Weekly => first.checked_add_signed(Duration::weeks(n.into())),
+            Recurrence::Monthly => first.checked_add_months(Months::new(n)),
+            Recurrence::Yearly => first.checked_add_months(Months::new(n.checked_mul(12)?)),
+        }
+    }
+
+    /// Repeats of `first` up to `at`, counted by calendar fields. Never fewer
+    /// than have started, and at most one more.
+    fn estimate(self, first: DateTime<Utc>, at: DateTime<Utc>) -> u32 {
+        use chrono::Datelike;
+        let months = |d: DateTime<Utc>| i64::from(d.year()) * 12 + i64::from(d.month0());
+        let count = match self {
+            Recurrence::Daily => (at - first).num_days(),
+            Recurrence::Weekly => (at - first).num_weeks(),
+            Recurrence::Monthly => months(at) - months(first),
+            Recurrence::Yearly => i64::from(at.year() - first.year()),
+        };
+        u32::try_from(count).unwrap_or(if count < 0 { 0 } else { u32::MAX })
+    }
+}
+
+/// A window that opens again every day, week, month or year
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecurringWindow {
+    /// Opening of the first occurrence
+    pub start: DateTime<Utc>,
+    /// Closing of the first occurrence
+    pub end: DateTime<Utc>,
+    pub repeat: Recurrence,
+}
+
+impl RecurringWindow {
+    /// The first occurrence still open at or after `at`, as the time it is
+    /// open from (no earlier than `at`) and the time it closes
+    fn next_open(&self, at: DateTime<Utc>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let length = self.end - self.start;
+        if at < self.start {
+            return Some((self.start, self.end));
+        }
+
+        // The latest occurrence to have started by `at`
+        let mut n = self.repeat.estimate(self.start, at);
+        let mut start = self.repeat.nth(self.start, n)?;
+        while start > at && n > 0 {
+            n -= 1;
+            start = self.repeat.nth(self.start, n)?;
+        }
+
+        let end = start.checked_add_signed(length)?;
+        if at < end {
+            return Some((at, end));
+        }
+        let next = self.repeat.nth(self.start, n + 1)?;
+        Some((next, next.checked_add_signed(length)?))
+    }
+}
+
+/// When a test may be taken
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Schedule {
+    #[serde(default)]
+    pub available_from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub available_until: Option<DateTime<Utc>>,
+    /// When not empty, the test is only open while one of these is
+    #[serde(default)]
+    pub windows: Vec<RecurringWindow>,
+    /// The stored windows could not be read, so the test stays closed until
+    /// its schedule is saved again
+    #[serde(skip)]
+    pub unreadable: bool,
+}
+
+/// Whether a test is open at a given time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum Availability {
+    /// Open, until `closes_at` if the schedule sets an end
+    Open { closes_at: Option<DateTime<Utc>> },
+    Upcoming { opens_at: DateTime<Utc> },
+    /// Will not open again
+    Expired,
+}
+
+impl Schedule {
+    /// A test's schedule as stored in its `available_from`,
+    /// `available_until` and `availability_windows` columns
+    pub fn from_stored(
+        test_id: &str,
+        available_from: Option<DateTime<Utc>>,
+        available_until: Option<DateTime<Utc>>,
+        windows: Option<&str>,
+    ) -> Self {
+        let windows = windows.map(serde_json::from_str::<Vec<RecurringWindow>>).transpose();
+        if let Err(ref e) = windows {
+            tracing::warn!("Failed to parse availability windows for test {}, keeping it closed: {}", test_id, e);
+        }
+        Schedule {
+            available_from,
+            available_until,
+            unreadable: windows.is_err(),
+            windows: windows.ok().flatten().unwrap_or_default(),
+        }
+    }
+
+    pub fn availability(&self, now: DateTime<Utc>) -> Availability {
+        if self.unreadable {
+            return Availability::Expired;
+        }
+        let earliest = self.available_from.map_or(now, |from| from.max(now));
+        let (opens_at, closes_at) = if self.windows.is_empty() {
+            (earliest, None)
+        } else {
+            let occurrences: Vec<_> = self
+                .windows
+                .iter()
+                .filter_map(|window| window.next_open(earliest))
+                .collect();
+            let Some(opens_at) = occurrences.iter().map(|(opens, _)| *opens).min() else {
+                return Availability::Expired;
+            };
+            let closes_at = occurrences
+                .iter()
+                .filter(|(opens, _)| *opens == opens_at)
+                .map(|(_, closes)| *closes)
+                .max();
+            (opens_at, closes_at)
+        };
+
+        if self.available_until.is_some_and(|until| opens_at >= until) {
+            return Availability::Expired;
+        }
+        if opens_at > now {
+            return Availability::Upcoming { opens_at };
+        }
+        let closes_at = match (closes_at, self.available_until) {
+            (Some(closes), Some(until)) => Some(closes.min(until)),
+            (closes, until) => closes.or(until),
+        };
+        Availability::Open { closes_at }
+    }
+
+    pub fn is_open(&self, now: DateTime<Utc>) -> bool {
+        matches!(self.availability(now), Availability::Open { .. })
+    }
+
+    /// Problems with the schedule: an empty date range, or windows that close
+    /// before they open or stay open past their next opening
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let (Some(from), Some(until)) = (self.available_from, self.available_until) {
+            if until <= from {
+                problems.push("available_until must be after available_from".to_string());
+            }
+        }
+        for (i, window) in self.windows.iter().enumerate() {
+            if window.end <= window.start {
+                problems.push(format!("Window {} must end after it starts", i + 1));
+            } else if window.repeat.nth(window.start, 1).is_some_and(|next| window.end > next) {
+                problems.push(format!("Window {} must close before it opens again", i + 1));
+            }
+        }
+        problems
+    }
+
+    /// The windows as stored in `tests.availability_windows`
+    pub fn windows_json(&self) -> Option<String> {
+        if self.windows.is_empty() {
+            None
+        } else {
+            serde_json::to_string(&self.windows).ok()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, 0, 0).unwrap()
+    }
+
+    fn straight_key_night() -> Schedule {
+        Schedule {
+            windows: vec![RecurringWindow {
+                start: at(2026, 1, 1, 0),
+                end: at(2026, 1, 2, 0),
+                repeat: Recurrence::Yearly,
+            }],
+            ..Schedule::default()
+        }
+    }
+
+    #[test]
+    fn test_date_range() {
+        let schedule = Schedule {
+            available_from: Some(at(2026, 3, 1, 0)),
+            available_until: Some(at(2026, 4, 1, 0)),
+            ..Schedule::default()
+        };
+        assert_eq!(
+            schedule.availability(at(2026, 2, 1, 0)),
+            Availability::Upcoming { opens_at: at(2026, 3, 1, 0) }
+        );
+        assert_eq!(
+            schedule.availability(at(2026, 3, 1, 0)),
+            Availability::Open { closes_at: Some(at(2026, 4, 1, 0)) }
+        );
+        assert_eq!(schedule.availability(at(2026, 4, 1, 0)), Availability::Expired);
+        assert!(Schedule::default().is_open(at(2026, 4, 1, 0)));
+    }
+
+    #[test]
+    fn test_yearly_window() {
+        let schedule = straight_key_night();
+        assert_eq!(
+            schedule.availability(at(2028, 1, 1, 20)),
+            Availability::Open { closes_at: Some(at(2028, 1, 2, 0)) }
+        );
+        assert_eq!(
+            schedule.availability(at(2028, 1, 2, 0)),
+            Availability::Upcoming { opens_at: at(2029, 1, 1, 0) }
+        );
+        assert_eq!(
+            schedule.availability(at(2025, 6, 1, 0)),
+            Availability::Upcoming { opens_at: at(2026, 1, 1, 0) }
+        );
+
+        // The range bounds which occurrences count
+        let limited = Schedule {
+            available_until: Some(at(2027, 6, 1, 0)),
+            ..straight_key_night()
+        };
+        assert_eq!(limited.availability(at(2027, 2, 1, 0)), Availability::Expired);
+        let from_2030 = Schedule {
+            available_from: Some(at(2030, 1, 1, 12)),
+            ..straight_key_night()
+        };
+        assert_eq!(
+            from_2030.availability(at(2028, 1, 1, 12)),
+            Availability::Upcoming { opens_at: at(2030, 1, 1, 12) }
+        );
+    }
+
+    #[test]
+    fn test_monthly_window_on_short_months() {
+        let schedule = Schedule {
+            windows: vec![RecurringWindow {
+                start: at(2026, 1, 31, 18),
+                end: at(2026, 1, 31, 22),
+                repeat: Recurrence::Monthly,
+            }],
+            ..Schedule::default()
+        };
+        assert!(schedule.is_open(at(2026, 2, 28, 19)));
+        assert_eq!(
+            schedule.availability(at(2026, 3, 1, 0)),
+            Availability::Upcoming { opens_at: at(2026, 3, 31, 18) }
+        );
+    }
+
+    #[test]
+    fn test_stored_windows() {
+        let stored = straight_key_night().windows_json();
+        let schedule = Schedule::from_stored("skn", None, None, stored.as_deref());
+        assert_eq!(schedule, straight_key_night());
+        assert!(Schedule::from_stored("open", None, None, None).is_open(at(2026, 3, 1, 0)));
+
+        // Unreadable windows keep the test closed rather than always open
+        let broken = Schedule::from_stored("broken", None, None, Some("[{\"start\": 1}]"));
+        assert!(broken.unreadable && broken.windows.is_empty());
+        assert_eq!(broken.availability(at(2026, 1, 1, 12)), Availability::Expired);
+    }
+
+    #[test]
+    fn test_weekly_windows_and_validation() {
+        let schedule = Schedule {
+            windows: vec![
+                RecurringWindow {
+                    start: at(2026, 3, 2, 18),
+                    end: at(2026, 3, 2, 20),
+                    repeat: Recurrence::Weekly,
+                },
+                RecurringWindow {
+                    start: at(2026, 3, 5, 18),
+                    end: at(2026, 3, 5, 20),
+                    repeat: Recurrence::Weekly,
+                },
+            ],
+            ..Schedule::default()
+        };
+        assert_eq!(
+            schedule.availability(at(2026, 3, 10, 12)),
+            Availability::Upcoming { opens_at: at(2026, 3, 12, 18) }
+        );
+        assert!(schedule.is_open(at(2026, 3, 16, 19)));
+
+        let invalid = Schedule {
+            available_from: Some(at(2026, 3, 2, 0)),
+            available_until: Some(at(2026, 3, 1, 0)),
+            windows: vec![RecurringWindow {
+                start: at(2026, 3, 2, 18),
+                end: at(2026, 3, 2, 18),
+                repeat: Recurrence::Daily,
+            }],
+            ..Schedule::default()
+        };
+        assert_eq!(invalid.validate().len(), 2);
+
+        // Occurrences may meet but not overlap; a month from 31 January is 28 February
+        let window = |start, end, repeat| Schedule {
+            windows: vec![RecurringWindow { start, end, repeat }],
+            ..Schedule::default()
+        };
+        assert!(window(at(2026, 3, 2, 18), at(2026, 3, 3, 18), Recurrence::Daily).validate().is_empty());
+        assert_eq!(
+            window(at(2026, 3, 2, 18), at(2026, 3, 3, 19), Recurrence::Daily).validate(),
+            vec!["Window 1 must close before it opens again".to_string()]
+        );
+        assert_eq!(window(at(2026, 3, 2, 18), at(2026, 3, 10, 18), Recurrence::Weekly).validate().len(), 1);
+        assert!(window(at(2026, 1, 31, 0), at(2026, 2, 28, 0), Recurrence::Monthly).validate().is_empty());
+        assert_eq!(window(at(2026, 1, 31, 0), at(2026, 3, 1, 0), Recurrence::Monthly).validate().len(), 1);
+        assert_eq!(window(at(2026, 1, 1, 0), at(2027, 1, 2, 0), Recurrence::Yearly).validate().len(), 1);
+    }
+}
@@ -0,0 +1,284 @@
+//! Audio segment timing
+//!
+//! Segments are drafted as marker files before they are entered on a test.
+//! Two formats are read:
+//!
+//! - marker lines as in `marks.txt`: `1:02-2:06 - practice`, where times are
+//!   `m:ss`, `h:mm:ss` or seconds, `start` means 0 and `end` means the end of
+//!   the audio
+//! - Audacity label tracks (File → Export → Labels): tab-separated start and
+//!   end seconds and a label. Point labels run until the next label.
+//!
+//! Whether a segment shows the copy box or the questions is inferred from its
+//! name, and can be changed before saving.
+
+use crate::Segment;
+use serde::Serialize;
+
+/// Which marker format a text was read as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkerFormat {
+    Marks,
+    Audacity,
+}
+
+/// Read segments from marker text, detecting its format. Fails with every
+/// malformed line, or with the problems `validate` finds in the result.
+pub fn parse_markers(text: &str) -> Result<(MarkerFormat, Vec<Segment>), String> {
+    let lines: Vec<(usize, &str)> = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim_end_matches('\r')))
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .collect();
+    if lines.is_empty() {
+        return Err("No markers found".to_string());
+    }
+
+    let format = if lines.iter().any(|(_, line)| line.contains('\t')) {
+        MarkerFormat::Audacity
+    } else {
+        MarkerFormat::Marks
+    };
+    let segments = match format {
+        MarkerFormat::Marks => parse_marks(&lines)?,
+        MarkerFormat::Audacity => parse_audacity(&lines)?,
+    };
+
+    let problems = validate(&segments);
+    if !problems.is_empty() {
+        return Err(problems.join("; "));
+    }
+    Ok((format, segments))
+}
+
+/// `start-end - name` lines
+fn parse_marks(lines: &[(usize, &str)]) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut problems = Vec::new();
+
+    for (number, line) in lines {
+        let parsed = line
+            .split_once(" - ")
+            .ok_or("expected 'start-end - name'".to_string())
+            .and_then(|(range, name)| {
+                let (start, end) = range
+                    .trim()
+                    .split_once('-')
+                    .ok_or(format!("expected a time range like 1:02-2:06, found '{}'", range.trim()))?;
+                let start = match start.trim() {
+                    "start" => 0,
+                    time => parse_time(time)?,
+                };
+                let end = match end.trim() {
+                    "end" => None,
+                    time => Some(parse_time(time)?),
+                };
+                Ok(inferred(name.trim(), start, end))
+            });
+        match parsed {
+            Ok(segment) => segments.push(segment),
+            Err(e) => problems.push(format!("Line {}: {}", number, e)),
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(segments)
+    } else {
+        Err(problems.join("; "))
+    }
+}
+
+/// Audacity label track: `start<TAB>end<TAB>label`, seconds as decimals.
+/// Spectral label lines (starting with a backslash) are skipped.
+fn parse_audacity(lines: &[(usize, &str)]) -> Result<Vec<Segment>, String> {
+    let mut labels: Vec<(i32, i32, &str)> = Vec::new();
+    let mut problems = Vec::new();
+
+    for (number, line) in lines {
+        if line.starts_with('\\') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        let parsed = match fields.as_slice() {
+            [start, end, label, ..] => {
+                parse_seconds(start).and_then(|start| Ok((start, parse_seconds(end)?, label.trim())))
+            }
+            _ => Err("expected start, end and label separated by tabs".to_string()),
+        };
+        match parsed {
+            Ok(label) => labels.push(label),
+            Err(e) => problems.push(format!("Line {}: {}", number, e)),
+        }
+    }
+    if !problems.is_empty() {
+        return Err(problems.join("; "));
+    }
+
+    labels.sort_by_key(|(start, _, _)| *start);
+    let next_starts: Vec<Option<i32>> = labels
+        .iter()
+        .skip(1)
+        .map(|(start, _, _)| Some(*start))
+        .chain([None])
+        .collect();
+    Ok(labels
+        .iter()
+        .zip(next_starts)
+        .map(|((start, end, name), next_start)| {
+            // A point label marks where its segment begins
+            let end = if end > start { Some(*end) } else { next_start };
+            inferred(name, *start, end)
+        })
+        .collect())
+}
+
+/// `m:ss`, `h:mm:ss` or plain seconds
+fn parse_time(time: &str) -> Result<i32, String> {
+    let invalid = || format!("invalid time '{}'", time);
+    let mut seconds: i32 = 0;
+    for (i, part) in time.split(':').enumerate() {
+        let value: i32 = part.parse().map_err(|_| invalid())?;
+        if i > 2 || (i > 0 && !(0..60).contains(&value)) {
+            return Err(invalid());
+        }
+        seconds = seconds
+            .checked_mul(60)
+            .and_then(|s| s.checked_add(value))
+            .ok_or_else(invalid)?;
+    }
+    Ok(seconds)
+}
+
+/// Decimal seconds, rounded to the nearest second
+fn parse_seconds(value: &str) -> Result<i32, String> {
+    let seconds: f64 = value
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid time '{}'", value.trim()))?;
+    if !seconds.is_finite() || seconds < 0.0 || seconds > f64::from(i32::MAX) {
+        return Err(format!("invalid time '{}'", value.trim()));
+    }
+    Ok(seconds.round() as i32)
+}
+
+/// A segment with copy and questions switched on for the test itself, copy
+/// for copy segments and questions for question segments
+fn inferred(name: &str, start_time: i32, end_time: Option<i32>) -> Segment {
+    let lower = name.to_lowercase();
+    let is_test = lower.contains("test") || lower.contains("exam");
+    Segment {
+        name: name.to_string(),
+        start_time,
+        end_time,
+        enables_copy: is_test || lower.contains("copy"),
+        enables_questions: is_test || lower.contains("question"),
+    }
+}
+
+/// Problems with a test's segments, in start order: empty names, segments that
+/// end before they start, and overlaps. Only the last may run to the end.
+pub fn validate(segments: &[Segment]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut sorted: Vec<&Segment> = segments.iter().collect();
+    sorted.sort_by_key(|segment| segment.start_time);
+
+    let mut previous: Option<&Segment> = None;
+    for (i, segment) in sorted.iter().enumerate() {
+        if segment.name.trim().is_empty() {
+            problems.push(format!("Segment {} has no name", i + 1));
+        }
+        if segment.start_time < 0 {
+            problems.push(format!("Segment '{}' starts before the audio", segment.name));
+        }
+        if let Some(end) = segment.end_time {
+            if end <= segment.start_time {
+                problems.push(format!(
+                    "Segment '{}' must end after it starts ({}s to {}s)",
+                    segment.name, segment.start_time, end
+                ));
+            }
+        }
+        if let Some(previous) = previous {
+            match previous.end_time {
+                Some(end) if segment.start_time >= end => {}
+                Some(_) => problems.push(format!(
+                    "Segment '{}' starts at {}s, before '{}' ends",
+                    segment.name, segment.start_time, previous.name
+                )),
+                None => problems.push(format!(
+                    "Segment '{}' follows '{}', which runs to the end of the audio",
+                    segment.name, previous.name
+                )),
+            }
+        }
+        previous = Some(segment);
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(segments: &[Segment]) -> Vec<(&str, i32, Option<i32>, bool, bool)> {
+        segments
+            .iter()
+            .map(|s| (s.name.as_str(), s.start_time, s.end_time, s.enables_copy, s.enables_questions))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_marks_file() {
+        let (format, segments) = parse_markers(include_str!("../marks.txt")).unwrap();
+        assert_eq!(format, MarkerFormat::Marks);
+        assert_eq!(
+            summary(&segments),
+            vec![
+                ("intro", 0, Some(62), false, false),
+                ("practice", 62, Some(126), false, false),
+                ("instructions", 126, Some(221), false, false),
+                ("test", 221, Some(531), true, true),
+                ("outro", 531, None, false, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_audacity_labels() {
+        let labels = "0.000000\t61.500000\tIntro\n\
+                      \\\t100.0\t2000.0\n\
+                      61.500000\t61.500000\tCopy practice\n\
+                      126.000000\t531.000000\tTest\n";
+        let (format, segments) = parse_markers(labels).unwrap();
+        assert_eq!(format, MarkerFormat::Audacity);
+        assert_eq!(
+            summary(&segments),
+            vec![
+                ("Intro", 0, Some(62), false, false),
+                ("Copy practice", 62, Some(126), true, false),
+                ("Test", 126, Some(531), true, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_markers_reports_problems() {
+        let err = parse_markers("start-1:02 - intro\n1:02-x - practice\nno range here\n").unwrap_err();
+        assert!(err.contains("Line 2: invalid time 'x'"), "{}", err);
+        assert!(err.contains("Line 3:"), "{}", err);
+
+        let err = parse_markers("0:00-1:30 - intro\n1:00-end - test\n2:00-3:00 - outro\n").unwrap_err();
+        assert!(err.contains("before 'intro' ends"), "{}", err);
+        assert!(err.contains("runs to the end"), "{}", err);
+
+        assert!(parse_markers("# just a comment\n").is_err());
+        assert_eq!(parse_time("1:02:03"), Ok(3723));
+        assert!(parse_time("1:75").is_err());
+
+        let err = parse_markers("99999999:00-end - test\n").unwrap_err();
+        assert!(err.contains("Line 1: invalid time '99999999:00'"), "{}", err);
+        assert!(parse_time("999999:59:59").is_err());
+    }
+}
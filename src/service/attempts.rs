@@ -0,0 +1,626 @@
+use super::{db_error, MemberRoster, TestCatalog};
+use crate::admin::{AllAttemptsQuery, AllAttemptsResponse};
+use crate::classes::{self, LicenseClass};
+use crate::db::Db;
+use crate::notify::Ntfy;
+use crate::qrz::{QrzClient, QrzContact};
+use crate::repo::{self, AttemptFilter, CertificateEvent, NewAttempt, ReissueError};
+use crate::{AttemptRequest, AttemptResponse, TestSubmission, TestSubmissionResponse};
+use axum::http::StatusCode;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use uuid::Uuid;
+
+/// Why a callsign may not start another attempt
+#[derive(Debug, PartialEq)]
+enum Ineligible {
+    /// Only one attempt per UTC day
+    AttemptedToday,
+    /// A passed attempt for the same class is pending or already approved
+    OpenAttempt,
+}
+
+/// Recording, grading and validating exam attempts
+pub struct AttemptService<'a> {
+    db: &'a Db,
+    qrz: Option<&'a QrzClient>,
+    ntfy: Option<&'a Ntfy>,
+    roster: MemberRoster<'a>,
+}
+
+impl<'a> AttemptService<'a> {
+    pub fn new(
+        db: &'a Db,
+        qrz: Option<&'a QrzClient>,
+        ntfy: Option<&'a Ntfy>,
+        roster: MemberRoster<'a>,
+    ) -> Self {
+        AttemptService {
+            db,
+            qrz,
+            ntfy,
+            roster,
+        }
+    }
+
+    async fn ineligibility(
+        &self,
+        callsign: &str,
+        class: LicenseClass,
+    ) -> Result<Option<Ineligible>, (StatusCode, String)> {
+        let attempts = self.db.attempts();
+
+        if attempts
+            .has_attempt_since(callsign, today_start())
+            .await
+            .map_err(db_error)?
+        {
+            return Ok(Some(Ineligible::AttemptedToday));
+        }
+
+        if attempts
+            .has_open_attempt(callsign, class.speed_wpm())
+            .await
+            .map_err(db_error)?
+        {
+            return Ok(Some(Ineligible::OpenAttempt));
+        }
+
+        Ok(None)
+    }
+
+    /// Record a self-graded attempt from the legacy `POST /api/attempts`
+    pub async fn record(&self, req: &AttemptRequest) -> Result<AttemptResponse, (StatusCode, String)> {
+        let callsign = normalize_callsign(&req.callsign)?;
+        let class = classes::check_speed(req.test_speed).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+        match self.ineligibility(&callsign, class).await? {
+            Some(Ineligible::AttemptedToday) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "You can only attempt the test once per day. Practice more at morsestorytime.com and keyersjourney.com, and try again tomorrow!".to_string(),
+                ))
+            }
+            Some(Ineligible::OpenAttempt) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    format!("You already have a passed {} attempt awaiting validation. Practice more at morsestorytime.com and keyersjourney.com", class.name()),
+                ))
+            }
+            None => {}
+        }
+
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now();
+
+        self.db
+            .attempts()
+            .insert(&NewAttempt {
+                id: &id,
+                callsign: &callsign,
+                test_speed: req.test_speed,
+                questions_correct: req.questions_correct,
+                copy_chars: req.copy_chars,
+                passed: req.passed,
+                created_at: now,
+                validation_status: if req.passed { Some("pending") } else { None },
+                audio_progress: req.audio_progress,
+                test_id: None,
+                copy_text: None,
+                consecutive_correct: None,
+                revision_id: None,
+                session_id: None,
+            })
+            .await
+            .map_err(db_error)?;
+
+        self.notify(&callsign, req.passed);
+
+        Ok(AttemptResponse {
+            id,
+            callsign,
+            test_speed: req.test_speed,
+            questions_correct: req.questions_correct,
+            copy_chars: req.copy_chars,
+            passed: req.passed,
+            created_at: now,
+            certificate_number: None, // Only assigned on admin approval
+        })
+    }
+
+    /// Grade a submission against an open test and record the attempt
+    pub async fn submit(
+        &self,
+        test_id: &str,
+        submission: &TestSubmission,
+    ) -> Result<TestSubmissionResponse, (StatusCode, String)> {
+        let callsign = normalize_callsign(&submission.callsign)?;
+
+        let catalog = TestCatalog::new(self.db);
+        let test = catalog.find_open(test_id).await?;
+        let session = catalog.session(&test, submission.session_id.as_deref()).await?;
+        let already_submitted = || {
+            (
+                StatusCode::CONFLICT,
+                "This exam session has already been submitted".to_string(),
+            )
+        };
+        if session.as_ref().is_some_and(|s| s.submitted_at.is_some()) {
+            return Err(already_submitted());
+        }
+        let class = classes::check_speed(test.speed_wpm).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+        match self.ineligibility(&callsign, class).await? {
+            Some(Ineligible::AttemptedToday) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "You can only attempt the test once per day. Try again tomorrow!".to_string(),
+                ))
+            }
+            Some(Ineligible::OpenAttempt) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    format!("You already have a passed {} attempt awaiting validation.", class.name()),
+                ))
+            }
+            None => {}
+        }
+
+        let grade = catalog.grade(&test, submission, session.as_ref()).await?;
+        let revision_id = session.as_ref().map_or(&test.revision_id, |s| &s.revision_id);
+
+        let id = Uuid::new_v4().to_string();
+        let recorded = self
+            .db
+            .attempts()
+            .insert_submitted(&NewAttempt {
+                id: &id,
+                callsign: &callsign,
+                test_speed: test.speed_wpm,
+                questions_correct: grade.score,
+                // Store the actual character count in copy_chars
+                copy_chars: submission.copy_text.as_ref().map_or(0, |t| t.len()) as i32,
+                passed: grade.passed,
+                created_at: Utc::now(),
+                validation_status: if grade.passed { Some("pending") } else { None },
+                audio_progress: submission.audio_progress,
+                test_id: Some(test_id),
+                copy_text: submission.copy_text.as_deref(),
+                consecutive_correct: Some(grade.consecutive_correct),
+                revision_id: Some(revision_id),
+                session_id: session.as_ref().map(|s| s.id.as_str()),
+            })
+            .await
+            .map_err(db_error)?;
+        // Lost a race with another submit of the same session
+        if !recorded {
+            return Err(already_submitted());
+        }
+
+        self.notify(&callsign, grade.passed);
+
+        Ok(TestSubmissionResponse {
+            passed: grade.passed,
+            score: grade.score,
+            passing_score: test.passing_score,
+            consecutive_correct: grade.consecutive_correct,
+            passing_copy_chars: grade.passing_copy_chars,
+            pass_reason: grade.pass_reason,
+            correct_answers: grade.passed.then_some(grade.correct_answers),
+            question_results: grade.passed.then_some(grade.question_results),
+            certificate_id: if grade.passed { Some(id) } else { None },
+        })
+    }
+
+    fn notify(&self, callsign: &str, passed: bool) {
+        if let Some(ntfy) = self.ntfy {
+            ntfy.spawn_attempt_notification(callsign, passed);
+        }
+    }
+
+    /// Approve a pending attempt, returning its new certificate number
+    pub async fn approve(&self, attempt_id: &str) -> Result<i32, (StatusCode, String)> {
+        let attempts = self.db.attempts();
+
+        // Fetch email and name from QRZ if configured
+        let contact = match self.qrz {
+            Some(qrz) => {
+                let callsign = attempts
+                    .callsign(attempt_id)
+                    .await
+                    .map_err(db_error)?
+                    .ok_or_else(not_pending)?;
+
+                qrz.lookup_contact(&callsign).await.unwrap_or_default()
+            }
+            None => QrzContact::default(),
+        };
+
+        let next_cert = attempts
+            .approve(attempt_id, contact.email.as_deref(), contact.name.as_deref(), Utc::now())
+            .await
+            .map_err(db_error)?
+            .ok_or_else(not_pending)?;
+
+        // Regenerate the Ham2K PoLo notes file
+        self.regenerate_roster().await;
+
+        Ok(next_cert)
+    }
+
+    /// Reject a pending attempt with an optional note
+    pub async fn reject(&self, attempt_id: &str, note: Option<&str>) -> Result<(), (StatusCode, String)> {
+        let rejected = self
+            .db
+            .attempts()
+            .reject(attempt_id, note, Utc::now())
+            .await
+            .map_err(db_error)?;
+
+        if !rejected {
+            return Err(not_pending());
+        }
+
+        Ok(())
+    }
+
+    /// Revoke an approved certificate. Its number is retired, never reused.
+    pub async fn revoke(&self, attempt_id: &str, reason: &str) -> Result<CertificateEvent, (StatusCode, String)> {
+        let reason = reason.trim();
+        if reason.is_empty() {
+            return Err((StatusCode::BAD_REQUEST, "A reason is required to revoke a certificate".to_string()));
+        }
+
+        let event = self
+            .db
+            .certificates()
+            .revoke(attempt_id, reason, Utc::now())
+            .await
+            .map_err(db_error)?
+            .ok_or((
+                StatusCode::NOT_FOUND,
+                "Attempt not found or not approved".to_string(),
+            ))?;
+
+        self.regenerate_roster().await;
+        Ok(event)
+    }
+
+    /// Restore a revoked certificate under its original number
+    pub async fn reinstate(
+        &self,
+        attempt_id: &str,
+        reason: Option<&str>,
+    ) -> Result<CertificateEvent, (StatusCode, String)> {
+        let event = self
+            .db
+            .certificates()
+            .reinstate(attempt_id, reason.map(str::trim).filter(|r| !r.is_empty()), Utc::now())
+            .await
+            .map_err(db_error)?
+            .ok_or((
+                StatusCode::NOT_FOUND,
+                "Attempt not found or not revoked".to_string(),
+            ))?;
+
+        self.regenerate_roster().await;
+        Ok(event)
+    }
+
+    /// Move an approved certificate to a new callsign, keeping its number
+    pub async fn reissue(
+        &self,
+        attempt_id: &str,
+        new_callsign: &str,
+        reason: Option<&str>,
+    ) -> Result<CertificateEvent, (StatusCode, String)> {
+        let new_callsign = normalize_callsign(new_callsign)?;
+
+        let event = self
+            .db
+            .certificates()
+            .reissue(attempt_id, &new_callsign, reason.map(str::trim).filter(|r| !r.is_empty()), Utc::now())
+            .await
+            .map_err(db_error)?
+            .map_err(|e| match e {
+                ReissueError::NotApproved => (
+                    StatusCode::NOT_FOUND,
+                    "Attempt not found or not approved".to_string(),
+                ),
+                ReissueError::CallsignCertified => (
+                    StatusCode::CONFLICT,
+                    format!("{} already holds a certificate for this class", new_callsign),
+                ),
+            })?;
+
+        self.regenerate_roster().await;
+        Ok(event)
+    }
+
+    /// Certificate changes are committed already; a stale members.txt is
+    /// logged rather than reported as a failure
+    async fn regenerate_roster(&self) {
+        if let Err(e) = self.roster.regenerate().await {
+            tracing::error!("Failed to regenerate PoLo notes: {}", e);
+        }
+    }
+
+    /// Filtered, paginated attempt list for the admin portal
+    pub async fn list(&self, query: &AllAttemptsQuery) -> Result<AllAttemptsResponse, (StatusCode, String)> {
+        // Validate pagination
+        if query.page < 1 {
+            return Err((StatusCode::BAD_REQUEST, "Page must be >= 1".to_string()));
+        }
+        if query.per_page < 1 || query.per_page > 100 {
+            return Err((StatusCode::BAD_REQUEST, "per_page must be between 1 and 100".to_string()));
+        }
+
+        let offset = (query.page - 1)
+            .checked_mul(query.per_page)
+            .ok_or((StatusCode::BAD_REQUEST, "Pagination overflow".to_string()))?;
+
+        let date_from = parse_date(query.date_from.as_deref(), "date_from")?;
+        let date_to = parse_date(query.date_to.as_deref(), "date_to")?;
+
+        // Date filters are inclusive whole days
+        let filter = AttemptFilter {
+            passed: query.passed,
+            callsign: query.callsign.clone(),
+            created_from: date_from.map(repo::day_start),
+            created_before: date_to.map(|d| repo::day_start(d) + Duration::days(1)),
+        };
+
+        let (total, items) = self
+            .db
+            .attempts()
+            .list_filtered(&filter, query.per_page, offset)
+            .await
+            .map_err(db_error)?;
+
+        Ok(AllAttemptsResponse {
+            items,
+            total,
+            page: query.page,
+            per_page: query.per_page,
+        })
+    }
+}
+
+fn normalize_callsign(callsign: &str) -> Result<String, (StatusCode, String)> {
+    let callsign = callsign.trim().to_uppercase();
+    if callsign.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Callsign is required".to_string()));
+    }
+    Ok(callsign)
+}
+
+fn not_pending() -> (StatusCode, String) {
+    (
+        StatusCode::NOT_FOUND,
+        "Attempt not found or not pending".to_string(),
+    )
+}
+
+/// Validate an optional YYYY-MM-DD query parameter
+fn parse_date(value: Option<&str>, name: &str) -> Result<Option<NaiveDate>, (StatusCode, String)> {
+    value
+        .map(|d| {
+            NaiveDate::parse_from_str(d, "%Y-%m-%d").map_err(|_| {
+                (StatusCode::BAD_REQUEST, format!("{} must be YYYY-MM-DD format", name))
+            })
+        })
+        .transpose()
+}
+
+/// Start of the current UTC day, for the once-per-day attempt limit
+fn today_start() -> DateTime<Utc> {
+    repo::day_start(Utc::now().date_naive())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing;
+    use crate::grading::QuestionType;
+    use crate::repo::NewQuestion;
+    use std::collections::HashMap;
+
+    const TEST_ID: &str = "20wpm-extra-1991";
+
+    struct Fixture {
+        db: Db,
+        dir: std::path::PathBuf,
+    }
+
+    impl Fixture {
+        async fn new() -> Self {
+            let db = testing::sqlite().await;
+            let dir = std::env::temp_dir().join(format!("kce-attempts-{}", Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            Fixture { db, dir }
+        }
+
+        fn service(&self) -> AttemptService<'_> {
+            let static_dir = self.dir.to_str().unwrap();
+            AttemptService::new(&self.db, None, None, MemberRoster::new(&self.db, static_dir))
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn submission(callsign: &str, answers: HashMap<String, String>) -> TestSubmission {
+        TestSubmission {
+            callsign: callsign.to_string(),
+            answers,
+            copy_text: None,
+            audio_progress: Some(100.0),
+            session_id: None,
+        }
+    }
+
+    async fn add_questions(db: &Db, count: i32) -> Vec<String> {
+        let mut ids = Vec::new();
+        for n in 1..=count {
+            let id = db
+                .questions()
+                .insert(&NewQuestion {
+                    test_id: TEST_ID,
+                    question_number: n,
+                    question_text: "What was the call?",
+                    question_type: QuestionType::Choice,
+                    options: &["W1AW", "K1ABC", "N0CALL", "AA1AA"],
+                    correct_option: "C",
+                    accepted_answers: &[],
+                    topic: None,
+                })
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+        db.test_revisions().publish(TEST_ID, None, Utc::now()).await.unwrap();
+        ids
+    }
+
+    #[tokio::test]
+    async fn test_submit_approve_and_members_txt() {
+        let fixture = Fixture::new().await;
+        let service = fixture.service();
+        let ids = add_questions(&fixture.db, 10).await;
+        let answers: HashMap<String, String> =
+            ids.iter().map(|id| (id.clone(), "C".to_string())).collect();
+
+        let response = service.submit(TEST_ID, &submission(" w1aw ", answers)).await.unwrap();
+        assert!(response.passed);
+        assert_eq!(response.score, 10);
+        let attempt_id = response.certificate_id.unwrap();
+
+        // Pending attempts block another try, as does the daily limit
+        let (status, message) = service
+            .submit(TEST_ID, &submission("W1AW", HashMap::new()))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(message.contains("once per day"));
+
+        assert_eq!(service.approve(&attempt_id).await.unwrap(), 1);
+        let (status, _) = service.approve(&attempt_id).await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let members = std::fs::read_to_string(fixture.dir.join("members.txt")).unwrap();
+        assert!(members.contains("W1AW 📜 Know Code Extra #1"));
+    }
+
+    #[tokio::test]
+    async fn test_pending_attempt_only_blocks_its_class() {
+        let fixture = Fixture::new().await;
+        let service = fixture.service();
+        let ids = add_questions(&fixture.db, 10).await;
+        let answers: HashMap<String, String> =
+            ids.iter().map(|id| (id.clone(), "C".to_string())).collect();
+
+        let yesterday = Utc::now() - chrono::Duration::days(1);
+        for (id, callsign, test_speed) in [("general", "W1AW", 13), ("extra", "K1ABC", 20)] {
+            fixture
+                .db
+                .attempts()
+                .insert(&crate::repo::NewAttempt {
+                    id,
+                    callsign,
+                    test_speed,
+                    questions_correct: 8,
+                    copy_chars: 0,
+                    passed: true,
+                    created_at: yesterday,
+                    validation_status: Some("pending"),
+                    audio_progress: None,
+                    test_id: None,
+                    copy_text: None,
+                    consecutive_correct: None,
+                    revision_id: None,
+                    session_id: None,
+                })
+                .await
+                .unwrap();
+        }
+
+        // A General attempt awaiting validation does not hold up the Extra test
+        let response = service.submit(TEST_ID, &submission("W1AW", answers.clone())).await.unwrap();
+        assert!(response.passed);
+
+        let (status, message) = service.submit(TEST_ID, &submission("K1ABC", answers)).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(message.contains("passed Extra attempt"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_submission_is_not_pending() {
+        let fixture = Fixture::new().await;
+        let service = fixture.service();
+        add_questions(&fixture.db, 10).await;
+
+        let response = service
+            .submit(TEST_ID, &submission("K1ABC", HashMap::new()))
+            .await
+            .unwrap();
+        assert!(!response.passed);
+        assert!(response.certificate_id.is_none());
+        assert!(response.correct_answers.is_none());
+        assert_eq!(fixture.db.attempts().count_by_status("pending").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_record_and_reject() {
+        let fixture = Fixture::new().await;
+        let service = fixture.service();
+
+        let req = AttemptRequest {
+            callsign: "n0call".to_string(),
+            test_speed: 20,
+            questions_correct: 8,
+            copy_chars: 120,
+            passed: true,
+            audio_progress: None,
+        };
+        let attempt = service.record(&req).await.unwrap();
+        assert_eq!(attempt.callsign, "N0CALL");
+
+        service.reject(&attempt.id, Some("Copy too short")).await.unwrap();
+        let (status, _) = service.reject(&attempt.id, None).await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let bad_speed = AttemptRequest {
+            callsign: "K1ABC".to_string(),
+            test_speed: 25,
+            ..req
+        };
+        let (status, message) = service.record(&bad_speed).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(message.contains("Invalid test speed"));
+    }
+
+    #[tokio::test]
+    async fn test_list_validates_query() {
+        let fixture = Fixture::new().await;
+        let service = fixture.service();
+
+        let query = |page, date_from: Option<&str>| AllAttemptsQuery {
+            page,
+            per_page: 25,
+            passed: None,
+            callsign: None,
+            date_from: date_from.map(str::to_string),
+            date_to: None,
+        };
+
+        let (status, _) = service.list(&query(0, None)).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (_, message) = service.list(&query(1, Some("01/02/2026"))).await.unwrap_err();
+        assert_eq!(message, "date_from must be YYYY-MM-DD format");
+
+        let response = service.list(&query(1, Some("2026-01-02"))).await.unwrap();
+        assert_eq!(response.total, 0);
+    }
+}
@@ -0,0 +1,371 @@
+use super::db_error;
+use crate::db::Db;
+use crate::repo::ExamSession;
+use crate::schedule::Availability;
+use crate::grading::{self, AnswerKey, QuestionType};
+use crate::{draw, shuffle, PublicQuestion, QuestionWithAnswer, Test, TestSubmission};
+use axum::http::StatusCode;
+use chrono::Utc;
+use std::collections::HashMap;
+
+/// Minimum run of consecutive correct copy characters to pass on copy alone.
+/// Can be made configurable per test later.
+const PASSING_COPY_CHARS: i32 = 100;
+
+/// Outcome of grading a submission against a test
+#[derive(Debug)]
+pub struct Grade {
+    pub score: i32,
+    pub consecutive_correct: i32,
+    pub passing_copy_chars: i32,
+    pub passed: bool,
+    pub pass_reason: Option<grading::PassReason>,
+    /// Question id -> correct answer, lettered as the session showed it
+    pub correct_answers: HashMap<String, String>,
+    pub question_results: Vec<grading::QuestionResult>,
+}
+
+/// Open tests, their questions, and grading against them
+pub struct TestCatalog<'a> {
+    db: &'a Db,
+}
+
+impl<'a> TestCatalog<'a> {
+    pub fn new(db: &'a Db) -> Self {
+        TestCatalog { db }
+    }
+
+    /// Active tests whose schedule has them open now
+    pub async fn list_open(&self) -> Result<Vec<Test>, (StatusCode, String)> {
+        let now = Utc::now();
+        let rows = self.db.tests().list_active().await.map_err(db_error)?;
+        Ok(rows
+            .into_iter()
+            .map(Test::from)
+            .filter_map(|mut test| match test.schedule.availability(now) {
+                Availability::Open { closes_at } => {
+                    test.closes_at = closes_at;
+                    Some(test)
+                }
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// An active test that is open now. 404 if there is no such active test,
+    /// 403 while its schedule has it closed.
+    pub async fn find_open(&self, test_id: &str) -> Result<Test, (StatusCode, String)> {
+        let mut test = self
+            .db
+            .tests()
+            .find_active(test_id)
+            .await
+            .map_err(db_error)?
+            .map(Test::from)
+            .ok_or((StatusCode::NOT_FOUND, "Test not found".to_string()))?;
+
+        match test.schedule.availability(Utc::now()) {
+            Availability::Open { closes_at } => {
+                test.closes_at = closes_at;
+                Ok(test)
+            }
+            Availability::Upcoming { opens_at } => Err((
+                StatusCode::FORBIDDEN,
+                format!("This test opens at {} UTC", opens_at.format("%Y-%m-%d %H:%M")),
+            )),
+            Availability::Expired => Err((
+                StatusCode::FORBIDDEN,
+                "This test is no longer available".to_string(),
+            )),
+        }
+    }
+
+    /// Start an exam session on an open test's published revision, drawing
+    /// its questions if the test asks only some of them
+    pub async fn start_session(&self, test_id: &str) -> Result<ExamSession, (StatusCode, String)> {
+        let test = self.find_open(test_id).await?;
+        let question_ids = match test.draw_count {
+            Some(count) => {
+                let questions = self
+                    .db
+                    .test_revisions()
+                    .questions_with_answers(&test.revision_id)
+                    .await
+                    .map_err(db_error)?;
+                let drawn = draw::draw(&questions, count.max(0) as usize, &mut rand::thread_rng());
+                let json = serde_json::to_string(&drawn)
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+                Some(json)
+            }
+            None => None,
+        };
+
+        let session = ExamSession {
+            id: uuid::Uuid::new_v4().to_string(),
+            test_id: test.id,
+            revision_id: test.revision_id,
+            shuffled: test.shuffle,
+            question_ids,
+            created_at: Utc::now(),
+            submitted_at: None,
+        };
+        self.db.exam_sessions().insert(&session).await.map_err(db_error)?;
+        Ok(session)
+    }
+
+    /// The exam session a request names, which must belong to the test.
+    /// Tests that shuffle or draw their questions cannot be taken without one.
+    pub async fn session(
+        &self,
+        test: &Test,
+        session_id: Option<&str>,
+    ) -> Result<Option<ExamSession>, (StatusCode, String)> {
+        let Some(session_id) = session_id else {
+            if test.shuffle || test.draw_count.is_some() {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "This test needs an exam session. Start one and load the questions again.".to_string(),
+                ));
+            }
+            return Ok(None);
+        };
+
+        self.db
+            .exam_sessions()
+            .find(session_id)
+            .await
+            .map_err(db_error)?
+            .filter(|session| session.test_id == test.id)
+            .map(Some)
+            .ok_or((StatusCode::NOT_FOUND, "Exam session not found".to_string()))
+    }
+
+    /// Questions of an open test, without correct answers. With a session,
+    /// they come from the revision it started on, in its order.
+    pub async fn public_questions(
+        &self,
+        test_id: &str,
+        session_id: Option<&str>,
+    ) -> Result<Vec<PublicQuestion>, (StatusCode, String)> {
+        let test = self.find_open(test_id).await?;
+        let session = self.session(&test, session_id).await?;
+        match &session {
+            Some(session) => self.session_questions(session).await,
+            None => self
+                .db
+                .test_revisions()
+                .public_questions(&test.revision_id)
+                .await
+                .map_err(db_error),
+        }
+    }
+
+    /// A session's questions as it shows them: only those drawn for it,
+    /// numbered from 1, and in its order if it is shuffled
+    pub async fn session_questions(
+        &self,
+        session: &ExamSession,
+    ) -> Result<Vec<PublicQuestion>, (StatusCode, String)> {
+        let questions = self
+            .db
+            .test_revisions()
+            .public_questions(&session.revision_id)
+            .await
+            .map_err(db_error)?;
+        let mut questions = keep_drawn(Some(session), questions, |q| &q.id);
+        if session.shuffled {
+            return Ok(shuffle::shuffle_questions(&session.id, questions));
+        }
+        for (position, question) in questions.iter_mut().enumerate() {
+            question.question_number = position as i32 + 1;
+        }
+        Ok(questions)
+    }
+
+    /// The questions a session asks with their answers, or every question of
+    /// the revision without a session
+    async fn questions_with_answers(
+        &self,
+        revision_id: &str,
+        session: Option<&ExamSession>,
+    ) -> Result<Vec<QuestionWithAnswer>, (StatusCode, String)> {
+        let questions = self
+            .db
+            .test_revisions()
+            .questions_with_answers(revision_id)
+            .await
+            .map_err(db_error)?;
+        Ok(keep_drawn(session, questions, |q| &q.id))
+    }
+
+    /// Question id -> correct answer for the questions a session asks
+    pub async fn session_answer_key(
+        &self,
+        session: &ExamSession,
+    ) -> Result<HashMap<String, String>, (StatusCode, String)> {
+        let questions = self.questions_with_answers(&session.revision_id, Some(session)).await?;
+        Ok(questions.into_iter().map(|q| (q.id, q.correct_option)).collect())
+    }
+
+    /// Grade answers and copy text against the answer key of the session's
+    /// revision, or the test's published revision without a session
+    pub async fn grade(
+        &self,
+        test: &Test,
+        submission: &TestSubmission,
+        session: Option<&ExamSession>,
+    ) -> Result<Grade, (StatusCode, String)> {
+        let revision_id = session.map_or(&test.revision_id, |s| &s.revision_id);
+        let questions = self.questions_with_answers(revision_id, session).await?;
+
+        // A shuffled session's letters are mapped back to the answer key's
+        // for grading, and the results lettered as the candidate saw them
+        let shuffled: HashMap<&str, usize> = match session.filter(|s| s.shuffled) {
+            Some(_) => questions
+                .iter()
+                .filter(|q| q.question_type == QuestionType::Choice)
+                .map(|q| (q.id.as_str(), q.options.0.len()))
+                .collect(),
+            None => HashMap::new(),
+        };
+        let relabel = |id: &str, answer: &str, map: fn(&str, &str, usize, &str) -> String| {
+            match (session, shuffled.get(id)) {
+                (Some(session), Some(&count)) => map(&session.id, id, count, answer),
+                _ => answer.to_string(),
+            }
+        };
+        let answers: HashMap<String, String> = submission
+            .answers
+            .iter()
+            .map(|(id, answer)| (id.clone(), relabel(id, answer, shuffle::to_canonical)))
+            .collect();
+        let answer_key: HashMap<String, AnswerKey> =
+            questions.iter().map(|q| (q.id.clone(), q.answer_key())).collect();
+        let (score, mut question_results) = grading::grade_questions(&answers, &answer_key);
+        for result in &mut question_results {
+            result.correct_answer = relabel(&result.question_id, &result.correct_answer, shuffle::to_shown);
+            result.user_answer = result
+                .user_answer
+                .as_deref()
+                .map(|answer| relabel(&result.question_id, answer, shuffle::to_shown));
+        }
+        let correct_answers = answer_key
+            .into_iter()
+            .map(|(id, key)| {
+                let correct = relabel(&id, &key.correct, shuffle::to_shown);
+                (id, correct)
+            })
+            .collect();
+
+        // Grade copy text only if the test has expected copy
+        let consecutive_correct = if let (Some(user_copy), Some(expected_copy)) =
+            (&submission.copy_text, &test.expected_copy_text)
+        {
+            let prosigns = self.db.prosigns().pairs().await.map_err(db_error)?;
+            grading::find_consecutive_correct(user_copy, expected_copy, &prosigns)
+        } else {
+            0
+        };
+
+        let (passed, pass_reason) = grading::is_passing(
+            score,
+            consecutive_correct,
+            test.passing_score,
+            PASSING_COPY_CHARS,
+        );
+
+        Ok(Grade {
+            score,
+            consecutive_correct,
+            passing_copy_chars: PASSING_COPY_CHARS,
+            passed,
+            pass_reason,
+            correct_answers,
+            question_results,
+        })
+    }
+}
+
+/// Keep only the questions drawn for a session, if it drew them
+fn keep_drawn<T>(session: Option<&ExamSession>, mut questions: Vec<T>, id: impl Fn(&T) -> &str) -> Vec<T> {
+    if let Some(drawn) = session.and_then(ExamSession::drawn_questions) {
+        questions.retain(|q| drawn.iter().any(|drawn_id| drawn_id == id(q)));
+    }
+    questions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing;
+    use crate::repo::NewQuestion;
+
+    const TEST_ID: &str = "20wpm-extra-1991";
+
+    async fn catalog_with_questions(correct: &[&'static str]) -> (Db, Vec<String>) {
+        let db = testing::sqlite().await;
+        let mut ids = Vec::new();
+        for (i, option) in correct.iter().enumerate() {
+            let id = db
+                .questions()
+                .insert(&NewQuestion {
+                    test_id: TEST_ID,
+                    question_number: i as i32 + 1,
+                    question_text: "What was the call?",
+                    question_type: QuestionType::Choice,
+                    options: &["W1AW", "K1ABC", "N0CALL", "AA1AA"],
+                    correct_option: option,
+                    accepted_answers: &[],
+                    topic: None,
+                })
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+        db.test_revisions().publish(TEST_ID, None, Utc::now()).await.unwrap();
+        (db, ids)
+    }
+
+    fn submission(answers: HashMap<String, String>) -> TestSubmission {
+        TestSubmission {
+            callsign: "W1AW".to_string(),
+            answers,
+            copy_text: None,
+            audio_progress: None,
+            session_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_grade_counts_correct_answers() {
+        let (db, ids) = catalog_with_questions(&["A"; 10]).await;
+        let catalog = TestCatalog::new(&db);
+        let test = catalog.find_open(TEST_ID).await.unwrap();
+
+        let answers: HashMap<String, String> = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id.clone(), if i < 7 { "A" } else { "B" }.to_string()))
+            .collect();
+        let grade = catalog.grade(&test, &submission(answers), None).await.unwrap();
+        assert_eq!(grade.score, 7);
+        assert!(grade.passed);
+        assert_eq!(grade.correct_answers.len(), 10);
+
+        let grade = catalog.grade(&test, &submission(HashMap::new()), None).await.unwrap();
+        assert_eq!(grade.score, 0);
+        assert!(!grade.passed);
+    }
+
+    #[tokio::test]
+    async fn test_inactive_tests_are_not_found() {
+        let (db, _) = catalog_with_questions(&["A"]).await;
+        let catalog = TestCatalog::new(&db);
+        assert_eq!(catalog.public_questions(TEST_ID, None).await.unwrap().len(), 1);
+
+        db.tests().deactivate(TEST_ID).await.unwrap();
+        let (status, _) = catalog.public_questions(TEST_ID, None).await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(catalog.list_open().await.unwrap().is_empty());
+    }
+}
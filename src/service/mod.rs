@@ -0,0 +1,41 @@
+//! Business logic shared by the HTTP handlers, CLI commands and background jobs
+//!
+//! Handlers parse requests and shape responses; everything in between (the
+//! daily attempt limit, grading, QRZ lookups, certificate numbering,
+//! members.txt and notifications) lives here. Services borrow what they need
+//! from `AppState` and reach the database only through the repositories.
+
+mod attempts;
+mod catalog;
+mod roster;
+
+pub use attempts::AttemptService;
+pub use catalog::TestCatalog;
+pub use roster::MemberRoster;
+
+use crate::AppState;
+use axum::http::StatusCode;
+
+impl AppState {
+    pub fn attempt_service(&self) -> AttemptService<'_> {
+        AttemptService::new(
+            &self.db,
+            self.qrz_client.as_ref(),
+            self.ntfy.as_ref(),
+            self.member_roster(),
+        )
+    }
+
+    pub fn test_catalog(&self) -> TestCatalog<'_> {
+        TestCatalog::new(&self.db)
+    }
+
+    pub fn member_roster(&self) -> MemberRoster<'_> {
+        MemberRoster::new(&self.db, &self.static_dir)
+    }
+}
+
+/// Database failures surface to callers as 500s
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
@@ -0,0 +1,153 @@
+use crate::classes::LicenseClass;
+use crate::db::Db;
+use crate::repo::RosterRow;
+use crate::{Endorsement, RosterEntry};
+use tokio::fs;
+
+/// Approved members: the public roster and the Ham2K PoLo notes file
+pub struct MemberRoster<'a> {
+    db: &'a Db,
+    static_dir: &'a str,
+}
+
+impl<'a> MemberRoster<'a> {
+    pub fn new(db: &'a Db, static_dir: &'a str) -> Self {
+        MemberRoster { db, static_dir }
+    }
+
+    /// Public roster, one entry per member in order of their first certificate
+    pub async fn entries(&self) -> Result<Vec<RosterEntry>, sqlx::Error> {
+        Ok(group_by_member(self.db.attempts().roster().await?))
+    }
+
+    /// Path of the members.txt file served to Ham2K PoLo
+    pub fn members_txt_path(&self) -> String {
+        format!("{}/members.txt", self.static_dir)
+    }
+
+    /// Contents of the Ham2K PoLo callsign notes file.
+    /// File format: one callsign per line with notes
+    /// Example: W1ABC 📜 Know Code Extra #1 (General, Extra)
+    pub async fn polo_notes(&self) -> Result<String, sqlx::Error> {
+        let members = self.entries().await?;
+
+        let mut content = String::from("# Know Code Extra Club Members\n");
+        content.push_str("# Ham2K PoLo Callsign Notes - https://knowcodeextra.com\n\n");
+
+        for member in members {
+            let cert_num = member.certificate_number.unwrap_or(0);
+            let classes: Vec<&str> = member.classes.iter().map(|e| e.class.name()).collect();
+            content.push_str(&format!(
+                "{} 📜 Know Code Extra #{} ({})\n",
+                member.callsign,
+                cert_num,
+                classes.join(", ")
+            ));
+        }
+
+        Ok(content)
+    }
+
+    /// Rewrite members.txt from the approved members
+    pub async fn regenerate(&self) -> Result<(), String> {
+        let content = self
+            .polo_notes()
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        let file_path = self.members_txt_path();
+        fs::write(&file_path, content)
+            .await
+            .map_err(|e| format!("Failed to write PoLo notes file: {}", e))?;
+
+        tracing::info!("Regenerated PoLo notes file at {}", file_path);
+        Ok(())
+    }
+}
+
+/// Fold certificates, in certificate order, into one entry per callsign
+fn group_by_member(rows: Vec<RosterRow>) -> Vec<RosterEntry> {
+    let mut entries: Vec<RosterEntry> = Vec::new();
+    for row in rows {
+        let index = match entries.iter().position(|e| e.callsign == row.callsign) {
+            Some(index) => index,
+            None => {
+                entries.push(RosterEntry {
+                    callsign: row.callsign.clone(),
+                    certificate_number: row.certificate_number,
+                    validated_at: row.validated_at,
+                    classes: Vec::new(),
+                });
+                entries.len() - 1
+            }
+        };
+        // Speeds outside the class table predate it and earn no endorsement
+        if let Some(class) = LicenseClass::from_speed(row.test_speed) {
+            let classes = &mut entries[index].classes;
+            classes.push(Endorsement {
+                class,
+                speed_wpm: row.test_speed,
+                certificate_number: row.certificate_number,
+                validated_at: row.validated_at,
+            });
+            classes.sort_by_key(|e| e.class);
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::testing;
+    use crate::repo::NewAttempt;
+    use chrono::Utc;
+
+    #[tokio::test]
+    async fn test_regenerate_writes_approved_members() {
+        let db = testing::sqlite().await;
+        let dir = std::env::temp_dir().join(format!("kce-roster-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let static_dir = dir.to_string_lossy().to_string();
+
+        for (id, callsign, test_speed) in [("a1", "W1AW", 20), ("a2", "K1ABC", 20), ("a3", "K1ABC", 13)] {
+            db.attempts()
+                .insert(&NewAttempt {
+                    id,
+                    callsign,
+                    test_speed,
+                    questions_correct: 8,
+                    copy_chars: 0,
+                    passed: true,
+                    created_at: Utc::now(),
+                    validation_status: Some("pending"),
+                    audio_progress: None,
+                    test_id: None,
+                    copy_text: None,
+                    consecutive_correct: None,
+                    revision_id: None,
+                    session_id: None,
+                })
+                .await
+                .unwrap();
+        }
+        db.attempts().approve("a2", None, None, Utc::now()).await.unwrap();
+        db.attempts().approve("a3", None, None, Utc::now()).await.unwrap();
+
+        let roster = MemberRoster::new(&db, &static_dir);
+        roster.regenerate().await.unwrap();
+
+        let content = std::fs::read_to_string(roster.members_txt_path()).unwrap();
+        assert!(content.starts_with("# Know Code Extra Club Members\n"));
+        assert!(content.contains("K1ABC 📜 Know Code Extra #1 (General, Extra)\n"));
+        assert!(!content.contains("W1AW"));
+
+        let entries = roster.entries().await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].certificate_number, Some(1));
+        let classes: Vec<_> = entries[0].classes.iter().map(|e| (e.class, e.certificate_number)).collect();
+        assert_eq!(classes, vec![(LicenseClass::General, Some(2)), (LicenseClass::Extra, Some(1))]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
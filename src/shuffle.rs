@@ -0,0 +1,134 @@
+//! Per-session order of questions and options
+//!
+//! Tests can shuffle their questions and each question's options so that an
+//! answer key from one sitting is useless in another. The order follows from
+//! hashing the exam session id with each question id (and option letter), so
+//! it is the same every time a session's questions are loaded and nothing but
+//! the session id needs storing. Only multiple-choice options are shuffled.
+//! Candidates answer with the letters they were shown, which `to_canonical`
+//! maps back to the answer key's letters before grading; `to_shown` letters
+//! the results the way the candidate saw them. Only the admin API learns
+//! which shown letter is which answer-key letter.
+
+use crate::grading::{self, QuestionType};
+use crate::PublicQuestion;
+use sha2::{Digest, Sha256};
+
+fn rank(session_id: &str, key: &[&str]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(session_id.as_bytes());
+    for part in key {
+        hasher.update([0]);
+        hasher.update(part.as_bytes());
+    }
+    hasher.finalize().into()
+}
+
+/// Canonical option indexes in the order the session shows them: shown option
+/// `i` is canonical option `order[i]`
+fn option_order(session_id: &str, question_id: &str, count: usize) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..count).collect();
+    order.sort_by_cached_key(|&i| rank(session_id, &[question_id, &grading::option_letter(i)]));
+    order
+}
+
+/// Questions in the session's order, numbered from 1 as shown, with their
+/// options in the session's order
+pub fn shuffle_questions(session_id: &str, mut questions: Vec<PublicQuestion>) -> Vec<PublicQuestion> {
+    questions.sort_by_cached_key(|q| rank(session_id, &[&q.id]));
+    for (position, question) in questions.iter_mut().enumerate() {
+        question.question_number = position as i32 + 1;
+        if question.question_type == QuestionType::Choice {
+            let canonical = std::mem::take(&mut question.options.0);
+            question.options.0 = option_order(session_id, &question.id, canonical.len())
+                .into_iter()
+                .map(|i| canonical[i].clone())
+                .collect();
+        }
+    }
+    questions
+}
+
+/// The answer-key letter for a letter the session showed on a question with
+/// `count` options. Anything else is passed through.
+pub fn to_canonical(session_id: &str, question_id: &str, count: usize, shown: &str) -> String {
+    match grading::option_index(shown, count) {
+        Some(index) => grading::option_letter(option_order(session_id, question_id, count)[index]),
+        None => shown.to_string(),
+    }
+}
+
+/// The letter the session shows for an answer-key letter of a question with
+/// `count` options. Anything else is passed through.
+pub fn to_shown(session_id: &str, question_id: &str, count: usize, canonical: &str) -> String {
+    let Some(index) = grading::option_index(canonical, count) else {
+        return canonical.to_string();
+    };
+    let order = option_order(session_id, question_id, count);
+    let shown = order.iter().position(|&i| i == index).unwrap_or(index);
+    grading::option_letter(shown)
+}
+
+/// The answer-key letter of each option in the order the session shows them
+pub fn answer_key_letters(session_id: &str, question_id: &str, count: usize) -> Vec<String> {
+    option_order(session_id, question_id, count).into_iter().map(grading::option_letter).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::db::JsonList;
+
+    /// Question n has n + 1 options, "<n>a", "<n>b"...
+    fn questions() -> Vec<PublicQuestion> {
+        (1..=7)
+            .map(|n| PublicQuestion {
+                id: format!("q{}", n),
+                test_id: "20wpm-extra-1991".to_string(),
+                question_number: n,
+                question_text: format!("Question {}", n),
+                question_type: QuestionType::Choice,
+                options: JsonList(
+                    (0..=n as usize)
+                        .map(|i| format!("{}{}", n, grading::option_letter(i).to_lowercase()))
+                        .collect(),
+                ),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_shuffle_is_stable_per_session() {
+        let first = shuffle_questions("session-1", questions());
+        let again = shuffle_questions("session-1", questions());
+        let other = shuffle_questions("session-2", questions());
+
+        let ids = |qs: &[PublicQuestion]| qs.iter().map(|q| q.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&first), ids(&again));
+        assert_ne!(ids(&first), ids(&other));
+        assert_eq!(
+            first.iter().map(|q| q.question_number).collect::<Vec<_>>(),
+            (1..=7).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_shown_letters_map_back_to_the_answer_key() {
+        for question in shuffle_questions("session-1", questions()) {
+            let count = question.options.0.len();
+            let number = question.id.trim_start_matches('q');
+            let letters = answer_key_letters("session-1", &question.id, count);
+            for (index, option) in question.options.0.iter().enumerate() {
+                let shown = grading::option_letter(index);
+                let canonical = to_canonical("session-1", &question.id, count, &shown);
+                assert_eq!(*option, format!("{}{}", number, canonical.to_lowercase()));
+                assert_eq!(to_shown("session-1", &question.id, count, &canonical), shown);
+                assert_eq!(letters[index], canonical);
+            }
+        }
+        assert_eq!(to_canonical("session-1", "q1", 2, "TRUE"), "TRUE");
+        assert_eq!(to_canonical("session-1", "q1", 2, "c"), "c");
+        assert_eq!(to_shown("session-1", "q1", 2, "TRUE"), "TRUE");
+    }
+}
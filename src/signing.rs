@@ -0,0 +1,156 @@
+//! Ed25519 signatures over issued certificates
+//!
+//! A signature covers the callsign, certificate number, test and issue time,
+//! so a downloaded certificate can be checked with nothing but the published
+//! public key. Revocation cannot be seen offline; `/verify/:number` reports it.
+//! Keys and signatures are encoded as unpadded base64url.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, SecondsFormat, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use rand::RngCore;
+
+/// Keeps certificate signatures from being valid for any other message
+const DOMAIN: &str = "knowcodeextra-certificate-v1";
+
+/// The facts a certificate signature vouches for
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignedFields {
+    pub callsign: String,
+    pub certificate_number: i32,
+    /// None for attempts recorded before tests were stored
+    pub test_id: Option<String>,
+    pub issued_at: DateTime<Utc>,
+}
+
+impl SignedFields {
+    /// `issued_at` as signed: RFC 3339 in UTC, whole seconds
+    pub fn issued_at_string(&self) -> String {
+        self.issued_at.to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+
+    fn message(&self) -> String {
+        format!(
+            "{}\n{}\n{}\n{}\n{}",
+            DOMAIN,
+            self.callsign,
+            self.certificate_number,
+            self.test_id.as_deref().unwrap_or(""),
+            self.issued_at_string()
+        )
+    }
+}
+
+/// The server's certificate signing key
+#[derive(Clone)]
+pub struct CertificateSigner {
+    key: SigningKey,
+}
+
+impl CertificateSigner {
+    /// Load a key from its base64url-encoded 32-byte seed
+    pub fn from_base64(encoded: &str) -> Result<Self, String> {
+        let seed: [u8; 32] = URL_SAFE_NO_PAD
+            .decode(encoded.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or("certificate_signing_key must be a base64url-encoded 32-byte seed")?;
+        Ok(CertificateSigner {
+            key: SigningKey::from_bytes(&seed),
+        })
+    }
+
+    /// A fresh random key, for `generate-signing-key`
+    pub fn generate() -> Self {
+        let mut seed = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut seed);
+        CertificateSigner {
+            key: SigningKey::from_bytes(&seed),
+        }
+    }
+
+    pub fn private_key_base64(&self) -> String {
+        URL_SAFE_NO_PAD.encode(self.key.to_bytes())
+    }
+
+    pub fn public_key_base64(&self) -> String {
+        URL_SAFE_NO_PAD.encode(self.key.verifying_key().to_bytes())
+    }
+
+    pub fn sign(&self, fields: &SignedFields) -> String {
+        URL_SAFE_NO_PAD.encode(self.key.sign(fields.message().as_bytes()).to_bytes())
+    }
+}
+
+impl std::fmt::Debug for CertificateSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CertificateSigner")
+            .field("public_key", &self.public_key_base64())
+            .finish()
+    }
+}
+
+/// Check a signature against a base64url-encoded public key
+pub fn verify(public_key: &str, fields: &SignedFields, signature: &str) -> Result<(), String> {
+    let key: [u8; 32] = URL_SAFE_NO_PAD
+        .decode(public_key.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("Public key must be a base64url-encoded 32-byte key")?;
+    let key = VerifyingKey::from_bytes(&key).map_err(|e| format!("Invalid public key: {}", e))?;
+
+    let signature: [u8; 64] = URL_SAFE_NO_PAD
+        .decode(signature.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("Signature must be a base64url-encoded 64-byte value")?;
+
+    key.verify_strict(fields.message().as_bytes(), &Signature::from_bytes(&signature))
+        .map_err(|_| "Signature does not match this certificate".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn fields() -> SignedFields {
+        SignedFields {
+            callsign: "W1AW".to_string(),
+            certificate_number: 42,
+            test_id: Some("20wpm-extra-1991".to_string()),
+            issued_at: Utc.with_ymd_and_hms(2026, 3, 1, 12, 30, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let signer = CertificateSigner::generate();
+        let reloaded = CertificateSigner::from_base64(&signer.private_key_base64()).unwrap();
+        assert_eq!(reloaded.public_key_base64(), signer.public_key_base64());
+
+        let signature = signer.sign(&fields());
+        assert!(verify(&signer.public_key_base64(), &fields(), &signature).is_ok());
+
+        let mut forged = fields();
+        forged.callsign = "K1ABC".to_string();
+        assert!(verify(&signer.public_key_base64(), &forged, &signature).is_err());
+
+        let other = CertificateSigner::generate();
+        assert!(verify(&other.public_key_base64(), &fields(), &signature).is_err());
+    }
+
+    #[test]
+    fn test_rejects_malformed_keys() {
+        assert!(CertificateSigner::from_base64("not a key").is_err());
+        assert!(CertificateSigner::from_base64(&URL_SAFE_NO_PAD.encode([0u8; 16])).is_err());
+        assert!(verify("short", &fields(), "sig").is_err());
+    }
+
+    #[test]
+    fn test_issued_at_is_whole_seconds() {
+        let mut fields = fields();
+        fields.issued_at += chrono::Duration::milliseconds(250);
+        assert_eq!(fields.issued_at_string(), "2026-03-01T12:30:00Z");
+    }
+}
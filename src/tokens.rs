@@ -0,0 +1,313 @@
+//! Long-lived, scoped API tokens for scripting against the admin API
+//!
+//! Tokens are shown once at creation and stored only as a SHA-256 hash.
+//! They are accepted by `require_admin_auth` alongside admin JWTs, but only
+//! for the routes their scopes cover.
+
+use axum::{
+    extract::{Path, State},
+    http::{Method, StatusCode},
+    response::{IntoResponse, Json},
+};
+use crate::db::{query, Db};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+use std::sync::Arc;
+
+/// Prefix identifying API tokens (as opposed to JWTs) in the Authorization header
+pub const TOKEN_PREFIX: &str = "kce_";
+
+/// Permission granted to an API token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Scope {
+    #[serde(rename = "roster:read")]
+    RosterRead,
+    #[serde(rename = "queue:read")]
+    QueueRead,
+    #[serde(rename = "queue:write")]
+    QueueWrite,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::RosterRead => "roster:read",
+            Scope::QueueRead => "queue:read",
+            Scope::QueueWrite => "queue:write",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "roster:read" => Some(Scope::RosterRead),
+            "queue:read" => Some(Scope::QueueRead),
+            "queue:write" => Some(Scope::QueueWrite),
+            _ => None,
+        }
+    }
+}
+
+/// Scope needed to call an admin route with an API token.
+/// `path` is relative to `/api/admin`. Returns None for routes that require
+/// an interactive admin session (test editing, settings, token management).
+pub fn required_scope(method: &Method, path: &str) -> Option<Scope> {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    match (method, segments.as_slice()) {
+        (&Method::GET, ["approved"]) => Some(Scope::RosterRead),
+        (&Method::GET, ["stats"])
+        | (&Method::GET, ["queue"])
+        | (&Method::GET, ["queue", _, "history"])
+        | (&Method::GET, ["search"])
+        | (&Method::GET, ["attempts"]) => Some(Scope::QueueRead),
+        (&Method::POST, ["queue", _, "approve"])
+        | (&Method::POST, ["queue", _, "reject"])
+        | (&Method::POST, ["approved", "mark-reached-out"]) => Some(Scope::QueueWrite),
+        _ => None,
+    }
+}
+
+/// Hash a presented token for lookup
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}{}", TOKEN_PREFIX, hex)
+}
+
+#[derive(Debug, FromRow)]
+struct ApiTokenRow {
+    id: String,
+    name: String,
+    scopes: String,
+    created_at: DateTime<Utc>,
+    last_used_at: Option<DateTime<Utc>>,
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiToken {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+fn parse_scopes(s: &str) -> Vec<Scope> {
+    s.split(',').filter_map(Scope::parse).collect()
+}
+
+impl From<ApiTokenRow> for ApiToken {
+    fn from(row: ApiTokenRow) -> Self {
+        ApiToken {
+            id: row.id,
+            name: row.name,
+            scopes: parse_scopes(&row.scopes),
+            created_at: row.created_at,
+            last_used_at: row.last_used_at,
+            revoked_at: row.revoked_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTokenRequest {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateTokenResponse {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    /// Plaintext token - only returned once
+    pub token: String,
+}
+
+/// Look up an active token, record its use and return its scopes
+pub async fn authenticate(
+    db: &Db,
+    token: &str,
+) -> Result<Option<Vec<Scope>>, sqlx::Error> {
+    let row: Option<(String, String)> = db
+        .fetch_optional(
+            query("SELECT id, scopes FROM api_tokens WHERE token_hash = ? AND revoked_at IS NULL")
+                .bind(hash_token(token)),
+        )
+        .await?;
+
+    let Some((id, scopes)) = row else {
+        return Ok(None);
+    };
+
+    db.execute(
+        query("UPDATE api_tokens SET last_used_at = ? WHERE id = ?")
+            .bind(Utc::now())
+            .bind(&id),
+    )
+    .await?;
+
+    Ok(Some(parse_scopes(&scopes)))
+}
+
+/// GET /api/admin/tokens - List API tokens (never includes token values)
+pub async fn list_tokens(
+    State(state): State<Arc<crate::AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let rows: Vec<ApiTokenRow> = state
+        .db
+        .fetch_all(query(
+            "SELECT id, name, scopes, created_at, last_used_at, revoked_at
+             FROM api_tokens ORDER BY created_at DESC",
+        ))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let tokens: Vec<ApiToken> = rows.into_iter().map(ApiToken::from).collect();
+
+    Ok(Json(tokens))
+}
+
+/// POST /api/admin/tokens - Create a named token with scopes
+pub async fn create_token(
+    State(state): State<Arc<crate::AppState>>,
+    Json(req): Json<CreateTokenRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let name = req.name.trim().to_string();
+    if name.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Token name is required".to_string()));
+    }
+
+    let mut scopes = req.scopes;
+    scopes.sort();
+    scopes.dedup();
+    if scopes.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "At least one scope is required".to_string(),
+        ));
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let token = generate_token();
+    let scopes_str = scopes
+        .iter()
+        .map(|s| s.as_str())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    state
+        .db
+        .execute(
+            query(
+                "INSERT INTO api_tokens (id, name, token_hash, scopes, created_at) VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(&id)
+            .bind(&name)
+            .bind(hash_token(&token))
+            .bind(&scopes_str)
+            .bind(Utc::now()),
+        )
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tracing::info!("Created API token '{}' with scopes {}", name, scopes_str);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateTokenResponse {
+            id,
+            name,
+            scopes,
+            token,
+        }),
+    ))
+}
+
+/// DELETE /api/admin/tokens/:id - Revoke a token
+pub async fn revoke_token(
+    State(state): State<Arc<crate::AppState>>,
+    Path(token_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let revoked = state
+        .db
+        .execute(
+            query("UPDATE api_tokens SET revoked_at = ? WHERE id = ? AND revoked_at IS NULL")
+                .bind(Utc::now())
+                .bind(&token_id),
+        )
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if revoked == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Token not found or already revoked".to_string(),
+        ));
+    }
+
+    Ok(Json(serde_json::json!({ "success": true })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_scope_mapping() {
+        assert_eq!(required_scope(&Method::GET, "/approved"), Some(Scope::RosterRead));
+        assert_eq!(required_scope(&Method::GET, "/queue"), Some(Scope::QueueRead));
+        assert_eq!(
+            required_scope(&Method::GET, "/queue/W1AW/history"),
+            Some(Scope::QueueRead)
+        );
+        assert_eq!(
+            required_scope(&Method::POST, "/queue/abc/approve"),
+            Some(Scope::QueueWrite)
+        );
+        assert_eq!(
+            required_scope(&Method::POST, "/approved/mark-reached-out"),
+            Some(Scope::QueueWrite)
+        );
+    }
+
+    #[test]
+    fn test_required_scope_denies_session_only_routes() {
+        assert_eq!(required_scope(&Method::GET, "/tokens"), None);
+        assert_eq!(required_scope(&Method::POST, "/tokens"), None);
+        assert_eq!(required_scope(&Method::PUT, "/tests/20wpm"), None);
+        assert_eq!(required_scope(&Method::GET, "/settings"), None);
+        assert_eq!(required_scope(&Method::POST, "/queue"), None);
+    }
+
+    #[test]
+    fn test_generated_tokens_are_prefixed_and_unique() {
+        let a = generate_token();
+        let b = generate_token();
+        assert!(a.starts_with(TOKEN_PREFIX));
+        assert_eq!(a.len(), TOKEN_PREFIX.len() + 64);
+        assert_ne!(a, b);
+        assert_ne!(hash_token(&a), hash_token(&b));
+    }
+
+    #[test]
+    fn test_scope_round_trip() {
+        for scope in [Scope::RosterRead, Scope::QueueRead, Scope::QueueWrite] {
+            assert_eq!(Scope::parse(scope.as_str()), Some(scope));
+        }
+        assert_eq!(parse_scopes("roster:read,bogus,queue:write"), vec![Scope::RosterRead, Scope::QueueWrite]);
+    }
+}